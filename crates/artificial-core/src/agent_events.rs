@@ -0,0 +1,144 @@
+//! Structured log of a tool-calling run.
+//!
+//! Raw stream events describe *tokens*; a UI showing "searching the web…"
+//! or an audit trail needs *steps*: which model was asked, which tool ran
+//! with which arguments, what it returned.
+//! [`crate::ArtificialClient::chat_complete_with_tools_observed`] reports
+//! those as [`AgentEvent`]s to an [`AgentEventSink`]:
+//!
+//! ```rust,no_run
+//! use artificial_core::agent_events::{AgentEventKind, MemoryEventLog};
+//! # use artificial_core::{ArtificialClient, tools::ToolRegistry, generic::GenericMessage, provider::*};
+//! # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(
+//! #     client: ArtificialClient<B>, params: ChatCompleteParameters<GenericMessage>, registry: ToolRegistry,
+//! # ) -> artificial_core::error::Result<()> {
+//! let log = MemoryEventLog::new();
+//! client.chat_complete_with_tools_observed(params, &registry, &log).await?;
+//!
+//! for event in log.events() {
+//!     if let AgentEventKind::ToolInvoked { tool, .. } = &event.kind {
+//!         println!("step {}: called {tool}", event.step);
+//!     }
+//! }
+//! // Persist the run, e.g. as JSON lines.
+//! let jsonl: Vec<String> = log.events().iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+//! # Ok(()) }
+//! ```
+//!
+//! Any `Fn(AgentEvent)` closure is a sink too, e.g. one that forwards into a
+//! channel feeding a websocket.
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::generic::{GenericMessage, GenericUsageReport};
+
+/// One step of a run, in the order it happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentEvent {
+    /// 1-based model round-trip the event belongs to.
+    pub step: usize,
+    /// Unix timestamp (milliseconds) at which the event was emitted.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub kind: AgentEventKind,
+}
+
+impl AgentEvent {
+    pub fn new(step: usize, kind: AgentEventKind) -> Self {
+        Self {
+            step,
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEventKind {
+    /// A new round-trip begins.
+    StepStarted,
+    /// The model answered; `tool_calls` is the number of requested calls.
+    ModelCalled {
+        model: String,
+        tool_calls: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<GenericUsageReport>,
+    },
+    ToolInvoked {
+        call_id: String,
+        tool: String,
+        arguments: serde_json::Value,
+    },
+    /// The content sent back to the model for `call_id`.
+    ToolResult {
+        call_id: String,
+        tool: String,
+        content: String,
+        /// The handler failed or the tool is unknown.
+        is_error: bool,
+    },
+    FinalAnswer {
+        message: GenericMessage,
+    },
+    /// The run aborted; the same error is returned to the caller.
+    Error {
+        message: String,
+    },
+}
+
+/// Receives the events of a run.
+///
+/// Invoked inline between steps, so implementations should not block.
+pub trait AgentEventSink: Send + Sync {
+    fn emit(&self, event: AgentEvent);
+}
+
+impl<F> AgentEventSink for F
+where
+    F: Fn(AgentEvent) + Send + Sync,
+{
+    fn emit(&self, event: AgentEvent) {
+        self(event)
+    }
+}
+
+/// Sink that drops every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoEvents;
+
+impl AgentEventSink for NoEvents {
+    fn emit(&self, _event: AgentEvent) {}
+}
+
+/// In-memory event log.
+///
+/// Cloning is cheap; all clones share the same buffer.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryEventLog {
+    events: Arc<Mutex<Vec<AgentEvent>>>,
+}
+
+impl MemoryEventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot of everything emitted so far.
+    pub fn events(&self) -> Vec<AgentEvent> {
+        self.events.lock().expect("event log poisoned").clone()
+    }
+}
+
+impl AgentEventSink for MemoryEventLog {
+    fn emit(&self, event: AgentEvent) {
+        self.events.lock().expect("event log poisoned").push(event);
+    }
+}
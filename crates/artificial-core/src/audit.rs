@@ -0,0 +1,469 @@
+//! Audit trail of every request and response.
+//!
+//! [`AuditedProvider`] wraps the backend of an [`crate::ArtificialClient`]
+//! and hands one [`AuditRecord`] per call to an [`AuditLogger`]: the
+//! rendered request with all parameters, the answer, usage and latency.
+//! Unlike [`crate::recorder::RecordingProvider`] it never samples, and a
+//! record that cannot be written fails the call, so nothing reaches the
+//! caller without being logged.
+//!
+//! Redaction callbacks run on every record before it is logged, in the order
+//! they were added:
+//!
+//! ```rust,no_run
+//! use artificial_core::{audit::{AuditedProvider, JsonlAuditLog}, ArtificialClient};
+//! # fn build<B>(backend: B) -> artificial_core::error::Result<()> {
+//! let backend = AuditedProvider::new(backend, JsonlAuditLog::open("llm-audit.jsonl")?)
+//!     .with_text_redaction(|text| text.replace("4111 1111 1111 1111", "[card]"))
+//!     .with_redaction(|record| record.request.tools = None);
+//! let client = ArtificialClient::new(backend);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Chat completions, tool loops and event streams are audited; a stream is
+//! logged once it ends. Templates run through
+//! [`crate::provider::PromptExecutionProvider`] go straight to the backend
+//! adapter and are not seen by the wrapper.
+use std::{
+    fmt,
+    fs::OpenOptions,
+    future::Future,
+    io::Write,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{
+        GenericChatCompletionResponse, GenericFunctionCallIntent, GenericMessage, GenericRole,
+        GenericUsageReport, ResponseContent, StreamEvent, StreamingEventsProvider,
+    },
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+        RequestSnapshot,
+    },
+    recorder::InteractionOutcome,
+};
+
+/// One audited call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Rendered messages, model and parameters.
+    pub request: RequestSnapshot,
+    pub outcome: InteractionOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<GenericUsageReport>,
+    pub latency_ms: u64,
+    /// Unix timestamp (milliseconds) at which the call completed.
+    pub recorded_at_ms: u64,
+    /// Whether the answer was streamed.
+    #[serde(default)]
+    pub streamed: bool,
+}
+
+impl AuditRecord {
+    /// Rewrite the content of every request message and of the answer.
+    pub fn redact_text(&mut self, redact: impl Fn(&str) -> String) {
+        let answer = match &mut self.outcome {
+            InteractionOutcome::Finished { message }
+            | InteractionOutcome::ToolCalls { message } => Some(message),
+            InteractionOutcome::Failed { .. } => None,
+        };
+        for message in self.request.messages.iter_mut().chain(answer) {
+            if let Some(content) = &mut message.content {
+                *content = redact(content);
+            }
+        }
+    }
+}
+
+/// Sink for [`AuditRecord`]s.
+///
+/// Called inline on the request path; an error fails the audited call. Any
+/// `Fn(&AuditRecord) -> Result<()>` closure is a logger, too.
+pub trait AuditLogger: Send + Sync {
+    fn log(&self, record: &AuditRecord) -> Result<()>;
+}
+
+impl<F> AuditLogger for F
+where
+    F: Fn(&AuditRecord) -> Result<()> + Send + Sync,
+{
+    fn log(&self, record: &AuditRecord) -> Result<()> {
+        self(record)
+    }
+}
+
+/// Appends one JSON object per line, flushed after every record.
+///
+/// Clones share the writer. Uses blocking I/O.
+#[derive(Clone)]
+pub struct JsonlAuditLog {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl JsonlAuditLog {
+    /// Append to the file at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| ArtificialError::Backend(Box::new(err)))?;
+        Ok(Self::from_writer(file))
+    }
+
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+        }
+    }
+}
+
+impl fmt::Debug for JsonlAuditLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonlAuditLog").finish_non_exhaustive()
+    }
+}
+
+impl AuditLogger for JsonlAuditLog {
+    fn log(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock().expect("audit log poisoned");
+        writer
+            .write_all(&line)
+            .and_then(|()| writer.flush())
+            .map_err(|err| ArtificialError::Backend(Box::new(err)))
+    }
+}
+
+type Redaction = Arc<dyn Fn(&mut AuditRecord) + Send + Sync>;
+
+/// Provider wrapper that logs every call, see the [module docs](self).
+///
+/// Speaks [`GenericMessage`] so the rendered requests are logged in a
+/// provider-independent form.
+pub struct AuditedProvider<B, L> {
+    inner: B,
+    logger: L,
+    redactions: Vec<Redaction>,
+}
+
+impl<B, L: AuditLogger> AuditedProvider<B, L> {
+    pub fn new(inner: B, logger: L) -> Self {
+        Self {
+            inner,
+            logger,
+            redactions: Vec::new(),
+        }
+    }
+
+    /// Adjust every record before it is logged, e.g. to drop PII.
+    pub fn with_redaction(
+        mut self,
+        redaction: impl Fn(&mut AuditRecord) + Send + Sync + 'static,
+    ) -> Self {
+        self.redactions.push(Arc::new(redaction));
+        self
+    }
+
+    /// Rewrite all message contents, see [`AuditRecord::redact_text`].
+    pub fn with_text_redaction(
+        self,
+        redact: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.with_redaction(move |record| record.redact_text(&redact))
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn logger(&self) -> &L {
+        &self.logger
+    }
+
+    fn audit(
+        &self,
+        request: RequestSnapshot,
+        outcome: InteractionOutcome,
+        usage: Option<GenericUsageReport>,
+        started: Instant,
+        streamed: bool,
+    ) -> Result<()> {
+        let mut record = AuditRecord {
+            request,
+            outcome,
+            usage,
+            latency_ms: started.elapsed().as_millis() as u64,
+            recorded_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            streamed,
+        };
+        for redaction in &self.redactions {
+            redaction(&mut record);
+        }
+        self.logger.log(&record)
+    }
+}
+
+impl<B, L> ChatCompletionProvider for AuditedProvider<B, L>
+where
+    B: ChatCompletionProvider,
+    L: AuditLogger,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+        let request = params.snapshot();
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = self.inner.chat_complete(params).await;
+            let (outcome, usage) = match &result {
+                Ok(response) => (
+                    match &response.content {
+                        ResponseContent::Finished(message) => InteractionOutcome::Finished {
+                            message: message.clone(),
+                        },
+                        ResponseContent::ToolCalls(message) => InteractionOutcome::ToolCalls {
+                            message: message.clone(),
+                        },
+                    },
+                    response.usage.clone(),
+                ),
+                Err(err) => (
+                    InteractionOutcome::Failed {
+                        error: err.to_string(),
+                    },
+                    None,
+                ),
+            };
+            self.audit(request, outcome, usage, started, false)?;
+            result
+        })
+    }
+}
+
+impl<B, L> StreamingEventsProvider for AuditedProvider<B, L>
+where
+    B: StreamingEventsProvider,
+    L: AuditLogger,
+    GenericMessage: Into<B::Message>,
+{
+    type EventStream<'s>
+        = Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send + 's>>
+    where
+        Self: 's;
+
+    fn chat_complete_events_stream<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Self::EventStream<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+        let request = params.snapshot();
+        Box::pin(Audited {
+            source: Box::pin(self.inner.chat_complete_events_stream(params)),
+            provider: self,
+            request: Some(request),
+            started: Instant::now(),
+            text: String::new(),
+            tool_calls: Vec::new(),
+            usage: None,
+        })
+    }
+}
+
+impl<B, L> HealthCheckProvider for AuditedProvider<B, L>
+where
+    B: HealthCheckProvider,
+    L: Send + Sync,
+{
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+/// Collects the answer of a stream and logs it once the stream ends.
+struct Audited<'s, S, B, L> {
+    source: S,
+    provider: &'s AuditedProvider<B, L>,
+    /// Taken when the record is written.
+    request: Option<RequestSnapshot>,
+    started: Instant,
+    text: String,
+    tool_calls: Vec<GenericFunctionCallIntent>,
+    usage: Option<GenericUsageReport>,
+}
+
+impl<S, B, L: AuditLogger> Audited<'_, S, B, L> {
+    fn finish(&mut self, error: Option<String>) -> Result<()> {
+        let Some(request) = self.request.take() else {
+            return Ok(());
+        };
+        let text = std::mem::take(&mut self.text);
+        let outcome = match (error, self.tool_calls.is_empty()) {
+            (Some(error), _) => InteractionOutcome::Failed { error },
+            (None, true) => InteractionOutcome::Finished {
+                message: GenericMessage::new(text, GenericRole::Assistant),
+            },
+            (None, false) => {
+                let mut message = GenericMessage::new(text, GenericRole::Assistant);
+                message.tool_calls = Some(std::mem::take(&mut self.tool_calls));
+                InteractionOutcome::ToolCalls { message }
+            }
+        };
+        self.provider
+            .audit(request, outcome, self.usage.take(), self.started, true)
+    }
+}
+
+impl<S, B, L> Stream for Audited<'_, S, B, L>
+where
+    S: Stream<Item = Result<StreamEvent>> + Unpin,
+    L: AuditLogger,
+{
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.request.is_none() {
+            return Poll::Ready(None);
+        }
+        let item = match Pin::new(&mut this.source).poll_next(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        match &item {
+            Some(Ok(StreamEvent::TextDelta(delta))) => this.text.push_str(delta),
+            Some(Ok(StreamEvent::ToolCallComplete { intent, .. })) => {
+                this.tool_calls.push(intent.clone())
+            }
+            Some(Ok(StreamEvent::Usage(usage))) => this.usage = Some(usage.clone()),
+            Some(Ok(_)) => {}
+            Some(Err(err)) => {
+                if let Err(log_err) = this.finish(Some(err.to_string())) {
+                    return Poll::Ready(Some(Err(log_err)));
+                }
+            }
+            None => {
+                if let Err(log_err) = this.finish(None) {
+                    return Poll::Ready(Some(Err(log_err)));
+                }
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{
+        model::{Model, OpenAiModel},
+        testing::{MockProvider, MockReply},
+    };
+
+    fn params(text: &str) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(text.into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn writes_redacted_jsonl_records() {
+        let path =
+            std::env::temp_dir().join(format!("artificial-audit-{}.jsonl", std::process::id()));
+        let mock = MockProvider::new();
+        mock.push(MockReply::text("Noted, ada@example.com.").with_usage(9, 4));
+        mock.push_error(ArtificialError::Other("down".into()));
+        let provider = AuditedProvider::new(mock, JsonlAuditLog::open(&path).unwrap())
+            .with_text_redaction(|text| text.replace("ada@example.com", "[email]"));
+
+        provider
+            .chat_complete(params("Mail me at ada@example.com"))
+            .await
+            .unwrap();
+        provider.chat_complete(params("again")).await.unwrap_err();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<AuditRecord> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert!(!log.contains("ada@example.com"), "{log}");
+        assert_eq!(
+            records[0].request.messages[0].content.as_deref(),
+            Some("Mail me at [email]")
+        );
+        assert_eq!(
+            records[0].outcome.message().unwrap().content.as_deref(),
+            Some("Noted, [email].")
+        );
+        assert_eq!(records[0].usage.as_ref().unwrap().prompt_tokens, 9);
+        assert!(records[1].outcome.is_failure());
+    }
+
+    #[tokio::test]
+    async fn streams_are_logged_when_they_end_and_logging_failures_surface() {
+        let mock = MockProvider::new();
+        mock.push(MockReply::chunks(["Hel", "lo"]));
+        mock.push(MockReply::chunks(["Hi"]));
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&records);
+        let provider = AuditedProvider::new(mock, move |record: &AuditRecord| {
+            let mut records = sink.lock().unwrap();
+            records.push(record.clone());
+            match records.len() {
+                1 => Ok(()),
+                _ => Err(ArtificialError::Other("disk full".into())),
+            }
+        });
+
+        let events: Vec<_> = provider
+            .chat_complete_events_stream(params("hi"))
+            .collect()
+            .await;
+        assert!(events.iter().all(Result::is_ok));
+        let record = records.lock().unwrap()[0].clone();
+        assert!(record.streamed);
+        assert_eq!(
+            record.outcome.message().unwrap().content.as_deref(),
+            Some("Hello")
+        );
+
+        let events: Vec<_> = provider
+            .chat_complete_events_stream(params("hi"))
+            .collect()
+            .await;
+        assert!(events.last().unwrap().is_err());
+    }
+}
@@ -0,0 +1,338 @@
+//! Response caching for repeated prompts.
+//!
+//! [`CachingProvider`] keeps successful chat completions keyed by the
+//! complete request (messages, model, tools and generation settings) and
+//! answers identical requests from memory while they are fresh, as set by
+//! the [`CachePolicy`].
+//!
+//! For semi-dynamic prompts—a daily summary, a digest of slowly changing
+//! data—[`CachePolicy::with_stale_while_revalidate`] trades freshness for
+//! latency: an entry past its TTL is still served immediately while a
+//! background task fetches a new answer for the next caller. Background
+//! work needs an executor, handed in with [`CachingProvider::with_spawner`];
+//! without one, stale entries are refreshed inline like expired ones.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use artificial_core::cache::{CachePolicy, CachingProvider};
+//! # fn wrap<B: Send + Sync + 'static>(backend: B) -> CachingProvider<B> {
+//! let policy = CachePolicy::new(Duration::from_secs(3600))
+//!     .with_stale_while_revalidate(Duration::from_secs(24 * 3600));
+//! let backend = CachingProvider::new(backend, policy).with_spawner(|refresh| {
+//!     tokio::spawn(refresh);
+//! });
+//! # backend }
+//! ```
+//!
+//! Cached answers carry no usage report, so token accounting reflects what
+//! was actually billed. Concurrent misses are each sent to the backend;
+//! wrap the backend in a [`crate::singleflight::SingleFlightProvider`] to
+//! merge them.
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::Result,
+    generic::{GenericChatCompletionResponse, GenericMessage},
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+    },
+    singleflight::request_key,
+};
+
+/// Future of a background refresh, handed to the spawner.
+pub type RefreshFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+type Spawner = Arc<dyn Fn(RefreshFuture) + Send + Sync>;
+
+type Entries = Arc<Mutex<HashMap<String, Entry>>>;
+
+/// How long cached answers are served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Entries younger than this are served without asking the backend.
+    pub ttl: Duration,
+    /// How long past [`Self::ttl`] an entry is still served while it is
+    /// being refreshed in the background. Zero disables it.
+    pub stale_while_revalidate: Duration,
+}
+
+impl CachePolicy {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            stale_while_revalidate: Duration::ZERO,
+        }
+    }
+
+    pub fn with_stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.stale_while_revalidate = window;
+        self
+    }
+
+    fn freshness(&self, age: Duration) -> Freshness {
+        if age < self.ttl {
+            Freshness::Fresh
+        } else if age < self.ttl + self.stale_while_revalidate {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+}
+
+enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
+}
+
+struct Entry {
+    response: GenericChatCompletionResponse<GenericMessage>,
+    stored: Instant,
+    refreshing: bool,
+}
+
+/// [`ChatCompletionProvider`] wrapper that caches answers per
+/// [`CachePolicy`].
+pub struct CachingProvider<B> {
+    inner: Arc<B>,
+    policy: CachePolicy,
+    entries: Entries,
+    spawner: Option<Spawner>,
+}
+
+impl<B> CachingProvider<B> {
+    pub fn new(inner: B, policy: CachePolicy) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            policy,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            spawner: None,
+        }
+    }
+
+    /// Run background refreshes with `spawner`, e.g. `tokio::spawn`.
+    pub fn with_spawner<F>(mut self, spawner: F) -> Self
+    where
+        F: Fn(RefreshFuture) + Send + Sync + 'static,
+    {
+        self.spawner = Some(Arc::new(spawner));
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+
+    /// Drop all cached answers.
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .expect("response cache poisoned")
+            .clear();
+    }
+
+    /// Drop entries past the stale window. Expired entries are replaced on
+    /// their next request anyway; call this to bound memory when many
+    /// requests are never repeated.
+    pub fn evict_expired(&self) {
+        let policy = self.policy;
+        self.entries
+            .lock()
+            .expect("response cache poisoned")
+            .retain(|_, entry| {
+                !matches!(policy.freshness(entry.stored.elapsed()), Freshness::Expired)
+            });
+    }
+}
+
+impl<B> ChatCompletionProvider for CachingProvider<B>
+where
+    B: ChatCompletionProvider + 'static,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+
+        Box::pin(async move {
+            let Some(key) = request_key(&params) else {
+                return self.inner.chat_complete(params).await;
+            };
+
+            let (cached, revalidate) = {
+                let mut entries = self.entries.lock().expect("response cache poisoned");
+                match entries.get_mut(&key) {
+                    Some(entry) => match self.policy.freshness(entry.stored.elapsed()) {
+                        Freshness::Fresh => (Some(cached_copy(&entry.response)), false),
+                        Freshness::Stale if self.spawner.is_some() => {
+                            let revalidate = !entry.refreshing;
+                            entry.refreshing = true;
+                            (Some(cached_copy(&entry.response)), revalidate)
+                        }
+                        Freshness::Stale | Freshness::Expired => (None, false),
+                    },
+                    None => (None, false),
+                }
+            };
+            if let Some(response) = cached {
+                if let (true, Some(spawner)) = (revalidate, &self.spawner) {
+                    // The refresh outlives this call; the caller's cancellation
+                    // must not abort it.
+                    let mut params = params;
+                    params.cancellation = None;
+                    spawner(refresh(
+                        Arc::clone(&self.inner),
+                        Arc::clone(&self.entries),
+                        key,
+                        params,
+                    ));
+                }
+                return Ok(response);
+            }
+
+            let response = self.inner.chat_complete(params).await?;
+            store(&self.entries, key, &response);
+            Ok(response)
+        })
+    }
+}
+
+impl<B: HealthCheckProvider> HealthCheckProvider for CachingProvider<B> {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+/// Fetch a new answer for `key`. A failed refresh keeps the stale entry
+/// until it expires and lets the next request try again.
+fn refresh<B>(
+    inner: Arc<B>,
+    entries: Entries,
+    key: String,
+    params: ChatCompleteParameters<GenericMessage>,
+) -> RefreshFuture
+where
+    B: ChatCompletionProvider + 'static,
+    GenericMessage: Into<B::Message>,
+{
+    Box::pin(async move {
+        match inner.chat_complete(params).await {
+            Ok(response) => store(&entries, key, &response),
+            Err(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("background refresh of a cached response failed");
+
+                let mut entries = entries.lock().expect("response cache poisoned");
+                if let Some(entry) = entries.get_mut(&key) {
+                    entry.refreshing = false;
+                }
+            }
+        }
+    })
+}
+
+fn store(entries: &Entries, key: String, response: &GenericChatCompletionResponse<GenericMessage>) {
+    entries.lock().expect("response cache poisoned").insert(
+        key,
+        Entry {
+            response: cached_copy(response),
+            stored: Instant::now(),
+            refreshing: false,
+        },
+    );
+}
+
+fn cached_copy(
+    response: &GenericChatCompletionResponse<GenericMessage>,
+) -> GenericChatCompletionResponse<GenericMessage> {
+    GenericChatCompletionResponse {
+        content: response.content.clone(),
+        usage: None,
+        metadata: response.metadata.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic::GenericRole,
+        model::{Model, OpenAiModel},
+        testing::MockProvider,
+    };
+
+    fn answers() -> MockProvider {
+        let mock = MockProvider::new();
+        mock.push_text("answer 1").push_text("answer 2");
+        mock
+    }
+
+    fn params() -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(
+                "summarise today".into(),
+                GenericRole::User,
+            )],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    async fn answer<B>(provider: &CachingProvider<B>) -> String
+    where
+        B: ChatCompletionProvider + 'static,
+        GenericMessage: Into<B::Message>,
+    {
+        let response = provider.chat_complete(params()).await.unwrap();
+        response.content.expect_finished().content.unwrap()
+    }
+
+    #[tokio::test]
+    async fn stale_answers_are_served_while_refreshing() {
+        let policy = CachePolicy::new(Duration::from_millis(20))
+            .with_stale_while_revalidate(Duration::from_secs(60));
+        let mock = answers();
+        let provider = CachingProvider::new(mock.clone(), policy).with_spawner(|refresh| {
+            tokio::spawn(refresh);
+        });
+
+        assert_eq!(answer(&provider).await, "answer 1");
+        assert_eq!(answer(&provider).await, "answer 1");
+        assert_eq!(mock.requests().len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(answer(&provider).await, "answer 1");
+        tokio::task::yield_now().await;
+        assert_eq!(answer(&provider).await, "answer 2");
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn without_spawner_stale_answers_are_refreshed_inline() {
+        let policy =
+            CachePolicy::new(Duration::ZERO).with_stale_while_revalidate(Duration::from_secs(60));
+        let provider = CachingProvider::new(answers(), policy);
+
+        assert_eq!(answer(&provider).await, "answer 1");
+        assert_eq!(answer(&provider).await, "answer 2");
+    }
+}
@@ -0,0 +1,246 @@
+//! Caller-side cancellation of in-flight requests.
+//!
+//! Dropping a future already cancels it in Rust, but the code that wants to
+//! abort (a "stop generating" button, a shutdown hook) rarely owns the
+//! future.  A [`CancellationToken`] is a cheap, clonable handle: hand one
+//! clone to [`crate::provider::ChatCompleteParameters::with_cancellation`]
+//! and call [`CancellationToken::cancel`] on another.
+//!
+//! ```rust
+//! use artificial_core::cancel::CancellationToken;
+//!
+//! let token = CancellationToken::new();
+//! let handle = token.clone();
+//!
+//! // …somewhere else, e.g. in a UI handler
+//! handle.cancel();
+//! assert!(token.is_cancelled());
+//! ```
+//!
+//! Providers stop at the next await point and fail with
+//! [`ArtificialError::Cancelled`]; a stream yields that error once and ends.
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures_core::Stream;
+
+use crate::error::{ArtificialError, Result};
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Wakers>,
+}
+
+/// Wakers of the pending futures and streams, keyed by their slot so each
+/// one removes its own entry when it is dropped.
+#[derive(Debug, Default)]
+struct Wakers {
+    next_slot: u64,
+    by_slot: HashMap<u64, Waker>,
+}
+
+/// Shared flag that aborts every request it was attached to.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<Inner>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel all requests holding a clone of this token. Idempotent.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        for (_, waker) in self.0.wakers.lock().unwrap().by_slot.drain() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once [`Self::cancel`] has been called.
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled(self.register())
+    }
+
+    /// Run `future` unless the token is cancelled first, in which case the
+    /// future is dropped and [`ArtificialError::Cancelled`] returned.
+    pub fn run<F, T>(&self, future: F) -> Cancellable<F>
+    where
+        F: Future<Output = Result<T>> + Unpin,
+    {
+        Cancellable {
+            inner: future,
+            registration: self.register(),
+        }
+    }
+
+    /// Forward `stream` until the token is cancelled, then yield
+    /// [`ArtificialError::Cancelled`] once and end.
+    pub fn guard<S>(&self, stream: S) -> Cancellable<Option<S>> {
+        Cancellable {
+            inner: Some(stream),
+            registration: self.register(),
+        }
+    }
+
+    fn register(&self) -> Registration {
+        Registration {
+            token: self.clone(),
+            slot: None,
+        }
+    }
+}
+
+/// One waiter on a token. Its waker lives in a slot of [`Wakers`] while it
+/// is pending and is removed on drop, so a long-lived token only holds the
+/// wakers of the requests still running.
+#[derive(Debug)]
+struct Registration {
+    token: CancellationToken,
+    slot: Option<u64>,
+}
+
+impl Registration {
+    /// `Ready` if cancelled, otherwise remember the waker for
+    /// [`CancellationToken::cancel`].
+    fn poll_cancelled(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.token.0.wakers.lock().unwrap();
+        // Re-check under the lock so a concurrent `cancel` cannot slip by.
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let slot = *self.slot.get_or_insert_with(|| {
+            wakers.next_slot += 1;
+            wakers.next_slot
+        });
+        match wakers.by_slot.get_mut(&slot) {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => {
+                wakers.by_slot.insert(slot, cx.waker().clone());
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            self.token.0.wakers.lock().unwrap().by_slot.remove(&slot);
+        }
+    }
+}
+
+/// Future returned by [`CancellationToken::cancelled`].
+#[derive(Debug)]
+pub struct Cancelled(Registration);
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.0.poll_cancelled(cx)
+    }
+}
+
+/// A future or stream bound to a [`CancellationToken`], see
+/// [`CancellationToken::run`] and [`CancellationToken::guard`].
+#[derive(Debug)]
+pub struct Cancellable<T> {
+    inner: T,
+    registration: Registration,
+}
+
+impl<F, T> Future for Cancellable<F>
+where
+    F: Future<Output = Result<T>> + Unpin,
+{
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.registration.poll_cancelled(cx).is_ready() {
+            return Poll::Ready(Err(ArtificialError::Cancelled));
+        }
+        Pin::new(&mut self.inner).poll(cx)
+    }
+}
+
+impl<S, T> Stream for Cancellable<Option<S>>
+where
+    S: Stream<Item = Result<T>> + Unpin,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let Some(stream) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+        if this.registration.poll_cancelled(cx).is_ready() {
+            this.inner = None;
+            return Poll::Ready(Some(Err(ArtificialError::Cancelled)));
+        }
+        Pin::new(stream).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn cancel_wakes_pending_future_and_ends_stream() {
+        let token = CancellationToken::new();
+
+        let pending = token.run(Box::pin(std::future::pending::<Result<()>>()));
+        let cancel = {
+            let token = token.clone();
+            tokio::spawn(async move { token.cancel() })
+        };
+        assert!(matches!(pending.await, Err(ArtificialError::Cancelled)));
+        cancel.await.unwrap();
+
+        let mut stream = token.guard(futures_util::stream::iter([Ok::<_, ArtificialError>(1)]));
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ArtificialError::Cancelled))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn finished_requests_release_their_wakers() {
+        let token = CancellationToken::new();
+        for _ in 0..3 {
+            let mut yielded = false;
+            let request = token.run(Box::pin(std::future::poll_fn(|cx| {
+                if std::mem::replace(&mut yielded, true) {
+                    Poll::Ready(Ok::<_, ArtificialError>(()))
+                } else {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })));
+            request.await.unwrap();
+        }
+        assert!(token.0.wakers.lock().unwrap().by_slot.is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+//! Canonical JSON for hashing and comparing requests.
+//!
+//! `serde_json` output depends on details that do not change the meaning of
+//! a value: map order (insertion order once any crate in the build enables
+//! `serde_json/preserve_order`), whitespace, and whether a number was
+//! serialized from `1` or `1.0`. Hashing that output gives different keys
+//! for equal requests in different processes.
+//!
+//! [`to_string`] writes the canonical form instead:
+//!
+//! * object keys sorted by their UTF-8 bytes, at every level;
+//! * no whitespace between tokens;
+//! * integral floats written as integers (`1.0` → `1`, `-0.0` → `0`), other
+//!   floats in the shortest form that round-trips.
+//!
+//! String contents are kept verbatim. [`fingerprint`] hashes the canonical
+//! form with 64-bit FNV-1a, whose output is fixed—unlike `DefaultHasher`,
+//! which may change between Rust releases—so fingerprints can be stored and
+//! compared across processes and deployments:
+//!
+//! ```rust
+//! use artificial_core::canonical;
+//! use serde_json::json;
+//!
+//! let a = json!({ "model": "gpt-4o", "temperature": 1.0 });
+//! let b = json!({ "temperature": 1, "model": "gpt-4o" });
+//! assert_eq!(canonical::to_string(&a)?, r#"{"model":"gpt-4o","temperature":1}"#);
+//! assert_eq!(canonical::fingerprint(&a)?, canonical::fingerprint(&b)?);
+//! # Ok::<(), artificial_core::error::ArtificialError>(())
+//! ```
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+use crate::error::Result;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+/// Integral floats beyond ±2^53 are not exact; keep them as floats.
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Serialize `value` as canonical JSON.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let value = serde_json::to_value(value)?;
+    let mut out = String::new();
+    write_value(&mut out, &value);
+    Ok(out)
+}
+
+/// 64-bit FNV-1a hash of the canonical JSON of `value`.
+pub fn hash<T: Serialize + ?Sized>(value: &T) -> Result<u64> {
+    Ok(fnv1a(to_string(value)?.as_bytes()))
+}
+
+/// [`hash`] as 16 lowercase hex digits.
+pub fn fingerprint<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    Ok(format!("{:016x}", hash(value)?))
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(out, n),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, item);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_number(out: &mut String, n: &Number) {
+    match n.as_f64() {
+        Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() <= MAX_EXACT_INTEGER => {
+            out.push_str(&(f as i64).to_string());
+        }
+        _ => out.push_str(&n.to_string()),
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push_str(&serde_json::to_string(s).expect("strings always serialize"));
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn sorts_nested_keys_and_normalizes_numbers() {
+        let value = json!({
+            "b": [{ "z": 1.5, "a": -0.0 }, 2.0, 1e300],
+            "a": { "y": "x\n\"", "x": null },
+        });
+        assert_eq!(
+            to_string(&value).unwrap(),
+            r#"{"a":{"x":null,"y":"x\n\""},"b":[{"a":0,"z":1.5},2,1e+300]}"#
+        );
+    }
+
+    #[test]
+    fn fingerprint_is_fixed_across_builds() {
+        // FNV-1a of `{"a":1}`; changing it invalidates stored fingerprints.
+        assert_eq!(fingerprint(&json!({ "a": 1 })).unwrap(), "9c3e82dd6fcae8b1");
+        assert_eq!(fnv1a(b""), FNV_OFFSET);
+    }
+}
@@ -0,0 +1,145 @@
+//! What a model can do, looked up before a request is built.
+//!
+//! Providers differ in which request features a model accepts.  Instead of
+//! scattering `match model { … }` checks across provider crates, every
+//! [`Model`] resolves to a [`ModelCapabilities`] record:
+//!
+//! * built-in models come with a static entry,
+//! * [`Model::Custom`] models default to [`ModelCapabilities::default`] and can
+//!   be described at startup with [`register_capabilities`].
+//!
+//! ```rust
+//! use artificial_core::capability::{register_capabilities, ModelCapabilities, ResponseFormat};
+//! use artificial_core::model::Model;
+//!
+//! register_capabilities(
+//!     "my-local-llama",
+//!     ModelCapabilities::default().with_response_format(ResponseFormat::JsonObject),
+//! );
+//!
+//! let caps = Model::Custom("my-local-llama").capabilities();
+//! assert_eq!(caps.response_format, ResponseFormat::JsonObject);
+//! ```
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+use crate::model::{DeepSeekModel, Model, OpenAiModel, XAiModel};
+
+/// How structured output can be requested from a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResponseFormat {
+    /// The provider enforces a JSON Schema (`response_format: json_schema`).
+    JsonSchema,
+    /// The provider only guarantees syntactically valid JSON
+    /// (`response_format: json_object`); the schema has to be described in
+    /// the prompt.
+    JsonObject,
+}
+
+/// Feature flags of a single model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Best structured-output mode the model supports.
+    pub response_format: ResponseFormat,
+    /// Whether the model accepts tool / function definitions.
+    pub tools: bool,
+    /// Whether the model returns its reasoning as separate text.
+    pub reasoning_content: bool,
+    /// Maximum number of tokens (prompt plus completion) per request, if
+    /// known.
+    pub context_window: Option<u32>,
+}
+
+impl Default for ModelCapabilities {
+    /// Capabilities of a current OpenAI chat model.
+    fn default() -> Self {
+        Self {
+            response_format: ResponseFormat::JsonSchema,
+            tools: true,
+            reasoning_content: false,
+            context_window: None,
+        }
+    }
+}
+
+impl ModelCapabilities {
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = response_format;
+        self
+    }
+
+    pub fn with_tools(mut self, tools: bool) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_reasoning_content(mut self, reasoning_content: bool) -> Self {
+        self.reasoning_content = reasoning_content;
+        self
+    }
+
+    pub fn with_context_window(mut self, context_window: u32) -> Self {
+        self.context_window = Some(context_window);
+        self
+    }
+}
+
+fn registry() -> &'static RwLock<HashMap<String, ModelCapabilities>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, ModelCapabilities>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Describe a model by its identifier, overriding the built-in entry.
+///
+/// Mostly useful for [`Model::Custom`] models; call it once at startup.
+pub fn register_capabilities(model: impl Into<String>, capabilities: ModelCapabilities) {
+    registry()
+        .write()
+        .expect("capability registry poisoned")
+        .insert(model.into(), capabilities);
+}
+
+impl Model {
+    /// Look up the capabilities of this model.
+    pub fn capabilities(&self) -> ModelCapabilities {
+        if let Some(registered) = registry()
+            .read()
+            .expect("capability registry poisoned")
+            .get(self.as_ref())
+        {
+            return *registered;
+        }
+
+        let defaults = ModelCapabilities::default();
+        match self {
+            Model::OpenAi(model) => defaults.with_context_window(openai_context_window(*model)),
+            Model::XAi(XAiModel::Grok4) => defaults.with_context_window(256_000),
+            Model::XAi(XAiModel::Grok3) => defaults.with_context_window(131_072),
+            Model::XAi(XAiModel::Grok3Mini) => defaults
+                .with_reasoning_content(true)
+                .with_context_window(131_072),
+            Model::DeepSeek(DeepSeekModel::DeepSeekChat) => defaults
+                .with_response_format(ResponseFormat::JsonObject)
+                .with_context_window(128_000),
+            Model::DeepSeek(DeepSeekModel::DeepSeekReasoner) => defaults
+                .with_response_format(ResponseFormat::JsonObject)
+                .with_tools(false)
+                .with_reasoning_content(true)
+                .with_context_window(128_000),
+            Model::Custom(_) => defaults,
+        }
+    }
+}
+
+fn openai_context_window(model: OpenAiModel) -> u32 {
+    match model {
+        OpenAiModel::Gpt4o | OpenAiModel::Gpt4oMini => 128_000,
+        OpenAiModel::Gpt4_1 | OpenAiModel::Gpt4_1Mini | OpenAiModel::Gpt4_1Nano => 1_047_576,
+        OpenAiModel::O3 | OpenAiModel::O3Mini | OpenAiModel::O4Mini => 200_000,
+        OpenAiModel::TextEmbedding3Small | OpenAiModel::TextEmbedding3Large => 8_191,
+        // The GPT-5 family.
+        _ => 400_000,
+    }
+}
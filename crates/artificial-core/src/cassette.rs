@@ -0,0 +1,545 @@
+//! Record provider traffic once, replay it deterministically in CI.
+//!
+//! A [`ReplayProvider`] in *record* mode forwards every call to a real
+//! backend and writes request and response – including the full event
+//! sequence of streams – into a [`Cassette`]. Saved as JSON next to the
+//! tests, the cassette then backs a *replay* mode provider that answers
+//! without network access or API keys:
+//!
+//! ```rust,no_run
+//! use artificial_core::cassette::{Cassette, ReplayProvider};
+//! # fn record<B>(backend: B) -> artificial_core::error::Result<()>
+//! # where B: artificial_core::provider::ChatCompletionProvider,
+//! #       artificial_core::generic::GenericMessage: Into<B::Message>,
+//! # {
+//! const CASSETTE: &str = "tests/cassettes/weather.json";
+//!
+//! let provider = if std::env::var("RECORD").is_ok() {
+//!     ReplayProvider::record(backend)
+//! } else {
+//!     let json = std::fs::read_to_string(CASSETTE).unwrap();
+//!     ReplayProvider::replay(Cassette::from_json(&json)?).with_fallback(backend)
+//! };
+//!
+//! // … run the test against `ArtificialClient::new(provider.clone())` …
+//!
+//! if std::env::var("RECORD").is_ok() {
+//!     std::fs::write(CASSETTE, provider.cassette().to_json()?).unwrap();
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests are matched on their [`RequestSnapshot`]: everything that
+//! influences the answer must be equal, so a changed prompt fails loudly
+//! instead of silently replaying a stale answer. Each recorded entry is
+//! served once, in recording order among equal requests.
+//!
+//! Only calls through [`ChatCompletionProvider`] and
+//! [`StreamingEventsProvider`] are taped; template execution renders its
+//! request inside the backend and is not covered.
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    canonical,
+    error::{ArtificialError, Result},
+    generic::{
+        GenericChatCompletionResponse, GenericMessage, GenericUsageReport, ResponseContent,
+        StreamEvent, StreamingEventsProvider,
+    },
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+        RequestSnapshot,
+    },
+    recorder::InteractionOutcome,
+};
+
+/// Recorded requests and their responses.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub entries: Vec<CassetteEntry>,
+}
+
+/// One request and what the backend answered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteEntry {
+    pub request: RequestSnapshot,
+    pub response: CassetteResponse,
+}
+
+/// A recorded answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CassetteResponse {
+    Completion {
+        outcome: InteractionOutcome,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<GenericUsageReport>,
+    },
+    /// Events of a stream in order; `error` if the stream ended with one.
+    Events {
+        events: Vec<StreamEvent>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl Cassette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Pretty-printed, so cassettes diff well under version control.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Whether a [`ReplayProvider`] calls its backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Forward every call and append it to the cassette.
+    Record,
+    /// Answer from the cassette only; unmatched requests fail.
+    Replay,
+    /// Answer from the cassette, forwarding (and recording) unmatched
+    /// requests.
+    ReplayOrRecord,
+}
+
+/// Backend of a replay-only [`ReplayProvider`]; every call fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Offline;
+
+/// Records calls into a [`Cassette`] or answers from one, see the
+/// [module docs](self).
+///
+/// Cloning is cheap; all clones share the cassette.
+#[derive(Debug)]
+pub struct ReplayProvider<B = Offline> {
+    inner: Arc<B>,
+    mode: CassetteMode,
+    state: Arc<Mutex<TapeState>>,
+}
+
+impl<B> Clone for ReplayProvider<B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            mode: self.mode,
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TapeState {
+    cassette: Cassette,
+    /// Entries already served during replay.
+    used: Vec<bool>,
+}
+
+impl<B> ReplayProvider<B> {
+    /// Forward every call to `inner`, taping it into an empty cassette.
+    pub fn record(inner: B) -> Self {
+        Self::new(inner, Cassette::new(), CassetteMode::Record)
+    }
+
+    pub fn new(inner: B, cassette: Cassette, mode: CassetteMode) -> Self {
+        let used = vec![false; cassette.entries.len()];
+        Self {
+            inner: Arc::new(inner),
+            mode,
+            state: Arc::new(Mutex::new(TapeState { cassette, used })),
+        }
+    }
+
+    /// Forward requests missing from the cassette to `inner` and record
+    /// them, see [`CassetteMode::ReplayOrRecord`].
+    pub fn with_fallback<N>(self, inner: N) -> ReplayProvider<N> {
+        let state = std::mem::take(&mut *self.state());
+        ReplayProvider {
+            inner: Arc::new(inner),
+            mode: CassetteMode::ReplayOrRecord,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Snapshot of the cassette, including everything recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.state().cassette.clone()
+    }
+
+    /// Recorded entries that were never served, e.g. to spot requests a
+    /// test no longer makes.
+    pub fn unused(&self) -> Vec<CassetteEntry> {
+        let state = self.state();
+        state
+            .cassette
+            .entries
+            .iter()
+            .zip(&state.used)
+            .filter(|(_, used)| !**used)
+            .map(|(entry, _)| entry.clone())
+            .collect()
+    }
+
+    fn state(&self) -> MutexGuard<'_, TapeState> {
+        self.state.lock().expect("cassette poisoned")
+    }
+
+    /// The first unserved response recorded for `request`, marked served.
+    fn play(&self, request: &RequestSnapshot) -> Option<CassetteResponse> {
+        let key = canonical::to_string(request).ok()?;
+        let mut state = self.state();
+        let index = state
+            .cassette
+            .entries
+            .iter()
+            .enumerate()
+            .position(|(i, entry)| {
+                !state.used[i] && canonical::to_string(&entry.request).ok().as_ref() == Some(&key)
+            })?;
+        state.used[index] = true;
+        Some(state.cassette.entries[index].response.clone())
+    }
+
+    fn tape(&self, request: RequestSnapshot, response: CassetteResponse) {
+        let mut state = self.state();
+        state
+            .cassette
+            .entries
+            .push(CassetteEntry { request, response });
+        state.used.push(true);
+    }
+
+    fn unmatched(request: &RequestSnapshot) -> ArtificialError {
+        ArtificialError::InvalidRequest(format!(
+            "no recorded response matches this {} request ({} messages)",
+            request.model,
+            request.messages.len()
+        ))
+    }
+}
+
+impl ReplayProvider<Offline> {
+    /// Answer from `cassette` only.
+    pub fn replay(cassette: Cassette) -> Self {
+        Self::new(Offline, cassette, CassetteMode::Replay)
+    }
+}
+
+impl<B> ChatCompletionProvider for ReplayProvider<B>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+        let request = params.snapshot();
+        Box::pin(async move {
+            if self.mode != CassetteMode::Record {
+                match self.play(&request) {
+                    Some(CassetteResponse::Completion { outcome, usage }) => {
+                        let content = match outcome {
+                            InteractionOutcome::Finished { message } => {
+                                ResponseContent::Finished(message)
+                            }
+                            InteractionOutcome::ToolCalls { message } => {
+                                ResponseContent::ToolCalls(message)
+                            }
+                            InteractionOutcome::Failed { error } => {
+                                return Err(ArtificialError::Other(error))
+                            }
+                        };
+                        return Ok(GenericChatCompletionResponse {
+                            content,
+                            usage,
+                            metadata: Default::default(),
+                        });
+                    }
+                    Some(CassetteResponse::Events { .. }) => {
+                        return Err(ArtificialError::InvalidRequest(
+                            "recorded response is a stream, not a completion".into(),
+                        ))
+                    }
+                    None if self.mode == CassetteMode::Replay => {
+                        return Err(Self::unmatched(&request))
+                    }
+                    None => {}
+                }
+            }
+
+            let result = self.inner.chat_complete(params).await;
+            let (outcome, usage) = match &result {
+                Ok(response) => (
+                    match &response.content {
+                        ResponseContent::Finished(message) => InteractionOutcome::Finished {
+                            message: message.clone(),
+                        },
+                        ResponseContent::ToolCalls(message) => InteractionOutcome::ToolCalls {
+                            message: message.clone(),
+                        },
+                    },
+                    response.usage.clone(),
+                ),
+                Err(err) => (
+                    InteractionOutcome::Failed {
+                        error: err.to_string(),
+                    },
+                    None,
+                ),
+            };
+            self.tape(request, CassetteResponse::Completion { outcome, usage });
+            result
+        })
+    }
+}
+
+impl<B> StreamingEventsProvider for ReplayProvider<B>
+where
+    B: StreamingEventsProvider,
+    GenericMessage: Into<B::Message>,
+{
+    type EventStream<'s>
+        = Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send + 's>>
+    where
+        Self: 's;
+
+    fn chat_complete_events_stream<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Self::EventStream<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+        let request = params.snapshot();
+        if self.mode != CassetteMode::Record {
+            match self.play(&request) {
+                Some(CassetteResponse::Events { events, error }) => {
+                    let items = events
+                        .into_iter()
+                        .map(Ok)
+                        .chain(error.map(|error| Err(ArtificialError::Other(error))));
+                    return Box::pin(Replayed(items.collect()));
+                }
+                Some(CassetteResponse::Completion { .. }) => {
+                    return Box::pin(Replayed::failed(ArtificialError::InvalidRequest(
+                        "recorded response is a completion, not a stream".into(),
+                    )))
+                }
+                None if self.mode == CassetteMode::Replay => {
+                    return Box::pin(Replayed::failed(Self::unmatched(&request)))
+                }
+                None => {}
+            }
+        }
+
+        Box::pin(Taping {
+            source: Box::pin(self.inner.chat_complete_events_stream(params)),
+            provider: self,
+            request: Some(request),
+            events: Vec::new(),
+        })
+    }
+}
+
+impl<B> HealthCheckProvider for ReplayProvider<B>
+where
+    B: HealthCheckProvider,
+{
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+/// Passes a live stream through and tapes it once it ends.
+struct Taping<'s, S, B> {
+    source: S,
+    provider: &'s ReplayProvider<B>,
+    /// Taken when the tape is written.
+    request: Option<RequestSnapshot>,
+    events: Vec<StreamEvent>,
+}
+
+impl<S, B> Taping<'_, S, B> {
+    fn finish(&mut self, error: Option<String>) {
+        if let Some(request) = self.request.take() {
+            let events = std::mem::take(&mut self.events);
+            self.provider
+                .tape(request, CassetteResponse::Events { events, error });
+        }
+    }
+}
+
+impl<S, B> Stream for Taping<'_, S, B>
+where
+    S: Stream<Item = Result<StreamEvent>> + Unpin,
+{
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = match Pin::new(&mut this.source).poll_next(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        match &item {
+            Some(Ok(event)) => this.events.push(event.clone()),
+            Some(Err(err)) => this.finish(Some(err.to_string())),
+            None => this.finish(None),
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Recorded stream items, yielded one per poll.
+pub struct Replayed(VecDeque<Result<StreamEvent>>);
+
+impl Replayed {
+    fn failed(error: ArtificialError) -> Self {
+        Self(VecDeque::from([Err(error)]))
+    }
+}
+
+impl Stream for Replayed {
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().0.pop_front())
+    }
+}
+
+impl ChatCompletionProvider for Offline {
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        _params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        Box::pin(async { Err(offline()) })
+    }
+}
+
+impl StreamingEventsProvider for Offline {
+    type EventStream<'s> = Replayed;
+
+    fn chat_complete_events_stream<'s, M>(
+        &'s self,
+        _params: ChatCompleteParameters<M>,
+    ) -> Self::EventStream<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        Replayed::failed(offline())
+    }
+}
+
+impl HealthCheckProvider for Offline {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        Box::pin(async { Vec::new() })
+    }
+}
+
+fn offline() -> ArtificialError {
+    ArtificialError::Other("replaying offline: no backend to forward to".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{
+        generic::GenericRole,
+        model::{Model, OpenAiModel},
+        testing::{MockProvider, MockReply},
+    };
+
+    fn params(text: &str) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(text.into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn recorded_traffic_replays_offline() {
+        let mock = MockProvider::new();
+        mock.push(MockReply::text("pong").with_usage(2, 1))
+            .push(MockReply::chunks(["po", "ng"]));
+        let recorder = ReplayProvider::record(mock);
+        recorder.chat_complete(params("ping")).await.unwrap();
+        let live: Vec<_> = recorder
+            .chat_complete_events_stream(params("stream"))
+            .collect()
+            .await;
+        assert_eq!(live.len(), 3);
+
+        let json = recorder.cassette().to_json().unwrap();
+        let replay = ReplayProvider::replay(Cassette::from_json(&json).unwrap());
+
+        let response = replay.chat_complete(params("ping")).await.unwrap();
+        assert_eq!(response.content.text(), Some("pong"));
+        assert_eq!(response.usage.unwrap().total_tokens, 3);
+        let events: Vec<StreamEvent> = replay
+            .chat_complete_events_stream(params("stream"))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(matches!(&events[1], StreamEvent::TextDelta(t) if t == "ng"));
+        assert!(replay.unused().is_empty());
+
+        // Each entry is served once, and a changed prompt does not match.
+        assert!(replay.chat_complete(params("ping")).await.is_err());
+        assert!(matches!(
+            replay.chat_complete(params("pong")).await,
+            Err(ArtificialError::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fallback_records_missing_requests() {
+        let mock = MockProvider::new();
+        mock.push_text("new");
+        let provider = ReplayProvider::replay(Cassette::new()).with_fallback(mock.clone());
+
+        let response = provider.chat_complete(params("hi")).await.unwrap();
+        assert_eq!(response.content.text(), Some("new"));
+        assert_eq!(provider.cassette().entries.len(), 1);
+        assert_eq!(mock.requests().len(), 1);
+    }
+}
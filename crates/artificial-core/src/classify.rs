@@ -0,0 +1,120 @@
+//! Single-label classification into a Rust enum.
+//!
+//! Implement [`VariantsEnum`] for a fieldless enum and let
+//! [`crate::ArtificialClient::classify`] pick the label:
+//!
+//! ```rust
+//! use artificial_core::classify::{ClassificationResult, VariantsEnum};
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq)]
+//! enum Sentiment { Positive, Negative, Neutral }
+//!
+//! impl VariantsEnum for Sentiment {
+//!     const VARIANTS: &'static [Self] = &[Self::Positive, Self::Negative, Self::Neutral];
+//!
+//!     fn label(&self) -> &'static str {
+//!         match self {
+//!             Self::Positive => "positive",
+//!             Self::Negative => "negative",
+//!             Self::Neutral => "neutral",
+//!         }
+//!     }
+//! }
+//!
+//! // What the model is constrained to answer:
+//! let answer: ClassificationResult<Sentiment> =
+//!     serde_json::from_str(r#"{ "label": "negative" }"#)?;
+//! assert_eq!(answer.label, Sentiment::Negative);
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+//!
+//! The schema of [`ClassificationResult`] lists the labels as an `enum`, so
+//! providers with strict structured outputs cannot answer anything else.
+use schemars::{r#gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer};
+
+/// A fieldless enum whose variants are the labels of a classification.
+pub trait VariantsEnum: Copy + Send + Sync + 'static {
+    /// Every variant, in the order they are presented to the model.
+    const VARIANTS: &'static [Self];
+
+    /// The label the model answers with; unique per variant.
+    fn label(&self) -> &'static str;
+
+    /// When to pick this label, if the label alone is ambiguous.
+    fn description(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        Self::VARIANTS.iter().copied().find(|v| v.label() == label)
+    }
+}
+
+/// The answer of a classification: exactly one label of `E`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassificationResult<E> {
+    pub label: E,
+}
+
+impl<E: VariantsEnum> ClassificationResult<E> {
+    /// Instructions listing every label (and its description).
+    pub fn instructions() -> String {
+        let mut text = String::from("Classify the text into exactly one of these labels:\n");
+        for variant in E::VARIANTS {
+            text.push_str("- ");
+            text.push_str(variant.label());
+            if let Some(description) = variant.description() {
+                text.push_str(": ");
+                text.push_str(description);
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl<E: VariantsEnum> JsonSchema for ClassificationResult<E> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "Classification".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        let labels: Vec<&str> = E::VARIANTS.iter().map(|v| v.label()).collect();
+        serde_json::from_value(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "label": { "type": "string", "enum": labels },
+            },
+            "required": ["label"],
+            "additionalProperties": false,
+        }))
+        .expect("classification schema is valid")
+    }
+}
+
+impl<'de, E: VariantsEnum> Deserialize<'de> for ClassificationResult<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Raw {
+            label: String,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        match E::from_label(&raw.label) {
+            Some(label) => Ok(Self { label }),
+            None => {
+                let known: Vec<&str> = E::VARIANTS.iter().map(|v| v.label()).collect();
+                Err(serde::de::Error::custom(format!(
+                    "unknown label `{}`, expected one of {known:?}",
+                    raw.label
+                )))
+            }
+        }
+    }
+}
@@ -29,18 +29,60 @@
 //!
 //! Any backend crate (e.g. `artificial-openai`, `artificial-ollama`) just
 //! implements Provider traits and the same client works out of the box.
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{
+    any::Any,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use futures_core::Stream;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 
 use crate::{
-    error::Result,
-    generic::{GenericChatCompletionResponse, StreamingEventsProvider},
+    agent_events::{AgentEventSink, NoEvents},
+    classify::{ClassificationResult, VariantsEnum},
+    context::ExecutionContext,
+    error::{ArtificialError, Result},
+    generic::{
+        GenericChatCompletionResponse, GenericMessage, GenericRole, GenericUsageReport,
+        ResponseContent, ResponseMetadata, StreamingEventsProvider, SystemRole,
+    },
+    latency::{LatencyEstimator, LatencyKey},
+    model::Model,
     provider::{
-        ChatCompleteParameters, ChatCompletionProvider, PromptExecutionProvider,
-        StreamingChatProvider, TranscriptionProvider, TranscriptionRequest, TranscriptionResult,
+        BackendHealth, BoxedPromptStream, BoxedResponseFut, ChatCompleteParameters,
+        ChatCompletionProvider, EmbeddingProvider, EmbeddingResponse, ExecutionPolicy,
+        HealthCheckProvider, HealthReport, ModerationProvider, ModerationResult,
+        MultiChoiceProvider, MultiChoiceResponse, PromptExecutionProvider, PromptStreamEvent,
+        PromptStreamingProvider, PromptWarmingProvider, StreamingChatProvider,
+        TranscriptionProvider, TranscriptionRequest, TranscriptionResult,
     },
-    template::{IntoPrompt, PromptTemplate},
+    schema_util::{parse_output, DynamicOutput, SchemaMode},
+    template::{IntoPrompt, PromptTemplate, TemplateParams, WarmablePrompt},
+    tools::{run_tool_loop, step_tool_run, AgentCheckpoint, ToolRegistry, ToolRunOutcome},
 };
 
+/// How the preamble of an [`ArtificialClient`] combines with the messages a
+/// template renders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PreambleMode {
+    /// The preamble comes first; a template's own system prompt refines it.
+    #[default]
+    Prepend,
+    /// The preamble takes the place of the template's system prompt: the
+    /// system messages a template renders before its first other message
+    /// are dropped.
+    ReplaceSystem,
+}
+
+type IsSystem = fn(&dyn Any) -> bool;
+
 /// A client bound to a single provider.
 ///
 /// Clone the client if you need to share it across tasks—`B` controls whether
@@ -48,26 +90,524 @@ use crate::{
 #[derive(Debug, Clone)]
 pub struct ArtificialClient<B> {
     backend: Arc<B>,
+    preamble: Vec<GenericMessage>,
+    /// Set in [`PreambleMode::ReplaceSystem`]; tells the system messages of
+    /// a template apart in the backend's message type.
+    replaces_system: Option<IsSystem>,
+    context: ExecutionContext,
+    latency: Arc<LatencyEstimator>,
 }
 
-impl<B> ArtificialClient<B>
-where
-    B: PromptExecutionProvider,
-{
+impl<B> ArtificialClient<B> {
     /// Create a new client that delegates all calls to `backend`.
     pub fn new(backend: B) -> Self {
         Self {
             backend: Arc::new(backend),
+            preamble: Vec::new(),
+            replaces_system: None,
+            context: ExecutionContext::new(),
+            latency: Arc::new(LatencyEstimator::new()),
+        }
+    }
+
+    /// Add a system message to the preamble sent ahead of every executed
+    /// template, e.g. organisation-wide safety rules.
+    ///
+    /// The preamble messages come first, in the order they were added,
+    /// followed by everything the template renders. A template's own system
+    /// prompt therefore refines the baseline, unless
+    /// [`PreambleMode::ReplaceSystem`] is set.
+    ///
+    /// ```rust
+    /// # use artificial_core::ArtificialClient;
+    /// # fn build<B>(backend: B) -> ArtificialClient<B> {
+    /// ArtificialClient::new(backend)
+    ///     .with_system_preamble("Never reveal customer data.")
+    ///     .with_system_preamble("Answer in British English.")
+    /// # }
+    /// ```
+    pub fn with_system_preamble(mut self, text: impl Into<String>) -> Self {
+        self.preamble
+            .push(GenericMessage::new(text.into(), GenericRole::System));
+        self
+    }
+
+    /// Replace the whole preamble, e.g. to drop it for a derived client
+    /// (`client.clone().with_preamble(Vec::new())`).
+    pub fn with_preamble(mut self, messages: Vec<GenericMessage>) -> Self {
+        self.preamble = messages;
+        self
+    }
+
+    pub fn preamble(&self) -> &[GenericMessage] {
+        &self.preamble
+    }
+
+    pub fn preamble_mode(&self) -> PreambleMode {
+        if self.replaces_system.is_some() {
+            PreambleMode::ReplaceSystem
+        } else {
+            PreambleMode::Prepend
         }
     }
 
+    /// Make `context` current whenever this client renders a template, see
+    /// [`crate::context`]. Values that differ per request (the current
+    /// user) belong in [`Self::prompt_execute_with_context`] instead.
+    pub fn with_context(mut self, context: ExecutionContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn context(&self) -> &ExecutionContext {
+        &self.context
+    }
+
+    /// Record into `estimator` instead of a private one, e.g. to share it
+    /// between clients of the same backend.
+    pub fn with_latency_estimator(mut self, estimator: Arc<LatencyEstimator>) -> Self {
+        self.latency = estimator;
+        self
+    }
+
+    /// Latency of the successful chat completions and template executions
+    /// made through this client (and its clones), per model and reasoning
+    /// effort. See [`crate::latency`].
+    pub fn latency(&self) -> &LatencyEstimator {
+        &self.latency
+    }
+
     /// Access the underlying backend (e.g. to tweak provider-specific settings).
     pub fn backend(&self) -> &B {
         &self.backend
     }
 }
 
-impl<B: PromptExecutionProvider> PromptExecutionProvider for ArtificialClient<B> {
+impl<B> ArtificialClient<B>
+where
+    B: PromptExecutionProvider,
+    B::Message: SystemRole,
+{
+    /// Choose whether the preamble precedes or replaces the system prompt
+    /// of executed templates.
+    ///
+    /// ```rust
+    /// # use artificial_core::{ArtificialClient, PreambleMode, generic::GenericMessage, provider::PromptExecutionProvider};
+    /// # fn build<B: PromptExecutionProvider<Message = GenericMessage>>(backend: B) -> ArtificialClient<B> {
+    /// ArtificialClient::new(backend)
+    ///     .with_system_preamble("You are the support assistant of ACME.")
+    ///     .with_preamble_mode(PreambleMode::ReplaceSystem)
+    /// # }
+    /// ```
+    pub fn with_preamble_mode(mut self, mode: PreambleMode) -> Self {
+        self.replaces_system = match mode {
+            PreambleMode::Prepend => None,
+            PreambleMode::ReplaceSystem => Some(|message| {
+                message
+                    .downcast_ref::<B::Message>()
+                    .is_some_and(SystemRole::is_system)
+            }),
+        };
+        self
+    }
+}
+
+impl<B: HealthCheckProvider> ArtificialClient<B> {
+    /// Probe every configured backend with a cheap request and report
+    /// per-backend status and latency.
+    ///
+    /// Intended for readiness probes: services can refuse traffic until
+    /// [`HealthReport::is_ready`] returns `true`.
+    pub async fn health_check(&self) -> HealthReport {
+        HealthReport {
+            backends: self.backend.check_health().await,
+        }
+    }
+}
+
+impl<B: PromptWarmingProvider> ArtificialClient<B> {
+    /// Send the static prefix of `P` to the provider so that subsequent
+    /// executions of `P` hit a warm prompt cache.
+    ///
+    /// Call this at startup or right before a burst of traffic; the request
+    /// carries [`PromptTemplate::cache_key`] and asks for a single output
+    /// token.
+    ///
+    /// The client's preamble is part of the warmed prefix.
+    pub async fn warm<P>(&self) -> Result<Option<GenericUsageReport>>
+    where
+        P: WarmablePrompt,
+        <P as IntoPrompt>::Message: Into<B::Message> + Clone + Send + Sync + 'static,
+        B::Message: Clone,
+        GenericMessage: Into<B::Message>,
+    {
+        let prefix: Vec<B::Message> = self
+            .preamble
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .chain(without_system(
+                self.replaces_system,
+                P::static_prefix().into_iter().map(Into::into),
+            ))
+            .collect();
+        let mut params = ChatCompleteParameters::new(prefix, P::MODEL);
+        if let Some(cache_key) = P::cache_key() {
+            params = params.with_cache_key(cache_key);
+        }
+        self.backend.warm_prefix(params).await
+    }
+}
+
+impl<B> ArtificialClient<B>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    /// Chat with the tools of `registry` available and execute every call
+    /// the model makes, until it answers without requesting tools.
+    ///
+    /// The registry's specs are added to `params.tools`; see
+    /// [`crate::tools`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Provider errors abort the run; exceeding
+    /// [`ToolRegistry::with_max_rounds`] yields [`crate::error::ArtificialError::Other`].
+    pub async fn chat_complete_with_tools<M>(
+        &self,
+        params: ChatCompleteParameters<M>,
+        registry: &ToolRegistry,
+    ) -> Result<ToolRunOutcome>
+    where
+        M: Into<GenericMessage> + Clone,
+    {
+        self.chat_complete_with_tools_observed(params, registry, &NoEvents)
+            .await
+    }
+
+    /// Like [`Self::chat_complete_with_tools`], reporting every step to
+    /// `events`; see [`crate::agent_events`].
+    pub async fn chat_complete_with_tools_observed<M>(
+        &self,
+        params: ChatCompleteParameters<M>,
+        registry: &ToolRegistry,
+        events: &dyn AgentEventSink,
+    ) -> Result<ToolRunOutcome>
+    where
+        M: Into<GenericMessage> + Clone,
+    {
+        let params = params.map_messages(Into::into);
+        let mut checkpoint = AgentCheckpoint::new(params.messages.clone());
+        run_tool_loop(
+            self.backend.as_ref(),
+            &params,
+            &mut checkpoint,
+            registry,
+            events,
+        )
+        .await
+    }
+
+    /// Continue a run from `checkpoint` until the model answers; see
+    /// [`AgentCheckpoint`]. `params` supplies the request settings, its
+    /// messages are ignored in favour of the checkpoint's.
+    pub async fn resume_with_tools(
+        &self,
+        params: &ChatCompleteParameters<GenericMessage>,
+        mut checkpoint: AgentCheckpoint,
+        registry: &ToolRegistry,
+    ) -> Result<ToolRunOutcome> {
+        run_tool_loop(
+            self.backend.as_ref(),
+            params,
+            &mut checkpoint,
+            registry,
+            &NoEvents,
+        )
+        .await
+    }
+
+    /// Advance `checkpoint` by one step: run its pending tool calls if it
+    /// has any, otherwise ask the model. Returns the outcome once the model
+    /// has answered, so callers can persist or inspect the checkpoint in
+    /// between.
+    pub async fn step_with_tools(
+        &self,
+        params: &ChatCompleteParameters<GenericMessage>,
+        checkpoint: &mut AgentCheckpoint,
+        registry: &ToolRegistry,
+    ) -> Result<Option<ToolRunOutcome>> {
+        step_tool_run(
+            self.backend.as_ref(),
+            params,
+            checkpoint,
+            registry,
+            &NoEvents,
+        )
+        .await
+    }
+}
+
+impl<B> ArtificialClient<B>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    /// Structured extraction without a template type: ask `model` to turn
+    /// `input` into a `T`, following `instructions`.
+    ///
+    /// The schema of `T` is sent as `response_format` (or described in a
+    /// system message for models limited to JSON mode, see
+    /// [`crate::capability`]) and the answer is parsed into `T`. The
+    /// client's preamble comes first, as for templates.
+    ///
+    /// ```rust,no_run
+    /// # use artificial_core::{ArtificialClient, generic::GenericMessage, model::*, provider::ChatCompletionProvider};
+    /// # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(client: ArtificialClient<B>) -> artificial_core::error::Result<()> {
+    /// #[derive(serde::Deserialize, schemars::JsonSchema)]
+    /// struct Invoice { number: String, total_cents: u64 }
+    ///
+    /// let invoice: Invoice = client
+    ///     .extract(
+    ///         "Extract the invoice number and the total in cents.",
+    ///         "Invoice #2024-17 … Total due: 1.234,50 EUR",
+    ///         Model::OpenAi(OpenAiModel::Gpt4oMini),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Use a [`PromptTemplate`] instead once the call needs a name, retries,
+    /// postprocessing or its own parameters.
+    ///
+    /// # Errors
+    ///
+    /// Provider errors are passed through. An answer that does not parse
+    /// into `T` yields [`ArtificialError::OutputParse`]; one that requests
+    /// tools yields [`ArtificialError::Invalid`].
+    pub async fn extract<T>(
+        &self,
+        instructions: impl Into<String>,
+        input: impl Into<String>,
+        model: Model,
+    ) -> Result<T>
+    where
+        T: JsonSchema + DeserializeOwned + 'static,
+    {
+        let mut messages = self.preamble.clone();
+        messages.push(GenericMessage::new(
+            instructions.into(),
+            GenericRole::System,
+        ));
+        messages.push(GenericMessage::new(input.into(), GenericRole::User));
+
+        let mode = SchemaMode::for_model(&model);
+        let params = ChatCompleteParameters::new(messages, model).with_output_schema::<T>(mode)?;
+        let answer = self.backend.chat_complete(params).await?.content;
+        let text = answer
+            .into_finished()?
+            .content
+            .ok_or(ArtificialError::EmptyResponse)?;
+        parse_output(&text)
+    }
+
+    /// Like [`Self::extract`], for output shapes known only at runtime: the
+    /// schema comes from `output` and the answer is returned as raw JSON.
+    ///
+    /// ```rust,no_run
+    /// # use artificial_core::{ArtificialClient, generic::GenericMessage, model::*, provider::ChatCompletionProvider, schema_util::DynamicOutput};
+    /// # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(client: ArtificialClient<B>, plugin_schema: serde_json::Value) -> artificial_core::error::Result<()> {
+    /// let output = DynamicOutput::new("plugin_result", plugin_schema);
+    /// let value = client
+    ///     .extract_dynamic(
+    ///         "Fill in the plugin's form from the message.",
+    ///         "Book a table for two at 7pm.",
+    ///         &output,
+    ///         Model::OpenAi(OpenAiModel::Gpt4oMini),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// As for [`Self::extract`]; an answer that does not match the top-level
+    /// shape of the schema yields [`ArtificialError::Invalid`], see
+    /// [`DynamicOutput::parse`].
+    pub async fn extract_dynamic(
+        &self,
+        instructions: impl Into<String>,
+        input: impl Into<String>,
+        output: &DynamicOutput,
+        model: Model,
+    ) -> Result<Value> {
+        let mut messages = self.preamble.clone();
+        messages.push(GenericMessage::new(
+            instructions.into(),
+            GenericRole::System,
+        ));
+        messages.push(GenericMessage::new(input.into(), GenericRole::User));
+
+        let mode = SchemaMode::for_model(&model);
+        let params = ChatCompleteParameters::new(messages, model).with_dynamic_output(output, mode);
+        let answer = self.backend.chat_complete(params).await?.content;
+        let text = answer
+            .into_finished()?
+            .content
+            .ok_or(ArtificialError::EmptyResponse)?;
+        output.parse(&text)
+    }
+
+    /// Single-label classification: which variant of `E` fits `text` best.
+    ///
+    /// Built on [`Self::extract`] with a schema that only admits the labels
+    /// of `E`, see [`crate::classify`].
+    ///
+    /// ```rust,no_run
+    /// # use artificial_core::{ArtificialClient, classify::VariantsEnum, generic::GenericMessage, model::*, provider::ChatCompletionProvider};
+    /// # #[derive(Clone, Copy)] enum Sentiment { Positive, Negative }
+    /// # impl VariantsEnum for Sentiment {
+    /// #     const VARIANTS: &'static [Self] = &[Self::Positive, Self::Negative];
+    /// #     fn label(&self) -> &'static str { match self { Self::Positive => "positive", Self::Negative => "negative" } }
+    /// # }
+    /// # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(client: ArtificialClient<B>) -> artificial_core::error::Result<()> {
+    /// let sentiment: Sentiment = client
+    ///     .classify("The update broke everything.", Model::OpenAi(OpenAiModel::Gpt4oMini))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn classify<E: VariantsEnum>(
+        &self,
+        text: impl Into<String>,
+        model: Model,
+    ) -> Result<E> {
+        let result: ClassificationResult<E> = self
+            .extract(ClassificationResult::<E>::instructions(), text, model)
+            .await?;
+        Ok(result.label)
+    }
+}
+
+/// A template rendered eagerly – preamble first – while the client's
+/// [`ExecutionContext`] is current.
+struct Rendered<P, M> {
+    messages: Vec<M>,
+    template: PhantomData<fn() -> P>,
+}
+
+impl<P, M> Rendered<P, M>
+where
+    P: IntoPrompt,
+    P::Message: Into<M>,
+    GenericMessage: Into<M>,
+    M: 'static,
+{
+    fn new<B>(client: &ArtificialClient<B>, context: &ExecutionContext, prompt: P) -> Self {
+        let mut messages: Vec<M> = client.preamble.iter().cloned().map(Into::into).collect();
+        messages.extend(without_system(
+            client.replaces_system,
+            context
+                .scope(|| prompt.into_prompt())
+                .into_iter()
+                .map(Into::into),
+        ));
+        Self {
+            messages,
+            template: PhantomData,
+        }
+    }
+}
+
+impl<P, M> IntoPrompt for Rendered<P, M>
+where
+    M: Send + Sync + 'static,
+{
+    type Message = M;
+
+    fn into_prompt(self) -> Vec<M> {
+        self.messages
+    }
+}
+
+impl<P, M> PromptTemplate for Rendered<P, M>
+where
+    M: Send + Sync + 'static,
+    P: PromptTemplate,
+{
+    type Output = P::Output;
+    const MODEL: Model = P::MODEL;
+
+    fn name() -> &'static str {
+        P::name()
+    }
+
+    fn version() -> Option<&'static str> {
+        P::version()
+    }
+
+    fn cache_key() -> Option<&'static str> {
+        P::cache_key()
+    }
+
+    fn params() -> TemplateParams {
+        P::params()
+    }
+}
+
+impl<B> ArtificialClient<B>
+where
+    B: PromptExecutionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    /// Like [`PromptExecutionProvider::prompt_execute`] with `context`
+    /// layered over the client's context for this request; on conflicts the
+    /// values of `context` win.
+    ///
+    /// ```rust,no_run
+    /// # use artificial_core::{ArtificialClient, context::ExecutionContext, generic::GenericMessage, provider::PromptExecutionProvider, template::PromptTemplate};
+    /// # async fn run<B, P>(client: ArtificialClient<B>, prompt: P)
+    /// # where B: PromptExecutionProvider<Message = GenericMessage>, P: PromptTemplate<Message = GenericMessage> + Send + Sync {
+    /// struct CurrentUser { id: u64, plan: &'static str }
+    ///
+    /// let ctx = ExecutionContext::new()
+    ///     .with_value("locale", "de-AT")
+    ///     .with_extension(CurrentUser { id: 42, plan: "pro" });
+    /// # let _ = client.prompt_execute_with_context(prompt, &ctx).await;
+    /// # }
+    /// ```
+    pub fn prompt_execute_with_context<'a, 'p, P>(
+        &'a self,
+        prompt: P,
+        context: &ExecutionContext,
+    ) -> BoxedResponseFut<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        <P as IntoPrompt>::Message: Into<B::Message>,
+    {
+        let mut layered = self.context.clone();
+        layered.extend(context);
+        let backend = Arc::clone(&self.backend);
+        let prompt = Rendered::<P, B::Message>::new(self, &layered, prompt);
+        Box::pin(traced(
+            P::name(),
+            &P::MODEL,
+            timed::<P, _, _>(Arc::clone(&self.latency), async move {
+                backend.prompt_execute(prompt).await.map(postprocess::<P>)
+            }),
+        ))
+    }
+}
+
+impl<B> PromptExecutionProvider for ArtificialClient<B>
+where
+    B: PromptExecutionProvider,
+    GenericMessage: Into<B::Message>,
+{
     type Message = B::Message;
 
     fn prompt_execute<'a, 'p, P>(
@@ -80,7 +620,169 @@ impl<B: PromptExecutionProvider> PromptExecutionProvider for ArtificialClient<B>
         <P as IntoPrompt>::Message: Into<Self::Message>,
     {
         let backend = Arc::clone(&self.backend);
-        Box::pin(async move { backend.prompt_execute(prompt).await })
+        let prompt = Rendered::<P, B::Message>::new(self, &self.context, prompt);
+        Box::pin(traced(
+            P::name(),
+            &P::MODEL,
+            timed::<P, _, _>(Arc::clone(&self.latency), async move {
+                backend.prompt_execute(prompt).await.map(postprocess::<P>)
+            }),
+        ))
+    }
+
+    fn prompt_execute_with_policy<'a, 'p, P>(
+        &'a self,
+        prompt: P,
+        policy: ExecutionPolicy,
+    ) -> Pin<Box<dyn Future<Output = Result<GenericChatCompletionResponse<P::Output>>> + Send + 'p>>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        let backend = Arc::clone(&self.backend);
+        let prompt = Rendered::<P, B::Message>::new(self, &self.context, prompt);
+        let mut policy = policy;
+        policy.thinking_budget = policy
+            .thinking_budget
+            .map(|budget| budget.fit_to(&self.latency, &P::MODEL));
+        Box::pin(traced(
+            P::name(),
+            &P::MODEL,
+            timed::<P, _, _>(Arc::clone(&self.latency), async move {
+                backend
+                    .prompt_execute_with_policy(prompt, policy)
+                    .await
+                    .map(postprocess::<P>)
+            }),
+        ))
+    }
+}
+
+/// Run `response` in a [`crate::telemetry`] span for `template` on `model`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn traced<F, T>(
+    template: &'static str,
+    model: &Model,
+    response: F,
+) -> impl Future<Output = F::Output>
+where
+    F: Future<Output = Result<GenericChatCompletionResponse<T>>>,
+{
+    #[cfg(feature = "tracing")]
+    let response = crate::telemetry::trace_response(
+        crate::telemetry::request_span("chat", model, Some(template)),
+        response,
+    );
+    response
+}
+
+/// Record the latency of a successful execution of template `P`.
+async fn timed<P, F, T>(estimator: Arc<LatencyEstimator>, response: F) -> Result<T>
+where
+    P: PromptTemplate,
+    F: Future<Output = Result<T>>,
+{
+    record_latency(
+        estimator,
+        LatencyKey::new(&P::MODEL, P::params().reasoning_effort),
+        response,
+    )
+    .await
+}
+
+async fn record_latency<F, T>(
+    estimator: Arc<LatencyEstimator>,
+    key: LatencyKey,
+    response: F,
+) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let started = Instant::now();
+    let result = response.await;
+    if result.is_ok() {
+        estimator.record(key, started.elapsed());
+    }
+    result
+}
+
+/// Apply [`PromptTemplate::postprocess`] to a finished answer and record
+/// which template produced it.
+fn postprocess<P: PromptTemplate>(
+    response: GenericChatCompletionResponse<P::Output>,
+) -> GenericChatCompletionResponse<P::Output> {
+    GenericChatCompletionResponse {
+        content: match response.content {
+            ResponseContent::Finished(output) => ResponseContent::Finished(P::postprocess(output)),
+            tool_calls => tool_calls,
+        },
+        usage: response.usage,
+        metadata: ResponseMetadata {
+            template: Some(P::name().to_owned()),
+            template_version: P::version().map(str::to_owned),
+            ..response.metadata
+        },
+    }
+}
+
+impl<B> PromptStreamingProvider for ArtificialClient<B>
+where
+    B: PromptStreamingProvider,
+    GenericMessage: Into<B::Message>,
+{
+    fn prompt_execute_stream<'a, 'p, P>(&'a self, prompt: P) -> BoxedPromptStream<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        P::Output: Send,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        let stream = Postprocessed::<P, _> {
+            inner: self
+                .backend
+                .prompt_execute_stream(Rendered::<P, B::Message>::new(self, &self.context, prompt)),
+            template: PhantomData,
+        };
+        #[cfg(feature = "tracing")]
+        let stream = crate::telemetry::trace_stream(
+            crate::telemetry::request_span("chat", &P::MODEL, Some(P::name())),
+            stream,
+        );
+        Box::pin(stream)
+    }
+}
+
+/// `messages` without their leading system messages if `is_system` is set.
+fn without_system<M: 'static>(
+    is_system: Option<IsSystem>,
+    messages: impl Iterator<Item = M>,
+) -> impl Iterator<Item = M> {
+    messages.skip_while(move |message| is_system.is_some_and(|is_system| is_system(message)))
+}
+
+/// Applies [`PromptTemplate::postprocess`] to the final event of a stream.
+struct Postprocessed<P, S> {
+    inner: S,
+    template: PhantomData<fn() -> P>,
+}
+
+impl<P, S> Stream for Postprocessed<P, S>
+where
+    P: PromptTemplate,
+    S: Stream<Item = Result<PromptStreamEvent<P::Output>>> + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx).map(|item| {
+            item.map(|event| match event {
+                Ok(PromptStreamEvent::Finished(output)) => {
+                    Ok(PromptStreamEvent::Finished(P::postprocess(output)))
+                }
+                other => other,
+            })
+        })
     }
 }
 
@@ -101,7 +803,32 @@ impl<B: ChatCompletionProvider> ChatCompletionProvider for ArtificialClient<B> {
     where
         M: Into<Self::Message> + Clone + Send + Sync + 's,
     {
-        self.backend.chat_complete(params)
+        let key = LatencyKey::new(&params.model, params.reasoning_effort);
+        #[cfg(feature = "tracing")]
+        let span = crate::telemetry::request_span("chat", &params.model, None);
+        let response = record_latency(
+            Arc::clone(&self.latency),
+            key,
+            self.backend.chat_complete(params),
+        );
+        #[cfg(feature = "tracing")]
+        let response = crate::telemetry::trace_response(span, response);
+        Box::pin(response)
+    }
+}
+
+impl<B: MultiChoiceProvider> MultiChoiceProvider for ArtificialClient<B> {
+    type Message = B::Message;
+
+    fn chat_complete_n<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+        n: u32,
+    ) -> Pin<Box<dyn Future<Output = Result<MultiChoiceResponse>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        self.backend.chat_complete_n(params, n)
     }
 }
 
@@ -146,3 +873,330 @@ impl<B: TranscriptionProvider> TranscriptionProvider for ArtificialClient<B> {
         self.backend.transcribe(request)
     }
 }
+
+impl<B: EmbeddingProvider> EmbeddingProvider for ArtificialClient<B> {
+    fn embed<'s>(
+        &'s self,
+        texts: Vec<String>,
+        model: Model,
+    ) -> Pin<Box<dyn Future<Output = Result<EmbeddingResponse>> + Send + 's>> {
+        self.backend.embed(texts, model)
+    }
+}
+
+impl<B: ModerationProvider> ModerationProvider for ArtificialClient<B> {
+    fn moderate<'s>(
+        &'s self,
+        inputs: Vec<String>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<ModerationResult>>> + Send + 's>> {
+        self.backend.moderate(inputs)
+    }
+}
+
+impl<B: PromptWarmingProvider> PromptWarmingProvider for ArtificialClient<B> {
+    type Message = B::Message;
+
+    fn warm_prefix<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<GenericUsageReport>>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        self.backend.warm_prefix(params)
+    }
+}
+
+impl<B: HealthCheckProvider> HealthCheckProvider for ArtificialClient<B> {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.backend.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{model::OpenAiModel, provider::BoxedResponseFut, template::RenderPrompt};
+
+    /// Records the rendered prompt and answers `null`.
+    #[derive(Default)]
+    struct Recording(Mutex<Vec<GenericMessage>>);
+
+    impl PromptExecutionProvider for Recording {
+        type Message = GenericMessage;
+
+        fn prompt_execute<'a, 'p, P>(&'a self, prompt: P) -> BoxedResponseFut<'p, P::Output>
+        where
+            'a: 'p,
+            P: PromptTemplate + Send + Sync + 'p,
+            <P as IntoPrompt>::Message: Into<Self::Message>,
+        {
+            *self.0.lock().unwrap() = prompt.into_prompt().into_iter().map(Into::into).collect();
+            Box::pin(async move {
+                Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(serde_json::from_value(
+                        serde_json::Value::Null,
+                    )?),
+                    usage: None,
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    struct Greet;
+
+    impl IntoPrompt for Greet {
+        type Message = GenericMessage;
+        fn into_prompt(self) -> Vec<Self::Message> {
+            vec![
+                GenericMessage::new("You greet people.".into(), GenericRole::System),
+                GenericMessage::new("Hi!".into(), GenericRole::User),
+            ]
+        }
+    }
+
+    impl PromptTemplate for Greet {
+        type Output = Option<String>;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    }
+
+    #[tokio::test]
+    async fn preamble_can_replace_the_template_system_prompt() {
+        let client = ArtificialClient::new(Recording::default())
+            .with_system_preamble("Be safe.")
+            .with_preamble_mode(PreambleMode::ReplaceSystem);
+        assert_eq!(client.preamble_mode(), PreambleMode::ReplaceSystem);
+
+        client.prompt_execute(Greet).await.unwrap();
+
+        let sent = client.backend().0.lock().unwrap().clone();
+        let contents: Vec<_> = sent.iter().map(|m| m.content.as_deref().unwrap()).collect();
+        assert_eq!(contents, ["Be safe.", "Hi!"]);
+    }
+
+    #[tokio::test]
+    async fn preamble_precedes_the_template() {
+        let client = ArtificialClient::new(Recording::default())
+            .with_system_preamble("Be safe.")
+            .with_system_preamble("Be brief.");
+
+        client.prompt_execute(Greet).await.unwrap();
+
+        let sent = client.backend().0.lock().unwrap().clone();
+        let contents: Vec<_> = sent.iter().map(|m| m.content.as_deref().unwrap()).collect();
+        assert_eq!(
+            contents,
+            ["Be safe.", "Be brief.", "You greet people.", "Hi!"]
+        );
+    }
+
+    struct Localized;
+
+    impl IntoPrompt for Localized {
+        type Message = GenericMessage;
+        fn into_prompt(self) -> Vec<Self::Message> {
+            let locale =
+                ExecutionContext::with_current(|ctx| ctx.value("locale").map(str::to_owned));
+            vec![GenericMessage::new(
+                locale.unwrap_or_default(),
+                GenericRole::System,
+            )]
+        }
+    }
+
+    impl PromptTemplate for Localized {
+        type Output = Option<String>;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    }
+
+    #[tokio::test]
+    async fn request_context_wins_over_client_context() {
+        let client = ArtificialClient::new(Recording::default())
+            .with_context(ExecutionContext::new().with_value("locale", "en"));
+
+        client.prompt_execute(Localized).await.unwrap();
+        assert_eq!(
+            client.backend().0.lock().unwrap()[0].content.as_deref(),
+            Some("en")
+        );
+
+        let ctx = ExecutionContext::new().with_value("locale", "fr");
+        client
+            .prompt_execute_with_context(Localized, &ctx)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.backend().0.lock().unwrap()[0].content.as_deref(),
+            Some("fr")
+        );
+    }
+
+    /// Not `Clone`, so it renders by hand.
+    struct Topic(String);
+
+    impl IntoPrompt for Topic {
+        type Message = GenericMessage;
+        fn into_prompt(self) -> Vec<Self::Message> {
+            self.render()
+        }
+    }
+
+    impl RenderPrompt for Topic {
+        type Message = GenericMessage;
+        fn render(&self) -> Vec<Self::Message> {
+            vec![GenericMessage::new(self.0.clone(), GenericRole::User)]
+        }
+    }
+
+    impl PromptTemplate for Topic {
+        type Output = Option<String>;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    }
+
+    #[tokio::test]
+    async fn borrowed_templates_can_be_executed_repeatedly() {
+        let client = ArtificialClient::new(Recording::default());
+        let topic = Topic("rust".into());
+
+        for _ in 0..2 {
+            client.backend().0.lock().unwrap().clear();
+            client.prompt_execute(&topic).await.unwrap();
+            assert_eq!(
+                client.backend().0.lock().unwrap()[0].content.as_deref(),
+                Some("rust")
+            );
+        }
+        assert_eq!(topic.0, "rust");
+    }
+
+    #[derive(Debug, serde::Deserialize, JsonSchema)]
+    struct Invoice {
+        number: String,
+    }
+
+    #[tokio::test]
+    async fn extract_sends_the_schema_and_parses_the_answer() {
+        let mock = crate::testing::MockProvider::new();
+        mock.push_text(r#"{"number":"2024-17"}"#)
+            .push_text(r#"{"number":"2024-18"}"#);
+        let client = ArtificialClient::new(mock.clone()).with_system_preamble("Be exact.");
+
+        let invoice: Invoice = client
+            .extract(
+                "Extract the invoice number.",
+                "Invoice #2024-17",
+                Model::OpenAi(OpenAiModel::Gpt4oMini),
+            )
+            .await
+            .unwrap();
+        assert_eq!(invoice.number, "2024-17");
+        let request = mock.last_request().unwrap();
+        let roles: Vec<_> = request.messages.iter().map(|m| m.role).collect();
+        assert_eq!(
+            roles,
+            [GenericRole::System, GenericRole::System, GenericRole::User]
+        );
+        assert_eq!(
+            request.response_format.unwrap()["json_schema"]["name"],
+            "Invoice"
+        );
+
+        // JSON-mode models get the schema as instructions instead.
+        let _: Invoice = client
+            .extract(
+                "Extract the invoice number.",
+                "Invoice #2024-18",
+                Model::DeepSeek(crate::model::DeepSeekModel::DeepSeekChat),
+            )
+            .await
+            .unwrap();
+        let request = mock.last_request().unwrap();
+        assert_eq!(request.messages.len(), 4);
+        assert_eq!(request.response_format.unwrap()["type"], "json_object");
+    }
+
+    #[tokio::test]
+    async fn extract_dynamic_uses_the_runtime_schema() {
+        let mock = crate::testing::MockProvider::new();
+        mock.push_text(r#"{"city":"Vienna"}"#)
+            .push_text(r#"{"town":"Vienna"}"#);
+        let client = ArtificialClient::new(mock.clone());
+        let output = DynamicOutput::new(
+            "location",
+            serde_json::json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"],
+                "additionalProperties": false
+            }),
+        );
+        let model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+
+        let value = client
+            .extract_dynamic("Where?", "I live in Vienna.", &output, model.clone())
+            .await
+            .unwrap();
+        assert_eq!(value["city"], "Vienna");
+        let format = mock.last_request().unwrap().response_format.unwrap();
+        assert_eq!(format["json_schema"]["schema"], *output.schema());
+
+        let missing = client
+            .extract_dynamic("Where?", "I live in Vienna.", &output, model)
+            .await;
+        assert!(matches!(missing, Err(ArtificialError::Invalid(_))));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Sentiment {
+        Positive,
+        Negative,
+    }
+
+    impl VariantsEnum for Sentiment {
+        const VARIANTS: &'static [Self] = &[Self::Positive, Self::Negative];
+
+        fn label(&self) -> &'static str {
+            match self {
+                Self::Positive => "positive",
+                Self::Negative => "negative",
+            }
+        }
+
+        fn description(&self) -> Option<&'static str> {
+            matches!(self, Self::Negative).then_some("complaints and bug reports")
+        }
+    }
+
+    #[tokio::test]
+    async fn classify_constrains_the_answer_to_the_labels() {
+        let mock = crate::testing::MockProvider::new();
+        mock.push_text(r#"{"label":"negative"}"#)
+            .push_text(r#"{"label":"angry"}"#);
+        let client = ArtificialClient::new(mock.clone());
+        let model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+
+        let sentiment: Sentiment = client
+            .classify("The update broke everything.", model.clone())
+            .await
+            .unwrap();
+        assert_eq!(sentiment, Sentiment::Negative);
+
+        let request = mock.last_request().unwrap();
+        let schema = &request.response_format.unwrap()["json_schema"]["schema"];
+        assert_eq!(
+            schema["properties"]["label"]["enum"],
+            serde_json::json!(["positive", "negative"])
+        );
+        assert!(request.messages[0]
+            .content
+            .as_deref()
+            .unwrap()
+            .contains("- negative: complaints and bug reports"));
+
+        let err = client.classify::<Sentiment>("grr", model).await;
+        assert!(matches!(err, Err(ArtificialError::OutputParse { .. })));
+    }
+}
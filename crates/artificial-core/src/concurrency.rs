@@ -0,0 +1,368 @@
+//! Self-tuning concurrency limit for bulk workloads.
+//!
+//! Picking a fixed number of parallel requests is guesswork: too low wastes
+//! throughput, too high runs into `429`s. [`AdaptiveConcurrency`] finds the
+//! limit at runtime with AIMD (additive increase, multiplicative decrease),
+//! the scheme TCP uses for its congestion window:
+//!
+//! * every successful call raises the limit by `1 / limit`, i.e. by one per
+//!   round of calls,
+//! * a rate-limit error, a call slower than the latency target or a hint of
+//!   little remaining headroom multiplies it by the backoff factor.
+//!
+//! Wrap the backend with [`ConcurrencyLimited`] (or add the controller as a
+//! [`crate::layer::ProviderLayer`]) and fan out freely; excess calls wait for
+//! a permit:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use artificial_core::concurrency::{AdaptiveConcurrency, ConcurrencyLimited};
+//! # fn wrap<B>(backend: B) {
+//! let limiter = AdaptiveConcurrency::new(4)
+//!     .with_bounds(1, 64)
+//!     .with_latency_target(Duration::from_secs(20));
+//! let provider = ConcurrencyLimited::new(backend, limiter.clone());
+//!
+//! // … `futures::stream::iter(jobs).buffer_unordered(64)` through
+//! // `ArtificialClient::new(provider)` …
+//!
+//! println!("settled at {} parallel requests", limiter.limit());
+//! # }
+//! ```
+//!
+//! Which errors mean "overloaded" is provider specific; pass a check such as
+//! `artificial_openai::error::is_rate_limited` to
+//! [`ConcurrencyLimited::with_overload_check`]. Rate-limit headers can feed
+//! in ahead of any error through [`AdaptiveConcurrency::hint_headroom`].
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{GenericChatCompletionResponse, GenericMessage},
+    layer::ProviderLayer,
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+    },
+};
+
+/// Headroom below which [`AdaptiveConcurrency::hint_headroom`] backs off.
+const LOW_HEADROOM: f32 = 0.1;
+
+/// How a call that held a [`Permit`] went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Completed; counts towards an increase unless it was too slow.
+    Success,
+    /// The provider pushed back (rate limit, overload); triggers a decrease.
+    Overloaded,
+    /// Failed for an unrelated reason; leaves the limit alone.
+    Failed,
+}
+
+/// AIMD concurrency limit, shared by all clones.
+#[derive(Debug, Clone)]
+pub struct AdaptiveConcurrency {
+    state: Arc<Mutex<LimiterState>>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    limit: f64,
+    min: usize,
+    max: usize,
+    backoff: f64,
+    latency_target: Option<Duration>,
+    in_flight: usize,
+    /// Bumped on every decrease, so one burst of failures from calls started
+    /// under the same limit only backs off once.
+    epoch: u64,
+    waiters: Vec<Waker>,
+}
+
+impl AdaptiveConcurrency {
+    /// Start at `initial` parallel calls, between 1 and 256, halving on
+    /// overload.
+    pub fn new(initial: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LimiterState {
+                limit: initial.max(1) as f64,
+                min: 1,
+                max: 256,
+                backoff: 0.5,
+                latency_target: None,
+                in_flight: 0,
+                epoch: 0,
+                waiters: Vec::new(),
+            })),
+        }
+    }
+
+    /// Keep the limit within `min..=max`.
+    pub fn with_bounds(self, min: usize, max: usize) -> Self {
+        {
+            let mut state = self.state();
+            state.min = min.max(1);
+            state.max = max.max(state.min);
+            state.limit = state.limit.clamp(state.min as f64, state.max as f64);
+        }
+        self
+    }
+
+    /// Factor applied on overload, clamped to `0.1..=0.95`. Default `0.5`.
+    pub fn with_backoff(self, factor: f64) -> Self {
+        self.state().backoff = factor.clamp(0.1, 0.95);
+        self
+    }
+
+    /// Treat successful calls slower than `target` as overload, for
+    /// providers that queue instead of rejecting.
+    pub fn with_latency_target(self, target: Duration) -> Self {
+        self.state().latency_target = Some(target);
+        self
+    }
+
+    /// Calls allowed in parallel right now.
+    pub fn limit(&self) -> usize {
+        self.state().limit as usize
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.state().in_flight
+    }
+
+    /// Wait until a call may start.
+    pub fn acquire(&self) -> Acquire<'_> {
+        Acquire { limiter: self }
+    }
+
+    /// Feed in the remaining share of a provider quota (`0.0..=1.0`, e.g.
+    /// from response headers); little headroom backs off before the
+    /// provider starts rejecting.
+    pub fn hint_headroom(&self, headroom: Option<f32>) {
+        if headroom.is_some_and(|h| h < LOW_HEADROOM) {
+            let epoch = self.state().epoch;
+            self.record(epoch, Outcome::Overloaded);
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, LimiterState> {
+        self.state.lock().expect("concurrency limiter poisoned")
+    }
+
+    fn record(&self, epoch: u64, outcome: Outcome) {
+        let mut state = self.state();
+        match outcome {
+            Outcome::Success => {
+                let limit = state.limit + 1.0 / state.limit;
+                state.limit = limit.min(state.max as f64);
+            }
+            Outcome::Overloaded if epoch == state.epoch => {
+                let limit = state.limit * state.backoff;
+                state.limit = limit.max(state.min as f64);
+                state.epoch += 1;
+            }
+            Outcome::Overloaded | Outcome::Failed => {}
+        }
+        wake_all(&mut state);
+    }
+}
+
+impl<B> ProviderLayer<B> for AdaptiveConcurrency {
+    type Provider = ConcurrencyLimited<B>;
+
+    fn layer(&self, inner: B) -> ConcurrencyLimited<B> {
+        ConcurrencyLimited::new(inner, self.clone())
+    }
+}
+
+fn wake_all(state: &mut LimiterState) {
+    for waker in state.waiters.drain(..) {
+        waker.wake();
+    }
+}
+
+/// Future of [`AdaptiveConcurrency::acquire`].
+pub struct Acquire<'a> {
+    limiter: &'a AdaptiveConcurrency,
+}
+
+impl Future for Acquire<'_> {
+    type Output = Permit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Permit> {
+        let mut state = self.limiter.state();
+        if state.in_flight < state.limit as usize {
+            state.in_flight += 1;
+            return Poll::Ready(Permit {
+                limiter: self.limiter.clone(),
+                epoch: state.epoch,
+                started: Instant::now(),
+                outcome: None,
+            });
+        }
+        state.waiters.push(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// A slot for one call. Report how it went with [`Permit::finish`];
+/// dropping it unreported counts as [`Outcome::Failed`].
+pub struct Permit {
+    limiter: AdaptiveConcurrency,
+    epoch: u64,
+    started: Instant,
+    outcome: Option<Outcome>,
+}
+
+impl Permit {
+    pub fn finish(mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut outcome = self.outcome.unwrap_or(Outcome::Failed);
+        {
+            let mut state = self.limiter.state();
+            state.in_flight -= 1;
+            if outcome == Outcome::Success
+                && state
+                    .latency_target
+                    .is_some_and(|target| self.started.elapsed() > target)
+            {
+                outcome = Outcome::Overloaded;
+            }
+        }
+        self.limiter.record(self.epoch, outcome);
+    }
+}
+
+/// [`ChatCompletionProvider`] wrapper that runs every call under an
+/// [`AdaptiveConcurrency`] permit.
+pub struct ConcurrencyLimited<B> {
+    inner: B,
+    limiter: AdaptiveConcurrency,
+    is_overload: fn(&ArtificialError) -> bool,
+}
+
+impl<B> ConcurrencyLimited<B> {
+    /// Without an overload check only the latency target can back off.
+    pub fn new(inner: B, limiter: AdaptiveConcurrency) -> Self {
+        Self {
+            inner,
+            limiter,
+            is_overload: |_| false,
+        }
+    }
+
+    /// Decide which errors mean the provider is overloaded.
+    pub fn with_overload_check(mut self, is_overload: fn(&ArtificialError) -> bool) -> Self {
+        self.is_overload = is_overload;
+        self
+    }
+
+    pub fn limiter(&self) -> &AdaptiveConcurrency {
+        &self.limiter
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B> ChatCompletionProvider for ConcurrencyLimited<B>
+where
+    B: ChatCompletionProvider,
+{
+    type Message = B::Message;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        Box::pin(async move {
+            let permit = self.limiter.acquire().await;
+            let result = self.inner.chat_complete(params).await;
+            permit.finish(match &result {
+                Ok(_) => Outcome::Success,
+                Err(err) if (self.is_overload)(err.root()) => Outcome::Overloaded,
+                Err(_) => Outcome::Failed,
+            });
+            result
+        })
+    }
+}
+
+impl<B> HealthCheckProvider for ConcurrencyLimited<B>
+where
+    B: HealthCheckProvider,
+{
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic::GenericRole,
+        model::{Model, OpenAiModel},
+        testing::MockProvider,
+    };
+
+    #[tokio::test]
+    async fn increases_additively_and_backs_off_once_per_burst() {
+        let limiter = AdaptiveConcurrency::new(4).with_bounds(2, 8);
+
+        for _ in 0..4 {
+            limiter.acquire().await.finish(Outcome::Success);
+        }
+        assert_eq!(limiter.limit(), 4);
+        limiter.acquire().await.finish(Outcome::Success);
+        assert_eq!(limiter.limit(), 5);
+
+        // Two failures of calls started under the same limit halve it once.
+        let (a, b) = (limiter.acquire().await, limiter.acquire().await);
+        a.finish(Outcome::Overloaded);
+        b.finish(Outcome::Overloaded);
+        assert_eq!(limiter.limit(), 2);
+
+        limiter.hint_headroom(Some(0.05));
+        assert_eq!(limiter.limit(), 2, "never below the lower bound");
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_permit_and_classifies_errors() {
+        let limiter = AdaptiveConcurrency::new(1);
+        let held = limiter.acquire().await;
+        let mut waiting = Box::pin(limiter.acquire());
+        assert!(futures_util::poll!(&mut waiting).is_pending());
+        drop(held);
+        drop(waiting.await);
+
+        let mock = MockProvider::new();
+        mock.push_error(ArtificialError::Other("429".into()));
+        let provider = ConcurrencyLimited::new(mock, AdaptiveConcurrency::new(4))
+            .with_overload_check(|err| matches!(err, ArtificialError::Other(m) if m == "429"));
+        let params = ChatCompleteParameters::new(
+            vec![GenericMessage::new("hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        );
+        assert!(provider.chat_complete(params).await.is_err());
+        assert_eq!(provider.limiter().limit(), 2);
+    }
+}
@@ -0,0 +1,161 @@
+//! Runtime values that fragments can read while a prompt is rendered.
+//!
+//! Some fragments depend on *who* is asking rather than on *what* is asked:
+//! the current user, their locale, enabled feature flags.  Threading those
+//! values through every fragment constructor gets old quickly, so an
+//! [`ExecutionContext`] is made *current* while [`crate::ArtificialClient`]
+//! renders a template and fragments look it up with
+//! [`ExecutionContext::with_current`]:
+//!
+//! ```rust
+//! use artificial_core::context::ExecutionContext;
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_core::template::IntoPrompt;
+//!
+//! struct Greeting;
+//!
+//! impl IntoPrompt for Greeting {
+//!     type Message = GenericMessage;
+//!     fn into_prompt(self) -> Vec<GenericMessage> {
+//!         let locale = ExecutionContext::with_current(|ctx| {
+//!             ctx.value("locale").unwrap_or("en").to_owned()
+//!         });
+//!         vec![GenericMessage::new(format!("Answer in locale {locale}."), GenericRole::System)]
+//!     }
+//! }
+//!
+//! let ctx = ExecutionContext::new().with_value("locale", "de-AT");
+//! let messages = ctx.scope(|| Greeting.into_prompt());
+//! assert_eq!(messages[0].content.as_deref(), Some("Answer in locale de-AT."));
+//! ```
+//!
+//! Besides string key-values the context carries *extensions*: at most one
+//! value per Rust type, for anything richer than a string.
+//!
+//! The context is only current during the synchronous call to
+//! [`crate::template::IntoPrompt::into_prompt`]; fragments must not stash it
+//! for later.
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+/// Key-values and typed extensions available to fragments during rendering.
+///
+/// Cloning is cheap; extensions are shared.
+#[derive(Clone, Default)]
+pub struct ExecutionContext {
+    values: HashMap<String, String>,
+    extensions: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<ExecutionContext>> = const { RefCell::new(None) };
+}
+
+impl ExecutionContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a string value, replacing an earlier one under the same key.
+    pub fn with_value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    /// Attach a typed value, replacing an earlier one of the same type.
+    pub fn with_extension<T: Any + Send + Sync>(mut self, extension: T) -> Self {
+        self.extensions
+            .insert(TypeId::of::<T>(), Arc::new(extension));
+        self
+    }
+
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn extension<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.extensions
+            .get(&TypeId::of::<T>())
+            .and_then(|extension| extension.downcast_ref())
+    }
+
+    /// `true` if neither values nor extensions are set.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.extensions.is_empty()
+    }
+
+    /// Copy everything from `other` into `self`; entries of `other` win.
+    pub fn extend(&mut self, other: &ExecutionContext) {
+        self.values
+            .extend(other.values.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.extensions
+            .extend(other.extensions.iter().map(|(k, v)| (*k, Arc::clone(v))));
+    }
+
+    /// Run `f` with `self` as the current context, e.g. to render a prompt
+    /// outside of the client. Scopes nest; the previous context is restored
+    /// afterwards, also on panic.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<ExecutionContext>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(self.clone()))));
+        f()
+    }
+
+    /// Call `f` with the current context, or an empty one outside of
+    /// [`Self::scope`].
+    pub fn with_current<R>(f: impl FnOnce(&ExecutionContext) -> R) -> R {
+        let current = CURRENT.with(|current| current.borrow().clone());
+        f(&current.unwrap_or_default())
+    }
+}
+
+impl fmt::Debug for ExecutionContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutionContext")
+            .field("values", &self.values)
+            .field("extensions", &self.extensions.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct UserId(u64);
+
+    #[test]
+    fn scopes_nest_and_restore() {
+        let outer = ExecutionContext::new()
+            .with_value("locale", "en")
+            .with_extension(UserId(1));
+        let inner = ExecutionContext::new().with_value("locale", "fr");
+
+        outer.scope(|| {
+            inner.scope(|| {
+                ExecutionContext::with_current(|ctx| {
+                    assert_eq!(ctx.value("locale"), Some("fr"));
+                    assert_eq!(ctx.extension::<UserId>(), None);
+                })
+            });
+            ExecutionContext::with_current(|ctx| {
+                assert_eq!(ctx.value("locale"), Some("en"));
+                assert_eq!(ctx.extension::<UserId>(), Some(&UserId(1)));
+            });
+        });
+        ExecutionContext::with_current(|ctx| assert!(ctx.is_empty()));
+    }
+}
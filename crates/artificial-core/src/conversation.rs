@@ -0,0 +1,426 @@
+//! Long-running chats without hand-managed message vectors.
+//!
+//! A [`Conversation`] owns the message history of one chat.  Every
+//! [`Conversation::send`] trims the history with the configured
+//! [`TrimStrategy`], sends it to the backend and appends the assistant's
+//! answer — plain text or tool calls — so the caller only adds user input and
+//! tool results:
+//!
+//! ```rust,no_run
+//! use artificial_core::conversation::{Conversation, SlidingWindow};
+//! use artificial_core::generic::ResponseContent;
+//! use artificial_core::model::{Model, OpenAiModel};
+//! # use artificial_core::{generic::GenericMessage, provider::ChatCompletionProvider};
+//! # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(backend: B) -> artificial_core::error::Result<()> {
+//! let mut chat = Conversation::new(Model::OpenAi(OpenAiModel::Gpt4oMini))
+//!     .with_system("You are a terse assistant.")
+//!     .with_trim(SlidingWindow::new(20));
+//!
+//! chat.user("What is the capital of Italy?");
+//! if let ResponseContent::Finished(answer) = chat.send(&backend).await?.content {
+//!     println!("{}", answer.content.unwrap_or_default());
+//! }
+//! # Ok(()) }
+//! ```
+//!
+//! Trimming never drops the leading system messages and never separates a
+//! tool result from the assistant message that requested it.
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    error::Result,
+    generic::{
+        GenericChatCompletionResponse, GenericFunctionSpec, GenericMessage, GenericRole,
+        ResponseContent, StoredConversation,
+    },
+    model::Model,
+    provider::{ChatCompleteParameters, ChatCompletionProvider, Temperature},
+    tokens::{counter_for, TokenCounter},
+};
+
+/// `name` of the system message [`SummarizeOldest`] inserts.
+pub const SUMMARY_NAME: &str = "conversation_summary";
+
+pub type TrimFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<GenericMessage>>> + Send + 'a>>;
+
+/// Shrinks a history before it is sent.
+pub trait TrimStrategy: Send + Sync {
+    /// Return the messages to keep. Implementations should use
+    /// [`pinned_len`] and [`safe_cut`] to respect system messages and tool
+    /// call pairs.
+    fn trim<'a>(&'a self, messages: Vec<GenericMessage>) -> TrimFuture<'a>;
+}
+
+/// Keeps the whole history.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepAll;
+
+impl TrimStrategy for KeepAll {
+    fn trim<'a>(&'a self, messages: Vec<GenericMessage>) -> TrimFuture<'a> {
+        Box::pin(async move { Ok(messages) })
+    }
+}
+
+/// Keeps the system prompt plus the most recent `max_messages` messages.
+#[derive(Debug, Clone, Copy)]
+pub struct SlidingWindow {
+    max_messages: usize,
+}
+
+impl SlidingWindow {
+    pub fn new(max_messages: usize) -> Self {
+        Self { max_messages }
+    }
+}
+
+impl TrimStrategy for SlidingWindow {
+    fn trim<'a>(&'a self, mut messages: Vec<GenericMessage>) -> TrimFuture<'a> {
+        Box::pin(async move {
+            let pinned = pinned_len(&messages);
+            let recent = messages.len() - pinned;
+            if recent > self.max_messages {
+                let cut = safe_cut(&messages, pinned + recent - self.max_messages);
+                messages.drain(pinned..cut);
+            }
+            Ok(messages)
+        })
+    }
+}
+
+/// Drops the oldest messages until the history fits into `max_tokens`.
+///
+/// The newest message is always kept, even if it alone exceeds the budget.
+pub struct TokenBudget {
+    max_tokens: usize,
+    counter: Box<dyn TokenCounter>,
+}
+
+impl TokenBudget {
+    /// Count with the most accurate counter available for `model`.
+    pub fn new(max_tokens: usize, model: &Model) -> Self {
+        Self {
+            max_tokens,
+            counter: counter_for(model),
+        }
+    }
+
+    pub fn with_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.counter = Box::new(counter);
+        self
+    }
+}
+
+impl TrimStrategy for TokenBudget {
+    fn trim<'a>(&'a self, mut messages: Vec<GenericMessage>) -> TrimFuture<'a> {
+        Box::pin(async move {
+            let pinned = pinned_len(&messages);
+            while messages.len() > pinned + 1
+                && self.counter.count_messages(&messages) > self.max_tokens
+            {
+                let cut = safe_cut(&messages, pinned + 1).min(messages.len() - 1);
+                messages.drain(pinned..cut.max(pinned + 1));
+            }
+            Ok(messages)
+        })
+    }
+}
+
+/// Once the history exceeds `max_messages`, condenses everything but the
+/// `keep_recent` newest messages into a single system message written by
+/// `model`.
+///
+/// An earlier summary is part of what gets summarised, so the history holds
+/// at most one.
+pub struct SummarizeOldest<B> {
+    backend: B,
+    model: Model,
+    max_messages: usize,
+    keep_recent: usize,
+}
+
+impl<B> SummarizeOldest<B> {
+    pub fn new(backend: B, model: Model, max_messages: usize) -> Self {
+        Self {
+            backend,
+            model,
+            max_messages,
+            keep_recent: max_messages / 2,
+        }
+    }
+
+    /// Messages left verbatim after summarising. Defaults to half of
+    /// `max_messages`.
+    pub fn with_keep_recent(mut self, keep_recent: usize) -> Self {
+        self.keep_recent = keep_recent;
+        self
+    }
+}
+
+impl<B> TrimStrategy for SummarizeOldest<B>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    fn trim<'a>(&'a self, mut messages: Vec<GenericMessage>) -> TrimFuture<'a> {
+        Box::pin(async move {
+            let pinned = pinned_len(&messages);
+            let recent = messages.len() - pinned;
+            if recent <= self.max_messages {
+                return Ok(messages);
+            }
+
+            let cut = safe_cut(&messages, pinned + recent.saturating_sub(self.keep_recent));
+            let summary =
+                summarize_messages(&self.backend, self.model.clone(), &messages[pinned..cut])
+                    .await?;
+
+            messages.splice(
+                pinned..cut,
+                [GenericMessage::new(
+                    format!("Summary of the earlier conversation:\n{summary}"),
+                    GenericRole::System,
+                )
+                .with_name(SUMMARY_NAME)],
+            );
+            Ok(messages)
+        })
+    }
+}
+
+/// Ask `model` for a short summary of `messages`, as [`SummarizeOldest`]
+/// does. An answer with tool calls yields an empty summary.
+pub async fn summarize_messages<B>(
+    backend: &B,
+    model: Model,
+    messages: &[GenericMessage],
+) -> Result<String>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    let transcript = messages
+        .iter()
+        .map(transcript_line)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let request = ChatCompleteParameters::new(
+        vec![
+            GenericMessage::new(
+                "Summarise the following conversation in a few sentences. Keep \
+                 facts, decisions, names and open questions; drop small talk."
+                    .into(),
+                GenericRole::System,
+            ),
+            GenericMessage::new(transcript, GenericRole::User),
+        ],
+        model,
+    );
+    Ok(match backend.chat_complete(request).await?.content {
+        ResponseContent::Finished(message) => message.content.unwrap_or_default(),
+        ResponseContent::ToolCalls(_) => String::new(),
+    })
+}
+
+fn transcript_line(message: &GenericMessage) -> String {
+    let mut line = format!(
+        "{}: {}",
+        message.role,
+        message.content.as_deref().unwrap_or("")
+    );
+    for call in message.tool_calls.iter().flatten() {
+        line.push_str(&format!(
+            " [called {}({})]",
+            call.function.name, call.function.arguments
+        ));
+    }
+    line
+}
+
+/// Number of leading system messages, which trimming keeps. A summary
+/// written by [`SummarizeOldest`] is not pinned.
+pub fn pinned_len(messages: &[GenericMessage]) -> usize {
+    messages
+        .iter()
+        .take_while(|m| m.role == GenericRole::System && m.name.as_deref() != Some(SUMMARY_NAME))
+        .count()
+}
+
+/// Moves a cut index forward past tool results, so no tool message is kept
+/// without the assistant message that issued its call.
+pub fn safe_cut(messages: &[GenericMessage], mut cut: usize) -> usize {
+    while messages
+        .get(cut)
+        .is_some_and(|m| m.role == GenericRole::Tool)
+    {
+        cut += 1;
+    }
+    cut.min(messages.len())
+}
+
+/// Message history of one chat plus the request settings it is sent with.
+pub struct Conversation {
+    model: Model,
+    messages: Vec<GenericMessage>,
+    tools: Option<Vec<GenericFunctionSpec>>,
+    temperature: Option<Temperature>,
+    trim: Box<dyn TrimStrategy>,
+}
+
+impl Conversation {
+    /// An empty conversation that keeps its whole history.
+    pub fn new(model: Model) -> Self {
+        Self {
+            model,
+            messages: Vec::new(),
+            tools: None,
+            temperature: None,
+            trim: Box::new(KeepAll),
+        }
+    }
+
+    pub fn with_system(mut self, text: impl Into<String>) -> Self {
+        let pinned = pinned_len(&self.messages);
+        self.messages.insert(
+            pinned,
+            GenericMessage::new(text.into(), GenericRole::System),
+        );
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<GenericFunctionSpec>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: Temperature) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_trim(mut self, trim: impl TrimStrategy + 'static) -> Self {
+        self.trim = Box::new(trim);
+        self
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn messages(&self) -> &[GenericMessage] {
+        &self.messages
+    }
+
+    pub fn push(&mut self, message: GenericMessage) {
+        self.messages.push(message);
+    }
+
+    /// The history in its persisted, versioned form.
+    pub fn to_stored(&self) -> StoredConversation {
+        StoredConversation::new(self.messages.clone())
+    }
+
+    /// Continue a history loaded with [`StoredConversation::from_json`].
+    pub fn with_history(mut self, stored: StoredConversation) -> Self {
+        self.messages = stored.messages;
+        self
+    }
+
+    /// Append a user turn.
+    pub fn user(&mut self, text: impl Into<String>) {
+        self.push(GenericMessage::new(text.into(), GenericRole::User));
+    }
+
+    /// Append the result of the tool call `tool_call_id`.
+    pub fn tool_result(&mut self, tool_call_id: impl ToString, content: impl Into<String>) {
+        self.push(
+            GenericMessage::new(content.into(), GenericRole::Tool).with_tool_call_id(tool_call_id),
+        );
+    }
+
+    /// Trim the history, send it and append the assistant's answer.
+    ///
+    /// # Errors
+    ///
+    /// Errors of the trim strategy and the backend are returned as is; the
+    /// history is left untrimmed and without an answer in that case.
+    pub async fn send<B>(
+        &mut self,
+        backend: &B,
+    ) -> Result<GenericChatCompletionResponse<GenericMessage>>
+    where
+        B: ChatCompletionProvider,
+        GenericMessage: Into<B::Message>,
+    {
+        let trimmed = self.trim.trim(self.messages.clone()).await?;
+
+        let mut params = ChatCompleteParameters::new(trimmed.clone(), self.model.clone());
+        params.tools = self.tools.clone();
+        params.temperature = self.temperature;
+        let response = backend.chat_complete(params).await?;
+
+        self.messages = trimmed;
+        self.messages.push(match &response.content {
+            ResponseContent::Finished(message) | ResponseContent::ToolCalls(message) => {
+                message.clone()
+            }
+        });
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::{GenericFunctionCall, GenericFunctionCallIntent};
+
+    fn msg(role: GenericRole, text: &str) -> GenericMessage {
+        GenericMessage::new(text.into(), role)
+    }
+
+    fn history() -> Vec<GenericMessage> {
+        vec![
+            msg(GenericRole::System, "sys"),
+            msg(GenericRole::User, "one"),
+            GenericMessage::new_tool_call(
+                "call".into(),
+                vec![GenericFunctionCallIntent {
+                    id: "call".into(),
+                    function: GenericFunctionCall {
+                        name: "lookup".into(),
+                        arguments: serde_json::json!({}),
+                    },
+                }],
+            ),
+            msg(GenericRole::Tool, "result").with_tool_call_id("call"),
+            msg(GenericRole::Assistant, "two"),
+            msg(GenericRole::User, "three"),
+        ]
+    }
+
+    fn contents(messages: &[GenericMessage]) -> Vec<&str> {
+        messages
+            .iter()
+            .map(|m| m.content.as_deref().unwrap_or("<call>"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn trimming_keeps_system_and_tool_pairs() {
+        let window = SlidingWindow::new(3).trim(history()).await.unwrap();
+        // Cutting at the tool result would orphan it, so it goes too.
+        assert_eq!(contents(&window), ["sys", "two", "three"]);
+
+        let window = SlidingWindow::new(4).trim(history()).await.unwrap();
+        assert_eq!(
+            contents(&window),
+            ["sys", "<call>", "result", "two", "three"]
+        );
+
+        let budget = TokenBudget::new(0, &Model::OpenAi(crate::model::OpenAiModel::Gpt4oMini))
+            .trim(history())
+            .await
+            .unwrap();
+        assert_eq!(contents(&budget), ["sys", "three"]);
+    }
+}
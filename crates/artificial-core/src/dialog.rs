@@ -0,0 +1,309 @@
+//! Multi-step structured conversations as a typed state machine.
+//!
+//! Real dialogs rarely fit into one prompt: first classify the intent, then
+//! fill the slots that intent needs, then confirm.  [`DialogFlow`] gives
+//! those flows a skeleton.  Every state declares
+//!
+//! * a **render** function turning the shared context `C` into a
+//!   [`PromptTemplate`], and
+//! * an **apply** function that stores the typed `P::Output` in the context
+//!   and picks the next [`Transition`].
+//!
+//! Because `apply` is plain synchronous Rust, the transition logic can be
+//! unit-tested without a model.  States that need the user's answer before
+//! continuing return [`Transition::AwaitInput`]; [`DialogFlow::run`] then
+//! hands control back and is called again with the same context once the
+//! user replied.
+//!
+//! ```rust,no_run
+//! use artificial_core::dialog::{DialogFlow, DialogOutcome, Transition};
+//! # use artificial_core::{generic::GenericMessage, model::*, template::*};
+//! # use artificial_core::provider::PromptExecutionProvider;
+//! # #[derive(serde::Deserialize, schemars::JsonSchema)] struct Intent { book_flight: bool }
+//! # struct ClassifyIntent(String);
+//! # impl IntoPrompt for ClassifyIntent { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+//! # impl PromptTemplate for ClassifyIntent { type Output = Intent; const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini); }
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+//! enum Step { Intent, Slots }
+//!
+//! #[derive(Default)]
+//! struct Booking { user_input: String, wants_flight: bool }
+//!
+//! # async fn run<B: PromptExecutionProvider<Message = GenericMessage>>(backend: B) -> artificial_core::error::Result<()> {
+//! let flow = DialogFlow::<B, Step, Booking>::new()
+//!     .state(
+//!         Step::Intent,
+//!         |ctx: &Booking| ClassifyIntent(ctx.user_input.clone()),
+//!         |ctx: &mut Booking, intent: Intent| {
+//!             ctx.wants_flight = intent.book_flight;
+//!             if intent.book_flight { Transition::Goto(Step::Slots) } else { Transition::Finish }
+//!         },
+//!     );
+//!
+//! let mut ctx = Booking { user_input: "Fly me to Rome".into(), ..Default::default() };
+//! match flow.run(&backend, Step::Intent, &mut ctx).await? {
+//!     DialogOutcome::Finished { .. } => {}
+//!     DialogOutcome::AwaitingInput { resume_at, .. } => { /* ask the user, then run(resume_at) */ }
+//! }
+//! # Ok(()) }
+//! ```
+use std::{collections::HashMap, fmt::Debug, future::Future, hash::Hash, pin::Pin, sync::Arc};
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::ResponseContent,
+    provider::PromptExecutionProvider,
+    template::{IntoPrompt, PromptTemplate},
+};
+
+/// What happens after a state's output has been applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition<S> {
+    /// Continue immediately with another state.
+    Goto(S),
+    /// Stop and wait for user input; resume at the given state.
+    AwaitInput(S),
+    /// The dialog is complete.
+    Finish,
+}
+
+/// Result of [`DialogFlow::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialogOutcome<S> {
+    /// A state returned [`Transition::Finish`].
+    Finished { path: Vec<S> },
+    /// A state returned [`Transition::AwaitInput`].
+    AwaitingInput { resume_at: S, path: Vec<S> },
+}
+
+impl<S> DialogOutcome<S> {
+    /// States executed during this run, in order.
+    pub fn path(&self) -> &[S] {
+        match self {
+            DialogOutcome::Finished { path } | DialogOutcome::AwaitingInput { path, .. } => path,
+        }
+    }
+}
+
+type StepFuture<'a, S> = Pin<Box<dyn Future<Output = Result<Transition<S>>> + Send + 'a>>;
+type Step<B, S, C> = Box<dyn for<'a> Fn(&'a B, &'a mut C) -> StepFuture<'a, S> + Send + Sync>;
+
+/// A set of states, each backed by its own prompt template and typed output.
+pub struct DialogFlow<B, S, C> {
+    steps: HashMap<S, Step<B, S, C>>,
+    max_steps: usize,
+}
+
+impl<B, S, C> Default for DialogFlow<B, S, C>
+where
+    B: PromptExecutionProvider,
+    S: Copy + Eq + Hash + Debug + Send + Sync + 'static,
+    C: Send,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, S, C> DialogFlow<B, S, C>
+where
+    B: PromptExecutionProvider,
+    S: Copy + Eq + Hash + Debug + Send + Sync + 'static,
+    C: Send,
+{
+    pub fn new() -> Self {
+        Self {
+            steps: HashMap::new(),
+            max_steps: 16,
+        }
+    }
+
+    /// Upper bound of states executed by a single [`Self::run`] call, which
+    /// guards against transition cycles. Defaults to 16.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Declare `state`: `render` builds its prompt from the context and
+    /// `apply` folds the typed answer back into it and picks the transition.
+    pub fn state<P, R, A>(mut self, state: S, render: R, apply: A) -> Self
+    where
+        P: PromptTemplate + Send + Sync + 'static,
+        P::Output: Send,
+        <P as IntoPrompt>::Message: Into<B::Message>,
+        R: Fn(&C) -> P + Send + Sync + 'static,
+        A: Fn(&mut C, P::Output) -> Transition<S> + Send + Sync + 'static,
+    {
+        let apply = Arc::new(apply);
+        let step: Step<B, S, C> = Box::new(move |backend, ctx| {
+            let prompt = render(ctx);
+            let apply = Arc::clone(&apply);
+            Box::pin(async move {
+                match backend.prompt_execute(prompt).await?.content {
+                    ResponseContent::Finished(output) => Ok(apply(ctx, output)),
+                    ResponseContent::ToolCalls(_) => Err(ArtificialError::Invalid(format!(
+                        "dialog state {state:?} received tool calls instead of an answer"
+                    ))),
+                }
+            })
+        });
+        self.steps.insert(state, step);
+        self
+    }
+
+    /// Execute a single state and return its transition without following
+    /// it.
+    pub async fn step(&self, backend: &B, state: S, ctx: &mut C) -> Result<Transition<S>> {
+        let step = self.steps.get(&state).ok_or_else(|| {
+            ArtificialError::InvalidRequest(format!("dialog state {state:?} is not declared"))
+        })?;
+        step(backend, ctx).await
+    }
+
+    /// Run from `start` until a state finishes the dialog or waits for user
+    /// input.
+    pub async fn run(&self, backend: &B, start: S, ctx: &mut C) -> Result<DialogOutcome<S>> {
+        let mut path = Vec::new();
+        let mut state = start;
+
+        loop {
+            if path.len() == self.max_steps {
+                return Err(ArtificialError::Other(format!(
+                    "dialog exceeded {} steps without finishing (path: {path:?})",
+                    self.max_steps
+                )));
+            }
+            path.push(state);
+
+            match self.step(backend, state, ctx).await? {
+                Transition::Goto(next) => state = next,
+                Transition::AwaitInput(resume_at) => {
+                    return Ok(DialogOutcome::AwaitingInput { resume_at, path });
+                }
+                Transition::Finish => return Ok(DialogOutcome::Finished { path }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        generic::{GenericChatCompletionResponse, GenericMessage},
+        model::{Model, OpenAiModel},
+        provider::BoxedResponseFut,
+    };
+
+    /// Answers every prompt with the next queued JSON value.
+    struct Scripted(Mutex<Vec<serde_json::Value>>);
+
+    impl PromptExecutionProvider for Scripted {
+        type Message = GenericMessage;
+
+        fn prompt_execute<'a, 'p, P>(&'a self, _prompt: P) -> BoxedResponseFut<'p, P::Output>
+        where
+            'a: 'p,
+            P: PromptTemplate + Send + Sync + 'p,
+            <P as IntoPrompt>::Message: Into<Self::Message>,
+        {
+            let next = self.0.lock().unwrap().remove(0);
+            Box::pin(async move {
+                Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(serde_json::from_value(next)?),
+                    usage: None,
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct Intent {
+        booking: bool,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct Slots {
+        city: Option<String>,
+    }
+
+    struct Ask<T>(std::marker::PhantomData<T>);
+
+    impl<T> IntoPrompt for Ask<T> {
+        type Message = GenericMessage;
+        fn into_prompt(self) -> Vec<Self::Message> {
+            Vec::new()
+        }
+    }
+
+    impl<T: JsonSchema + for<'de> Deserialize<'de> + 'static> PromptTemplate for Ask<T> {
+        type Output = T;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum State {
+        Intent,
+        Slots,
+        Confirm,
+    }
+
+    fn flow() -> DialogFlow<Scripted, State, Option<String>> {
+        DialogFlow::new()
+            .state(
+                State::Intent,
+                |_: &Option<String>| Ask::<Intent>(Default::default()),
+                |_, intent: Intent| match intent.booking {
+                    true => Transition::Goto(State::Slots),
+                    false => Transition::Finish,
+                },
+            )
+            .state(
+                State::Slots,
+                |_: &Option<String>| Ask::<Slots>(Default::default()),
+                |city, slots: Slots| {
+                    *city = slots.city;
+                    match city {
+                        Some(_) => Transition::Goto(State::Confirm),
+                        None => Transition::AwaitInput(State::Slots),
+                    }
+                },
+            )
+    }
+
+    #[tokio::test]
+    async fn follows_transitions_until_input_is_needed() {
+        let backend = Scripted(Mutex::new(vec![
+            serde_json::json!({ "booking": true }),
+            serde_json::json!({ "city": null }),
+            serde_json::json!({ "city": "Rome" }),
+        ]));
+        let flow = flow();
+        let mut city = None;
+
+        let outcome = flow.run(&backend, State::Intent, &mut city).await.unwrap();
+        assert_eq!(
+            outcome,
+            DialogOutcome::AwaitingInput {
+                resume_at: State::Slots,
+                path: vec![State::Intent, State::Slots],
+            }
+        );
+
+        // `Confirm` was never declared.
+        let err = flow
+            .run(&backend, State::Slots, &mut city)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ArtificialError::InvalidRequest(_)));
+        assert_eq!(city.as_deref(), Some("Rome"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Cheap draft, expensive verification.
+//!
+//! Most requests are handled fine by a small model; only some need a large
+//! one.  [`DraftVerify`] runs a *draft* template first and escalates to a
+//! *verify* template only when
+//!
+//! * the draft could not be parsed or failed validation, or
+//! * a caller-supplied heuristic flags the draft (e.g. a low
+//!   `ThinkResult::confidence`).
+//!
+//! The verify template decides the expensive model through its own
+//! [`PromptTemplate::MODEL`] and receives the draft (if any) so it can check
+//! and correct it rather than start from scratch.  Usage of both stages is
+//! reported separately in [`DraftVerifyOutcome`].
+//!
+//! ```rust,no_run
+//! use artificial_core::draft_verify::DraftVerify;
+//! # use artificial_core::{generic::GenericMessage, model::*, template::*};
+//! # use artificial_core::provider::PromptExecutionProvider;
+//! # #[derive(serde::Deserialize, schemars::JsonSchema)] struct Label { label: String, confidence: f32 }
+//! # struct Classify; struct Review(Option<String>);
+//! # impl IntoPrompt for Classify { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+//! # impl PromptTemplate for Classify { type Output = Label; const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini); }
+//! # impl IntoPrompt for Review { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+//! # impl PromptTemplate for Review { type Output = Label; const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4o); }
+//! # async fn run<B: PromptExecutionProvider<Message = GenericMessage>>(backend: B) -> artificial_core::error::Result<()> {
+//! let outcome = DraftVerify::new(&backend)
+//!     .with_trigger(|draft: &Label| draft.confidence < 0.8)
+//!     .run(Classify, |draft| Review(draft.map(|d| d.label.clone())))
+//!     .await?;
+//!
+//! println!("label: {} (escalated: {})", outcome.output.label, outcome.escalation.is_some());
+//! # Ok(()) }
+//! ```
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{GenericUsageReport, ResponseContent},
+    provider::PromptExecutionProvider,
+    template::{IntoPrompt, PromptTemplate},
+};
+
+type Trigger<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// Why the verify stage ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Escalation {
+    /// The trigger heuristic flagged the draft.
+    Triggered,
+    /// The draft answer was empty, could not be parsed or was rejected;
+    /// carries the error message.
+    DraftInvalid(String),
+}
+
+/// Final answer plus per-stage accounting.
+#[derive(Debug)]
+pub struct DraftVerifyOutcome<T> {
+    /// The verified answer if the verify stage ran, otherwise the draft.
+    pub output: T,
+    /// `None` if the draft was accepted as is.
+    pub escalation: Option<Escalation>,
+    pub draft_usage: Option<GenericUsageReport>,
+    pub verify_usage: Option<GenericUsageReport>,
+}
+
+/// Two-stage execution strategy, see the [module docs](self).
+pub struct DraftVerify<'b, B, T> {
+    backend: &'b B,
+    trigger: Trigger<T>,
+}
+
+impl<'b, B, T> DraftVerify<'b, B, T>
+where
+    B: PromptExecutionProvider,
+    T: Send,
+{
+    /// Without a trigger, only invalid drafts are escalated.
+    pub fn new(backend: &'b B) -> Self {
+        Self {
+            backend,
+            trigger: Box::new(|_| false),
+        }
+    }
+
+    /// Escalate drafts for which `trigger` returns `true`.
+    pub fn with_trigger(mut self, trigger: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        self.trigger = Box::new(trigger);
+        self
+    }
+
+    /// Execute `draft`; on escalation build the verify template from the
+    /// draft answer (`None` if it was invalid) and execute that instead.
+    ///
+    /// # Errors
+    ///
+    /// Transport and provider errors of either stage are returned as is;
+    /// only empty drafts and parse/validation failures of the draft lead to
+    /// escalation.
+    pub async fn run<D, V, F>(&self, draft: D, verify: F) -> Result<DraftVerifyOutcome<T>>
+    where
+        D: PromptTemplate<Output = T> + Send + Sync,
+        V: PromptTemplate<Output = T> + Send + Sync,
+        <D as IntoPrompt>::Message: Into<B::Message>,
+        <V as IntoPrompt>::Message: Into<B::Message>,
+        F: FnOnce(Option<&T>) -> V,
+    {
+        let (draft_output, draft_usage, escalation) = match self.backend.prompt_execute(draft).await
+        {
+            Ok(response) => {
+                let output = finished::<D>(response.content)?;
+                let escalation = (self.trigger)(&output).then_some(Escalation::Triggered);
+                (Some(output), response.usage, escalation)
+            }
+            Err(err) if is_invalid_answer(&err) => {
+                (None, None, Some(Escalation::DraftInvalid(err.to_string())))
+            }
+            Err(err) => return Err(err),
+        };
+
+        match (escalation, draft_output) {
+            (None, Some(output)) => Ok(DraftVerifyOutcome {
+                output,
+                escalation: None,
+                draft_usage,
+                verify_usage: None,
+            }),
+            (escalation, draft_output) => {
+                let response = self
+                    .backend
+                    .prompt_execute(verify(draft_output.as_ref()))
+                    .await?;
+                Ok(DraftVerifyOutcome {
+                    output: finished::<V>(response.content)?,
+                    escalation,
+                    draft_usage,
+                    verify_usage: response.usage,
+                })
+            }
+        }
+    }
+}
+
+/// Errors that mean "the model answered, but the answer is unusable".
+fn is_invalid_answer(err: &ArtificialError) -> bool {
+    matches!(
+        err.root(),
+        ArtificialError::Serialization(_)
+            | ArtificialError::OutputParse { .. }
+            | ArtificialError::Invalid(_)
+            | ArtificialError::EmptyResponse
+    )
+}
+
+fn finished<P: PromptTemplate>(content: ResponseContent<P::Output>) -> Result<P::Output> {
+    match content {
+        ResponseContent::Finished(output) => Ok(output),
+        ResponseContent::ToolCalls(_) => Err(ArtificialError::Invalid(format!(
+            "prompt `{}` answered with tool calls",
+            P::name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        generic::{GenericChatCompletionResponse, GenericMessage},
+        model::{Model, OpenAiModel},
+        provider::BoxedResponseFut,
+        testing::MockProvider,
+    };
+
+    /// The small model answers with a low confidence, the large one with a
+    /// high one.
+    struct ByModel;
+
+    impl PromptExecutionProvider for ByModel {
+        type Message = GenericMessage;
+
+        fn prompt_execute<'a, 'p, P>(&'a self, _prompt: P) -> BoxedResponseFut<'p, P::Output>
+        where
+            'a: 'p,
+            P: PromptTemplate + Send + Sync + 'p,
+            <P as IntoPrompt>::Message: Into<Self::Message>,
+        {
+            let (answer, tokens) = match P::MODEL {
+                Model::OpenAi(OpenAiModel::Gpt4oMini) => {
+                    (serde_json::json!({ "confidence": 0.4 }), 10)
+                }
+                _ => (serde_json::json!({ "confidence": 0.9 }), 100),
+            };
+            Box::pin(async move {
+                Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(serde_json::from_value(answer)?),
+                    usage: Some(GenericUsageReport {
+                        prompt_tokens: tokens,
+                        completion_tokens: 0,
+                        total_tokens: tokens,
+                    }),
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct Answer {
+        confidence: f32,
+    }
+
+    struct Draft;
+    struct Verify;
+
+    macro_rules! template {
+        ($name:ident, $model:expr) => {
+            impl IntoPrompt for $name {
+                type Message = GenericMessage;
+                fn into_prompt(self) -> Vec<Self::Message> {
+                    Vec::new()
+                }
+            }
+            impl PromptTemplate for $name {
+                type Output = Answer;
+                const MODEL: Model = $model;
+            }
+        };
+    }
+    template!(Draft, Model::OpenAi(OpenAiModel::Gpt4oMini));
+    template!(Verify, Model::OpenAi(OpenAiModel::Gpt4o));
+
+    #[tokio::test]
+    async fn escalates_only_when_triggered() {
+        let confident = DraftVerify::new(&ByModel)
+            .with_trigger(|a: &Answer| a.confidence < 0.3)
+            .run(Draft, |_| Verify)
+            .await
+            .unwrap();
+        assert!(confident.escalation.is_none());
+        assert!(confident.verify_usage.is_none());
+
+        let unsure = DraftVerify::new(&ByModel)
+            .with_trigger(|a: &Answer| a.confidence < 0.8)
+            .run(Draft, |draft| {
+                assert_eq!(draft.map(|d| d.confidence), Some(0.4));
+                Verify
+            })
+            .await
+            .unwrap();
+        assert_eq!(unsure.escalation, Some(Escalation::Triggered));
+        assert_eq!(unsure.output.confidence, 0.9);
+        assert_eq!(unsure.draft_usage.unwrap().total_tokens, 10);
+        assert_eq!(unsure.verify_usage.unwrap().total_tokens, 100);
+    }
+
+    #[tokio::test]
+    async fn empty_drafts_are_escalated() {
+        let mock = MockProvider::new();
+        mock.push_error(ArtificialError::EmptyResponse)
+            .push_json(serde_json::json!({ "confidence": 0.9 }));
+
+        let outcome = DraftVerify::new(&mock)
+            .run(Draft, |draft| {
+                assert!(draft.is_none());
+                Verify
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            outcome.escalation,
+            Some(Escalation::DraftInvalid(_))
+        ));
+        assert_eq!(outcome.output.confidence, 0.9);
+        assert_eq!(mock.requests().len(), 2);
+    }
+}
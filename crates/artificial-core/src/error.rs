@@ -4,8 +4,13 @@
 //! variants before bubbling them up to the [`ArtificialClient`].  This keeps
 //! the public API small while still conveying rich diagnostic information.
 
+use std::{fmt, sync::Arc};
+
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::redact::RedactedText;
+
 /// Convenient alias used throughout the workspace.
 pub type Result<T> = std::result::Result<T, ArtificialError>;
 
@@ -34,6 +39,56 @@ pub enum ArtificialError {
     #[error("backend returned an error: {0}")]
     Backend(Box<dyn std::error::Error + Send + Sync + 'static>),
 
+    /// A streaming response was cut off by the network after the provider
+    /// had accepted the request.  `bytes_received` tells how far the stream
+    /// got, which lets callers tell an early abort from a late one and decide
+    /// whether resuming or retrying is worthwhile.
+    #[error("stream disconnected after {bytes_received} bytes: {source}")]
+    StreamDisconnected {
+        bytes_received: u64,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+
+    /// The model finished without any content: no text (or only
+    /// whitespace) and no tool calls.  Usually transient; see
+    /// [`crate::provider::ExecutionPolicy::with_retry_on_empty`].
+    #[error("model returned an empty response")]
+    EmptyResponse,
+
+    /// The model requested the same tool with the same arguments more often
+    /// than [`crate::tools::ToolRegistry::with_max_repeated_calls`] allows –
+    /// most likely it is stuck in a loop.
+    #[error("tool loop detected: `{tool}` was called {calls} times with identical arguments")]
+    ToolLoopDetected { tool: String, calls: usize },
+
+    /// A [`crate::moderation::ModeratedProvider`] screened the input and the
+    /// moderation provider flagged it; the request was not sent. Also
+    /// returned when an [`crate::output_filter::OutputFilter`] halts an
+    /// answer.
+    #[error("content violates policy: {}", categories.join(", "))]
+    PolicyViolation { categories: Vec<String> },
+
+    /// The request was aborted through its
+    /// [`crate::cancel::CancellationToken`].
+    #[error("request was cancelled")]
+    Cancelled,
+
+    /// The model answered, but the answer does not deserialize into the
+    /// requested output type.
+    ///
+    /// `source` carries the path to the offending field, e.g.
+    /// `items[2].score`; `raw` is the text that was parsed. The message shows
+    /// schema, path and serde's description of the failure, never the whole
+    /// answer.
+    #[error("output does not match `{schema_name}`: {source}")]
+    OutputParse {
+        raw: RawOutput,
+        #[source]
+        source: serde_path_to_error::Error<serde_json::Error>,
+        schema_name: String,
+    },
+
     #[error("invalid request: {0}")]
     InvalidRequest(String),
 
@@ -42,4 +97,204 @@ pub enum ArtificialError {
 
     #[error("other: {0}")]
     Other(String),
+
+    /// One error handed to several callers, e.g. the duplicates collapsed
+    /// by [`crate::singleflight::SingleFlightProvider`]. [`Self::root`] and
+    /// [`Self::context`] look through it.
+    #[error(transparent)]
+    Shared(Arc<ArtificialError>),
+
+    /// Any of the above, annotated with *which* call failed.
+    ///
+    /// The context only carries identifiers (template name, prompt
+    /// fingerprint, model, attempt) and never message contents, so the error
+    /// can be logged verbatim.
+    #[error("{context}: {source}")]
+    WithContext {
+        context: ErrorContext,
+        #[source]
+        source: Box<ArtificialError>,
+    },
+}
+
+impl ArtificialError {
+    /// Attach call identifiers to the error.
+    ///
+    /// Errors that already carry a context are returned unchanged so the
+    /// innermost (most specific) context wins.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        match self {
+            already @ ArtificialError::WithContext { .. } => already,
+            source => ArtificialError::WithContext {
+                context,
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The call identifiers attached via [`Self::with_context`], if any.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ArtificialError::WithContext { context, .. } => Some(context),
+            ArtificialError::Shared(inner) => inner.context(),
+            _ => None,
+        }
+    }
+
+    /// The underlying error with any context wrapper stripped.
+    pub fn root(&self) -> &ArtificialError {
+        match self {
+            ArtificialError::WithContext { source, .. } => source.root(),
+            ArtificialError::Shared(inner) => inner.root(),
+            other => other,
+        }
+    }
+}
+
+/// Model output kept in [`ArtificialError::OutputParse`].
+///
+/// `Display` and [`Self::as_str`] give the full text; `Debug` follows
+/// [`crate::redact`], so `{:?}` of the error does not leak the answer.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RawOutput(String);
+
+impl RawOutput {
+    pub fn new(raw: impl Into<String>) -> Self {
+        Self(raw.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Debug for RawOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&RedactedText(&self.0), f)
+    }
+}
+
+impl fmt::Display for RawOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Identifies the call an [`ArtificialError`] originated from.
+///
+/// Rendered as e.g.
+/// `prompt `app::Summarize` (fingerprint 3f2a9c0d11b8e7a4) on model `gpt-4o-mini`, attempt 2`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Name of the executed [`crate::template::PromptTemplate`], if the call
+    /// went through `prompt_execute`.
+    pub template: Option<&'static str>,
+    /// Stable hash of the rendered request messages (see
+    /// [`Self::fingerprint_of`]).  Lets you group failures of the same
+    /// prompt without logging its contents.
+    pub fingerprint: Option<String>,
+    /// Model identifier as sent to the provider.
+    pub model: Option<String>,
+    /// 1-based number of the logical attempt. Transport-level retries inside
+    /// a provider are not counted.
+    pub attempt: u32,
+}
+
+impl ErrorContext {
+    pub fn new() -> Self {
+        Self {
+            attempt: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_template(mut self, template: &'static str) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    pub fn with_model(mut self, model: impl AsRef<str>) -> Self {
+        self.model = Some(model.as_ref().to_string());
+        self
+    }
+
+    pub fn with_fingerprint(mut self, fingerprint: impl Into<String>) -> Self {
+        self.fingerprint = Some(fingerprint.into());
+        self
+    }
+
+    pub fn with_attempt(mut self, attempt: u32) -> Self {
+        self.attempt = attempt;
+        self
+    }
+
+    /// [`crate::canonical::fingerprint`] of `value`, stable across
+    /// processes.
+    ///
+    /// Returns `None` if `value` cannot be serialised.
+    pub fn fingerprint_of<T: Serialize + ?Sized>(value: &T) -> Option<String> {
+        crate::canonical::fingerprint(value).ok()
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.template {
+            Some(template) => write!(f, "prompt `{template}`")?,
+            None => write!(f, "chat completion")?,
+        }
+        if let Some(fingerprint) = &self.fingerprint {
+            write!(f, " (fingerprint {fingerprint})")?;
+        }
+        if let Some(model) = &self.model {
+            write!(f, " on model `{model}`")?;
+        }
+        write!(f, ", attempt {}", self.attempt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_is_rendered_in_single_line() {
+        let err = ArtificialError::Invalid("boom".into()).with_context(
+            ErrorContext::new()
+                .with_template("app::Summarize")
+                .with_model("gpt-4o-mini")
+                .with_fingerprint("abc")
+                .with_attempt(2),
+        );
+
+        assert_eq!(
+            err.to_string(),
+            "prompt `app::Summarize` (fingerprint abc) on model `gpt-4o-mini`, attempt 2: invalid: boom"
+        );
+        assert!(matches!(err.root(), ArtificialError::Invalid(_)));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn innermost_context_wins() {
+        let err = ArtificialError::Other("x".into())
+            .with_context(ErrorContext::new().with_model("inner"))
+            .with_context(ErrorContext::new().with_model("outer"));
+
+        assert_eq!(err.context().unwrap().model.as_deref(), Some("inner"));
+    }
+
+    #[test]
+    fn shared_errors_keep_their_kind() {
+        let err = ArtificialError::Shared(Arc::new(
+            ArtificialError::EmptyResponse.with_context(ErrorContext::new().with_model("m")),
+        ));
+
+        assert!(matches!(err.root(), ArtificialError::EmptyResponse));
+        assert_eq!(err.context().unwrap().model.as_deref(), Some("m"));
+    }
 }
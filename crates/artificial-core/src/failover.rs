@@ -0,0 +1,313 @@
+//! Graceful degradation between two backends.
+//!
+//! [`FailoverProvider`] sends requests to a primary backend and falls back
+//! to a secondary one—a cheaper model, another region, another provider—when
+//! the primary fails. A circuit breaker keeps a failing primary out of the
+//! way:
+//!
+//! * **closed** – requests go to the primary; a failed request is answered
+//!   by the secondary instead.
+//! * **open** – after [`FailoverProvider::with_failure_threshold`]
+//!   consecutive failures, requests go straight to the secondary.
+//! * **half-open** – once [`FailoverProvider::with_probe_interval`] has
+//!   passed, a single request probes the primary again. Success closes the
+//!   circuit, failure keeps it open for another interval.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use artificial_core::{failover::FailoverProvider, model::{Model, OpenAiModel}};
+//! # fn wrap<P, S>(openai: P, fallback: S) {
+//! let provider = FailoverProvider::new(openai, fallback)
+//!     .with_failure_threshold(3)
+//!     .with_probe_interval(Duration::from_secs(60))
+//!     .with_fallback_model(Model::OpenAi(OpenAiModel::Gpt4oMini));
+//! # }
+//! ```
+//!
+//! Timeouts count as failures once the backend reports them, so set
+//! [`crate::provider::ChatCompleteParameters::with_timeout`] to bound how long
+//! a hanging primary can hold a request. Cancelled and invalid requests,
+//! and policy violations, are not the backend's fault and neither count nor
+//! fall back; [`FailoverProvider::with_failure_check`] changes that choice.
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{GenericChatCompletionResponse, GenericMessage},
+    model::Model,
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+    },
+};
+
+/// Where a [`FailoverProvider`] currently sends requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// To the primary.
+    Closed,
+    /// To the secondary.
+    Open,
+    /// To the secondary, while one request probes the primary.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probing_since: Option<Instant>,
+}
+
+enum Route {
+    Primary,
+    Probe,
+    Secondary,
+}
+
+/// [`ChatCompletionProvider`] that fails over from `primary` to `secondary`.
+///
+/// Speaks [`GenericMessage`] so both backends can receive the same request.
+pub struct FailoverProvider<P, S> {
+    primary: P,
+    secondary: S,
+    breaker: Mutex<Breaker>,
+    failure_threshold: u32,
+    probe_interval: Duration,
+    fallback_model: Option<Model>,
+    is_failure: fn(&ArtificialError) -> bool,
+}
+
+impl<P, S> FailoverProvider<P, S> {
+    /// Opens after 5 consecutive failures and probes every 30 seconds.
+    pub fn new(primary: P, secondary: S) -> Self {
+        Self {
+            primary,
+            secondary,
+            breaker: Mutex::default(),
+            failure_threshold: 5,
+            probe_interval: Duration::from_secs(30),
+            fallback_model: None,
+            is_failure: is_backend_failure,
+        }
+    }
+
+    pub fn with_failure_threshold(mut self, failures: u32) -> Self {
+        self.failure_threshold = failures.max(1);
+        self
+    }
+
+    pub fn with_probe_interval(mut self, interval: Duration) -> Self {
+        self.probe_interval = interval;
+        self
+    }
+
+    /// Model to request from the secondary instead of the requested one.
+    pub fn with_fallback_model(mut self, model: Model) -> Self {
+        self.fallback_model = Some(model);
+        self
+    }
+
+    /// Decide which errors of the primary count as failures and fall back.
+    pub fn with_failure_check(mut self, is_failure: fn(&ArtificialError) -> bool) -> Self {
+        self.is_failure = is_failure;
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let breaker = self.breaker();
+        match (breaker.opened_at, breaker.probing_since) {
+            (None, _) => CircuitState::Closed,
+            (Some(_), None) => CircuitState::Open,
+            (Some(_), Some(_)) => CircuitState::HalfOpen,
+        }
+    }
+
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    fn route(&self) -> Route {
+        let mut breaker = self.breaker();
+        let Some(opened_at) = breaker.opened_at else {
+            return Route::Primary;
+        };
+        // A probe whose caller went away must not block the next one.
+        let probe_pending = breaker
+            .probing_since
+            .is_some_and(|since| since.elapsed() < self.probe_interval);
+        if probe_pending || opened_at.elapsed() < self.probe_interval {
+            return Route::Secondary;
+        }
+        breaker.probing_since = Some(Instant::now());
+        Route::Probe
+    }
+
+    fn record_success(&self) {
+        *self.breaker() = Breaker::default();
+    }
+
+    fn record_failure(&self, probe: bool) {
+        let mut breaker = self.breaker();
+        breaker.consecutive_failures += 1;
+        breaker.probing_since = None;
+        if probe || breaker.consecutive_failures >= self.failure_threshold {
+            breaker.opened_at = Some(Instant::now());
+        }
+    }
+
+    fn breaker(&self) -> MutexGuard<'_, Breaker> {
+        self.breaker.lock().expect("circuit breaker poisoned")
+    }
+}
+
+/// Everything but errors caused by the request itself.
+fn is_backend_failure(err: &ArtificialError) -> bool {
+    !matches!(
+        err.root(),
+        ArtificialError::Cancelled
+            | ArtificialError::InvalidRequest(_)
+            | ArtificialError::PolicyViolation { .. }
+    )
+}
+
+impl<P, S> ChatCompletionProvider for FailoverProvider<P, S>
+where
+    P: ChatCompletionProvider,
+    S: ChatCompletionProvider,
+    GenericMessage: Into<P::Message> + Into<S::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let mut params = params.map_messages(Into::<GenericMessage>::into);
+        Box::pin(async move {
+            let probe = match self.route() {
+                Route::Primary => false,
+                Route::Probe => true,
+                Route::Secondary => {
+                    if let Some(model) = &self.fallback_model {
+                        params.model = model.clone();
+                    }
+                    return self.secondary.chat_complete(params).await;
+                }
+            };
+
+            match self.primary.chat_complete(params.clone()).await {
+                Ok(response) => {
+                    self.record_success();
+                    Ok(response)
+                }
+                Err(err) if (self.is_failure)(&err) => {
+                    self.record_failure(probe);
+                    if let Some(model) = &self.fallback_model {
+                        params.model = model.clone();
+                    }
+                    self.secondary.chat_complete(params).await
+                }
+                Err(err) => {
+                    if probe {
+                        self.breaker().probing_since = None;
+                    }
+                    Err(err)
+                }
+            }
+        })
+    }
+}
+
+impl<P, S> HealthCheckProvider for FailoverProvider<P, S>
+where
+    P: HealthCheckProvider,
+    S: HealthCheckProvider,
+{
+    /// Both backends, primary first.
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        Box::pin(async move {
+            let mut health = self.primary.check_health().await;
+            health.extend(self.secondary.check_health().await);
+            health
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generic::GenericRole, model::OpenAiModel, testing::MockProvider};
+
+    fn params() -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new("hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4o),
+        )
+    }
+
+    async fn answer<P, S>(provider: &FailoverProvider<P, S>) -> String
+    where
+        P: ChatCompletionProvider,
+        S: ChatCompletionProvider,
+        GenericMessage: Into<P::Message> + Into<S::Message>,
+    {
+        let response = provider.chat_complete(params()).await.unwrap();
+        response.content.expect_finished().content.unwrap()
+    }
+
+    #[tokio::test]
+    async fn opens_after_consecutive_failures_and_closes_on_probe() {
+        let (primary, secondary) = (MockProvider::new(), MockProvider::new());
+        let provider = FailoverProvider::new(primary.clone(), secondary.clone())
+            .with_failure_threshold(2)
+            .with_probe_interval(Duration::from_millis(20))
+            .with_fallback_model(Model::OpenAi(OpenAiModel::Gpt4oMini));
+
+        for _ in 0..2 {
+            primary.push_error(ArtificialError::Other("down".into()));
+            secondary.push_text("fallback");
+            assert_eq!(answer(&provider).await, "fallback");
+        }
+        assert_eq!(provider.state(), CircuitState::Open);
+        assert_eq!(
+            secondary.last_request().unwrap().model,
+            Model::OpenAi(OpenAiModel::Gpt4oMini)
+        );
+
+        // Open: the primary is not asked at all.
+        secondary.push_text("fallback");
+        assert_eq!(answer(&provider).await, "fallback");
+        assert_eq!(primary.requests().len(), 2);
+
+        std::thread::sleep(Duration::from_millis(30));
+        primary.push_text("primary");
+        assert_eq!(answer(&provider).await, "primary");
+        assert_eq!(provider.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn request_errors_do_not_fail_over() {
+        let (primary, secondary) = (MockProvider::new(), MockProvider::new());
+        primary.push_error(ArtificialError::InvalidRequest("bad".into()));
+        let provider = FailoverProvider::new(primary, secondary.clone());
+
+        let err = provider.chat_complete(params()).await.unwrap_err();
+        assert!(matches!(err, ArtificialError::InvalidRequest(_)));
+        assert!(secondary.requests().is_empty());
+        assert_eq!(provider.state(), CircuitState::Closed);
+    }
+}
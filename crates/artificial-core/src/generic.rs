@@ -13,23 +13,70 @@
 //! Only if the additional data is **required by multiple back-ends** or
 //! **fundamentally provider-independent**.  Otherwise extend the
 //! provider-specific message type instead of bloating this one.
-use std::fmt::Display;
+//!
+//! ## Wire format and compatibility
+//!
+//! Conversations are persisted with these types, so their JSON form is a
+//! public contract, pinned by the snapshot tests at the bottom of this file:
+//!
+//! * new fields are `Option`/`Vec` with `#[serde(default)]` and are skipped
+//!   when empty, so histories written by older versions keep loading and
+//!   older readers are not confronted with unknown keys;
+//! * fields and variants are never renamed or removed within a
+//!   [`CONVERSATION_SCHEMA_VERSION`];
+//! * breaking changes bump the version, and [`StoredConversation::from_json`]
+//!   migrates every older version it knows.
+use std::fmt::{self, Display};
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    error::{ArtificialError, Result},
+    output_repair::Repair,
+    redact::{self, RedactedText},
+};
+
 /// Lightweight container representing a single chat message that is
 /// independent of any specific LLM provider.
 ///
 /// * `message` – the raw UTF-8 content. Markdown is fine, but keep newlines
 ///   and indentation portable.
 /// * `role` – see [`GenericRole`] for permitted values.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// * `reasoning` – chain-of-thought text returned by reasoning models that
+///   expose it (DeepSeek Reasoner, Grok). Only set on assistant responses and
+///   never sent back to the provider.
+/// * `attachments` – files the provider already stores, referenced by ID and
+///   sent alongside `content`.
+///
+/// `Debug` output hides `content`, `reasoning` and tool arguments, see
+/// [`crate::redact`].
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GenericMessage {
+    #[serde(default)]
     pub content: Option<String>,
     pub role: GenericRole,
+    #[serde(default)]
     pub name: Option<String>,
+    #[serde(default)]
     pub tool_calls: Option<Vec<GenericFunctionCallIntent>>,
+    #[serde(default)]
     pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<GenericAttachment>,
+}
+
+/// Messages that can tell whether they carry a system prompt, so a client
+/// can replace the system prompt of a template (`PreambleMode::ReplaceSystem`).
+pub trait SystemRole {
+    fn is_system(&self) -> bool;
+}
+
+impl SystemRole for GenericMessage {
+    fn is_system(&self) -> bool {
+        self.role == GenericRole::System
+    }
 }
 
 impl GenericMessage {
@@ -49,6 +96,8 @@ impl GenericMessage {
             name: None,
             tool_call_id: None,
             tool_calls: None,
+            reasoning: None,
+            attachments: Vec::new(),
         }
     }
 
@@ -59,6 +108,8 @@ impl GenericMessage {
             name: None,
             tool_calls: Some(tool_calls),
             tool_call_id: Some(tool_call_id),
+            reasoning: None,
+            attachments: Vec::new(),
         }
     }
 
@@ -71,6 +122,125 @@ impl GenericMessage {
         self.tool_call_id = Some(tool_call_id.to_string());
         self
     }
+
+    /// Attach a file previously uploaded to the provider, e.g. a PDF for
+    /// document QA.
+    ///
+    /// ```rust
+    /// use artificial_core::generic::{GenericAttachment, GenericMessage, GenericRole};
+    ///
+    /// let question = GenericMessage::new("Summarise the contract.".into(), GenericRole::User)
+    ///     .with_file("file-abc123");
+    /// assert_eq!(question.attachments, [GenericAttachment::File { file_id: "file-abc123".into() }]);
+    /// ```
+    pub fn with_file(mut self, file_id: impl Into<String>) -> Self {
+        self.attachments.push(GenericAttachment::File {
+            file_id: file_id.into(),
+        });
+        self
+    }
+}
+
+impl fmt::Debug for GenericMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("GenericMessage");
+        debug
+            .field("content", &self.content.as_deref().map(RedactedText))
+            .field("role", &self.role)
+            .field("name", &self.name);
+        match redact::content_visible() {
+            true => debug.field("tool_calls", &self.tool_calls),
+            false => debug.field(
+                "tool_calls",
+                &self.tool_calls.as_ref().map(|calls| {
+                    calls
+                        .iter()
+                        .map(|call| call.function.name.as_str())
+                        .collect::<Vec<_>>()
+                }),
+            ),
+        };
+        debug
+            .field("tool_call_id", &self.tool_call_id)
+            .field("reasoning", &self.reasoning.as_deref().map(RedactedText))
+            .field("attachments", &self.attachments)
+            .finish()
+    }
+}
+
+/// Version of the [`StoredConversation`] format written by this crate.
+///
+/// * `0` – a bare JSON array of [`GenericMessage`]s, as stored before the
+///   format was versioned.
+/// * `1` – `{"schema_version": 1, "messages": [...]}`.
+pub const CONVERSATION_SCHEMA_VERSION: u32 = 1;
+
+/// A conversation as persisted between sessions, tagged with the format
+/// version it was written in.
+///
+/// ```rust
+/// use artificial_core::generic::{GenericMessage, GenericRole, StoredConversation};
+///
+/// let stored = StoredConversation::new(vec![GenericMessage::new("Hi".into(), GenericRole::User)]);
+/// let json = stored.to_json()?;
+///
+/// // Histories saved as a plain array load as well.
+/// let legacy = StoredConversation::from_json(r#"[{"role":"user","content":"Hi"}]"#)?;
+/// assert_eq!(legacy.messages.len(), StoredConversation::from_json(&json)?.messages.len());
+/// # Ok::<(), artificial_core::error::ArtificialError>(())
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredConversation {
+    pub schema_version: u32,
+    pub messages: Vec<GenericMessage>,
+}
+
+impl StoredConversation {
+    pub fn new(messages: Vec<GenericMessage>) -> Self {
+        Self {
+            schema_version: CONVERSATION_SCHEMA_VERSION,
+            messages,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Load any known version, migrated to [`CONVERSATION_SCHEMA_VERSION`].
+    ///
+    /// Conversations written by a newer crate version are rejected with
+    /// [`ArtificialError::Invalid`] instead of being misread.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        if value.is_array() {
+            return Ok(Self::new(serde_json::from_value(value)?));
+        }
+
+        let version = value
+            .get("schema_version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| {
+                ArtificialError::Invalid("stored conversation has no `schema_version`".into())
+            })?;
+        if version > CONVERSATION_SCHEMA_VERSION as u64 {
+            return Err(ArtificialError::Invalid(format!(
+                "stored conversation has schema version {version}, this build reads up to {CONVERSATION_SCHEMA_VERSION}"
+            )));
+        }
+        Ok(Self {
+            schema_version: CONVERSATION_SCHEMA_VERSION,
+            ..serde_json::from_value(value)?
+        })
+    }
+}
+
+/// Content sent next to a message's text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GenericAttachment {
+    /// A file stored by the provider, referenced by its ID.
+    File { file_id: String },
 }
 
 /// High-level chat roles recognised by most LLM providers.
@@ -102,32 +272,137 @@ impl Display for GenericRole {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GenericChatCompletionResponse<T> {
     pub content: ResponseContent<T>,
     pub usage: Option<GenericUsageReport>,
+    /// What the provider reported about the response; fields it does not
+    /// report stay `None`.
+    pub metadata: ResponseMetadata,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ResponseContent<T> {
     Finished(T),
     ToolCalls(GenericMessage),
 }
 
-#[derive(Debug, Clone)]
+impl<T> ResponseContent<T> {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, ResponseContent::Finished(_))
+    }
+
+    pub fn as_finished(&self) -> Option<&T> {
+        match self {
+            ResponseContent::Finished(output) => Some(output),
+            ResponseContent::ToolCalls(_) => None,
+        }
+    }
+
+    /// The answer, or [`ArtificialError::Invalid`] if the model requested
+    /// tools instead.
+    ///
+    /// ```rust
+    /// use artificial_core::generic::ResponseContent;
+    ///
+    /// let content: ResponseContent<String> = ResponseContent::Finished("42".into());
+    /// assert_eq!(content.into_finished()?, "42");
+    /// # Ok::<(), artificial_core::error::ArtificialError>(())
+    /// ```
+    pub fn into_finished(self) -> Result<T> {
+        match self {
+            ResponseContent::Finished(output) => Ok(output),
+            ResponseContent::ToolCalls(message) => Err(ArtificialError::Invalid(format!(
+                "expected a finished answer, model requested {} tool call(s)",
+                message.tool_calls.as_ref().map_or(0, Vec::len)
+            ))),
+        }
+    }
+
+    /// The answer; panics if the model requested tools instead.
+    #[track_caller]
+    pub fn expect_finished(self) -> T {
+        match self {
+            ResponseContent::Finished(output) => output,
+            ResponseContent::ToolCalls(message) => panic!(
+                "expected a finished answer, model requested tool calls: {:?}",
+                message.tool_calls
+            ),
+        }
+    }
+
+    /// The requested tool calls; empty for finished answers.
+    pub fn tool_calls(&self) -> &[GenericFunctionCallIntent] {
+        match self {
+            ResponseContent::ToolCalls(message) => message.tool_calls.as_deref().unwrap_or(&[]),
+            ResponseContent::Finished(_) => &[],
+        }
+    }
+}
+
+impl ResponseContent<GenericMessage> {
+    /// The assistant message, whether it is an answer or a tool-call
+    /// request – e.g. to append it to the conversation.
+    pub fn into_message(self) -> GenericMessage {
+        match self {
+            ResponseContent::Finished(message) | ResponseContent::ToolCalls(message) => message,
+        }
+    }
+
+    /// Text of a finished answer.
+    pub fn text(&self) -> Option<&str> {
+        self.as_finished()?.content.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GenericUsageReport {
     pub prompt_tokens: i64,
     pub completion_tokens: i64,
     pub total_tokens: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Provider details of a response, for tracing and reproducing answers.
+///
+/// Multi-step executions (repairs, retries) report the last request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseMetadata {
+    /// The provider's response id, e.g. `chatcmpl-…`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The model that answered, e.g. a dated snapshot of the requested alias.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Backend configuration the answer was produced with. Equal seeds only
+    /// give equal answers while the fingerprint stays the same.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
+    /// Unix timestamp (seconds) at which the provider created the response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    /// Finish reason as reported by the provider, e.g. `stop`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// Name of the prompt template that produced the answer, when executed
+    /// through `ArtificialClient`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Version the template declared, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_version: Option<String>,
+    /// Local fixes applied to the raw answer before it parsed, see
+    /// [`crate::output_repair`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repairs: Vec<Repair>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GenericFunctionCallIntent {
     pub id: String,
     pub function: GenericFunctionCall,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GenericFunctionCall {
     pub name: String,
     pub arguments: serde_json::Value,
@@ -142,18 +417,32 @@ pub enum GenericStramingChatChunk {
     OutputTextDone,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenericFunctionSpec {
     pub name: String,
     pub description: String,
     pub parameters: serde_json::Value,
 }
 
-#[derive(Debug, Clone)]
+/// One event of a streamed answer.
+///
+/// Serialised adjacently tagged, e.g. `{"type":"text_delta","data":"Hi"}`
+/// or `{"type":"message_end"}`. Streams are persisted (see
+/// `artificial_core::cassette`) and forwarded to clients in this form, so
+/// it follows the same compatibility rules as messages (see the module
+/// docs): tags and field names never change, and new variants or fields
+/// only ever add to it. Consumers should skip events whose `type` they do
+/// not know rather than fail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum StreamEvent {
     /// Plain text delta emitted by the assistant.
     TextDelta(String),
 
+    /// Reasoning ("thinking") text emitted before the answer by models that
+    /// expose it, e.g. DeepSeek Reasoner or Grok 3 Mini.
+    ReasoningDelta(String),
+
     /// A tool-call was initiated (OpenAI-style indexed stream).
     ToolCallStart {
         index: usize,
@@ -167,6 +456,18 @@ pub enum StreamEvent {
         arguments_fragment: String,
     },
 
+    /// The arguments of tool-call `index` can no longer become valid JSON.
+    ///
+    /// Emitted as soon as the first bad fragment arrives; no
+    /// `ToolCallComplete` follows for that call.
+    ToolCallMalformed {
+        index: usize,
+        name: Option<String>,
+        /// The arguments received so far, including the bad fragment.
+        arguments: String,
+        reason: String,
+    },
+
     /// A completed tool-call intent (arguments parsed into JSON).
     ToolCallComplete {
         index: usize,
@@ -178,10 +479,21 @@ pub enum StreamEvent {
 
     /// Optional token usage report at the end of the stream.
     Usage(GenericUsageReport),
+
+    /// Assistant text tripped an output filter, see
+    /// `artificial_core::output_filter`.
+    ///
+    /// With `halted` this is the last event of the stream. Otherwise the
+    /// offending text is masked with `*` in the deltas that follow.
+    Filtered {
+        categories: Vec<String>,
+        halted: bool,
+    },
 }
 
 /// Provider-agnostic trait for streaming structured events (text + tool-calls).
 /// This complements the existing text-only `StreamingChatProvider` trait.
+#[cfg(feature = "client")]
 pub trait StreamingEventsProvider: crate::provider::ChatCompletionProvider {
     type EventStream<'s>: futures_core::stream::Stream<Item = crate::error::Result<StreamEvent>>
         + Send
@@ -196,3 +508,128 @@ pub trait StreamingEventsProvider: crate::provider::ChatCompletionProvider {
     where
         M: Into<Self::Message> + Clone + Send + Sync + 's;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_message() -> GenericMessage {
+        GenericMessage {
+            content: Some("Weather?".into()),
+            role: GenericRole::Assistant,
+            name: Some("bot".into()),
+            tool_calls: Some(vec![GenericFunctionCallIntent {
+                id: "call-1".into(),
+                function: GenericFunctionCall {
+                    name: "weather".into(),
+                    arguments: serde_json::json!({ "city": "Rome" }),
+                },
+            }]),
+            tool_call_id: None,
+            reasoning: Some("look it up".into()),
+            attachments: vec![GenericAttachment::File {
+                file_id: "file-1".into(),
+            }],
+        }
+    }
+
+    /// Changing this string breaks stored histories; see the module docs.
+    const FULL_MESSAGE_V1: &str = concat!(
+        r#"{"content":"Weather?","role":"assistant","name":"bot","#,
+        r#""tool_calls":[{"id":"call-1","function":{"name":"weather","arguments":{"city":"Rome"}}}],"#,
+        r#""tool_call_id":null,"reasoning":"look it up","#,
+        r#""attachments":[{"type":"file","file_id":"file-1"}]}"#
+    );
+
+    #[test]
+    fn message_wire_format_is_stable() {
+        assert_eq!(
+            serde_json::to_string(&full_message()).unwrap(),
+            FULL_MESSAGE_V1
+        );
+
+        let parsed: GenericMessage = serde_json::from_str(FULL_MESSAGE_V1).unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), FULL_MESSAGE_V1);
+    }
+
+    #[test]
+    fn older_and_newer_payloads_still_load() {
+        // Only the required field, as minimal writers produce it.
+        let minimal: GenericMessage = serde_json::from_str(r#"{"role":"tool"}"#).unwrap();
+        assert_eq!((minimal.role, minimal.content), (GenericRole::Tool, None));
+
+        // Keys added by a later version are ignored.
+        let newer: GenericMessage =
+            serde_json::from_str(r#"{"role":"user","content":"hi","citations":[]}"#).unwrap();
+        assert_eq!(newer.content.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn stream_event_wire_format_is_stable() {
+        let intent = full_message().tool_calls.unwrap().remove(0);
+        let events = vec![
+            StreamEvent::TextDelta("Hi".into()),
+            StreamEvent::ReasoningDelta("hmm".into()),
+            StreamEvent::ToolCallStart {
+                index: 0,
+                id: Some("call-1".into()),
+                name: None,
+            },
+            StreamEvent::ToolCallArgumentsDelta {
+                index: 0,
+                arguments_fragment: "{\"ci".into(),
+            },
+            StreamEvent::ToolCallMalformed {
+                index: 1,
+                name: Some("weather".into()),
+                arguments: "{]".into(),
+                reason: "expected value".into(),
+            },
+            StreamEvent::ToolCallComplete { index: 0, intent },
+            StreamEvent::MessageEnd,
+            StreamEvent::Usage(GenericUsageReport {
+                prompt_tokens: 3,
+                completion_tokens: 2,
+                total_tokens: 5,
+            }),
+            StreamEvent::Filtered {
+                categories: vec!["pii".into()],
+                halted: true,
+            },
+        ];
+        // Changing any of these lines breaks recorded streams and clients.
+        let expected = [
+            r#"{"type":"text_delta","data":"Hi"}"#,
+            r#"{"type":"reasoning_delta","data":"hmm"}"#,
+            r#"{"type":"tool_call_start","data":{"index":0,"id":"call-1","name":null}}"#,
+            r#"{"type":"tool_call_arguments_delta","data":{"index":0,"arguments_fragment":"{\"ci"}}"#,
+            r#"{"type":"tool_call_malformed","data":{"index":1,"name":"weather","arguments":"{]","reason":"expected value"}}"#,
+            r#"{"type":"tool_call_complete","data":{"index":0,"intent":{"id":"call-1","function":{"name":"weather","arguments":{"city":"Rome"}}}}}"#,
+            r#"{"type":"message_end"}"#,
+            r#"{"type":"usage","data":{"prompt_tokens":3,"completion_tokens":2,"total_tokens":5}}"#,
+            r#"{"type":"filtered","data":{"categories":["pii"],"halted":true}}"#,
+        ];
+
+        for (event, json) in events.iter().zip(expected) {
+            assert_eq!(serde_json::to_string(event).unwrap(), json);
+            assert_eq!(&serde_json::from_str::<StreamEvent>(json).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn stored_conversations_are_versioned() {
+        let stored = StoredConversation::new(vec![full_message()]);
+        let json = stored.to_json().unwrap();
+        assert!(json.starts_with(r#"{"schema_version":1,"messages":["#));
+        assert_eq!(
+            StoredConversation::from_json(&json).unwrap().messages.len(),
+            1
+        );
+
+        let legacy = StoredConversation::from_json(&format!("[{FULL_MESSAGE_V1}]")).unwrap();
+        assert_eq!(legacy.schema_version, CONVERSATION_SCHEMA_VERSION);
+
+        let future = StoredConversation::from_json(r#"{"schema_version":99,"messages":[]}"#);
+        assert!(matches!(future, Err(ArtificialError::Invalid(_))));
+    }
+}
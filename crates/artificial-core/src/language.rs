@@ -0,0 +1,503 @@
+//! Keep answers in the language the deployment asked for.
+//!
+//! Models drift back to English as soon as the question, the retrieved
+//! documents or a tool result are English, system prompt or not. A
+//! [`LanguageGuard`] checks the language of every finished answer and, if it
+//! is wrong, shows the answer to the model again with a request to reply in
+//! the expected language:
+//!
+//! ```rust
+//! use artificial_core::language::{LanguageCode, LanguageGuard};
+//! # use artificial_core::{layer::ProviderLayer, provider::ChatCompletionProvider, generic::GenericMessage};
+//! # fn wrap<B: ChatCompletionProvider>(backend: B) where GenericMessage: Into<B::Message> {
+//! let provider = LanguageGuard::new(LanguageCode::German)
+//!     .with_max_retries(2)
+//!     .layer(backend);
+//! # }
+//! ```
+//!
+//! The built-in [`detect_language`] is a small heuristic: stop words for the
+//! Latin-script languages, the script for the others. Answers it cannot
+//! classify with confidence, typically very short ones, are accepted as
+//! they are. Deployments with a proper language identification library plug
+//! it in with [`LanguageGuard::with_detector`].
+use std::{fmt, future::Future, pin::Pin, str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{
+        GenericChatCompletionResponse, GenericMessage, GenericRole, GenericUsageReport,
+        ResponseContent,
+    },
+    layer::ProviderLayer,
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+    },
+};
+
+/// A language an answer can be requested in, serialized as its ISO 639-1
+/// code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LanguageCode {
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "de")]
+    German,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "es")]
+    Spanish,
+    #[serde(rename = "it")]
+    Italian,
+    #[serde(rename = "pt")]
+    Portuguese,
+    #[serde(rename = "nl")]
+    Dutch,
+    #[serde(rename = "ru")]
+    Russian,
+    #[serde(rename = "ja")]
+    Japanese,
+    #[serde(rename = "zh")]
+    Chinese,
+    #[serde(rename = "ko")]
+    Korean,
+}
+
+impl LanguageCode {
+    pub const ALL: [LanguageCode; 11] = [
+        LanguageCode::English,
+        LanguageCode::German,
+        LanguageCode::French,
+        LanguageCode::Spanish,
+        LanguageCode::Italian,
+        LanguageCode::Portuguese,
+        LanguageCode::Dutch,
+        LanguageCode::Russian,
+        LanguageCode::Japanese,
+        LanguageCode::Chinese,
+        LanguageCode::Korean,
+    ];
+
+    /// The ISO 639-1 code, e.g. `de`.
+    pub fn code(self) -> &'static str {
+        match self {
+            LanguageCode::English => "en",
+            LanguageCode::German => "de",
+            LanguageCode::French => "fr",
+            LanguageCode::Spanish => "es",
+            LanguageCode::Italian => "it",
+            LanguageCode::Portuguese => "pt",
+            LanguageCode::Dutch => "nl",
+            LanguageCode::Russian => "ru",
+            LanguageCode::Japanese => "ja",
+            LanguageCode::Chinese => "zh",
+            LanguageCode::Korean => "ko",
+        }
+    }
+
+    /// The English name, as used in instructions to the model.
+    pub fn name(self) -> &'static str {
+        match self {
+            LanguageCode::English => "English",
+            LanguageCode::German => "German",
+            LanguageCode::French => "French",
+            LanguageCode::Spanish => "Spanish",
+            LanguageCode::Italian => "Italian",
+            LanguageCode::Portuguese => "Portuguese",
+            LanguageCode::Dutch => "Dutch",
+            LanguageCode::Russian => "Russian",
+            LanguageCode::Japanese => "Japanese",
+            LanguageCode::Chinese => "Chinese",
+            LanguageCode::Korean => "Korean",
+        }
+    }
+
+    /// Common short words of Latin-script languages, used for detection.
+    fn stop_words(self) -> &'static [&'static str] {
+        match self {
+            LanguageCode::English => &[
+                "the", "and", "is", "are", "you", "of", "to", "that", "it", "with", "for", "this",
+                "not", "have", "be", "was", "can", "your",
+            ],
+            LanguageCode::German => &[
+                "der", "die", "das", "und", "ist", "nicht", "ich", "sie", "du", "mit", "ein",
+                "eine", "zu", "den", "auf", "für", "es", "sind", "auch", "wir", "werden",
+            ],
+            LanguageCode::French => &[
+                "le", "la", "les", "et", "est", "une", "des", "vous", "pas", "que", "pour", "dans",
+                "du", "ce", "sur", "avec", "nous", "il", "je", "sont",
+            ],
+            LanguageCode::Spanish => &[
+                "el", "la", "los", "las", "y", "es", "que", "una", "por", "para", "con", "no",
+                "del", "se", "lo", "su", "como", "pero", "está", "usted",
+            ],
+            LanguageCode::Italian => &[
+                "il", "la", "che", "e", "di", "non", "per", "una", "sono", "con", "del", "della",
+                "gli", "è", "ma", "anche", "questo", "ci",
+            ],
+            LanguageCode::Portuguese => &[
+                "o", "a", "os", "as", "e", "que", "não", "uma", "para", "com", "do", "da", "em",
+                "é", "por", "mais", "você", "são",
+            ],
+            LanguageCode::Dutch => &[
+                "de", "het", "een", "en", "is", "van", "niet", "dat", "ik", "je", "op", "met",
+                "voor", "zijn", "wij", "ook", "maar", "u",
+            ],
+            LanguageCode::Russian
+            | LanguageCode::Japanese
+            | LanguageCode::Chinese
+            | LanguageCode::Korean => &[],
+        }
+    }
+}
+
+impl fmt::Display for LanguageCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for LanguageCode {
+    type Err = ArtificialError;
+
+    /// Parse an ISO 639-1 code; region suffixes such as `de-AT` are ignored.
+    fn from_str(s: &str) -> Result<Self> {
+        let primary = s.split(['-', '_']).next().unwrap_or_default();
+        LanguageCode::ALL
+            .into_iter()
+            .find(|language| language.code().eq_ignore_ascii_case(primary))
+            .ok_or_else(|| ArtificialError::Invalid(format!("unsupported language code: {s}")))
+    }
+}
+
+/// Fewest stop words a Latin-script text needs before it is classified.
+const MIN_STOP_WORDS: usize = 3;
+
+/// Guess the language of `text`, or `None` if it is too short or too mixed
+/// to tell.
+///
+/// ```rust
+/// use artificial_core::language::{detect_language, LanguageCode};
+///
+/// assert_eq!(
+///     detect_language("Das ist leider nicht möglich, aber wir können es versuchen."),
+///     Some(LanguageCode::German),
+/// );
+/// assert_eq!(detect_language("OK"), None);
+/// ```
+pub fn detect_language(text: &str) -> Option<LanguageCode> {
+    if let Some(language) = detect_by_script(text) {
+        return Some(language);
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut scores: Vec<(usize, LanguageCode)> = LanguageCode::ALL
+        .into_iter()
+        .map(|language| {
+            let stop_words = language.stop_words();
+            let hits = words
+                .iter()
+                .filter(|word| stop_words.contains(&word.as_str()))
+                .count();
+            (hits, language)
+        })
+        .collect();
+    scores.sort_by_key(|(hits, _)| std::cmp::Reverse(*hits));
+
+    match scores.as_slice() {
+        [(best, language), (second, _), ..] if *best >= MIN_STOP_WORDS && best > second => {
+            Some(*language)
+        }
+        _ => None,
+    }
+}
+
+/// Classify non-Latin scripts by their characters; `None` for texts that
+/// are mostly Latin.
+fn detect_by_script(text: &str) -> Option<LanguageCode> {
+    let (mut latin, mut cyrillic, mut kana, mut han, mut hangul) = (0, 0, 0, 0, 0);
+    for c in text.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' => latin += 1,
+            '\u{0400}'..='\u{04FF}' => cyrillic += 1,
+            '\u{3040}'..='\u{30FF}' => kana += 1,
+            '\u{4E00}'..='\u{9FFF}' => han += 1,
+            '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' => hangul += 1,
+            _ => {}
+        }
+    }
+    let other = cyrillic + kana + han + hangul;
+    if other == 0 || other < latin {
+        return None;
+    }
+    Some(if hangul >= cyrillic.max(kana + han) {
+        LanguageCode::Korean
+    } else if cyrillic >= kana + han {
+        LanguageCode::Russian
+    } else if kana > 0 {
+        // Japanese mixes kana into Han characters; Chinese has none.
+        LanguageCode::Japanese
+    } else {
+        LanguageCode::Chinese
+    })
+}
+
+type Detector = dyn Fn(&str) -> Option<LanguageCode> + Send + Sync;
+
+/// Layer that checks the language of finished answers and asks the model to
+/// translate wrong ones. Clones share the detector.
+#[derive(Clone)]
+pub struct LanguageGuard {
+    expected: LanguageCode,
+    max_retries: u32,
+    detector: Arc<Detector>,
+}
+
+impl fmt::Debug for LanguageGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LanguageGuard")
+            .field("expected", &self.expected)
+            .field("max_retries", &self.max_retries)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LanguageGuard {
+    /// Expect answers in `expected`, retrying once.
+    pub fn new(expected: LanguageCode) -> Self {
+        Self {
+            expected,
+            max_retries: 1,
+            detector: Arc::new(detect_language),
+        }
+    }
+
+    /// How often a wrong-language answer is sent back. `0` only checks.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Replace [`detect_language`], e.g. with a statistical detector.
+    pub fn with_detector<F>(mut self, detector: F) -> Self
+    where
+        F: Fn(&str) -> Option<LanguageCode> + Send + Sync + 'static,
+    {
+        self.detector = Arc::new(detector);
+        self
+    }
+
+    pub fn expected(&self) -> LanguageCode {
+        self.expected
+    }
+
+    /// The language of `text` if it is detected and not the expected one.
+    pub fn mismatch(&self, text: &str) -> Option<LanguageCode> {
+        (self.detector)(text).filter(|detected| *detected != self.expected)
+    }
+
+    fn correction(&self, detected: LanguageCode) -> String {
+        format!(
+            "You answered in {} but must answer in {}. Repeat your previous answer in {}, \
+             without commenting on the change.",
+            detected.name(),
+            self.expected.name(),
+            self.expected.name()
+        )
+    }
+}
+
+impl<B> ProviderLayer<B> for LanguageGuard {
+    type Provider = LanguageGuardedProvider<B>;
+
+    fn layer(&self, inner: B) -> Self::Provider {
+        LanguageGuardedProvider {
+            inner,
+            guard: self.clone(),
+        }
+    }
+}
+
+/// Backend wrapper from [`LanguageGuard`].
+pub struct LanguageGuardedProvider<B> {
+    inner: B,
+    guard: LanguageGuard,
+}
+
+impl<B> LanguageGuardedProvider<B> {
+    pub fn guard(&self) -> &LanguageGuard {
+        &self.guard
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B> ChatCompletionProvider for LanguageGuardedProvider<B>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    /// Answers with tool calls pass unchecked. The usage of all attempts is
+    /// summed up.
+    ///
+    /// # Errors
+    ///
+    /// [`ArtificialError::Invalid`] if the answer is still in the wrong
+    /// language after [`LanguageGuard::with_max_retries`] attempts.
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let mut params = params.map_messages(Into::<GenericMessage>::into);
+        Box::pin(async move {
+            let mut usage: Option<GenericUsageReport> = None;
+            let mut retries = 0;
+            loop {
+                let mut response = self.inner.chat_complete(params.clone()).await?;
+                usage = match (usage, response.usage.take()) {
+                    (Some(total), Some(next)) => Some(GenericUsageReport {
+                        prompt_tokens: total.prompt_tokens + next.prompt_tokens,
+                        completion_tokens: total.completion_tokens + next.completion_tokens,
+                        total_tokens: total.total_tokens + next.total_tokens,
+                    }),
+                    (total, next) => next.or(total),
+                };
+
+                let ResponseContent::Finished(message) = &response.content else {
+                    return Ok(GenericChatCompletionResponse { usage, ..response });
+                };
+                let detected = message
+                    .content
+                    .as_deref()
+                    .and_then(|text| self.guard.mismatch(text));
+                let Some(detected) = detected else {
+                    return Ok(GenericChatCompletionResponse { usage, ..response });
+                };
+                if retries >= self.guard.max_retries {
+                    return Err(ArtificialError::Invalid(format!(
+                        "answer is in {detected} instead of {}",
+                        self.guard.expected
+                    )));
+                }
+
+                #[cfg(feature = "tracing")]
+                tracing::debug!(%detected, expected = %self.guard.expected, "answer in wrong language, retrying");
+
+                params.messages.push(message.clone());
+                params.messages.push(GenericMessage::new(
+                    self.guard.correction(detected),
+                    GenericRole::User,
+                ));
+                retries += 1;
+            }
+        })
+    }
+}
+
+impl<B: HealthCheckProvider> HealthCheckProvider for LanguageGuardedProvider<B> {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{Model, OpenAiModel},
+        testing::{MockProvider, MockReply},
+    };
+
+    fn params() -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(
+                "Wie viele Urlaubstage habe ich?".into(),
+                GenericRole::User,
+            )],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[test]
+    fn detects_scripts_and_stop_words() {
+        assert_eq!(
+            detect_language("You have 28 vacation days and they carry over to the next year."),
+            Some(LanguageCode::English)
+        );
+        assert_eq!(
+            detect_language("Vous avez droit à 28 jours de congé par an, et ils sont reportés."),
+            Some(LanguageCode::French)
+        );
+        assert_eq!(
+            detect_language("У вас 28 дней отпуска."),
+            Some(LanguageCode::Russian)
+        );
+        assert_eq!(
+            detect_language("年に28日の休暇があります。"),
+            Some(LanguageCode::Japanese)
+        );
+        assert_eq!(detect_language("28"), None);
+        assert_eq!(
+            "de-AT".parse::<LanguageCode>().unwrap(),
+            LanguageCode::German
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_language_is_sent_back_once() {
+        let mock = MockProvider::new();
+        mock.push(
+            MockReply::text("You have 28 vacation days and they carry over to the next year.")
+                .with_usage(10, 5),
+        );
+        mock.push(
+            MockReply::text("Sie haben 28 Urlaubstage, und die werden auch übertragen.")
+                .with_usage(20, 5),
+        );
+        let provider = LanguageGuard::new(LanguageCode::German).layer(mock.clone());
+
+        let response = provider.chat_complete(params()).await.unwrap();
+        assert!(response
+            .content
+            .expect_finished()
+            .content
+            .unwrap()
+            .starts_with("Sie haben"));
+        assert_eq!(response.usage.unwrap().total_tokens, 40);
+
+        let retry = mock.last_request().unwrap().messages;
+        assert_eq!(retry.len(), 3);
+        assert!(retry[2]
+            .content
+            .as_deref()
+            .unwrap()
+            .contains("must answer in German"));
+    }
+
+    #[tokio::test]
+    async fn persistent_mismatch_is_an_error() {
+        let mock = MockProvider::new();
+        mock.push_text("You have 28 vacation days and they carry over to the next year.");
+        let provider = LanguageGuard::new(LanguageCode::German)
+            .with_max_retries(0)
+            .layer(mock);
+
+        let err = provider.chat_complete(params()).await.unwrap_err();
+        assert!(matches!(err, ArtificialError::Invalid(_)));
+    }
+}
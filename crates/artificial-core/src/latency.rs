@@ -0,0 +1,222 @@
+//! Observed latency per model.
+//!
+//! Timeouts, reasoning budgets and hedging delays are only as good as the
+//! latency they assume. A [`LatencyEstimator`] learns it from live traffic:
+//! for every model (and reasoning effort) it keeps an exponentially smoothed
+//! mean and percentiles over a window of recent calls.
+//!
+//! [`crate::ArtificialClient`] records every successful call it makes into
+//! its estimator, see [`crate::ArtificialClient::latency`]:
+//!
+//! ```rust,no_run
+//! # use artificial_core::{ArtificialClient, model::{Model, OpenAiModel}};
+//! # fn report<B>(client: &ArtificialClient<B>) {
+//! let model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+//! if let Some(estimate) = client.latency().estimate(&model, None) {
+//!     println!("p90 {:?} over {} calls", estimate.p90, estimate.samples);
+//! }
+//!
+//! // Export all series to a metrics backend.
+//! for (key, estimate) in client.latency().snapshot() {
+//!     println!("{} {:?}: {} ms", key.model, key.effort, estimate.smoothed.as_millis());
+//! }
+//! # }
+//! ```
+//!
+//! [`crate::provider::ThinkingBudget::fit_to`] uses the estimates to skip
+//! reasoning efforts that cannot finish within a budget, and `p90` makes a
+//! reasonable delay before hedging a request to a second backend.
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::{model::Model, provider::ReasoningEffort};
+
+/// What a latency series is kept for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LatencyKey {
+    /// The model identifier, e.g. `gpt-4o-mini`.
+    pub model: String,
+    /// Reasoning effort requested, if any.
+    pub effort: Option<ReasoningEffort>,
+}
+
+impl LatencyKey {
+    pub fn new(model: &Model, effort: Option<ReasoningEffort>) -> Self {
+        Self {
+            model: model.as_ref().to_owned(),
+            effort,
+        }
+    }
+}
+
+/// Latency of one model as observed so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyEstimate {
+    /// Exponentially smoothed mean; follows shifts faster than the
+    /// percentiles.
+    pub smoothed: Duration,
+    /// Percentiles over the window of recent calls.
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    /// Calls recorded in total.
+    pub samples: u64,
+}
+
+#[derive(Debug)]
+struct Series {
+    smoothed_ms: f64,
+    recent: VecDeque<Duration>,
+    samples: u64,
+}
+
+/// Thread-safe latency statistics per [`LatencyKey`].
+#[derive(Debug)]
+pub struct LatencyEstimator {
+    smoothing: f64,
+    window: usize,
+    series: Mutex<HashMap<LatencyKey, Series>>,
+}
+
+impl Default for LatencyEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyEstimator {
+    /// Smoothing factor 0.2 and percentiles over the last 200 calls.
+    pub fn new() -> Self {
+        Self {
+            smoothing: 0.2,
+            window: 200,
+            series: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Weight of a new sample in the smoothed mean, `0.0..=1.0`. Higher
+    /// values react faster and fluctuate more.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Number of recent calls the percentiles are computed over.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    pub fn record(&self, key: LatencyKey, latency: Duration) {
+        let mut series = self.series.lock().expect("latency estimator poisoned");
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let series = series.entry(key).or_insert_with(|| Series {
+            smoothed_ms: latency_ms,
+            recent: VecDeque::with_capacity(self.window),
+            samples: 0,
+        });
+        series.smoothed_ms += self.smoothing * (latency_ms - series.smoothed_ms);
+        if series.recent.len() == self.window {
+            series.recent.pop_front();
+        }
+        series.recent.push_back(latency);
+        series.samples += 1;
+    }
+
+    /// Estimate for `model` at `effort`, `None` before the first call.
+    pub fn estimate(
+        &self,
+        model: &Model,
+        effort: Option<ReasoningEffort>,
+    ) -> Option<LatencyEstimate> {
+        let series = self.series.lock().expect("latency estimator poisoned");
+        series.get(&LatencyKey::new(model, effort)).map(estimate)
+    }
+
+    /// All series, ordered by model and effort.
+    pub fn snapshot(&self) -> Vec<(LatencyKey, LatencyEstimate)> {
+        let series = self.series.lock().expect("latency estimator poisoned");
+        let mut snapshot: Vec<_> = series
+            .iter()
+            .map(|(key, series)| (key.clone(), estimate(series)))
+            .collect();
+        snapshot.sort_by(|(a, _), (b, _)| {
+            (&a.model, a.effort.map(|e| e.as_ref().to_owned()))
+                .cmp(&(&b.model, b.effort.map(|e| e.as_ref().to_owned())))
+        });
+        snapshot
+    }
+
+    /// Forget everything, e.g. after switching regions.
+    pub fn reset(&self) {
+        self.series
+            .lock()
+            .expect("latency estimator poisoned")
+            .clear();
+    }
+}
+
+fn estimate(series: &Series) -> LatencyEstimate {
+    let mut sorted: Vec<Duration> = series.recent.iter().copied().collect();
+    sorted.sort_unstable();
+    let percentile = |p: f64| {
+        let rank = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len());
+        sorted[rank - 1]
+    };
+    LatencyEstimate {
+        smoothed: Duration::from_secs_f64(series.smoothed_ms / 1000.0),
+        p50: percentile(0.5),
+        p90: percentile(0.9),
+        p99: percentile(0.99),
+        samples: series.samples,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::OpenAiModel;
+
+    #[test]
+    fn percentiles_cover_the_window_and_the_mean_follows_shifts() {
+        let model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+        let estimator = LatencyEstimator::new().with_window(100).with_smoothing(0.5);
+        for ms in 1..=100 {
+            estimator.record(LatencyKey::new(&model, None), Duration::from_millis(ms));
+        }
+
+        let estimate = estimator.estimate(&model, None).unwrap();
+        assert_eq!(estimate.p50, Duration::from_millis(50));
+        assert_eq!(estimate.p90, Duration::from_millis(90));
+        assert_eq!(estimate.p99, Duration::from_millis(99));
+        assert_eq!(estimate.samples, 100);
+        assert!(estimate.smoothed > Duration::from_millis(95));
+        assert!(estimator
+            .estimate(&model, Some(ReasoningEffort::High))
+            .is_none());
+    }
+
+    #[test]
+    fn budgets_skip_efforts_that_do_not_fit() {
+        use crate::provider::ThinkingBudget;
+
+        let model = Model::OpenAi(OpenAiModel::Gpt5);
+        let estimator = LatencyEstimator::new();
+        let high = LatencyKey::new(&model, Some(ReasoningEffort::High));
+        estimator.record(high, Duration::from_secs(50));
+
+        let budget = ThinkingBudget::new(Duration::from_secs(60));
+        assert_eq!(
+            budget.fit_to(&estimator, &model).start,
+            ReasoningEffort::Medium
+        );
+        let generous = ThinkingBudget::new(Duration::from_secs(120));
+        assert_eq!(
+            generous.fit_to(&estimator, &model).start,
+            ReasoningEffort::High
+        );
+    }
+}
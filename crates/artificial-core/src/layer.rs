@@ -0,0 +1,349 @@
+//! Composable middleware for providers.
+//!
+//! A [`ProviderLayer`] wraps a backend into another backend – the same idea
+//! as `tower::Layer`. [`ProviderBuilder`] stacks layers so cross-cutting
+//! concerns (logging, caching, rate limiting, redaction, …) stay out of the
+//! adapters:
+//!
+//! ```rust
+//! use artificial_core::layer::{InterceptLayer, Interceptor, ProviderBuilder};
+//! use artificial_core::recorder::{MemoryRecorder, RecordingProvider};
+//! use artificial_core::usage::UsageLedger;
+//! # use artificial_core::{ArtificialClient, generic::GenericMessage, provider::*};
+//!
+//! #[derive(Clone)]
+//! struct Redact;
+//!
+//! impl Interceptor for Redact {
+//!     fn before(&self, params: &mut ChatCompleteParameters<GenericMessage>) -> artificial_core::error::Result<()> {
+//!         for message in &mut params.messages {
+//!             message.content = message.content.take().map(|c| c.replace("hunter2", "[redacted]"));
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn build<B: ChatCompletionProvider<Message = GenericMessage>>(backend: B) {
+//! let usage = UsageLedger::new();
+//! let recorder = MemoryRecorder::new();
+//! let provider = ProviderBuilder::new()
+//!     // Outermost first: usage sees every call, the recorder only redacted ones.
+//!     .layer(usage.clone())
+//!     .layer(InterceptLayer::new(Redact))
+//!     .layer(move |inner: B| RecordingProvider::new(inner, recorder.clone()))
+//!     .build(backend);
+//! let client = ArtificialClient::new(provider);
+//! # }
+//! ```
+//!
+//! Any `Fn(B) -> P` closure is a layer, so existing wrappers such as
+//! [`crate::recorder::RecordingProvider`] plug in without extra glue. For
+//! hooks that only need to look at (or adjust) the request and the response,
+//! implement the lighter [`Interceptor`] instead.
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    error::Result,
+    generic::{GenericChatCompletionResponse, GenericMessage},
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+    },
+    usage::{UsageLedger, UsageTracker},
+};
+
+/// Wraps a backend `B` into [`Self::Provider`].
+pub trait ProviderLayer<B> {
+    type Provider;
+
+    fn layer(&self, inner: B) -> Self::Provider;
+}
+
+impl<B, P, F> ProviderLayer<B> for F
+where
+    F: Fn(B) -> P,
+{
+    type Provider = P;
+
+    fn layer(&self, inner: B) -> P {
+        self(inner)
+    }
+}
+
+/// Layer that returns the backend unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identity;
+
+impl<B> ProviderLayer<B> for Identity {
+    type Provider = B;
+
+    fn layer(&self, inner: B) -> B {
+        inner
+    }
+}
+
+/// `Outer` applied on top of `Inner`.
+#[derive(Debug, Clone)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<B, Inner, Outer> ProviderLayer<B> for Stack<Inner, Outer>
+where
+    Inner: ProviderLayer<B>,
+    Outer: ProviderLayer<Inner::Provider>,
+{
+    type Provider = Outer::Provider;
+
+    fn layer(&self, inner: B) -> Self::Provider {
+        self.outer.layer(self.inner.layer(inner))
+    }
+}
+
+/// Collects layers and applies them to a backend.
+///
+/// The first added layer is the **outermost**: it sees requests first and
+/// responses last.
+#[derive(Debug, Clone)]
+pub struct ProviderBuilder<L> {
+    layer: L,
+}
+
+impl ProviderBuilder<Identity> {
+    pub fn new() -> Self {
+        Self { layer: Identity }
+    }
+}
+
+impl Default for ProviderBuilder<Identity> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> ProviderBuilder<L> {
+    /// Add `layer` below all layers added so far.
+    pub fn layer<N>(self, layer: N) -> ProviderBuilder<Stack<N, L>> {
+        ProviderBuilder {
+            layer: Stack {
+                inner: layer,
+                outer: self.layer,
+            },
+        }
+    }
+
+    /// Wrap `backend` in every layer.
+    pub fn build<B>(&self, backend: B) -> L::Provider
+    where
+        L: ProviderLayer<B>,
+    {
+        self.layer.layer(backend)
+    }
+}
+
+/// Counts usage into this ledger, see [`UsageTracker`].
+impl<B> ProviderLayer<B> for UsageLedger {
+    type Provider = UsageTracker<B>;
+
+    fn layer(&self, inner: B) -> UsageTracker<B> {
+        UsageTracker::new(inner).with_ledger(self.clone())
+    }
+}
+
+/// Synchronous hooks around every chat completion.
+///
+/// Both hooks run inline on the request path, so they should be cheap.
+pub trait Interceptor: Send + Sync {
+    /// Inspect or rewrite the request. An error aborts the call before it is
+    /// sent.
+    fn before(&self, params: &mut ChatCompleteParameters<GenericMessage>) -> Result<()> {
+        let _ = params;
+        Ok(())
+    }
+
+    /// Inspect or rewrite the outcome, including failures.
+    fn after(
+        &self,
+        params: &ChatCompleteParameters<GenericMessage>,
+        result: &mut Result<GenericChatCompletionResponse<GenericMessage>>,
+    ) {
+        let _ = (params, result);
+    }
+}
+
+/// Layer running an [`Interceptor`], see [`Intercepted`].
+#[derive(Debug, Clone)]
+pub struct InterceptLayer<I> {
+    interceptor: I,
+}
+
+impl<I> InterceptLayer<I> {
+    pub fn new(interceptor: I) -> Self {
+        Self { interceptor }
+    }
+}
+
+impl<B, I: Clone> ProviderLayer<B> for InterceptLayer<I> {
+    type Provider = Intercepted<B, I>;
+
+    fn layer(&self, inner: B) -> Self::Provider {
+        Intercepted::new(inner, self.interceptor.clone())
+    }
+}
+
+/// [`ChatCompletionProvider`] wrapper that runs an [`Interceptor`].
+///
+/// Speaks [`GenericMessage`] so interceptors work for every backend.
+pub struct Intercepted<B, I> {
+    inner: B,
+    interceptor: I,
+}
+
+impl<B, I> Intercepted<B, I> {
+    pub fn new(inner: B, interceptor: I) -> Self {
+        Self { inner, interceptor }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B, I> ChatCompletionProvider for Intercepted<B, I>
+where
+    B: ChatCompletionProvider,
+    I: Interceptor,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let mut params = params.map_messages(Into::<GenericMessage>::into);
+        Box::pin(async move {
+            self.interceptor.before(&mut params)?;
+            let mut result = self.inner.chat_complete(params.clone()).await;
+            self.interceptor.after(&params, &mut result);
+            result
+        })
+    }
+}
+
+impl<B, I> HealthCheckProvider for Intercepted<B, I>
+where
+    B: HealthCheckProvider,
+    I: Send + Sync,
+{
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        error::ArtificialError,
+        generic::{GenericRole, GenericUsageReport, ResponseContent},
+        model::{Model, OpenAiModel},
+    };
+
+    /// Answers with the last message it received.
+    struct Echo;
+
+    impl ChatCompletionProvider for Echo {
+        type Message = GenericMessage;
+
+        fn chat_complete<'s, M>(
+            &'s self,
+            params: ChatCompleteParameters<M>,
+        ) -> Pin<
+            Box<
+                dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>>
+                    + Send
+                    + 's,
+            >,
+        >
+        where
+            M: Into<Self::Message> + Clone + Send + Sync + 's,
+        {
+            let last: GenericMessage = params.messages.last().cloned().unwrap().into();
+            Box::pin(async move {
+                Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(GenericMessage::new(
+                        last.content.unwrap_or_default(),
+                        GenericRole::Assistant,
+                    )),
+                    usage: Some(GenericUsageReport {
+                        prompt_tokens: 1,
+                        completion_tokens: 1,
+                        total_tokens: 2,
+                    }),
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    /// Redacts "secret", rejects "forbidden" and logs every answer.
+    #[derive(Clone, Default)]
+    struct Guard(Arc<Mutex<Vec<String>>>);
+
+    impl Interceptor for Guard {
+        fn before(&self, params: &mut ChatCompleteParameters<GenericMessage>) -> Result<()> {
+            for message in &mut params.messages {
+                if message.content.as_deref() == Some("forbidden") {
+                    return Err(ArtificialError::InvalidRequest("forbidden".into()));
+                }
+                message.content = message.content.take().map(|c| c.replace("secret", "***"));
+            }
+            Ok(())
+        }
+
+        fn after(
+            &self,
+            _params: &ChatCompleteParameters<GenericMessage>,
+            result: &mut Result<GenericChatCompletionResponse<GenericMessage>>,
+        ) {
+            if let Ok(response) = result {
+                if let ResponseContent::Finished(message) = &response.content {
+                    let text = message.content.clone().unwrap_or_default();
+                    self.0.lock().unwrap().push(text);
+                }
+            }
+        }
+    }
+
+    fn params(text: &str) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(text.into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn layers_see_requests_and_responses() {
+        let guard = Guard::default();
+        let usage = UsageLedger::new();
+        let provider = ProviderBuilder::new()
+            .layer(usage.clone())
+            .layer(InterceptLayer::new(guard.clone()))
+            .build(Echo);
+
+        provider.chat_complete(params("my secret")).await.unwrap();
+        let err = provider.chat_complete(params("forbidden")).await;
+
+        assert!(matches!(err, Err(ArtificialError::InvalidRequest(_))));
+        assert_eq!(*guard.0.lock().unwrap(), ["my ***"]);
+        assert_eq!(usage.total().requests, 1);
+    }
+}
@@ -1,9 +1,94 @@
+//! Provider-agnostic building blocks of the Artificial SDK.
+//!
+//! With `default-features = false` only the plain data types are built:
+//! messages and roles ([`generic`]), model identifiers ([`model`]),
+//! [`capability`] lookups, [`error`]s, [`canonical`] JSON and
+//! [`output_repair`]. They serialize with serde and can be shared with
+//! services that never talk to a provider.
+
+#[cfg(feature = "client")]
+pub mod agent_events;
+#[cfg(feature = "client")]
+pub mod audit;
+#[cfg(feature = "client")]
+pub mod cache;
+#[cfg(feature = "client")]
+pub mod cancel;
+pub mod canonical;
+pub mod capability;
+#[cfg(feature = "client")]
+pub mod cassette;
+#[cfg(feature = "client")]
+pub mod classify;
+#[cfg(feature = "client")]
 mod client;
+#[cfg(feature = "client")]
+pub mod concurrency;
+#[cfg(feature = "client")]
+pub mod context;
+#[cfg(feature = "client")]
+pub mod conversation;
+#[cfg(feature = "client")]
+pub mod dialog;
+#[cfg(feature = "client")]
+pub mod draft_verify;
 pub mod error;
+#[cfg(feature = "client")]
+pub mod failover;
 pub mod generic;
+#[cfg(feature = "client")]
+pub mod language;
+#[cfg(feature = "client")]
+pub mod latency;
+#[cfg(feature = "client")]
+pub mod layer;
 pub mod model;
+#[cfg(feature = "client")]
+pub mod moderation;
+#[cfg(feature = "client")]
+pub mod output_filter;
+pub mod output_repair;
+#[cfg(feature = "client")]
+pub mod pipeline;
+#[cfg(feature = "client")]
 pub mod provider;
+#[cfg(feature = "client")]
+pub mod quota;
+#[cfg(feature = "client")]
+pub mod recorder;
+pub mod redact;
+#[cfg(feature = "client")]
+pub mod registry;
+#[cfg(feature = "client")]
+pub mod replay;
+#[cfg(feature = "client")]
+pub mod rollout;
+#[cfg(feature = "client")]
 pub mod schema_util;
+#[cfg(feature = "client")]
+pub mod semantic_cache;
+#[cfg(feature = "client")]
+pub mod singleflight;
+#[cfg(feature = "client")]
+pub mod stream;
+#[cfg(feature = "tracing")]
+pub mod telemetry;
+#[cfg(feature = "client")]
 pub mod template;
+#[cfg(all(feature = "client", any(test, feature = "test-util")))]
+pub mod testing;
+#[cfg(feature = "client")]
+pub mod tokens;
+#[cfg(feature = "client")]
+pub mod tool_output;
+#[cfg(feature = "client")]
+pub mod tools;
+#[cfg(feature = "client")]
+pub mod transcript;
+#[cfg(feature = "client")]
+pub mod usage;
+#[cfg(feature = "client")]
+pub mod validation;
 
-pub use client::ArtificialClient;
+#[cfg(feature = "client")]
+pub use client::{ArtificialClient, PreambleMode};
@@ -30,11 +30,16 @@ use std::str::FromStr;
 /// Universal identifier for an LLM model.
 ///
 /// * `OpenAi` – Enumerated list of officially supported OpenAI models.
+/// * `XAi` / `DeepSeek` – Models served through OpenAI-compatible endpoints.
 /// * `Custom` – Any provider / model name not yet covered by a dedicated enum. Use this if you run a self-hosted or beta model.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Model {
     /// Built-in OpenAI models (chat completion API).
     OpenAi(OpenAiModel),
+    /// xAI Grok models (OpenAI-compatible API).
+    XAi(XAiModel),
+    /// DeepSeek models (OpenAI-compatible API).
+    DeepSeek(DeepSeekModel),
     /// Fully qualified provider/model ID (`"provider:model-name"` or similar).
     Custom(&'static str),
 }
@@ -69,6 +74,27 @@ pub enum OpenAiModel {
     O3,
     O3Mini,
     O4Mini,
+    /// Embedding models, see [`crate::provider::EmbeddingProvider`].
+    TextEmbedding3Small,
+    TextEmbedding3Large,
+}
+
+/// Models served by xAI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XAiModel {
+    Grok4,
+    Grok3,
+    Grok3Mini,
+}
+
+/// Models served by DeepSeek.
+///
+/// `DeepSeekReasoner` returns its chain of thought in a separate
+/// `reasoning_content` field next to the answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeepSeekModel {
+    DeepSeekChat,
+    DeepSeekReasoner,
 }
 
 impl From<OpenAiModel> for Model {
@@ -77,6 +103,18 @@ impl From<OpenAiModel> for Model {
     }
 }
 
+impl From<XAiModel> for Model {
+    fn from(val: XAiModel) -> Self {
+        Model::XAi(val)
+    }
+}
+
+impl From<DeepSeekModel> for Model {
+    fn from(val: DeepSeekModel) -> Self {
+        Model::DeepSeek(val)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ModelParseError(pub String);
 
@@ -115,6 +153,8 @@ impl AsRef<str> for OpenAiModel {
             OpenAiModel::O3 => "o3",
             OpenAiModel::O3Mini => "o3-mini",
             OpenAiModel::O4Mini => "o4-mini",
+            OpenAiModel::TextEmbedding3Small => "text-embedding-3-small",
+            OpenAiModel::TextEmbedding3Large => "text-embedding-3-large",
         }
     }
 }
@@ -148,6 +188,52 @@ impl FromStr for OpenAiModel {
             "o3" => Ok(OpenAiModel::O3),
             "o3-mini" => Ok(OpenAiModel::O3Mini),
             "o4-mini" => Ok(OpenAiModel::O4Mini),
+            "text-embedding-3-small" => Ok(OpenAiModel::TextEmbedding3Small),
+            "text-embedding-3-large" => Ok(OpenAiModel::TextEmbedding3Large),
+            _ => Err(ModelParseError(s.to_string())),
+        }
+    }
+}
+
+impl AsRef<str> for XAiModel {
+    fn as_ref(&self) -> &str {
+        match self {
+            XAiModel::Grok4 => "grok-4",
+            XAiModel::Grok3 => "grok-3",
+            XAiModel::Grok3Mini => "grok-3-mini",
+        }
+    }
+}
+
+impl FromStr for XAiModel {
+    type Err = ModelParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grok-4" => Ok(XAiModel::Grok4),
+            "grok-3" => Ok(XAiModel::Grok3),
+            "grok-3-mini" => Ok(XAiModel::Grok3Mini),
+            _ => Err(ModelParseError(s.to_string())),
+        }
+    }
+}
+
+impl AsRef<str> for DeepSeekModel {
+    fn as_ref(&self) -> &str {
+        match self {
+            DeepSeekModel::DeepSeekChat => "deepseek-chat",
+            DeepSeekModel::DeepSeekReasoner => "deepseek-reasoner",
+        }
+    }
+}
+
+impl FromStr for DeepSeekModel {
+    type Err = ModelParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deepseek-chat" => Ok(DeepSeekModel::DeepSeekChat),
+            "deepseek-reasoner" => Ok(DeepSeekModel::DeepSeekReasoner),
             _ => Err(ModelParseError(s.to_string())),
         }
     }
@@ -157,6 +243,8 @@ impl AsRef<str> for Model {
     fn as_ref(&self) -> &str {
         match self {
             Model::OpenAi(model) => model.as_ref(),
+            Model::XAi(model) => model.as_ref(),
+            Model::DeepSeek(model) => model.as_ref(),
             Model::Custom(custom) => custom,
         }
     }
@@ -166,13 +254,16 @@ impl FromStr for Model {
     type Err = ModelParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Model::OpenAi(OpenAiModel::from_str(s)?))
+        OpenAiModel::from_str(s)
+            .map(Model::OpenAi)
+            .or_else(|_| XAiModel::from_str(s).map(Model::XAi))
+            .or_else(|_| DeepSeekModel::from_str(s).map(Model::DeepSeek))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Model, OpenAiModel};
+    use super::{DeepSeekModel, Model, OpenAiModel, XAiModel};
     use std::str::FromStr;
 
     #[test]
@@ -210,6 +301,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compatible_models_parse_into_their_provider() {
+        assert_eq!(
+            Model::from_str("grok-3-mini").unwrap(),
+            Model::XAi(XAiModel::Grok3Mini)
+        );
+        assert_eq!(
+            Model::from_str("deepseek-reasoner").unwrap(),
+            Model::DeepSeek(DeepSeekModel::DeepSeekReasoner)
+        );
+        assert!(Model::from_str("unknown-model").is_err());
+    }
+
     #[test]
     fn model_as_ref_covers_openai_and_custom() {
         let openai = Model::OpenAi(OpenAiModel::Gpt5Mini);
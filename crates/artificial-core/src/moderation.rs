@@ -0,0 +1,164 @@
+//! Guardrail stage that screens user input before it reaches the model.
+//!
+//! [`ModeratedProvider`] sends the content of every user message to a
+//! [`ModerationProvider`] first. If any of it is flagged the request is not
+//! dispatched and the call fails with [`ArtificialError::PolicyViolation`]:
+//!
+//! ```rust
+//! use artificial_core::{error::ArtificialError, moderation::ModeratedProvider};
+//! # use artificial_core::{ArtificialClient, generic::GenericMessage, provider::*};
+//! # async fn run<B, Mo>(backend: B, moderation: Mo, params: ChatCompleteParameters<GenericMessage>)
+//! # where B: ChatCompletionProvider<Message = GenericMessage>, Mo: ModerationProvider {
+//! let client = ArtificialClient::new(ModeratedProvider::new(backend, moderation));
+//!
+//! match client.chat_complete(params).await {
+//!     Err(err) => match err.root() {
+//!         ArtificialError::PolicyViolation { categories } => eprintln!("rejected: {categories:?}"),
+//!         _ => eprintln!("failed: {err}"),
+//!     },
+//!     Ok(response) => { /* … */ }
+//! }
+//! # }
+//! ```
+//!
+//! The OpenAI adapter implements [`ModerationProvider`] and can serve as both
+//! arguments. System, assistant and tool messages are not screened.
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{GenericChatCompletionResponse, GenericMessage, GenericRole},
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+        ModerationProvider,
+    },
+};
+
+/// [`ChatCompletionProvider`] wrapper that moderates user content first.
+///
+/// Speaks [`GenericMessage`] so it can read the content for every backend.
+pub struct ModeratedProvider<B, Mo> {
+    inner: B,
+    moderation: Mo,
+}
+
+impl<B, Mo> ModeratedProvider<B, Mo> {
+    pub fn new(inner: B, moderation: Mo) -> Self {
+        Self { inner, moderation }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B, Mo> ModeratedProvider<B, Mo>
+where
+    Mo: ModerationProvider,
+{
+    /// Fail with [`ArtificialError::PolicyViolation`] if any user message
+    /// in `messages` is flagged.
+    pub async fn screen(&self, messages: &[GenericMessage]) -> Result<()> {
+        let inputs: Vec<String> = messages
+            .iter()
+            .filter(|m| m.role == GenericRole::User)
+            .filter_map(|m| m.content.clone())
+            .filter(|content| !content.trim().is_empty())
+            .collect();
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        let mut categories: Vec<String> = Vec::new();
+        for result in self.moderation.moderate(inputs).await? {
+            if result.flagged {
+                categories.extend(result.categories);
+            }
+        }
+        categories.sort();
+        categories.dedup();
+
+        match categories.is_empty() {
+            true => Ok(()),
+            false => Err(ArtificialError::PolicyViolation { categories }),
+        }
+    }
+}
+
+impl<B, Mo> ChatCompletionProvider for ModeratedProvider<B, Mo>
+where
+    B: ChatCompletionProvider,
+    Mo: ModerationProvider,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+        Box::pin(async move {
+            self.screen(&params.messages).await?;
+            self.inner.chat_complete(params).await
+        })
+    }
+}
+
+impl<B, Mo> HealthCheckProvider for ModeratedProvider<B, Mo>
+where
+    B: HealthCheckProvider,
+    Mo: Send + Sync,
+{
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{Model, OpenAiModel},
+        testing::MockProvider,
+    };
+
+    fn params(user: &str) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![
+                GenericMessage::new(
+                    "attack vectors are out of scope".into(),
+                    GenericRole::System,
+                ),
+                GenericMessage::new(user.into(), GenericRole::User),
+            ],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn flagged_user_content_is_not_dispatched() {
+        let mock = MockProvider::new();
+        mock.push_text("fine").flag("attack", ["violence"]);
+        let provider = ModeratedProvider::new(mock.clone(), mock.clone());
+
+        let err = provider
+            .chat_complete(params("plan an attack"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ArtificialError::PolicyViolation { ref categories } if categories == &["violence"]
+        ));
+        assert!(mock.requests().is_empty());
+
+        provider.chat_complete(params("hello")).await.unwrap();
+        assert_eq!(mock.requests().len(), 1);
+        assert_eq!(mock.moderated(), ["plan an attack", "hello"]);
+    }
+}
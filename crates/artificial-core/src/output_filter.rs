@@ -0,0 +1,473 @@
+//! Guardrail stage that scans the assistant's answer before it is shown.
+//!
+//! [`crate::moderation`] screens what goes *into* the model; an
+//! [`OutputFilter`] checks what comes *out*. It runs a set of [`TextFilter`]s
+//! over the assistant text and either masks the matches with `*` or halts the
+//! answer, depending on its [`FilterAction`]:
+//!
+//! ```rust
+//! use artificial_core::output_filter::{FilterAction, OutputFilter, TermFilter};
+//! # use artificial_core::{generic::StreamingEventsProvider, layer::ProviderLayer};
+//! # fn wrap<B: StreamingEventsProvider>(backend: B) {
+//! let filter = OutputFilter::new(FilterAction::Mask)
+//!     .with_filter(TermFilter::new("secrets", ["sk-live-", "BEGIN PRIVATE KEY"]));
+//! let provider = filter.layer(backend);
+//! # }
+//! ```
+//!
+//! # Streaming
+//!
+//! Event streams are filtered with a buffer-and-scan window: the last
+//! [`OutputFilter::with_window`] bytes of text are held back until more text
+//! arrives, so a match split across deltas is still caught before any part of
+//! it is emitted. The window must be at least as long as the longest match.
+//! Every hit is announced by a [`StreamEvent::Filtered`] event in front of the
+//! masked text; a halting filter ends the stream right after it.
+//!
+//! Non-streaming answers are masked in place, or fail with
+//! [`ArtificialError::PolicyViolation`] when the filter halts.
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    ops::Range,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use futures_core::Stream;
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{
+        GenericChatCompletionResponse, GenericMessage, ResponseContent, StreamEvent,
+        StreamingEventsProvider,
+    },
+    layer::ProviderLayer,
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+    },
+};
+
+/// Default number of bytes held back while streaming.
+const DEFAULT_WINDOW: usize = 64;
+
+/// A span of text that tripped a filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterMatch {
+    pub category: String,
+    /// Byte range of the match; must fall on `char` boundaries.
+    pub range: Range<usize>,
+}
+
+/// Finds offending spans in assistant text.
+///
+/// Closures `Fn(&str) -> Vec<FilterMatch>` implement this, for checks that
+/// do not warrant a type of their own.
+pub trait TextFilter: Send + Sync {
+    fn scan(&self, text: &str) -> Vec<FilterMatch>;
+}
+
+impl<F> TextFilter for F
+where
+    F: Fn(&str) -> Vec<FilterMatch> + Send + Sync,
+{
+    fn scan(&self, text: &str) -> Vec<FilterMatch> {
+        self(text)
+    }
+}
+
+/// Matches any of a list of terms, ignoring ASCII case.
+#[derive(Debug, Clone)]
+pub struct TermFilter {
+    category: String,
+    terms: Vec<String>,
+}
+
+impl TermFilter {
+    pub fn new<I, T>(category: impl Into<String>, terms: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        Self {
+            category: category.into(),
+            terms: terms
+                .into_iter()
+                .map(|t| t.as_ref().to_ascii_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl TextFilter for TermFilter {
+    fn scan(&self, text: &str) -> Vec<FilterMatch> {
+        // ASCII lowercasing keeps byte offsets intact.
+        let haystack = text.to_ascii_lowercase();
+        self.terms
+            .iter()
+            .flat_map(|term| {
+                haystack
+                    .match_indices(term.as_str())
+                    .map(|(start, _)| FilterMatch {
+                        category: self.category.clone(),
+                        range: start..start + term.len(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// What happens to text that trips a filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Replace every matched character with `*` and carry on.
+    Mask,
+    /// Stop the answer before the first match.
+    Halt,
+}
+
+/// A set of [`TextFilter`]s and what to do when one of them trips. Clones
+/// share the filters.
+#[derive(Clone)]
+pub struct OutputFilter {
+    filters: Vec<Arc<dyn TextFilter>>,
+    action: FilterAction,
+    window: usize,
+}
+
+impl fmt::Debug for OutputFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutputFilter")
+            .field("filters", &self.filters.len())
+            .field("action", &self.action)
+            .field("window", &self.window)
+            .finish()
+    }
+}
+
+impl OutputFilter {
+    pub fn new(action: FilterAction) -> Self {
+        Self {
+            filters: Vec::new(),
+            action,
+            window: DEFAULT_WINDOW,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: impl TextFilter + 'static) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Bytes of streamed text held back for matches spanning several
+    /// deltas. Default 64.
+    pub fn with_window(mut self, bytes: usize) -> Self {
+        self.window = bytes;
+        self
+    }
+
+    /// Every match of every filter in `text`, ordered by position.
+    pub fn scan(&self, text: &str) -> Vec<FilterMatch> {
+        let mut matches: Vec<FilterMatch> =
+            self.filters.iter().flat_map(|f| f.scan(text)).collect();
+        matches.sort_by_key(|m| (m.range.start, m.range.end));
+        matches
+    }
+
+    /// Filter a complete answer: the masked text, or
+    /// [`ArtificialError::PolicyViolation`] when the filter halts.
+    pub fn apply(&self, text: &str) -> Result<String> {
+        let matches = self.scan(text);
+        if matches.is_empty() {
+            return Ok(text.to_owned());
+        }
+        match self.action {
+            FilterAction::Mask => Ok(mask(text, &matches)),
+            FilterAction::Halt => Err(ArtificialError::PolicyViolation {
+                categories: categories(&matches),
+            }),
+        }
+    }
+
+    /// Filter the text deltas of an event stream.
+    pub fn filter_events<S>(&self, events: S) -> FilteredEvents<S>
+    where
+        S: Stream<Item = Result<StreamEvent>>,
+    {
+        FilteredEvents {
+            inner: Box::pin(events),
+            filter: self.clone(),
+            pending: String::new(),
+            queue: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Wrap a backend so its answers are filtered.
+    pub fn provider<B>(&self, inner: B) -> FilteredProvider<B> {
+        FilteredProvider {
+            inner,
+            filter: self.clone(),
+        }
+    }
+}
+
+impl<B> ProviderLayer<B> for OutputFilter {
+    type Provider = FilteredProvider<B>;
+
+    fn layer(&self, inner: B) -> FilteredProvider<B> {
+        self.provider(inner)
+    }
+}
+
+fn categories(matches: &[FilterMatch]) -> Vec<String> {
+    let mut categories: Vec<String> = matches.iter().map(|m| m.category.clone()).collect();
+    categories.sort();
+    categories.dedup();
+    categories
+}
+
+fn mask(text: &str, matches: &[FilterMatch]) -> String {
+    text.char_indices()
+        .map(
+            |(i, c)| match matches.iter().any(|m| m.range.contains(&i)) {
+                true => '*',
+                false => c,
+            },
+        )
+        .collect()
+}
+
+/// Event stream returned by [`OutputFilter::filter_events`].
+pub struct FilteredEvents<S> {
+    inner: Pin<Box<S>>,
+    filter: OutputFilter,
+    /// Text scanned but not yet emitted.
+    pending: String,
+    queue: VecDeque<Result<StreamEvent>>,
+    done: bool,
+}
+
+impl<S> FilteredEvents<S> {
+    /// Scan the pending text and queue what may be emitted; everything when
+    /// `flush` is set, all but the window otherwise.
+    fn scan_pending(&mut self, flush: bool) {
+        let matches = self.filter.scan(&self.pending);
+        if !matches.is_empty() {
+            let categories = categories(&matches);
+            match self.filter.action {
+                FilterAction::Halt => {
+                    let start = matches[0].range.start;
+                    if start > 0 {
+                        self.queue
+                            .push_back(Ok(StreamEvent::TextDelta(self.pending[..start].into())));
+                    }
+                    self.queue.push_back(Ok(StreamEvent::Filtered {
+                        categories,
+                        halted: true,
+                    }));
+                    self.pending.clear();
+                    self.done = true;
+                    return;
+                }
+                FilterAction::Mask => {
+                    self.pending = mask(&self.pending, &matches);
+                    self.queue.push_back(Ok(StreamEvent::Filtered {
+                        categories,
+                        halted: false,
+                    }));
+                }
+            }
+        }
+
+        let keep = if flush { 0 } else { self.filter.window };
+        let mut ready = self.pending.len().saturating_sub(keep);
+        while !self.pending.is_char_boundary(ready) {
+            ready -= 1;
+        }
+        if ready > 0 {
+            let text: String = self.pending.drain(..ready).collect();
+            self.queue.push_back(Ok(StreamEvent::TextDelta(text)));
+        }
+    }
+}
+
+impl<S> Stream for FilteredEvents<S>
+where
+    S: Stream<Item = Result<StreamEvent>>,
+{
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            if self.done {
+                return Poll::Ready(None);
+            }
+            match ready!(self.inner.as_mut().poll_next(cx)) {
+                Some(Ok(StreamEvent::TextDelta(text))) => {
+                    self.pending.push_str(&text);
+                    self.scan_pending(false);
+                }
+                Some(other) => {
+                    // Text goes out before whatever follows it.
+                    self.scan_pending(true);
+                    if !self.done {
+                        self.queue.push_back(other);
+                    }
+                }
+                None => {
+                    self.scan_pending(true);
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+/// Backend wrapper from [`OutputFilter::provider`].
+pub struct FilteredProvider<B> {
+    inner: B,
+    filter: OutputFilter,
+}
+
+impl<B> FilteredProvider<B> {
+    pub fn filter(&self) -> &OutputFilter {
+        &self.filter
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B: ChatCompletionProvider> ChatCompletionProvider for FilteredProvider<B> {
+    type Message = B::Message;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        Box::pin(async move {
+            let mut response = self.inner.chat_complete(params).await?;
+            let message = match &mut response.content {
+                ResponseContent::Finished(message) | ResponseContent::ToolCalls(message) => message,
+            };
+            if let Some(content) = &message.content {
+                message.content = Some(self.filter.apply(content)?);
+            }
+            Ok(response)
+        })
+    }
+}
+
+impl<B: StreamingEventsProvider> StreamingEventsProvider for FilteredProvider<B> {
+    type EventStream<'s>
+        = FilteredEvents<B::EventStream<'s>>
+    where
+        Self: 's;
+
+    fn chat_complete_events_stream<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Self::EventStream<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        self.filter
+            .filter_events(self.inner.chat_complete_events_stream(params))
+    }
+}
+
+impl<B: HealthCheckProvider> HealthCheckProvider for FilteredProvider<B> {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{
+        generic::GenericRole,
+        model::{Model, OpenAiModel},
+        testing::{MockProvider, MockReply},
+    };
+
+    fn params() -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new("key?".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    async fn events(action: FilterAction) -> Vec<StreamEvent> {
+        let mock = MockProvider::new();
+        mock.push(MockReply::chunks([
+            "Your key is sk-",
+            "LIVE-123, ",
+            "keep it safe.",
+        ]));
+        let provider = OutputFilter::new(action)
+            .with_filter(TermFilter::new("secrets", ["sk-live-123"]))
+            .with_window(16)
+            .provider(mock);
+
+        provider
+            .chat_complete_events_stream(params())
+            .map(Result::unwrap)
+            .collect()
+            .await
+    }
+
+    fn text(events: &[StreamEvent]) -> String {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::TextDelta(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn matches_split_across_deltas_are_masked() {
+        let events = events(FilterAction::Mask).await;
+        assert_eq!(text(&events), "Your key is ***********, keep it safe.");
+        assert!(events.iter().any(|e| matches!(
+            e,
+            StreamEvent::Filtered { categories, halted: false } if categories == &["secrets"]
+        )));
+        assert!(matches!(events.last(), Some(StreamEvent::MessageEnd)));
+    }
+
+    #[tokio::test]
+    async fn halting_ends_the_stream_before_the_match() {
+        let events = events(FilterAction::Halt).await;
+        assert_eq!(text(&events), "Your key is ");
+        assert!(matches!(
+            events.last(),
+            Some(StreamEvent::Filtered { halted: true, .. })
+        ));
+
+        let mock = MockProvider::new();
+        mock.push_text("Your key is sk-live-123");
+        let provider = OutputFilter::new(FilterAction::Halt)
+            .with_filter(TermFilter::new("secrets", ["sk-live-123"]))
+            .provider(mock);
+        let err = provider.chat_complete(params()).await.unwrap_err();
+        assert!(matches!(err, ArtificialError::PolicyViolation { .. }));
+    }
+}
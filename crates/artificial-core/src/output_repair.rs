@@ -0,0 +1,259 @@
+//! Cheap, local fixes for structured answers that are *almost* JSON.
+//!
+//! Models asked for JSON still wrap it in markdown code fences, put a
+//! sentence in front ("Sure, here is the result:") or leave a trailing comma
+//! behind. An [`OutputRepair`] pass removes exactly these mistakes before the
+//! text is parsed, and reports each [`Repair`] it made so callers can see
+//! how often a model needs help:
+//!
+//! ```rust
+//! use artificial_core::output_repair::{OutputRepair, Repair};
+//!
+//! let raw = "Here you go:\n```json\n{\"tags\": [\"a\", \"b\",],}\n```";
+//! let repaired = OutputRepair::new().repair(raw);
+//!
+//! assert_eq!(repaired.text, r#"{"tags": ["a", "b"]}"#);
+//! assert_eq!(
+//!     repaired.repairs,
+//!     [Repair::StrippedCodeFence, Repair::RemovedTrailingCommas]
+//! );
+//! ```
+//!
+//! Text that already is valid JSON is returned unchanged, so the pass is
+//! safe to run on every answer. Anything the pass cannot fix is left for the
+//! regular parse error (and the model-side repairs of
+//! `ExecutionPolicy::max_repairs`).
+use serde::{Deserialize, Serialize};
+
+/// A single fix applied by [`OutputRepair`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Repair {
+    /// The JSON was wrapped in a markdown code fence.
+    StrippedCodeFence,
+    /// Prose in front of the JSON value was dropped.
+    RemovedLeadingText,
+    /// Prose after the end of the JSON value was dropped.
+    RemovedTrailingText,
+    /// Commas directly before `}` or `]` were removed.
+    RemovedTrailingCommas,
+}
+
+/// Result of [`OutputRepair::repair`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairedOutput {
+    pub text: String,
+    /// The repairs applied, in order; empty if `text` is the input.
+    pub repairs: Vec<Repair>,
+}
+
+impl RepairedOutput {
+    pub fn is_repaired(&self) -> bool {
+        !self.repairs.is_empty()
+    }
+}
+
+/// Pre-parse stage that turns near-JSON model output into JSON.
+///
+/// All repairs are enabled by default; individual ones can be switched off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputRepair {
+    pub strip_code_fences: bool,
+    pub strip_surrounding_text: bool,
+    pub remove_trailing_commas: bool,
+}
+
+impl Default for OutputRepair {
+    fn default() -> Self {
+        Self {
+            strip_code_fences: true,
+            strip_surrounding_text: true,
+            remove_trailing_commas: true,
+        }
+    }
+}
+
+impl OutputRepair {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strip_code_fences(mut self, enabled: bool) -> Self {
+        self.strip_code_fences = enabled;
+        self
+    }
+
+    /// Drop prose before the first `{`/`[` and after the matching bracket.
+    pub fn with_strip_surrounding_text(mut self, enabled: bool) -> Self {
+        self.strip_surrounding_text = enabled;
+        self
+    }
+
+    pub fn with_remove_trailing_commas(mut self, enabled: bool) -> Self {
+        self.remove_trailing_commas = enabled;
+        self
+    }
+
+    /// Apply the enabled repairs to `raw`, unless it already parses as JSON.
+    pub fn repair(&self, raw: &str) -> RepairedOutput {
+        if is_json(raw) {
+            return RepairedOutput {
+                text: raw.to_owned(),
+                repairs: Vec::new(),
+            };
+        }
+
+        let mut repairs = Vec::new();
+        let mut text = raw.trim();
+
+        if self.strip_code_fences {
+            if let Some(inner) = fenced_block(text) {
+                text = inner.trim();
+                repairs.push(Repair::StrippedCodeFence);
+            }
+        }
+
+        if self.strip_surrounding_text {
+            if let Some(start) = text.find(['{', '[']) {
+                if !text[..start].trim().is_empty() {
+                    repairs.push(Repair::RemovedLeadingText);
+                }
+                text = &text[start..];
+                if let Some(end) = value_end(text) {
+                    if !text[end..].trim().is_empty() {
+                        repairs.push(Repair::RemovedTrailingText);
+                    }
+                    text = &text[..end];
+                }
+            }
+        }
+
+        let mut text = text.to_owned();
+        if self.remove_trailing_commas {
+            if let Some(cleaned) = without_trailing_commas(&text) {
+                text = cleaned;
+                repairs.push(Repair::RemovedTrailingCommas);
+            }
+        }
+
+        RepairedOutput { text, repairs }
+    }
+}
+
+fn is_json(text: &str) -> bool {
+    serde_json::from_str::<serde::de::IgnoredAny>(text).is_ok()
+}
+
+/// The body of the first ```` ``` ```` fence in `text`, without the
+/// language tag. An unterminated fence runs to the end of the text.
+fn fenced_block(text: &str) -> Option<&str> {
+    let open = text.find("```")?;
+    let after_open = &text[open + 3..];
+    let body = match after_open.find('\n') {
+        Some(newline) => &after_open[newline + 1..],
+        None => after_open,
+    };
+    Some(match body.find("```") {
+        Some(close) => &body[..close],
+        None => body,
+    })
+}
+
+/// Byte offset just past the bracket closing the value `text` starts with.
+fn value_end(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `text` without commas that only precede whitespace and a closing
+/// bracket, or `None` if there are none. Commas inside strings are kept.
+fn without_trailing_commas(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut removed = false;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' && text[index + 1..].trim_start().starts_with(['}', ']']) {
+            removed = true;
+            continue;
+        }
+        out.push(c);
+    }
+    removed.then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_json_is_left_alone() {
+        let repair = OutputRepair::new();
+        for raw in [r#"{"a": [1, 2]}"#, r#""plain string""#, "42"] {
+            let repaired = repair.repair(raw);
+            assert_eq!(repaired.text, raw);
+            assert!(!repaired.is_repaired());
+        }
+    }
+
+    #[test]
+    fn chatter_around_the_value_is_dropped() {
+        let repaired =
+            OutputRepair::new().repair(r#"Sure! {"note": "a, b}"} Let me know if you need more."#);
+        assert_eq!(repaired.text, r#"{"note": "a, b}"}"#);
+        assert_eq!(
+            repaired.repairs,
+            [Repair::RemovedLeadingText, Repair::RemovedTrailingText]
+        );
+    }
+
+    #[test]
+    fn commas_inside_strings_survive() {
+        let repaired = OutputRepair::new().repair(r#"{"list": ",]", "n": [1,],}"#);
+        assert_eq!(repaired.text, r#"{"list": ",]", "n": [1]}"#);
+        assert_eq!(repaired.repairs, [Repair::RemovedTrailingCommas]);
+    }
+
+    #[test]
+    fn disabled_repairs_are_skipped() {
+        let repaired = OutputRepair::new()
+            .with_strip_code_fences(false)
+            .with_strip_surrounding_text(false)
+            .repair("```json\n{\"a\": 1,}\n```");
+        assert_eq!(repaired.text, "```json\n{\"a\": 1}\n```");
+        assert_eq!(repaired.repairs, [Repair::RemovedTrailingCommas]);
+    }
+}
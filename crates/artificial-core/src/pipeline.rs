@@ -0,0 +1,527 @@
+//! Pipelines configured at runtime.
+//!
+//! Which model runs a template, how its answer is checked and where to go
+//! when it fails is operational tuning that should not need a recompile. A
+//! [`PipelineConfig`] describes it declaratively; [`Pipelines`] resolves the
+//! templates against a [`PromptRegistry`] and runs them by name:
+//!
+//! ```yaml
+//! pipelines:
+//!   ticket-summary:
+//!     template: summarise@2.1.0   # or just `summarise` for the latest version
+//!     model: gpt-4o-mini          # defaults to the template's model
+//!     temperature: 0.2
+//!     attempts: 2                 # per model, before falling back
+//!     validations:
+//!       - check: not_empty
+//!         field: summary
+//!       - check: max_length
+//!         field: summary
+//!         max: 400
+//!       - check: one_of
+//!         field: priority
+//!         values: [low, normal, high]
+//!     fallbacks: [gpt-4o]
+//! ```
+//!
+//! JSON is always supported; YAML and TOML need the `yaml` and `toml`
+//! features. To change the routing, load the file again and swap the
+//! [`Pipelines`] value.
+//!
+//! ```rust,no_run
+//! use artificial_core::{pipeline::{PipelineConfig, Pipelines}, registry::PromptRegistry};
+//! # async fn run<B>(backend: B, registry: PromptRegistry) -> artificial_core::error::Result<()>
+//! # where B: artificial_core::provider::ChatCompletionProvider<Message = artificial_core::generic::GenericMessage> {
+//! let config = PipelineConfig::load("pipelines.yaml")?;
+//! let pipelines = Pipelines::new(&config, &registry)?;
+//!
+//! let summary = pipelines
+//!     .run(&backend, "ticket-summary", serde_json::json!({ "ticket": "Printer on fire" }))
+//!     .await?;
+//! println!("{} via {}", summary.output, summary.model.as_ref());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Templates take part by being registered with
+//! [`PromptRegistry::register_executable`]; the pipeline input is
+//! deserialized into the template and its answer returned as JSON.
+use std::{collections::BTreeMap, fmt, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{GenericMessage, GenericUsageReport, ResponseContent, ResponseMetadata},
+    model::Model,
+    provider::{ChatCompleteParameters, ChatCompletionProvider, Temperature},
+    registry::{Executable, PromptInfo, PromptRegistry},
+};
+
+/// The pipelines of a configuration file, by name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    #[serde(default)]
+    pub pipelines: BTreeMap<String, PipelineSpec>,
+}
+
+/// One pipeline: template → model → validations → fallbacks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineSpec {
+    /// Registered template name, optionally pinned as `name@1.2.0`.
+    /// Unpinned names use the latest registered version.
+    pub template: String,
+    /// Model identifier such as `gpt-4o-mini`; defaults to the template's
+    /// model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Tries per model, counting failed requests and failed validations.
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub validations: Vec<OutputCheck>,
+    /// Models tried in order once the primary model used up its attempts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<String>,
+}
+
+fn default_attempts() -> u32 {
+    1
+}
+
+/// A check on the parsed answer. `field` is a dotted path into the output
+/// (`summary`, `items.0.name`); an empty path checks the whole output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum OutputCheck {
+    /// The field exists and is not `null`, `""`, `[]` or `{}`.
+    NotEmpty { field: String },
+    /// Strings by characters, arrays by items.
+    MaxLength { field: String, max: usize },
+    /// The field equals one of `values`.
+    OneOf { field: String, values: Vec<Value> },
+}
+
+impl OutputCheck {
+    /// A description of the problem, or `None` if `output` passes.
+    pub fn violation(&self, output: &Value) -> Option<String> {
+        let (field, value) = match self {
+            Self::NotEmpty { field }
+            | Self::MaxLength { field, .. }
+            | Self::OneOf { field, .. } => (field, lookup(output, field)),
+        };
+        match (self, value) {
+            (Self::NotEmpty { .. }, None | Some(Value::Null)) => {
+                Some(format!("`{field}` is missing"))
+            }
+            (Self::NotEmpty { .. }, Some(value)) => {
+                let empty = match value {
+                    Value::String(s) => s.trim().is_empty(),
+                    Value::Array(a) => a.is_empty(),
+                    Value::Object(o) => o.is_empty(),
+                    _ => false,
+                };
+                empty.then(|| format!("`{field}` is empty"))
+            }
+            (Self::MaxLength { max, .. }, Some(value)) => {
+                let length = match value {
+                    Value::String(s) => s.chars().count(),
+                    Value::Array(a) => a.len(),
+                    _ => return Some(format!("`{field}` has no length")),
+                };
+                (length > *max)
+                    .then(|| format!("`{field}` is {length} long, at most {max} allowed"))
+            }
+            (Self::OneOf { values, .. }, Some(value)) => (!values.contains(value))
+                .then(|| format!("`{field}` is {value}, expected one of {values:?}")),
+            (_, None) => Some(format!("`{field}` is missing")),
+        }
+    }
+}
+
+fn lookup<'v>(output: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(output, |value, segment| match value {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => value.get(segment),
+        })
+}
+
+impl PipelineConfig {
+    pub fn from_json(source: &str) -> Result<Self> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(source: &str) -> Result<Self> {
+        serde_yaml::from_str(source)
+            .map_err(|err| ArtificialError::Invalid(format!("pipeline config: {err}")))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(source: &str) -> Result<Self> {
+        toml::from_str(source)
+            .map_err(|err| ArtificialError::Invalid(format!("pipeline config: {err}")))
+    }
+
+    /// Read `path`, choosing the format by its extension (`json`, and
+    /// `yaml`/`yml` or `toml` with the respective feature).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|err| {
+            ArtificialError::Invalid(format!("cannot read {}: {err}", path.display()))
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&source),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::from_yaml(&source),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&source),
+            _ => Err(ArtificialError::Invalid(format!(
+                "unsupported pipeline config format: {}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+struct Pipeline {
+    info: PromptInfo,
+    executable: Executable,
+    models: Vec<Model>,
+    temperature: Option<Temperature>,
+    max_tokens: Option<u32>,
+    attempts: u32,
+    validations: Vec<OutputCheck>,
+}
+
+/// Answer of [`Pipelines::run`].
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    /// The template's postprocessed output.
+    pub output: Value,
+    /// The model that produced it.
+    pub model: Model,
+    /// Attempts over all models, including the successful one.
+    pub attempts: u32,
+    /// Summed over all attempts.
+    pub usage: Option<GenericUsageReport>,
+    pub metadata: ResponseMetadata,
+}
+
+/// Pipelines resolved against a [`PromptRegistry`], ready to run.
+pub struct Pipelines {
+    pipelines: BTreeMap<String, Pipeline>,
+}
+
+impl fmt::Debug for Pipelines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.pipelines.keys()).finish()
+    }
+}
+
+impl Pipelines {
+    /// Resolve every pipeline of `config`. Unknown templates and models are
+    /// reported together, one per line.
+    pub fn new(config: &PipelineConfig, registry: &PromptRegistry) -> Result<Self> {
+        let mut pipelines = BTreeMap::new();
+        let mut problems = Vec::new();
+        for (name, spec) in &config.pipelines {
+            match resolve(spec, registry) {
+                Ok(pipeline) => {
+                    pipelines.insert(name.clone(), pipeline);
+                }
+                Err(problem) => problems.push(format!("{name}: {problem}")),
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(Self { pipelines }),
+            false => Err(ArtificialError::Invalid(format!(
+                "{} pipeline problem(s) found:\n{}",
+                problems.len(),
+                problems.join("\n")
+            ))),
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.pipelines.keys().map(String::as_str)
+    }
+
+    /// Run pipeline `name` with `input`, the JSON form of its template.
+    ///
+    /// Each model gets the configured number of attempts; a request error or
+    /// a failed validation moves on to the next attempt, then to the next
+    /// fallback. The last error is returned once all are used up.
+    pub async fn run<B>(&self, backend: &B, name: &str, input: Value) -> Result<PipelineOutput>
+    where
+        B: ChatCompletionProvider,
+        GenericMessage: Into<B::Message>,
+    {
+        let pipeline = self
+            .pipelines
+            .get(name)
+            .ok_or_else(|| ArtificialError::InvalidRequest(format!("unknown pipeline `{name}`")))?;
+        let messages = (pipeline.executable.render)(input)?;
+        let template_params = (pipeline.executable.params)();
+
+        let mut usage: Option<GenericUsageReport> = None;
+        let mut attempts = 0;
+        let mut last_error = None;
+        for model in &pipeline.models {
+            let mut params = ChatCompleteParameters::new(messages.clone(), model.clone());
+            params.response_format = (pipeline.executable.attach_schema)(
+                template_params.schema_mode_for(model),
+                &mut params.messages,
+            )?;
+            params.temperature = match pipeline.temperature {
+                Some(temperature) => Some(temperature),
+                None => template_params.checked_temperature()?,
+            };
+            params.max_tokens = pipeline.max_tokens.or(template_params.max_tokens);
+            params.seed = template_params.seed;
+
+            for _ in 0..pipeline.attempts {
+                attempts += 1;
+                let response = match backend.chat_complete(params.clone()).await {
+                    Ok(response) => response,
+                    Err(ArtificialError::Cancelled) => return Err(ArtificialError::Cancelled),
+                    Err(err) => {
+                        last_error = Some(err);
+                        continue;
+                    }
+                };
+                if let Some(report) = response.usage {
+                    usage = Some(match usage {
+                        None => report,
+                        Some(total) => GenericUsageReport {
+                            prompt_tokens: total.prompt_tokens + report.prompt_tokens,
+                            completion_tokens: total.completion_tokens + report.completion_tokens,
+                            total_tokens: total.total_tokens + report.total_tokens,
+                        },
+                    });
+                }
+
+                match check(pipeline, response.content) {
+                    Ok(output) => {
+                        return Ok(PipelineOutput {
+                            output,
+                            model: model.clone(),
+                            attempts,
+                            usage,
+                            metadata: ResponseMetadata {
+                                template: Some(pipeline.info.name.clone()),
+                                template_version: Some(pipeline.info.version.to_string()),
+                                ..response.metadata
+                            },
+                        });
+                    }
+                    Err(err) => last_error = Some(err),
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            ArtificialError::InvalidRequest(format!("pipeline `{name}` has no attempts"))
+        }))
+    }
+}
+
+/// Parse an answer and run the validations on it.
+fn check(pipeline: &Pipeline, content: ResponseContent<GenericMessage>) -> Result<Value> {
+    let answer = match content {
+        ResponseContent::Finished(message) => message.content.unwrap_or_default(),
+        ResponseContent::ToolCalls(_) => {
+            return Err(ArtificialError::Invalid(
+                "pipeline templates cannot call tools".into(),
+            ))
+        }
+    };
+    let output = (pipeline.executable.parse)(&answer)?;
+    let violations: Vec<String> = pipeline
+        .validations
+        .iter()
+        .filter_map(|check| check.violation(&output))
+        .collect();
+    match violations.is_empty() {
+        true => Ok(output),
+        false => Err(ArtificialError::Invalid(format!(
+            "output failed validation: {}",
+            violations.join("; ")
+        ))),
+    }
+}
+
+fn resolve(
+    spec: &PipelineSpec,
+    registry: &PromptRegistry,
+) -> std::result::Result<Pipeline, String> {
+    let (template, version) = match spec.template.split_once('@') {
+        Some((template, version)) => (template, Some(version)),
+        None => (spec.template.as_str(), None),
+    };
+    let info = match version {
+        Some(version) => {
+            let version = version
+                .parse()
+                .map_err(|err: ArtificialError| err.to_string())?;
+            registry.get(template, &version)
+        }
+        None => registry.latest(template),
+    }
+    .ok_or_else(|| format!("template `{}` is not registered", spec.template))?;
+    let executable = registry
+        .executable(info)
+        .ok_or_else(|| format!("template `{}` is not executable", spec.template))?;
+
+    let parse_model =
+        |name: &String| Model::from_str(name).map_err(|_| format!("unknown model `{name}`"));
+    let mut models = vec![match &spec.model {
+        Some(model) => parse_model(model)?,
+        None => (executable.model)(),
+    }];
+    for fallback in &spec.fallbacks {
+        models.push(parse_model(fallback)?);
+    }
+
+    Ok(Pipeline {
+        info: info.clone(),
+        executable,
+        models,
+        temperature: spec
+            .temperature
+            .map(Temperature::new)
+            .transpose()
+            .map_err(|err| err.to_string())?,
+        max_tokens: spec.max_tokens,
+        attempts: spec.attempts.max(1),
+        validations: spec.validations.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        generic::GenericRole,
+        model::OpenAiModel,
+        template::{IntoPrompt, PromptTemplate},
+        testing::MockProvider,
+    };
+
+    #[derive(Deserialize)]
+    struct Triage {
+        ticket: String,
+    }
+
+    #[derive(Serialize, Deserialize, schemars::JsonSchema)]
+    struct Verdict {
+        priority: String,
+    }
+
+    impl IntoPrompt for Triage {
+        type Message = GenericMessage;
+
+        fn into_prompt(self) -> Vec<GenericMessage> {
+            vec![GenericMessage::new(self.ticket, GenericRole::User)]
+        }
+    }
+
+    impl PromptTemplate for Triage {
+        type Output = Verdict;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+
+        fn name() -> &'static str {
+            "triage"
+        }
+
+        fn version() -> Option<&'static str> {
+            Some("1.0.0")
+        }
+    }
+
+    fn pipelines() -> Pipelines {
+        let mut registry = PromptRegistry::new();
+        registry.register_executable::<Triage>().unwrap();
+        let config = PipelineConfig::from_json(
+            r#"{ "pipelines": { "triage": {
+                "template": "triage@1.0.0",
+                "validations": [{ "check": "one_of", "field": "priority", "values": ["low", "high"] }],
+                "fallbacks": ["gpt-4o"]
+            } } }"#,
+        )
+        .unwrap();
+        Pipelines::new(&config, &registry).unwrap()
+    }
+
+    #[tokio::test]
+    async fn invalid_answers_fall_back_to_the_next_model() {
+        let backend = MockProvider::new();
+        backend.push_json(json!({ "priority": "urgent!!" }));
+        backend.push_json(json!({ "priority": "high" }));
+
+        let answer = pipelines()
+            .run(&backend, "triage", json!({ "ticket": "Printer on fire" }))
+            .await
+            .unwrap();
+
+        assert_eq!(answer.output, json!({ "priority": "high" }));
+        assert_eq!(answer.model, Model::OpenAi(OpenAiModel::Gpt4o));
+        assert_eq!(answer.attempts, 2);
+        assert_eq!(answer.metadata.template_version.as_deref(), Some("1.0.0"));
+        let models: Vec<_> = backend.requests().iter().map(|r| r.model.clone()).collect();
+        assert_eq!(
+            models,
+            [
+                Model::OpenAi(OpenAiModel::Gpt4oMini),
+                Model::OpenAi(OpenAiModel::Gpt4o)
+            ]
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_configs_parse() {
+        let config = PipelineConfig::from_yaml(
+            "pipelines:\n  triage:\n    template: triage\n    attempts: 2\n    validations:\n      - check: not_empty\n        field: priority\n",
+        )
+        .unwrap();
+        let spec = &config.pipelines["triage"];
+        assert_eq!(spec.attempts, 2);
+        assert_eq!(
+            spec.validations,
+            [OutputCheck::NotEmpty {
+                field: "priority".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn unknown_templates_and_models_are_reported_together() {
+        let config = PipelineConfig::from_json(
+            r#"{ "pipelines": {
+                "a": { "template": "missing" },
+                "b": { "template": "triage", "model": "gpt-17" }
+            } }"#,
+        )
+        .unwrap();
+        let mut registry = PromptRegistry::new();
+        registry.register_executable::<Triage>().unwrap();
+
+        let err = Pipelines::new(&config, &registry).unwrap_err().to_string();
+        assert!(
+            err.contains("a: template `missing` is not registered"),
+            "{err}"
+        );
+        assert!(err.contains("b: unknown model `gpt-17`"), "{err}");
+    }
+}
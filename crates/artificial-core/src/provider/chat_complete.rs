@@ -1,9 +1,16 @@
-use std::{future::Future, pin::Pin};
+use std::{fmt, future::Future, pin::Pin, time::Duration};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    error::Result,
+    cancel::CancellationToken,
+    error::{ArtificialError, Result},
     generic::{GenericChatCompletionResponse, GenericFunctionSpec, GenericMessage},
+    latency::LatencyEstimator,
     model::Model,
+    redact,
+    schema_util::{DynamicOutput, SchemaMode},
 };
 use futures_core::stream::Stream;
 
@@ -55,13 +62,65 @@ pub trait StreamingChatProvider: Send + Sync {
         M: Into<Self::Message> + Clone + Send + Sync + 's;
 }
 
-#[derive(Debug, Clone)]
+/// `Debug` output shows the number of messages rather than their content,
+/// see [`crate::redact`].
+#[derive(Clone)]
 pub struct ChatCompleteParameters<M: Clone> {
     pub messages: Vec<M>,
     pub model: Model,
     pub tools: Option<Vec<GenericFunctionSpec>>,
-    pub temperature: Option<f64>,
+    pub temperature: Option<Temperature>,
+    pub top_p: Option<TopP>,
+    /// Upper bound for generated tokens (including reasoning tokens).
+    pub max_tokens: Option<u32>,
+    /// Sequences that end generation when produced.
+    pub stop: Option<Vec<String>>,
+    pub presence_penalty: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+    /// Best-effort determinism: equal seeds and parameters tend to produce
+    /// equal answers.
+    pub seed: Option<i64>,
+    /// How much reasoning a reasoning model may do before answering.
+    /// Backends without such a knob ignore it.
+    pub reasoning_effort: Option<ReasoningEffort>,
     pub response_format: Option<serde_json::Value>,
+    /// Routing hint for provider-side prompt caching. Requests sharing a key
+    /// (and a common prefix) are more likely to hit a warm cache.
+    pub cache_key: Option<String>,
+    /// Upper bound for a single HTTP attempt, replacing the backend's
+    /// configured default. Streams count until their last chunk.
+    pub timeout: Option<Duration>,
+    /// Aborts the request (or stream) when cancelled.
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl<M: Clone + fmt::Debug> fmt::Debug for ChatCompleteParameters<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ChatCompleteParameters");
+        match redact::content_visible() {
+            true => debug.field("messages", &self.messages),
+            false => debug.field(
+                "messages",
+                &format_args!("<{} messages>", self.messages.len()),
+            ),
+        };
+        debug
+            .field("model", &self.model)
+            .field("tools", &self.tools)
+            .field("temperature", &self.temperature)
+            .field("top_p", &self.top_p)
+            .field("max_tokens", &self.max_tokens)
+            .field("stop", &self.stop)
+            .field("presence_penalty", &self.presence_penalty)
+            .field("frequency_penalty", &self.frequency_penalty)
+            .field("seed", &self.seed)
+            .field("reasoning_effort", &self.reasoning_effort)
+            .field("response_format", &self.response_format)
+            .field("cache_key", &self.cache_key)
+            .field("timeout", &self.timeout)
+            .field("cancellation", &self.cancellation)
+            .finish()
+    }
 }
 
 impl<M: Clone> ChatCompleteParameters<M> {
@@ -71,7 +130,17 @@ impl<M: Clone> ChatCompleteParameters<M> {
             model,
             tools: None,
             temperature: None,
+            top_p: None,
+            max_tokens: None,
+            stop: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            seed: None,
+            reasoning_effort: None,
             response_format: None,
+            cache_key: None,
+            timeout: None,
+            cancellation: None,
         }
     }
 
@@ -87,18 +156,404 @@ impl<M: Clone> ChatCompleteParameters<M> {
         self.tools.as_ref()
     }
 
-    pub fn with_temperature(mut self, temperature: f64) -> Self {
+    pub fn with_temperature(mut self, temperature: Temperature) -> Self {
         self.temperature = Some(temperature);
         self
     }
 
+    pub fn with_top_p(mut self, top_p: TopP) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_stop<S: Into<String>>(mut self, stop: impl IntoIterator<Item = S>) -> Self {
+        self.stop = Some(stop.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_presence_penalty(mut self, penalty: f64) -> Self {
+        self.presence_penalty = Some(penalty);
+        self
+    }
+
+    pub fn with_frequency_penalty(mut self, penalty: f64) -> Self {
+        self.frequency_penalty = Some(penalty);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_reasoning_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(effort);
+        self
+    }
+
     pub fn with_response_format(mut self, response_format: serde_json::Value) -> Self {
         self.response_format = Some(response_format);
         self
     }
 
+    /// Ask for output matching the schema of `T`, attached as `mode` says;
+    /// replaces any `response_format` set before. The answer still has to be
+    /// parsed by the caller.
+    pub fn with_output_schema<T>(mut self, mode: SchemaMode) -> Result<Self>
+    where
+        T: JsonSchema + 'static,
+        GenericMessage: Into<M>,
+    {
+        self.response_format = mode.attach::<T, M>(&mut self.messages)?;
+        Ok(self)
+    }
+
+    /// Like [`Self::with_output_schema`], for a schema known only at
+    /// runtime; parse the answer with [`DynamicOutput::parse`].
+    pub fn with_dynamic_output(mut self, output: &DynamicOutput, mode: SchemaMode) -> Self
+    where
+        GenericMessage: Into<M>,
+    {
+        self.response_format = output.attach(mode, &mut self.messages);
+        self
+    }
+
     pub fn with_tools(mut self, tools: Vec<GenericFunctionSpec>) -> Self {
         self.tools = Some(tools);
         self
     }
+
+    pub fn with_cache_key(mut self, cache_key: impl Into<String>) -> Self {
+        self.cache_key = Some(cache_key.into());
+        self
+    }
+
+    /// Override the backend's request timeout, e.g. for slow reasoning
+    /// models.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Abort the request once `token` is cancelled; the call then fails
+    /// with [`crate::error::ArtificialError::Cancelled`].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Convert every message with `f` while keeping all other parameters.
+    ///
+    /// Wrapping providers use this to normalise caller messages (e.g. into
+    /// [`GenericMessage`]) before forwarding the request.
+    pub fn map_messages<N: Clone>(self, f: impl FnMut(M) -> N) -> ChatCompleteParameters<N> {
+        ChatCompleteParameters {
+            messages: self.messages.into_iter().map(f).collect(),
+            model: self.model,
+            tools: self.tools,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            stop: self.stop,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            seed: self.seed,
+            reasoning_effort: self.reasoning_effort,
+            response_format: self.response_format,
+            cache_key: self.cache_key,
+            timeout: self.timeout,
+            cancellation: self.cancellation,
+        }
+    }
+
+    /// The request in serialisable form, e.g. to record it or to match it
+    /// against a recording.
+    pub fn snapshot(&self) -> RequestSnapshot
+    where
+        M: Into<GenericMessage>,
+    {
+        RequestSnapshot {
+            model: self.model.as_ref().to_string(),
+            messages: self.messages.iter().cloned().map(Into::into).collect(),
+            tools: self.tools.clone(),
+            temperature: self.temperature.map(Temperature::get),
+            top_p: self.top_p.map(TopP::get),
+            max_tokens: self.max_tokens,
+            stop: self.stop.clone(),
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            seed: self.seed,
+            reasoning_effort: self.reasoning_effort,
+            response_format: self.response_format.clone(),
+            cache_key: self.cache_key.clone(),
+        }
+    }
+}
+
+/// Sampling temperature, `0.0..=2.0`.
+///
+/// Out-of-range values are rejected when the value is created rather than
+/// by the provider, which answers them with an unhelpful 400:
+///
+/// ```rust
+/// use artificial_core::provider::{Temperature, TopP};
+///
+/// assert_eq!(Temperature::new(0.7)?.get(), 0.7);
+/// assert!(Temperature::new(2.5).is_err());
+/// assert!(TopP::try_from(f64::NAN).is_err());
+/// # Ok::<(), artificial_core::error::ArtificialError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Temperature(f64);
+
+impl Temperature {
+    /// # Errors
+    ///
+    /// [`ArtificialError::InvalidRequest`] outside `0.0..=2.0` or for NaN.
+    pub fn new(value: f64) -> Result<Self> {
+        in_range("temperature", value, 2.0).map(Self)
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+/// Nucleus sampling probability mass, `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct TopP(f64);
+
+impl TopP {
+    /// # Errors
+    ///
+    /// [`ArtificialError::InvalidRequest`] outside `0.0..=1.0` or for NaN.
+    pub fn new(value: f64) -> Result<Self> {
+        in_range("top_p", value, 1.0).map(Self)
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+fn in_range(name: &str, value: f64, max: f64) -> Result<f64> {
+    match (0.0..=max).contains(&value) {
+        true => Ok(value),
+        false => Err(ArtificialError::InvalidRequest(format!(
+            "{name} must be within 0..={max}, got {value}"
+        ))),
+    }
+}
+
+impl TryFrom<f64> for Temperature {
+    type Error = ArtificialError;
+
+    fn try_from(value: f64) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl From<Temperature> for f64 {
+    fn from(value: Temperature) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<f64> for TopP {
+    type Error = ArtificialError;
+
+    fn try_from(value: f64) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl From<TopP> for f64 {
+    fn from(value: TopP) -> Self {
+        value.0
+    }
+}
+
+/// Everything of a [`ChatCompleteParameters`] that determines the answer,
+/// see [`ChatCompleteParameters::snapshot`].
+///
+/// Runtime-only settings (`timeout`, `cancellation`) are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestSnapshot {
+    /// Model identifier as in [`Model::as_ref`].
+    pub model: String,
+    pub messages: Vec<GenericMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<GenericFunctionSpec>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_key: Option<String>,
+}
+
+/// Provider-agnostic reasoning budget of reasoning models (OpenAI o-series
+/// and GPT-5, …). Less effort answers faster and cheaper.
+/// Ordered from least to most effort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl AsRef<str> for ReasoningEffort {
+    fn as_ref(&self) -> &str {
+        match self {
+            ReasoningEffort::Minimal => "minimal",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+impl ReasoningEffort {
+    /// The next lower effort, `None` below [`ReasoningEffort::Minimal`].
+    pub fn lower(self) -> Option<Self> {
+        match self {
+            ReasoningEffort::High => Some(ReasoningEffort::Medium),
+            ReasoningEffort::Medium => Some(ReasoningEffort::Low),
+            ReasoningEffort::Low => Some(ReasoningEffort::Minimal),
+            ReasoningEffort::Minimal => None,
+        }
+    }
+}
+
+/// Wall-clock budget for a reasoning model.
+///
+/// The first attempt runs at `start` effort. An attempt that times out is
+/// retried one effort lower, down to `floor`, so a slow answer degrades to a
+/// faster one instead of failing. Every attempt but the last gets half of
+/// the remaining time; the last gets all of it.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use artificial_core::provider::{ReasoningEffort, ThinkingBudget};
+///
+/// let budget = ThinkingBudget::new(Duration::from_secs(60)).with_floor(ReasoningEffort::Minimal);
+/// assert_eq!(budget.efforts().len(), 4);
+/// assert_eq!(budget.attempt_timeout(Duration::from_secs(60), 0), Duration::from_secs(30));
+/// assert_eq!(budget.attempt_timeout(Duration::from_secs(8), 3), Duration::from_secs(8));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThinkingBudget {
+    /// Time for all attempts together.
+    pub total: Duration,
+    pub start: ReasoningEffort,
+    /// Lowest effort to retry with. A floor above `start` means no
+    /// lower-effort retries.
+    pub floor: ReasoningEffort,
+}
+
+impl ThinkingBudget {
+    /// Start at [`ReasoningEffort::High`] and go down to
+    /// [`ReasoningEffort::Low`].
+    pub fn new(total: Duration) -> Self {
+        Self {
+            total,
+            start: ReasoningEffort::High,
+            floor: ReasoningEffort::Low,
+        }
+    }
+
+    pub fn starting_at(mut self, effort: ReasoningEffort) -> Self {
+        self.start = effort;
+        self
+    }
+
+    pub fn with_floor(mut self, effort: ReasoningEffort) -> Self {
+        self.floor = effort;
+        self
+    }
+
+    /// The effort of every attempt, in order.
+    pub fn efforts(&self) -> Vec<ReasoningEffort> {
+        let mut efforts = vec![self.start];
+        let mut effort = self.start;
+        while effort > self.floor {
+            match effort.lower() {
+                Some(lower) => efforts.push(lower),
+                None => break,
+            }
+            effort = efforts[efforts.len() - 1];
+        }
+        efforts
+    }
+
+    /// Timeout of the 0-based `attempt` with `remaining` time left.
+    pub fn attempt_timeout(&self, remaining: Duration, attempt: usize) -> Duration {
+        match attempt + 1 < self.efforts().len() {
+            true => remaining / 2,
+            false => remaining,
+        }
+    }
+
+    /// Start at the highest effort whose observed p90 latency on `model`
+    /// fits into the first attempt's timeout, so the budget is not spent on
+    /// an attempt that will likely time out. Efforts without estimates are
+    /// assumed to fit.
+    pub fn fit_to(self, estimator: &LatencyEstimator, model: &Model) -> Self {
+        let efforts = self.efforts();
+        let fits = |(index, effort): &(usize, ReasoningEffort)| {
+            let budget = Self {
+                start: *effort,
+                ..self
+            };
+            estimator
+                .estimate(model, Some(*effort))
+                .is_none_or(|estimate| estimate.p90 <= budget.attempt_timeout(self.total, 0))
+                || *index + 1 == efforts.len()
+        };
+        match efforts.iter().copied().enumerate().find(fits) {
+            Some((_, effort)) => self.starting_at(effort),
+            None => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_floor_above_the_start_allows_no_lower_attempts() {
+        let budget = ThinkingBudget::new(Duration::from_secs(60))
+            .starting_at(ReasoningEffort::Low)
+            .with_floor(ReasoningEffort::High);
+
+        assert_eq!(budget.efforts(), [ReasoningEffort::Low]);
+        assert_eq!(
+            budget.attempt_timeout(Duration::from_secs(60), 0),
+            Duration::from_secs(60)
+        );
+    }
 }
@@ -0,0 +1,25 @@
+use std::{future::Future, pin::Pin};
+
+use crate::{error::Result, generic::GenericUsageReport, model::Model};
+
+/// Vectors returned by [`EmbeddingProvider::embed`], one per input text and
+/// in input order.
+#[derive(Debug, Clone)]
+pub struct EmbeddingResponse {
+    pub embeddings: Vec<Vec<f32>>,
+    /// Summed over all requests the provider needed; embeddings only bill
+    /// prompt tokens.
+    pub usage: Option<GenericUsageReport>,
+}
+
+/// Provider capability for turning texts into embedding vectors.
+///
+/// Implementations split large inputs into as many requests as the backend
+/// requires, so callers may pass any number of texts.
+pub trait EmbeddingProvider: Send + Sync {
+    fn embed<'s>(
+        &'s self,
+        texts: Vec<String>,
+        model: Model,
+    ) -> Pin<Box<dyn Future<Output = Result<EmbeddingResponse>> + Send + 's>>;
+}
@@ -0,0 +1,63 @@
+use std::{fmt, future::Future, pin::Pin, time::Duration};
+
+/// Outcome of a single backend probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// The backend answered the probe successfully.
+    Healthy,
+    /// The probe failed; the payload carries a human-readable reason.
+    Unhealthy(String),
+}
+
+impl HealthStatus {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, HealthStatus::Healthy)
+    }
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthStatus::Healthy => write!(f, "healthy"),
+            HealthStatus::Unhealthy(reason) => write!(f, "unhealthy: {reason}"),
+        }
+    }
+}
+
+/// Health information about one configured backend.
+#[derive(Debug, Clone)]
+pub struct BackendHealth {
+    /// Short identifier of the backend (e.g. `"openai"`).
+    pub backend: String,
+    pub status: HealthStatus,
+    /// Wall-clock time the probe took, including network latency.
+    pub latency: Duration,
+}
+
+/// Aggregated result of [`crate::ArtificialClient::health_check`].
+#[derive(Debug, Clone, Default)]
+pub struct HealthReport {
+    pub backends: Vec<BackendHealth>,
+}
+
+impl HealthReport {
+    /// `true` if at least one backend was probed and **all** of them are
+    /// healthy. Use this as the answer to a readiness probe.
+    pub fn is_ready(&self) -> bool {
+        !self.backends.is_empty() && self.backends.iter().all(|b| b.status.is_healthy())
+    }
+
+    /// Iterate over the backends that failed their probe.
+    pub fn unhealthy(&self) -> impl Iterator<Item = &BackendHealth> {
+        self.backends.iter().filter(|b| !b.status.is_healthy())
+    }
+}
+
+/// Provider capability for cheap reachability probes.
+///
+/// Implementations should issue the **cheapest** request the provider offers
+/// (e.g. listing models) and never consume completion tokens. Composite
+/// providers wrapping several backends return one entry per backend.
+pub trait HealthCheckProvider: Send + Sync {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>>;
+}
@@ -1,7 +1,19 @@
 mod chat_complete;
 pub use chat_complete::*;
+mod embedding;
+pub use embedding::*;
+mod health;
+pub use health::*;
+mod moderation;
+pub use moderation::*;
+mod multi_choice;
+pub use multi_choice::*;
 mod prompt_execute;
+mod prompt_stream;
 pub use crate::generic::StreamingEventsProvider;
 pub use prompt_execute::*;
+pub use prompt_stream::*;
 mod transcription;
 pub use transcription::*;
+mod warm;
+pub use warm::*;
@@ -0,0 +1,22 @@
+use std::{collections::BTreeMap, future::Future, pin::Pin};
+
+use crate::error::Result;
+
+/// Verdict of a moderation provider on one input.
+#[derive(Debug, Clone, Default)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    /// Categories the input was flagged for, e.g. `"harassment"`.
+    pub categories: Vec<String>,
+    /// Provider score per category, `0.0..=1.0`.
+    pub scores: BTreeMap<String, f64>,
+}
+
+/// Provider capability for screening text against a content policy.
+pub trait ModerationProvider: Send + Sync {
+    /// One result per input, in input order.
+    fn moderate<'s>(
+        &'s self,
+        inputs: Vec<String>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<ModerationResult>>> + Send + 's>>;
+}
@@ -0,0 +1,71 @@
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    error::Result,
+    generic::{GenericMessage, GenericUsageReport, ResponseMetadata},
+    provider::ChatCompleteParameters,
+};
+
+/// One of the answers of [`MultiChoiceProvider::chat_complete_n`].
+#[derive(Debug, Clone)]
+pub struct Choice {
+    pub message: GenericMessage,
+    /// As reported by the provider, e.g. `stop` or `length`.
+    pub finish_reason: Option<String>,
+}
+
+impl Choice {
+    /// Whether the model ended the answer itself rather than hitting a limit
+    /// or a filter.
+    pub fn is_complete(&self) -> bool {
+        matches!(
+            self.finish_reason.as_deref(),
+            None | Some("stop" | "tool_calls")
+        )
+    }
+}
+
+/// Answers of [`MultiChoiceProvider::chat_complete_n`], in the provider's
+/// order.
+#[derive(Debug, Clone)]
+pub struct MultiChoiceResponse {
+    pub choices: Vec<Choice>,
+    /// Covers all choices; the prompt is billed once.
+    pub usage: Option<GenericUsageReport>,
+    pub metadata: ResponseMetadata,
+}
+
+/// Provider capability for sampling several answers to one request, e.g.
+/// for best-of-n selection or self-consistency voting.
+///
+/// Backends that support it generate all answers in one call, so the
+/// prompt tokens are paid once.
+///
+/// ```rust,no_run
+/// # use artificial_core::{generic::GenericMessage, provider::*};
+/// # async fn vote<B: MultiChoiceProvider<Message = GenericMessage>>(backend: B, params: ChatCompleteParameters<GenericMessage>) -> artificial_core::error::Result<()> {
+/// use std::collections::HashMap;
+///
+/// let params = params.with_temperature(Temperature::new(0.8)?);
+/// let response = backend.chat_complete_n(params, 5).await?;
+/// let mut votes: HashMap<String, usize> = HashMap::new();
+/// for choice in response.choices.iter().filter(|c| c.is_complete()) {
+///     *votes.entry(choice.message.content.clone().unwrap_or_default()).or_default() += 1;
+/// }
+/// let majority = votes.into_iter().max_by_key(|(_, count)| *count);
+/// # Ok(())
+/// # }
+/// ```
+pub trait MultiChoiceProvider: Send + Sync {
+    /// Chat message type consumed by this backend.
+    type Message: Send + Sync + 'static;
+
+    /// Request `n` independent answers to `params`.
+    fn chat_complete_n<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+        n: u32,
+    ) -> Pin<Box<dyn Future<Output = Result<MultiChoiceResponse>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's;
+}
@@ -3,6 +3,9 @@ use std::{future::Future, pin::Pin};
 use crate::{
     error::Result,
     generic::GenericChatCompletionResponse,
+    model::Model,
+    output_repair::OutputRepair,
+    provider::ThinkingBudget,
     template::{IntoPrompt, PromptTemplate},
 };
 
@@ -38,6 +41,127 @@ pub trait PromptExecutionProvider: Send + Sync {
         'a: 'p,
         P: PromptTemplate + Send + Sync + 'p,
         <P as IntoPrompt>::Message: Into<Self::Message>;
+
+    /// Like [`Self::prompt_execute`], with opt-in behaviour configured by
+    /// `policy` (see [`ExecutionPolicy`]).
+    ///
+    /// The default implementation ignores the policy; providers override it
+    /// for the options they support.
+    fn prompt_execute_with_policy<'a, 'p, P>(
+        &'a self,
+        prompt: P,
+        policy: ExecutionPolicy,
+    ) -> BoxedResponseFut<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        let _ = policy;
+        self.prompt_execute(prompt)
+    }
+}
+
+/// Opt-in execution behaviour for
+/// [`PromptExecutionProvider::prompt_execute_with_policy`].
+///
+/// ```rust
+/// use artificial_core::model::{Model, OpenAiModel};
+/// use artificial_core::provider::ExecutionPolicy;
+///
+/// // Re-prompt up to two times if the answer does not match `P::Output`,
+/// // loosening the temperature and moving to a larger model on the way.
+/// let policy = ExecutionPolicy::new()
+///     .with_max_repairs(2)
+///     .with_temperature_schedule([0.0, 0.3, 0.7])
+///     .with_model_escalation([Model::OpenAi(OpenAiModel::Gpt4o)]);
+///
+/// assert_eq!(policy.temperature_for(3), Some(0.7));
+/// assert_eq!(policy.model_for(1), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionPolicy {
+    /// How often a response that fails to deserialize into `P::Output` is
+    /// sent back to the model together with the error, asking for a
+    /// corrected answer. `0` (default) fails on the first malformed answer.
+    pub max_repairs: u32,
+    /// Temperature per attempt, starting with the first one; the last entry
+    /// is reused for further attempts. Empty leaves the provider default.
+    pub temperatures: Vec<f64>,
+    /// Models for the repair attempts 2, 3, …; the last entry is reused.
+    /// The first attempt always uses [`PromptTemplate::MODEL`].
+    pub escalation: Vec<Model>,
+    /// Repeat the request once if the model returned
+    /// [`crate::error::ArtificialError::EmptyResponse`]. The extra request
+    /// does not count against `max_repairs`.
+    pub retry_on_empty: bool,
+    /// Retry timed-out attempts with less reasoning effort; replaces the
+    /// template's [`crate::template::TemplateParams::thinking_budget`].
+    pub thinking_budget: Option<ThinkingBudget>,
+    /// Fix code fences, surrounding prose and trailing commas locally
+    /// before parsing; applied repairs are listed in
+    /// [`crate::generic::ResponseMetadata::repairs`]. Runs before any
+    /// model-side repair.
+    pub output_repair: Option<OutputRepair>,
+}
+
+impl ExecutionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_repairs(mut self, max_repairs: u32) -> Self {
+        self.max_repairs = max_repairs;
+        self
+    }
+
+    /// Retrying a deterministic failure deterministically rarely helps, so
+    /// later attempts can sample more freely.
+    pub fn with_temperature_schedule(
+        mut self,
+        temperatures: impl IntoIterator<Item = f64>,
+    ) -> Self {
+        self.temperatures = temperatures.into_iter().collect();
+        self
+    }
+
+    pub fn with_model_escalation(mut self, models: impl IntoIterator<Item = Model>) -> Self {
+        self.escalation = models.into_iter().collect();
+        self
+    }
+
+    pub fn with_retry_on_empty(mut self, retry_on_empty: bool) -> Self {
+        self.retry_on_empty = retry_on_empty;
+        self
+    }
+
+    pub fn with_thinking_budget(mut self, budget: ThinkingBudget) -> Self {
+        self.thinking_budget = Some(budget);
+        self
+    }
+
+    pub fn with_output_repair(mut self, repair: OutputRepair) -> Self {
+        self.output_repair = Some(repair);
+        self
+    }
+
+    /// Temperature for the 1-based `attempt`, if scheduled.
+    pub fn temperature_for(&self, attempt: u32) -> Option<f64> {
+        scheduled(&self.temperatures, (attempt as usize).saturating_sub(1)).copied()
+    }
+
+    /// Model override for the 1-based `attempt`; `None` keeps the template's
+    /// model.
+    pub fn model_for(&self, attempt: u32) -> Option<&Model> {
+        match attempt {
+            0 | 1 => None,
+            n => scheduled(&self.escalation, n as usize - 2),
+        }
+    }
+}
+
+fn scheduled<T>(schedule: &[T], index: usize) -> Option<&T> {
+    schedule.get(index).or_else(|| schedule.last())
 }
 
 pub type BoxedResponseFut<'p, Output> =
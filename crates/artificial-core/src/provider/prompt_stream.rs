@@ -0,0 +1,167 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+
+use crate::{
+    error::Result,
+    provider::PromptExecutionProvider,
+    stream::{Partial, PartialJson},
+    template::{IntoPrompt, PromptTemplate},
+};
+
+/// Item of a [`PromptStreamingProvider::prompt_execute_stream`] stream.
+#[derive(Debug, Clone)]
+pub enum PromptStreamEvent<T> {
+    /// Raw text as the model produces it – for structured prompts this is
+    /// partial JSON, good for progress indicators.
+    Delta(String),
+    /// The complete answer, parsed into the template's output. Always the
+    /// last item of a successful stream.
+    Finished(T),
+}
+
+pub type BoxedPromptStream<'p, T> =
+    Pin<Box<dyn Stream<Item = Result<PromptStreamEvent<T>>> + Send + 'p>>;
+
+/// Item of a [`PromptStreamingProvider::prompt_execute_stream_partial`]
+/// stream.
+#[derive(Debug, Clone)]
+pub enum PartialStreamEvent<T> {
+    /// The answer as far as it is known, emitted whenever it grew.
+    Partial(Partial<T>),
+    /// The complete answer, parsed into the template's output. Always the
+    /// last item of a successful stream.
+    Finished(T),
+}
+
+pub type BoxedPartialStream<'p, T> =
+    Pin<Box<dyn Stream<Item = Result<PartialStreamEvent<T>>> + Send + 'p>>;
+
+/// A [`PromptExecutionProvider`] that can stream the answer of a template
+/// while it is generated.
+///
+/// ```rust,no_run
+/// use artificial_core::provider::{PromptStreamEvent, PromptStreamingProvider};
+/// # use artificial_core::{generic::GenericMessage, model::*, template::*};
+/// # #[derive(serde::Deserialize, schemars::JsonSchema)] struct Summary { text: String }
+/// # struct Summarize;
+/// # impl IntoPrompt for Summarize { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+/// # impl PromptTemplate for Summarize { type Output = Summary; const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini); }
+/// # async fn run<B: PromptStreamingProvider<Message = GenericMessage>>(backend: B) -> artificial_core::error::Result<()> {
+/// use futures_util::StreamExt;
+///
+/// let mut stream = backend.prompt_execute_stream(Summarize);
+/// while let Some(event) = stream.next().await {
+///     match event? {
+///         PromptStreamEvent::Delta(text) => print!("{text}"),
+///         PromptStreamEvent::Finished(summary) => println!("\n\n{}", summary.text),
+///     }
+/// }
+/// # Ok(()) }
+/// ```
+pub trait PromptStreamingProvider: PromptExecutionProvider {
+    /// Stream text deltas of the answer to `prompt`, followed by the answer
+    /// parsed into `P::Output` once the model is done.
+    ///
+    /// The final parse is not repaired; an answer that does not match the
+    /// schema ends the stream with an error.
+    fn prompt_execute_stream<'a, 'p, P>(&'a self, prompt: P) -> BoxedPromptStream<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        P::Output: Send,
+        <P as IntoPrompt>::Message: Into<Self::Message>;
+
+    /// Like [`prompt_execute_stream`](Self::prompt_execute_stream), but
+    /// parses the deltas as they arrive and yields progressively filled
+    /// [`Partial`] views of the answer.
+    ///
+    /// Use [`Partial::items`] to render list entries as soon as they are
+    /// complete, or [`Partial::text`] to show a text field while it is
+    /// written.
+    ///
+    /// ```rust,no_run
+    /// use artificial_core::provider::{PartialStreamEvent, PromptStreamingProvider};
+    /// # use artificial_core::{generic::GenericMessage, model::*, template::*};
+    /// # #[derive(serde::Deserialize, schemars::JsonSchema)] struct Outline { chapters: Vec<Chapter> }
+    /// # #[derive(serde::Deserialize, schemars::JsonSchema)] struct Chapter { title: String }
+    /// # struct Plan;
+    /// # impl IntoPrompt for Plan { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+    /// # impl PromptTemplate for Plan { type Output = Outline; const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini); }
+    /// # async fn run<B: PromptStreamingProvider<Message = GenericMessage>>(backend: B) -> artificial_core::error::Result<()> {
+    /// use futures_util::StreamExt;
+    ///
+    /// let mut shown = 0;
+    /// let mut stream = backend.prompt_execute_stream_partial(Plan);
+    /// while let Some(event) = stream.next().await {
+    ///     match event? {
+    ///         PartialStreamEvent::Partial(outline) => {
+    ///             let chapters: Vec<Chapter> = outline.items("/chapters");
+    ///             for chapter in &chapters[shown..] {
+    ///                 println!("- {}", chapter.title);
+    ///             }
+    ///             shown = chapters.len();
+    ///         }
+    ///         PartialStreamEvent::Finished(outline) => println!("{} chapters", outline.chapters.len()),
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    fn prompt_execute_stream_partial<'a, 'p, P>(
+        &'a self,
+        prompt: P,
+    ) -> BoxedPartialStream<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        P::Output: Send,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        Box::pin(PartialEvents {
+            inner: self.prompt_execute_stream(prompt),
+            json: PartialJson::new(),
+            last: None,
+        })
+    }
+}
+
+/// Turns the deltas of a prompt stream into [`Partial`] snapshots.
+struct PartialEvents<'p, T> {
+    inner: BoxedPromptStream<'p, T>,
+    json: PartialJson,
+    last: Option<Partial<T>>,
+}
+
+impl<T> Stream for PartialEvents<'_, T> {
+    type Item = Result<PartialStreamEvent<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let event = match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => event,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            match event {
+                PromptStreamEvent::Finished(output) => {
+                    return Poll::Ready(Some(Ok(PartialStreamEvent::Finished(output))));
+                }
+                PromptStreamEvent::Delta(delta) => {
+                    this.json.push(&delta);
+                    let Some(partial) = this.json.snapshot() else {
+                        continue;
+                    };
+                    if this.last.as_ref() != Some(&partial) {
+                        this.last = Some(partial.clone());
+                        return Poll::Ready(Some(Ok(PartialStreamEvent::Partial(partial))));
+                    }
+                }
+            }
+        }
+    }
+}
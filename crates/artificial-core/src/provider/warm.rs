@@ -0,0 +1,23 @@
+use std::{future::Future, pin::Pin};
+
+use crate::{error::Result, generic::GenericUsageReport, provider::ChatCompleteParameters};
+
+/// Provider capability to pre-populate its prompt cache.
+///
+/// Implementations send `params` with the **smallest possible** completion
+/// budget; the answer is discarded.  The only purpose of the call is to make
+/// the provider process (and cache) the prefix, so that later requests
+/// starting with the same messages are cheaper and faster.
+pub trait PromptWarmingProvider: Send + Sync {
+    /// Chat message type consumed by this backend.
+    type Message: Send + Sync + 'static;
+
+    /// Issue the warm-up request and return the usage it incurred, if the
+    /// provider reports it.
+    fn warm_prefix<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<GenericUsageReport>>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's;
+}
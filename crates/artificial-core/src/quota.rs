@@ -0,0 +1,382 @@
+//! Token-bucket quotas that survive process restarts.
+//!
+//! A [`QuotaManager`] keeps one [`TokenBucket`] per key, e.g. `"requests"`
+//! and `"tokens"` for a provider's per-minute limits. [`QuotaManager::reserve`]
+//! takes from a bucket and tells how long to wait before the call fits the
+//! budget.
+//!
+//! Short-lived workers would start every life with full buckets and burst
+//! straight into the provider's limits after each deploy. With a
+//! [`QuotaStore`] the state is saved on [`QuotaManager::persist`] and picked
+//! up again by [`QuotaManager::restore`]; buckets refill for the time the
+//! process was down:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use artificial_core::quota::{FileQuotaStore, QuotaManager};
+//! # async fn run() -> artificial_core::error::Result<()> {
+//! let quota = QuotaManager::new()
+//!     .with_bucket("requests", 500.0, Duration::from_secs(60))
+//!     .with_bucket("tokens", 200_000.0, Duration::from_secs(60))
+//!     .with_store(FileQuotaStore::new("/var/lib/worker/quota.json"));
+//! quota.restore().await?;
+//!
+//! let wait = quota.reserve("requests", 1.0).max(quota.reserve("tokens", 1_200.0));
+//! // … sleep for `wait`, send the request …
+//!
+//! quota.persist().await?; // e.g. on shutdown or every few seconds
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Stores carry state from one process to its successor. Workers that run
+//! *concurrently* against the same store overwrite each other's state; give
+//! each its own key prefix or share budgets through the provider instead.
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ArtificialError, Result};
+
+/// Persisted part of a [`TokenBucket`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BucketState {
+    /// Tokens left; negative while reservations are queued.
+    pub tokens: f64,
+    /// Unix time in milliseconds at which `tokens` was accurate.
+    pub updated_at_ms: u64,
+}
+
+/// `capacity` tokens, refilled evenly over `period`.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_ms: f64,
+    state: BucketState,
+}
+
+impl TokenBucket {
+    /// A full bucket.
+    pub fn new(capacity: f64, period: Duration) -> Self {
+        Self {
+            capacity,
+            refill_per_ms: capacity / period.as_millis().max(1) as f64,
+            state: BucketState {
+                tokens: capacity,
+                updated_at_ms: now_ms(),
+            },
+        }
+    }
+
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    pub fn state(&self) -> BucketState {
+        self.state
+    }
+
+    /// Continue from a saved state, refilled up to now.
+    pub fn restore(&mut self, state: BucketState) {
+        self.state = BucketState {
+            tokens: state.tokens.min(self.capacity),
+            updated_at_ms: state.updated_at_ms,
+        };
+        self.refill(now_ms());
+    }
+
+    /// Adopt a limit reported by the provider: `capacity` per `period` with
+    /// `remaining` left. Local reservations the provider has not seen yet
+    /// are kept, so the bucket never holds more than either side believes.
+    pub fn sync(&mut self, capacity: f64, period: Duration, remaining: f64) {
+        self.refill(now_ms());
+        self.capacity = capacity;
+        self.refill_per_ms = capacity / period.as_millis().max(1) as f64;
+        self.state.tokens = self.state.tokens.min(remaining).min(capacity);
+    }
+
+    /// Take `amount` tokens and return how long to wait until they are
+    /// covered. The reservation counts immediately, so concurrent callers
+    /// queue up behind each other.
+    pub fn reserve(&mut self, amount: f64) -> Duration {
+        self.reserve_at(amount, now_ms())
+    }
+
+    fn reserve_at(&mut self, amount: f64, now_ms: u64) -> Duration {
+        self.refill(now_ms);
+        self.state.tokens -= amount;
+        if self.state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_millis((-self.state.tokens / self.refill_per_ms).ceil() as u64)
+        }
+    }
+
+    fn refill(&mut self, now_ms: u64) {
+        let elapsed = now_ms.saturating_sub(self.state.updated_at_ms);
+        self.state.tokens =
+            (self.state.tokens + elapsed as f64 * self.refill_per_ms).min(self.capacity);
+        self.state.updated_at_ms = self.state.updated_at_ms.max(now_ms);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Where [`QuotaManager`] keeps bucket state between processes.
+///
+/// Implement it for whatever the deployment already has (Redis, a database
+/// row, …); [`FileQuotaStore`] and [`MemoryQuotaStore`] cover the simple
+/// cases.
+pub trait QuotaStore: Send + Sync {
+    fn load<'s>(
+        &'s self,
+        key: &'s str,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<BucketState>>> + Send + 's>>;
+
+    fn save<'s>(
+        &'s self,
+        key: &'s str,
+        state: BucketState,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 's>>;
+}
+
+/// In-process store, e.g. for tests or to hand state between clients.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryQuotaStore {
+    states: Arc<Mutex<HashMap<String, BucketState>>>,
+}
+
+impl MemoryQuotaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn states(&self) -> std::sync::MutexGuard<'_, HashMap<String, BucketState>> {
+        self.states.lock().expect("quota store poisoned")
+    }
+}
+
+impl QuotaStore for MemoryQuotaStore {
+    fn load<'s>(
+        &'s self,
+        key: &'s str,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<BucketState>>> + Send + 's>> {
+        let state = self.states().get(key).copied();
+        Box::pin(async move { Ok(state) })
+    }
+
+    fn save<'s>(
+        &'s self,
+        key: &'s str,
+        state: BucketState,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 's>> {
+        self.states().insert(key.to_owned(), state);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// All buckets in one JSON file, replaced atomically on every save.
+///
+/// Uses blocking file I/O; the file is tiny, but keep saves off hot paths.
+#[derive(Debug, Clone)]
+pub struct FileQuotaStore {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+impl FileQuotaStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn read(&self) -> Result<HashMap<String, BucketState>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(ArtificialError::Backend(Box::new(err))),
+        }
+    }
+}
+
+impl QuotaStore for FileQuotaStore {
+    fn load<'s>(
+        &'s self,
+        key: &'s str,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<BucketState>>> + Send + 's>> {
+        Box::pin(async move {
+            let _guard = self.lock.lock().expect("quota file lock poisoned");
+            Ok(self.read()?.get(key).copied())
+        })
+    }
+
+    fn save<'s>(
+        &'s self,
+        key: &'s str,
+        state: BucketState,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 's>> {
+        Box::pin(async move {
+            let _guard = self.lock.lock().expect("quota file lock poisoned");
+            let mut states = self.read()?;
+            states.insert(key.to_owned(), state);
+
+            let tmp = self.path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec(&states)?)
+                .and_then(|()| std::fs::rename(&tmp, &self.path))
+                .map_err(|err| ArtificialError::Backend(Box::new(err)))
+        })
+    }
+}
+
+/// Named token buckets with optional persistence. Clones share state.
+#[derive(Clone, Default)]
+pub struct QuotaManager {
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+    store: Option<Arc<dyn QuotaStore>>,
+}
+
+impl QuotaManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `capacity` units per `period` under `key`.
+    pub fn with_bucket(self, key: impl Into<String>, capacity: f64, period: Duration) -> Self {
+        self.buckets()
+            .insert(key.into(), TokenBucket::new(capacity, period));
+        self
+    }
+
+    pub fn with_store(mut self, store: impl QuotaStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Take `amount` from the bucket `key` and return how long to wait
+    /// before using it. Unknown keys are unlimited.
+    pub fn reserve(&self, key: &str, amount: f64) -> Duration {
+        self.buckets()
+            .get_mut(key)
+            .map_or(Duration::ZERO, |bucket| bucket.reserve(amount))
+    }
+
+    /// Align the bucket `key` with a limit reported by the provider, see
+    /// [`TokenBucket::sync`]. Unknown keys get a new bucket holding
+    /// `remaining`.
+    pub fn sync(&self, key: &str, capacity: f64, period: Duration, remaining: f64) {
+        let mut buckets = self.buckets();
+        match buckets.get_mut(key) {
+            Some(bucket) => bucket.sync(capacity, period, remaining),
+            None => {
+                let mut bucket = TokenBucket::new(capacity, period);
+                bucket.state.tokens = remaining.min(capacity);
+                buckets.insert(key.to_owned(), bucket);
+            }
+        }
+    }
+
+    pub fn state(&self, key: &str) -> Option<BucketState> {
+        self.buckets().get(key).map(TokenBucket::state)
+    }
+
+    /// Load the saved state of every bucket. Buckets without saved state
+    /// stay as they are.
+    pub async fn restore(&self) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        for key in self.keys() {
+            if let Some(state) = store.load(&key).await? {
+                if let Some(bucket) = self.buckets().get_mut(&key) {
+                    bucket.restore(state);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the state of every bucket.
+    pub async fn persist(&self) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let states: Vec<(String, BucketState)> = self
+            .buckets()
+            .iter()
+            .map(|(key, bucket)| (key.clone(), bucket.state()))
+            .collect();
+        for (key, state) in states {
+            store.save(&key, state).await?;
+        }
+        Ok(())
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.buckets().keys().cloned().collect()
+    }
+
+    fn buckets(&self) -> std::sync::MutexGuard<'_, HashMap<String, TokenBucket>> {
+        self.buckets.lock().expect("quota buckets poisoned")
+    }
+}
+
+impl std::fmt::Debug for QuotaManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuotaManager")
+            .field("buckets", &*self.buckets())
+            .field("persistent", &self.store.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills_over_time_and_queues_reservations() {
+        let mut bucket = TokenBucket::new(10.0, Duration::from_secs(10));
+        let start = bucket.state().updated_at_ms;
+
+        assert_eq!(bucket.reserve_at(10.0, start), Duration::ZERO);
+        assert_eq!(bucket.reserve_at(2.0, start), Duration::from_secs(2));
+        assert_eq!(bucket.reserve_at(1.0, start), Duration::from_secs(3));
+        assert_eq!(bucket.reserve_at(1.0, start + 10_000), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn a_new_process_continues_with_the_saved_budget() {
+        let path =
+            std::env::temp_dir().join(format!("artificial-quota-{}.json", std::process::id()));
+        let quota = || {
+            QuotaManager::new()
+                .with_bucket("requests", 100.0, Duration::from_secs(3600))
+                .with_store(FileQuotaStore::new(&path))
+        };
+
+        let before_restart = quota();
+        before_restart.restore().await.unwrap();
+        assert_eq!(before_restart.reserve("requests", 100.0), Duration::ZERO);
+        before_restart.persist().await.unwrap();
+
+        let after_restart = quota();
+        after_restart.restore().await.unwrap();
+        assert!(after_restart.reserve("requests", 1.0) > Duration::ZERO);
+        assert_eq!(after_restart.reserve("unknown", 1.0), Duration::ZERO);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,411 @@
+//! Capture of prompt/response pairs for debugging and evaluation.
+//!
+//! [`RecordingProvider`] wraps any [`ChatCompletionProvider`] and hands every
+//! completed call to an [`InteractionRecorder`] as a serialisable
+//! [`Interaction`].  What ends up in the recorder is controlled by a
+//! [`Sampling`] policy, so production services can keep e.g. 1 % of all
+//! traffic plus every failure without paying for a full log.
+//!
+//! ```rust
+//! use artificial_core::recorder::{MemoryRecorder, RecordingProvider, Sampling};
+//! # fn wrap<B>(backend: B) {
+//! let recorder = MemoryRecorder::new();
+//! let provider = RecordingProvider::new(backend, recorder.clone())
+//!     .with_sampling(Sampling::rate(0.01).with_failures());
+//! # }
+//! ```
+//!
+//! The wrapper speaks [`GenericMessage`] so the rendered inputs can be stored
+//! in a provider-independent way (and replayed later against another model).
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    generic::{
+        GenericChatCompletionResponse, GenericFunctionSpec, GenericMessage, GenericUsageReport,
+        ResponseContent,
+    },
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+        Temperature,
+    },
+};
+
+/// One recorded chat completion: rendered request plus outcome.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Model identifier as requested by the caller.
+    pub model: String,
+    pub messages: Vec<GenericMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<GenericFunctionSpec>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    pub outcome: InteractionOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<GenericUsageReport>,
+    pub latency_ms: u64,
+    /// Unix timestamp (milliseconds) at which the call completed.
+    pub recorded_at_ms: u64,
+}
+
+/// What the provider answered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum InteractionOutcome {
+    Finished { message: GenericMessage },
+    ToolCalls { message: GenericMessage },
+    Failed { error: String },
+}
+
+impl InteractionOutcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, InteractionOutcome::Failed { .. })
+    }
+
+    /// The assistant message, unless the call failed.
+    pub fn message(&self) -> Option<&GenericMessage> {
+        match self {
+            InteractionOutcome::Finished { message }
+            | InteractionOutcome::ToolCalls { message } => Some(message),
+            InteractionOutcome::Failed { .. } => None,
+        }
+    }
+}
+
+/// Sink for recorded interactions.
+///
+/// Implementations must be cheap and non-blocking; they are invoked inline on
+/// the request path.  Any `Fn(Interaction)` closure is a recorder, too.
+pub trait InteractionRecorder: Send + Sync {
+    fn record(&self, interaction: Interaction);
+}
+
+impl<F> InteractionRecorder for F
+where
+    F: Fn(Interaction) + Send + Sync,
+{
+    fn record(&self, interaction: Interaction) {
+        self(interaction)
+    }
+}
+
+/// In-memory recorder, mostly useful for tests and short-lived jobs.
+///
+/// Cloning is cheap; all clones share the same buffer.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryRecorder {
+    interactions: Arc<Mutex<Vec<Interaction>>>,
+}
+
+impl MemoryRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot of everything recorded so far.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.interactions.lock().expect("recorder poisoned").clone()
+    }
+
+    /// Drain the buffer.
+    pub fn take(&self) -> Vec<Interaction> {
+        std::mem::take(&mut *self.interactions.lock().expect("recorder poisoned"))
+    }
+}
+
+impl InteractionRecorder for MemoryRecorder {
+    fn record(&self, interaction: Interaction) {
+        self.interactions
+            .lock()
+            .expect("recorder poisoned")
+            .push(interaction);
+    }
+}
+
+/// Decides which interactions are handed to the recorder.
+///
+/// The default records **everything**.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampling {
+    /// Fraction of calls to record, `0.0..=1.0`.
+    pub rate: f64,
+    /// Record failed calls regardless of `rate`.
+    pub always_record_failures: bool,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Sampling {
+    /// Record every interaction.
+    pub fn all() -> Self {
+        Self {
+            rate: 1.0,
+            always_record_failures: true,
+        }
+    }
+
+    /// Record only failed interactions.
+    pub fn failures_only() -> Self {
+        Self {
+            rate: 0.0,
+            always_record_failures: true,
+        }
+    }
+
+    /// Record a random `rate` fraction (clamped to `0.0..=1.0`) of all
+    /// interactions.
+    pub fn rate(rate: f64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            always_record_failures: false,
+        }
+    }
+
+    /// Additionally record every failure.
+    pub fn with_failures(mut self) -> Self {
+        self.always_record_failures = true;
+        self
+    }
+
+    fn roll(&self) -> bool {
+        if self.rate >= 1.0 {
+            return true;
+        }
+        if self.rate <= 0.0 {
+            return false;
+        }
+
+        // `RandomState` is seeded randomly per instance which is plenty for
+        // sampling and saves us a dependency on `rand`.
+        let random = RandomState::new().build_hasher().finish();
+        (random as f64 / u64::MAX as f64) < self.rate
+    }
+}
+
+/// [`ChatCompletionProvider`] wrapper that records interactions.
+pub struct RecordingProvider<B, R> {
+    inner: B,
+    recorder: R,
+    sampling: Sampling,
+}
+
+impl<B, R> RecordingProvider<B, R> {
+    /// Wrap `inner`, recording **every** call into `recorder`.
+    pub fn new(inner: B, recorder: R) -> Self {
+        Self {
+            inner,
+            recorder,
+            sampling: Sampling::default(),
+        }
+    }
+
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn recorder(&self) -> &R {
+        &self.recorder
+    }
+}
+
+impl<B, R> ChatCompletionProvider for RecordingProvider<B, R>
+where
+    B: ChatCompletionProvider,
+    R: InteractionRecorder,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+
+        Box::pin(async move {
+            let sampled = self.sampling.roll();
+            let snapshot =
+                (sampled || self.sampling.always_record_failures).then(|| params.clone());
+
+            let started = Instant::now();
+            let result = self.inner.chat_complete(params).await;
+            let latency = started.elapsed();
+
+            let Some(params) = snapshot else {
+                return result;
+            };
+            if !sampled && result.is_ok() {
+                return result;
+            }
+
+            let (outcome, usage) = match &result {
+                Ok(response) => {
+                    let outcome = match &response.content {
+                        ResponseContent::Finished(message) => InteractionOutcome::Finished {
+                            message: message.clone(),
+                        },
+                        ResponseContent::ToolCalls(message) => InteractionOutcome::ToolCalls {
+                            message: message.clone(),
+                        },
+                    };
+                    (outcome, response.usage.clone())
+                }
+                Err(err) => (
+                    InteractionOutcome::Failed {
+                        error: err.to_string(),
+                    },
+                    None,
+                ),
+            };
+
+            self.recorder.record(Interaction {
+                model: params.model.as_ref().to_string(),
+                messages: params.messages,
+                tools: params.tools,
+                temperature: params.temperature.map(Temperature::get),
+                response_format: params.response_format,
+                outcome,
+                usage,
+                latency_ms: latency.as_millis() as u64,
+                recorded_at_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+            });
+
+            result
+        })
+    }
+}
+
+impl<B, R> HealthCheckProvider for RecordingProvider<B, R>
+where
+    B: HealthCheckProvider,
+    R: Send + Sync,
+{
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ArtificialError,
+        generic::GenericRole,
+        model::{Model, OpenAiModel},
+    };
+
+    /// Answers with an echo, or fails if the last message says "fail".
+    struct Echo;
+
+    impl ChatCompletionProvider for Echo {
+        type Message = GenericMessage;
+
+        fn chat_complete<'s, M>(
+            &'s self,
+            params: ChatCompleteParameters<M>,
+        ) -> Pin<
+            Box<
+                dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>>
+                    + Send
+                    + 's,
+            >,
+        >
+        where
+            M: Into<Self::Message> + Clone + Send + Sync + 's,
+        {
+            let last: GenericMessage = params.messages.last().cloned().unwrap().into();
+            Box::pin(async move {
+                if last.content.as_deref() == Some("fail") {
+                    return Err(ArtificialError::Other("boom".into()));
+                }
+                Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(GenericMessage::new(
+                        last.content.unwrap_or_default(),
+                        GenericRole::Assistant,
+                    )),
+                    usage: None,
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    fn params(text: &str) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(text.into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn records_everything_by_default() {
+        let recorder = MemoryRecorder::new();
+        let provider = RecordingProvider::new(Echo, recorder.clone());
+
+        provider.chat_complete(params("hello")).await.unwrap();
+        provider.chat_complete(params("fail")).await.unwrap_err();
+
+        let recorded = recorder.take();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(
+            recorded[0].outcome.message().unwrap().content.as_deref(),
+            Some("hello")
+        );
+        assert!(recorded[1].outcome.is_failure());
+    }
+
+    #[tokio::test]
+    async fn failures_only_skips_successes() {
+        let recorder = MemoryRecorder::new();
+        let provider =
+            RecordingProvider::new(Echo, recorder.clone()).with_sampling(Sampling::failures_only());
+
+        provider.chat_complete(params("hello")).await.unwrap();
+        provider.chat_complete(params("fail")).await.unwrap_err();
+
+        let recorded = recorder.take();
+        assert_eq!(recorded.len(), 1);
+        assert!(recorded[0].outcome.is_failure());
+    }
+
+    #[tokio::test]
+    async fn zero_rate_records_nothing() {
+        let recorder = MemoryRecorder::new();
+        let provider =
+            RecordingProvider::new(Echo, recorder.clone()).with_sampling(Sampling::rate(0.0));
+
+        provider.chat_complete(params("hello")).await.unwrap();
+        provider.chat_complete(params("fail")).await.unwrap_err();
+
+        assert!(recorder.take().is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+//! Conversation content in `Debug` output.
+//!
+//! A stray `{:?}` of a request in a log line would dump the whole
+//! conversation. [`GenericMessage`](crate::generic::GenericMessage),
+//! `ChatCompleteParameters` and the request types of the provider crates
+//! therefore print message text as `<redacted: 42 chars>`, tool calls by name
+//! only and message lists by length.
+//!
+//! To see everything—while debugging locally, say—set the environment
+//! variable `ARTIFICIAL_DEBUG_CONTENT=1` before the first value is printed,
+//! or build with the `unredacted-debug` feature.
+use std::{fmt, sync::OnceLock};
+
+/// Environment variable that turns on full `Debug` output.
+pub const DEBUG_CONTENT_ENV: &str = "ARTIFICIAL_DEBUG_CONTENT";
+
+/// Whether `Debug` output includes conversation content.
+///
+/// The environment variable is read once per process.
+pub fn content_visible() -> bool {
+    if cfg!(feature = "unredacted-debug") {
+        return true;
+    }
+    static VISIBLE: OnceLock<bool> = OnceLock::new();
+    *VISIBLE.get_or_init(|| {
+        std::env::var(DEBUG_CONTENT_ENV)
+            .is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false"))
+    })
+}
+
+/// Formats as the quoted text if [`content_visible`], as its length
+/// otherwise.
+pub struct RedactedText<'a>(pub &'a str);
+
+impl fmt::Debug for RedactedText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match content_visible() {
+            true => fmt::Debug::fmt(self.0, f),
+            false => write!(f, "<redacted: {} chars>", self.0.chars().count()),
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "unredacted-debug")))]
+mod tests {
+    use super::*;
+    use crate::generic::{GenericMessage, GenericRole};
+
+    #[test]
+    fn debug_output_hides_message_text() {
+        if content_visible() {
+            return;
+        }
+        let message = GenericMessage::new("my card is 4111".into(), GenericRole::User);
+        let debug = format!("{message:?}");
+        assert!(!debug.contains("4111"), "{debug}");
+        assert!(debug.contains("<redacted: 15 chars>"), "{debug}");
+    }
+}
@@ -0,0 +1,326 @@
+//! Versioned prompt templates.
+//!
+//! Prompt wording changes as often as code, and a regression in answer
+//! quality needs to be traced to the wording that caused it. Templates
+//! declare a semantic version through [`PromptTemplate::version`];
+//! [`crate::ArtificialClient`] copies name and version into every response's
+//! [`ResponseMetadata`], and a [`PromptRegistry`] keeps what is known about
+//! each version so logged answers can be resolved again.
+//!
+//! ```rust
+//! use artificial_core::{generic::*, model::*, registry::PromptRegistry, template::*};
+//!
+//! struct Summarise(String);
+//! # impl IntoPrompt for Summarise { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+//!
+//! impl PromptTemplate for Summarise {
+//!     type Output = String;
+//!     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+//!
+//!     fn name() -> &'static str {
+//!         "summarise"
+//!     }
+//!
+//!     fn version() -> Option<&'static str> {
+//!         Some("2.1.0")
+//!     }
+//! }
+//!
+//! let mut registry = PromptRegistry::new();
+//! registry.register::<Summarise>().unwrap();
+//!
+//! let latest = registry.latest("summarise").unwrap();
+//! assert_eq!(latest.version.to_string(), "2.1.0");
+//! ```
+//!
+//! Registering the same name and version twice is fine as long as model and
+//! output schema agree; a mismatch means the template changed without a
+//! version bump and is rejected.
+//!
+//! Templates registered with [`PromptRegistry::register_executable`] can
+//! also be rendered from JSON input and run by name, which is what
+//! [`crate::pipeline`] builds on.
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    canonical,
+    error::{ArtificialError, Result},
+    generic::{GenericMessage, ResponseMetadata},
+    model::Model,
+    schema_util::{derive_response_schema, parse_output, SchemaMode},
+    template::{PromptTemplate, TemplateParams},
+};
+
+/// A `MAJOR.MINOR.PATCH` version number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct PromptVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl PromptVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for PromptVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for PromptVersion {
+    type Err = ArtificialError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid =
+            || ArtificialError::Invalid(format!("`{s}` is not a MAJOR.MINOR.PATCH version"));
+        let mut parts = s
+            .split('.')
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()));
+        let version = Self::new(
+            parts.next().ok_or_else(invalid)??,
+            parts.next().ok_or_else(invalid)??,
+            parts.next().ok_or_else(invalid)??,
+        );
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(version),
+        }
+    }
+}
+
+/// What the registry knows about one template version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PromptInfo {
+    pub name: String,
+    pub version: PromptVersion,
+    pub model: String,
+    pub cache_key: Option<String>,
+    /// [`canonical::fingerprint`] of the output's JSON Schema.
+    pub schema_fingerprint: String,
+}
+
+/// Type-erased operations of a template registered with
+/// [`PromptRegistry::register_executable`].
+#[derive(Clone, Copy)]
+pub(crate) struct Executable {
+    pub model: fn() -> Model,
+    pub params: fn() -> TemplateParams,
+    /// Build the template from JSON input and render it.
+    pub render: fn(Value) -> Result<Vec<GenericMessage>>,
+    /// Attach the output schema to rendered messages.
+    pub attach_schema: fn(SchemaMode, &mut Vec<GenericMessage>) -> Result<Option<Value>>,
+    /// Parse and postprocess an answer, as JSON.
+    pub parse: fn(&str) -> Result<Value>,
+}
+
+impl fmt::Debug for Executable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Executable").finish_non_exhaustive()
+    }
+}
+
+/// Registered template versions, by name.
+#[derive(Debug, Default)]
+pub struct PromptRegistry {
+    templates: BTreeMap<String, BTreeMap<PromptVersion, PromptInfo>>,
+    executables: BTreeMap<(String, PromptVersion), Executable>,
+}
+
+impl PromptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the current version of `P`.
+    ///
+    /// Fails if `P` declares no version, the version does not parse, or the
+    /// same version was registered with a different model or output schema.
+    pub fn register<P: PromptTemplate>(&mut self) -> Result<&PromptInfo> {
+        let version = P::version().ok_or_else(|| {
+            ArtificialError::Invalid(format!("template `{}` declares no version", P::name()))
+        })?;
+        let info = PromptInfo {
+            name: P::name().to_owned(),
+            version: version.parse()?,
+            model: P::MODEL.as_ref().to_owned(),
+            cache_key: P::cache_key().map(str::to_owned),
+            schema_fingerprint: canonical::fingerprint(&derive_response_schema::<P::Output>())?,
+        };
+
+        let versions = self.templates.entry(info.name.clone()).or_default();
+        if let Some(existing) = versions.get(&info.version) {
+            if *existing != info {
+                return Err(ArtificialError::Invalid(format!(
+                    "template `{}` {} changed without a version bump",
+                    info.name, info.version
+                )));
+            }
+        }
+        Ok(versions.entry(info.version).or_insert(info))
+    }
+
+    /// Like [`Self::register`], and also make `P` runnable by name: it is
+    /// deserialized from JSON input, rendered, and its answer is returned
+    /// as JSON.
+    pub fn register_executable<P>(&mut self) -> Result<&PromptInfo>
+    where
+        P: PromptTemplate<Message = GenericMessage> + DeserializeOwned + 'static,
+        P::Output: Serialize,
+    {
+        let key = {
+            let info = self.register::<P>()?;
+            (info.name.clone(), info.version)
+        };
+        self.executables.insert(
+            key.clone(),
+            Executable {
+                model: || P::MODEL,
+                params: P::params,
+                render: render::<P>,
+                attach_schema: attach_schema::<P>,
+                parse: parse::<P>,
+            },
+        );
+        Ok(&self.templates[&key.0][&key.1])
+    }
+
+    /// Whether `name` in `version` was registered with
+    /// [`Self::register_executable`].
+    pub fn is_executable(&self, name: &str, version: &PromptVersion) -> bool {
+        self.executables.contains_key(&(name.to_owned(), *version))
+    }
+
+    pub(crate) fn executable(&self, info: &PromptInfo) -> Option<Executable> {
+        self.executables
+            .get(&(info.name.clone(), info.version))
+            .copied()
+    }
+
+    pub fn get(&self, name: &str, version: &PromptVersion) -> Option<&PromptInfo> {
+        self.templates.get(name)?.get(version)
+    }
+
+    /// The highest registered version of `name`.
+    pub fn latest(&self, name: &str) -> Option<&PromptInfo> {
+        self.templates.get(name)?.values().next_back()
+    }
+
+    /// All registered versions of `name`, oldest first.
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &PromptInfo> {
+        self.templates
+            .get(name)
+            .into_iter()
+            .flat_map(|v| v.values())
+    }
+
+    /// The template version that produced a response.
+    pub fn resolve(&self, metadata: &ResponseMetadata) -> Option<&PromptInfo> {
+        let version = metadata.template_version.as_deref()?.parse().ok()?;
+        self.get(metadata.template.as_deref()?, &version)
+    }
+}
+
+fn render<P>(input: Value) -> Result<Vec<GenericMessage>>
+where
+    P: PromptTemplate<Message = GenericMessage> + DeserializeOwned,
+{
+    Ok(serde_json::from_value::<P>(input)?.into_prompt())
+}
+
+fn attach_schema<P: PromptTemplate>(
+    mode: SchemaMode,
+    messages: &mut Vec<GenericMessage>,
+) -> Result<Option<Value>> {
+    mode.attach::<P::Output, GenericMessage>(messages)
+}
+
+fn parse<P>(answer: &str) -> Result<Value>
+where
+    P: PromptTemplate,
+    P::Output: Serialize,
+{
+    let output = parse_output::<P::Output>(answer)?;
+    Ok(serde_json::to_value(P::postprocess(output))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic::{GenericMessage, GenericRole},
+        model::{Model, OpenAiModel},
+        provider::PromptExecutionProvider,
+        template::IntoPrompt,
+        testing::MockProvider,
+        ArtificialClient,
+    };
+
+    struct Greet<const V: u64>;
+
+    impl<const V: u64> IntoPrompt for Greet<V> {
+        type Message = GenericMessage;
+
+        fn into_prompt(self) -> Vec<GenericMessage> {
+            vec![GenericMessage::new("Say hi".into(), GenericRole::User)]
+        }
+    }
+
+    impl<const V: u64> PromptTemplate for Greet<V> {
+        type Output = String;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+
+        fn name() -> &'static str {
+            "greet"
+        }
+
+        fn version() -> Option<&'static str> {
+            Some(match V {
+                1 => "1.0.0",
+                _ => "1.1.0",
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn responses_resolve_to_their_template_version() {
+        let mut registry = PromptRegistry::new();
+        registry.register::<Greet<1>>().unwrap();
+        registry.register::<Greet<2>>().unwrap();
+        registry.register::<Greet<2>>().unwrap();
+        assert_eq!(registry.versions("greet").count(), 2);
+        assert_eq!(
+            registry.latest("greet").unwrap().version,
+            PromptVersion::new(1, 1, 0)
+        );
+
+        let mock = MockProvider::new();
+        mock.push_json("hi");
+        let client = ArtificialClient::new(mock);
+        let response = client.prompt_execute(Greet::<1>).await.unwrap();
+        assert_eq!(response.metadata.template_version.as_deref(), Some("1.0.0"));
+        let info = registry.resolve(&response.metadata).unwrap();
+        assert_eq!(info.version, PromptVersion::new(1, 0, 0));
+    }
+
+    #[test]
+    fn versions_must_be_semantic() {
+        assert!("1.2".parse::<PromptVersion>().is_err());
+        assert!("1.2.3.4".parse::<PromptVersion>().is_err());
+        assert!("1.x.3".parse::<PromptVersion>().is_err());
+        assert_eq!(
+            "10.2.3".parse::<PromptVersion>().unwrap(),
+            PromptVersion::new(10, 2, 3)
+        );
+    }
+}
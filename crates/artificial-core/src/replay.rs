@@ -0,0 +1,345 @@
+//! Re-run recorded interactions against a new model or prompt version.
+//!
+//! The [`crate::recorder`] captures the *rendered* input of every sampled
+//! call.  [`ReplayRunner`] sends those inputs again—optionally to a
+//! different model and/or through a rewrite function that applies an updated
+//! prompt—and compares the new answers with the recorded ones.  The
+//! resulting [`ReplayReport`] is the safety net for prompt and model
+//! migrations: it tells you how many answers stayed the same, changed,
+//! regressed into errors or got fixed.
+//!
+//! ```rust,no_run
+//! use artificial_core::model::{Model, OpenAiModel};
+//! use artificial_core::recorder::Interaction;
+//! use artificial_core::replay::ReplayRunner;
+//! # async fn run<B>(backend: B, recorded: Vec<Interaction>)
+//! # where B: artificial_core::provider::ChatCompletionProvider,
+//! #       artificial_core::generic::GenericMessage: Into<B::Message>,
+//! # {
+//! let report = ReplayRunner::new(&backend)
+//!     .with_model(Model::OpenAi(OpenAiModel::Gpt5Mini))
+//!     .run(&recorded)
+//!     .await;
+//!
+//! println!("{}", report.summary());
+//! # }
+//! ```
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    generic::{GenericMessage, ResponseContent},
+    model::Model,
+    provider::{ChatCompleteParameters, ChatCompletionProvider, Temperature},
+    recorder::{Interaction, InteractionOutcome},
+};
+
+type Rewrite = Box<dyn Fn(Vec<GenericMessage>) -> Vec<GenericMessage> + Send + Sync>;
+
+/// Executes recorded interactions again and compares the results.
+pub struct ReplayRunner<'b, B> {
+    backend: &'b B,
+    model: Option<Model>,
+    rewrite: Option<Rewrite>,
+}
+
+impl<'b, B> ReplayRunner<'b, B>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    pub fn new(backend: &'b B) -> Self {
+        Self {
+            backend,
+            model: None,
+            rewrite: None,
+        }
+    }
+
+    /// Send every interaction to `model` instead of the recorded one.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Transform the recorded messages before sending them, e.g. to swap in
+    /// the system prompt of an updated template.
+    pub fn with_rewrite(
+        mut self,
+        rewrite: impl Fn(Vec<GenericMessage>) -> Vec<GenericMessage> + Send + Sync + 'static,
+    ) -> Self {
+        self.rewrite = Some(Box::new(rewrite));
+        self
+    }
+
+    /// Replay all `interactions` sequentially.
+    pub async fn run(&self, interactions: &[Interaction]) -> ReplayReport {
+        let mut entries = Vec::with_capacity(interactions.len());
+
+        for (index, interaction) in interactions.iter().enumerate() {
+            let (model, replayed, usage) = self.replay_one(interaction).await;
+            let comparison = Comparison::between(&interaction.outcome, &replayed);
+
+            entries.push(ReplayEntry {
+                index,
+                original_model: interaction.model.clone(),
+                replay_model: model,
+                original: interaction.outcome.clone(),
+                replayed,
+                original_tokens: interaction.usage.as_ref().map(|u| u.total_tokens),
+                replay_tokens: usage,
+                comparison,
+            });
+        }
+
+        ReplayReport { entries }
+    }
+
+    async fn replay_one(
+        &self,
+        interaction: &Interaction,
+    ) -> (String, InteractionOutcome, Option<i64>) {
+        let model = match &self.model {
+            Some(model) => model.clone(),
+            None => match Model::from_str(&interaction.model) {
+                Ok(model) => model,
+                Err(err) => {
+                    return (
+                        interaction.model.clone(),
+                        InteractionOutcome::Failed {
+                            error: format!("{err}; pass an explicit model to the replay runner"),
+                        },
+                        None,
+                    );
+                }
+            },
+        };
+
+        let messages = match &self.rewrite {
+            Some(rewrite) => rewrite(interaction.messages.clone()),
+            None => interaction.messages.clone(),
+        };
+
+        let mut params = ChatCompleteParameters::new(messages, model.clone());
+        params.tools = interaction.tools.clone();
+        params.temperature = match interaction.temperature.map(Temperature::new).transpose() {
+            Ok(temperature) => temperature,
+            Err(err) => {
+                let error = err.to_string();
+                return (
+                    model.as_ref().to_string(),
+                    InteractionOutcome::Failed { error },
+                    None,
+                );
+            }
+        };
+        params.response_format = interaction.response_format.clone();
+
+        let model_name = model.as_ref().to_string();
+        match self.backend.chat_complete(params).await {
+            Ok(response) => {
+                let tokens = response.usage.map(|u| u.total_tokens);
+                let outcome = match response.content {
+                    ResponseContent::Finished(message) => InteractionOutcome::Finished { message },
+                    ResponseContent::ToolCalls(message) => {
+                        InteractionOutcome::ToolCalls { message }
+                    }
+                };
+                (model_name, outcome, tokens)
+            }
+            Err(err) => (
+                model_name,
+                InteractionOutcome::Failed {
+                    error: err.to_string(),
+                },
+                None,
+            ),
+        }
+    }
+}
+
+/// How a replayed answer relates to the recorded one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    /// Same answer. JSON answers are compared structurally, so key order and
+    /// whitespace do not matter.
+    Identical,
+    /// Both calls succeeded but the answers differ.
+    Changed,
+    /// The recorded call succeeded, the replay failed.
+    Regressed,
+    /// The recorded call failed, the replay succeeded.
+    Fixed,
+    BothFailed,
+}
+
+impl Comparison {
+    fn between(original: &InteractionOutcome, replayed: &InteractionOutcome) -> Self {
+        match (original.message(), replayed.message()) {
+            (None, None) => Comparison::BothFailed,
+            (Some(_), None) => Comparison::Regressed,
+            (None, Some(_)) => Comparison::Fixed,
+            (Some(a), Some(b)) => {
+                let same_kind = matches!(
+                    (original, replayed),
+                    (
+                        InteractionOutcome::Finished { .. },
+                        InteractionOutcome::Finished { .. }
+                    ) | (
+                        InteractionOutcome::ToolCalls { .. },
+                        InteractionOutcome::ToolCalls { .. }
+                    )
+                );
+                if same_kind && same_answer(a, b) {
+                    Comparison::Identical
+                } else {
+                    Comparison::Changed
+                }
+            }
+        }
+    }
+}
+
+fn same_answer(a: &GenericMessage, b: &GenericMessage) -> bool {
+    let (a_text, b_text) = (
+        a.content.as_deref().unwrap_or_default().trim(),
+        b.content.as_deref().unwrap_or_default().trim(),
+    );
+
+    let text_equal = match (
+        serde_json::from_str::<serde_json::Value>(a_text),
+        serde_json::from_str::<serde_json::Value>(b_text),
+    ) {
+        (Ok(a_json), Ok(b_json)) => a_json == b_json,
+        _ => a_text == b_text,
+    };
+
+    let calls = |m: &GenericMessage| {
+        m.tool_calls.as_ref().map(|calls| {
+            calls
+                .iter()
+                .map(|c| (c.function.name.clone(), c.function.arguments.clone()))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    text_equal && calls(a) == calls(b)
+}
+
+/// Result of one replayed interaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    /// Position of the interaction in the replayed slice.
+    pub index: usize,
+    pub original_model: String,
+    pub replay_model: String,
+    pub original: InteractionOutcome,
+    pub replayed: InteractionOutcome,
+    pub original_tokens: Option<i64>,
+    pub replay_tokens: Option<i64>,
+    pub comparison: Comparison,
+}
+
+/// Comparison report produced by [`ReplayRunner::run`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub entries: Vec<ReplayEntry>,
+}
+
+impl ReplayReport {
+    /// Entries matching `comparison`.
+    pub fn filter(&self, comparison: Comparison) -> impl Iterator<Item = &ReplayEntry> {
+        self.entries
+            .iter()
+            .filter(move |e| e.comparison == comparison)
+    }
+
+    /// `true` if at least one previously successful call now fails.
+    pub fn has_regressions(&self) -> bool {
+        self.filter(Comparison::Regressed).next().is_some()
+    }
+
+    pub fn summary(&self) -> ReplaySummary {
+        let count = |c| self.filter(c).count();
+        let tokens = |f: fn(&ReplayEntry) -> Option<i64>| self.entries.iter().filter_map(f).sum();
+
+        ReplaySummary {
+            total: self.entries.len(),
+            identical: count(Comparison::Identical),
+            changed: count(Comparison::Changed),
+            regressed: count(Comparison::Regressed),
+            fixed: count(Comparison::Fixed),
+            both_failed: count(Comparison::BothFailed),
+            original_tokens: tokens(|e| e.original_tokens),
+            replay_tokens: tokens(|e| e.replay_tokens),
+        }
+    }
+}
+
+/// Aggregated numbers of a [`ReplayReport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplaySummary {
+    pub total: usize,
+    pub identical: usize,
+    pub changed: usize,
+    pub regressed: usize,
+    pub fixed: usize,
+    pub both_failed: usize,
+    pub original_tokens: i64,
+    pub replay_tokens: i64,
+}
+
+impl fmt::Display for ReplaySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} replayed: {} identical, {} changed, {} regressed, {} fixed, {} failed both; tokens {} -> {}",
+            self.total,
+            self.identical,
+            self.changed,
+            self.regressed,
+            self.fixed,
+            self.both_failed,
+            self.original_tokens,
+            self.replay_tokens
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::GenericRole;
+
+    fn finished(text: &str) -> InteractionOutcome {
+        InteractionOutcome::Finished {
+            message: GenericMessage::new(text.into(), GenericRole::Assistant),
+        }
+    }
+
+    #[test]
+    fn json_answers_compare_structurally() {
+        assert_eq!(
+            Comparison::between(
+                &finished(r#"{"a":1,"b":2}"#),
+                &finished(r#"{ "b": 2, "a": 1 }"#)
+            ),
+            Comparison::Identical
+        );
+        assert_eq!(
+            Comparison::between(&finished("yes"), &finished("no")),
+            Comparison::Changed
+        );
+        assert_eq!(
+            Comparison::between(
+                &finished("yes"),
+                &InteractionOutcome::Failed {
+                    error: "boom".into()
+                }
+            ),
+            Comparison::Regressed
+        );
+    }
+}
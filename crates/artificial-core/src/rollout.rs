@@ -0,0 +1,254 @@
+//! Gradual rollout of prompt changes.
+//!
+//! A [`Rollout`] holds the current prompt (the *control*) and any number of
+//! candidate variants, each with a share of the traffic. Requests are
+//! assigned by a stable unit key—user id, tenant, conversation—so the same
+//! user keeps seeing the same variant while the share stays put.
+//!
+//! ```rust
+//! use artificial_core::rollout::Rollout;
+//!
+//! let system_prompt = Rollout::new("support-system-prompt", "You are a helpful agent.")
+//!     .with_variant("concise", 0.05, "You are a helpful agent. Answer in two sentences.");
+//! let control = system_prompt.control();
+//!
+//! let assignment = system_prompt.assign("user-42");
+//! println!("{} -> {}", assignment.variant, assignment.value);
+//!
+//! // Ramp up without a deploy …
+//! control.set_weight("concise", 0.25);
+//! // … or revert everyone to the control instantly.
+//! control.kill();
+//! assert!(system_prompt.assign("user-42").is_control());
+//! ```
+//!
+//! Variant values can be anything: prompt strings, template constructors,
+//! models. Record [`Assignment::variant`] along with the outcome to compare
+//! the variants afterwards.
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::canonical;
+
+/// Name under which [`Rollout::assign`] reports the control.
+pub const CONTROL: &str = "control";
+
+/// Shares are kept in basis points; one bucket is 0.01 %.
+const BUCKETS: u32 = 10_000;
+
+/// A control value and weighted variants competing with it.
+pub struct Rollout<T> {
+    control_value: T,
+    names: Vec<String>,
+    values: Vec<T>,
+    state: RolloutControl,
+}
+
+/// Runtime switches of a [`Rollout`], cheap to clone and hand to an admin
+/// endpoint or a feature-flag listener.
+#[derive(Clone)]
+pub struct RolloutControl {
+    inner: Arc<ControlState>,
+}
+
+struct ControlState {
+    name: String,
+    /// Name and share in buckets of every variant, in [`Rollout`] order.
+    variants: Mutex<Vec<(String, u32)>>,
+    killed: AtomicBool,
+}
+
+/// The variant a request was assigned to.
+#[derive(Debug, Clone, Copy)]
+pub struct Assignment<'a, T> {
+    /// Variant name, [`CONTROL`] for the control.
+    pub variant: &'a str,
+    pub value: &'a T,
+}
+
+impl<T> Assignment<'_, T> {
+    pub fn is_control(&self) -> bool {
+        self.variant == CONTROL
+    }
+}
+
+impl<T> Rollout<T> {
+    /// A rollout that sends all traffic to `control` until variants are
+    /// added. `name` salts the assignment, so different rollouts split the
+    /// same users independently.
+    pub fn new(name: impl Into<String>, control: T) -> Self {
+        Self {
+            control_value: control,
+            names: Vec::new(),
+            values: Vec::new(),
+            state: RolloutControl {
+                inner: Arc::new(ControlState {
+                    name: name.into(),
+                    variants: Mutex::new(Vec::new()),
+                    killed: AtomicBool::new(false),
+                }),
+            },
+        }
+    }
+
+    /// Add a variant receiving `weight` of the traffic, e.g. `0.05` for 5 %.
+    ///
+    /// Weights are clamped to `0.0..=1.0`; once the variants add up to more
+    /// than the whole traffic, the later ones get only what is left.
+    /// Handles from an earlier [`Self::control`] see the new variant, too.
+    pub fn with_variant(mut self, name: impl Into<String>, weight: f64, value: T) -> Self {
+        let name = name.into();
+        self.state
+            .inner
+            .variants
+            .lock()
+            .expect("rollout weights poisoned")
+            .push((name.clone(), to_buckets(weight)));
+        self.names.push(name);
+        self.values.push(value);
+        self
+    }
+
+    /// Handle for changing weights and the kill switch at runtime.
+    pub fn control(&self) -> RolloutControl {
+        self.state.clone()
+    }
+
+    /// The variant for `unit`, the same one on every call as long as the
+    /// weights do not change. Raising a variant's weight keeps its existing
+    /// users and adds new ones.
+    pub fn assign(&self, unit: &str) -> Assignment<'_, T> {
+        let hash = canonical::hash(&(self.state.inner.name.as_str(), unit))
+            .expect("string pairs always serialize");
+        self.assign_bucket((hash % u64::from(BUCKETS)) as u32)
+    }
+
+    /// A variant drawn at random, for requests without a meaningful unit.
+    pub fn assign_random(&self) -> Assignment<'_, T> {
+        let hash = RandomState::new().hash_one(std::time::SystemTime::now());
+        self.assign_bucket((hash % u64::from(BUCKETS)) as u32)
+    }
+
+    fn assign_bucket(&self, bucket: u32) -> Assignment<'_, T> {
+        let control = Assignment {
+            variant: CONTROL,
+            value: &self.control_value,
+        };
+        if self.state.is_killed() {
+            return control;
+        }
+
+        let variants = self
+            .state
+            .inner
+            .variants
+            .lock()
+            .expect("rollout weights poisoned");
+        let mut upper = 0;
+        for (index, (_, weight)) in variants.iter().enumerate() {
+            upper += weight;
+            if bucket < upper {
+                return Assignment {
+                    variant: &self.names[index],
+                    value: &self.values[index],
+                };
+            }
+        }
+        control
+    }
+}
+
+impl RolloutControl {
+    /// Change the share of variant `name`. Returns `false` if there is no
+    /// such variant.
+    pub fn set_weight(&self, name: &str, weight: f64) -> bool {
+        let mut variants = self
+            .inner
+            .variants
+            .lock()
+            .expect("rollout weights poisoned");
+        let Some((_, buckets)) = variants.iter_mut().find(|(n, _)| n == name) else {
+            return false;
+        };
+        *buckets = to_buckets(weight);
+        true
+    }
+
+    /// Current share of variant `name`, ignoring the kill switch.
+    pub fn weight(&self, name: &str) -> Option<f64> {
+        let variants = self
+            .inner
+            .variants
+            .lock()
+            .expect("rollout weights poisoned");
+        let (_, buckets) = variants.iter().find(|(n, _)| n == name)?;
+        Some(f64::from(*buckets) / f64::from(BUCKETS))
+    }
+
+    /// Send all traffic to the control, effective with the next assignment.
+    pub fn kill(&self) {
+        self.inner.killed.store(true, Ordering::Relaxed);
+    }
+
+    /// Undo [`Self::kill`]; the variants get their weights again.
+    pub fn resume(&self) {
+        self.inner.killed.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.inner.killed.load(Ordering::Relaxed)
+    }
+}
+
+fn to_buckets(weight: f64) -> u32 {
+    (weight.clamp(0.0, 1.0) * f64::from(BUCKETS)).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_follow_the_weights_and_the_kill_switch() {
+        let rollout = Rollout::new("greeting", "hello").with_variant("new", 0.2, "hi");
+        let control = rollout.control();
+        let new_users = |rollout: &Rollout<&str>| {
+            (0..2_000)
+                .filter(|i| !rollout.assign(&format!("user-{i}")).is_control())
+                .count()
+        };
+
+        let before = new_users(&rollout);
+        assert!((300..500).contains(&before), "{before}");
+        assert_eq!(
+            rollout.assign("user-7").variant,
+            rollout.assign("user-7").variant
+        );
+
+        control.set_weight("new", 0.5);
+        assert!(new_users(&rollout) > before);
+        assert_eq!(control.weight("new"), Some(0.5));
+
+        control.kill();
+        assert_eq!(new_users(&rollout), 0);
+        control.resume();
+        assert!(new_users(&rollout) > before);
+        assert!(!control.set_weight("missing", 1.0));
+    }
+
+    #[test]
+    fn variants_can_be_added_after_taking_the_control() {
+        let rollout = Rollout::new("greeting", "hello");
+        let control = rollout.control();
+        let rollout = rollout.with_variant("new", 1.0, "hi");
+
+        assert_eq!(control.weight("new"), Some(1.0));
+        assert_eq!(*rollout.assign("user-1").value, "hi");
+    }
+}
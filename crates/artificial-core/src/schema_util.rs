@@ -7,10 +7,91 @@
 //! sophisticated setup (e.g. inline- vs. $ref-based schemas, custom
 //! serialization logic) you can always bypass this helper and build the
 //! schema manually.
+//!
+//! Output shapes that are only known at runtime – declared by plugins or
+//! loaded from configuration – cannot implement [`JsonSchema`]; describe
+//! them with a [`DynamicOutput`] instead.
 
 use schemars::{r#gen::SchemaSettings, JsonSchema, SchemaGenerator};
+use serde::de::DeserializeOwned;
 use serde_json::{self, Value};
 
+use crate::{
+    capability::ResponseFormat,
+    error::{ArtificialError, RawOutput, Result},
+    generic::{GenericMessage, GenericRole},
+    model::Model,
+};
+
+/// How the schema of the expected output is attached to a request.
+///
+/// Templates choose via [`crate::template::TemplateParams::with_schema_mode`],
+/// plain chat requests via
+/// [`crate::provider::ChatCompleteParameters::with_output_schema`]. Without a
+/// choice the best mode of the model is used, see [`SchemaMode::for_model`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchemaMode {
+    /// `response_format: json_schema` with `strict: true`; the provider
+    /// enforces the schema.
+    JsonSchemaStrict,
+    /// `response_format: json_object`; the schema is described in an
+    /// appended system message (see [`schema_instructions`]).
+    JsonObject,
+    /// Nothing is attached; the prompt itself has to ask for the right
+    /// JSON. For models that reject `response_format` altogether.
+    None,
+}
+
+impl SchemaMode {
+    /// The mode matching what `model` supports, see
+    /// [`crate::capability::ModelCapabilities::response_format`].
+    pub fn for_model(model: &Model) -> Self {
+        model.capabilities().response_format.into()
+    }
+
+    /// Attach the schema of `T` as this mode says: returns the
+    /// `response_format` to send and appends schema instructions to
+    /// `messages` where needed.
+    ///
+    /// ```
+    /// use artificial_core::{generic::GenericMessage, schema_util::SchemaMode};
+    ///
+    /// #[derive(schemars::JsonSchema)]
+    /// struct Answer { text: String }
+    ///
+    /// let mut messages: Vec<GenericMessage> = Vec::new();
+    /// let format = SchemaMode::JsonObject.attach::<Answer, _>(&mut messages)?;
+    /// assert_eq!(format.unwrap()["type"], "json_object");
+    /// assert_eq!(messages.len(), 1);
+    /// # Ok::<(), artificial_core::error::ArtificialError>(())
+    /// ```
+    pub fn attach<T, M>(self, messages: &mut Vec<M>) -> Result<Option<Value>>
+    where
+        T: JsonSchema + 'static,
+        GenericMessage: Into<M>,
+    {
+        match self {
+            SchemaMode::JsonSchemaStrict => derive_response_format::<T>().map(Some),
+            SchemaMode::JsonObject => {
+                if std::any::TypeId::of::<T>() != std::any::TypeId::of::<Value>() {
+                    messages.push(schema_instructions(&derive_response_schema::<T>()).into());
+                }
+                Ok(Some(serde_json::json!({ "type": "json_object" })))
+            }
+            SchemaMode::None => Ok(None),
+        }
+    }
+}
+
+impl From<ResponseFormat> for SchemaMode {
+    fn from(format: ResponseFormat) -> Self {
+        match format {
+            ResponseFormat::JsonSchema => SchemaMode::JsonSchemaStrict,
+            ResponseFormat::JsonObject => SchemaMode::JsonObject,
+        }
+    }
+}
+
 /// Generate a JSON Schema for the given `T` **inline**, i.e. without
 /// `$ref` pointers to external definitions.
 ///
@@ -49,3 +130,319 @@ where
 
     serde_json::to_value(root).expect("generated schema should be serialisable")
 }
+
+/// Produce an OpenAI-style `response_format` object for `T`.
+///
+/// * If `T == serde_json::Value` we ask for an *unstructured* JSON blob.
+/// * Otherwise we inline a full JSON Schema (see [`derive_response_schema`])
+///   and request strict validation against it.
+///
+/// # Errors
+///
+/// [`ArtificialError::InvalidRequest`] if the schema has no `title`, which
+/// is used as the schema name.
+pub fn derive_response_format<T>() -> Result<Value>
+where
+    T: JsonSchema + 'static,
+{
+    // Fast-path: caller wants raw JSON.
+    if std::any::TypeId::of::<T>() == std::any::TypeId::of::<Value>() {
+        return Ok(serde_json::json!({ "type": "json_object" }));
+    }
+
+    let schema = derive_response_schema::<T>();
+    let title = schema
+        .get("title")
+        .and_then(Value::as_str)
+        .map(str::to_owned)
+        .ok_or(ArtificialError::InvalidRequest(
+            "json schema has no title".into(),
+        ))?;
+
+    Ok(strict_response_format(&title, schema))
+}
+
+fn strict_response_format(name: &str, schema: Value) -> Value {
+    serde_json::json!({
+        "type": "json_schema",
+        "json_schema": {
+            "strict": true,
+            "name": name,
+            "schema": schema,
+        }
+    })
+}
+
+/// Deserialize the model's `raw` answer into `T`.
+///
+/// # Errors
+///
+/// [`ArtificialError::OutputParse`] with the raw text, the path of the field
+/// that failed and the schema name of `T`:
+///
+/// ```
+/// use artificial_core::{error::ArtificialError, schema_util::parse_output};
+/// use schemars::JsonSchema;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize, JsonSchema)]
+/// struct Scores { items: Vec<u8> }
+///
+/// let err = parse_output::<Scores>(r#"{"items": [1, "two"]}"#).unwrap_err();
+/// let ArtificialError::OutputParse { raw, source, schema_name } = err else { unreachable!() };
+/// assert_eq!(schema_name, "Scores");
+/// assert_eq!(source.path().to_string(), "items[1]");
+/// assert_eq!(raw.as_str(), r#"{"items": [1, "two"]}"#);
+/// ```
+pub fn parse_output<T>(raw: &str) -> Result<T>
+where
+    T: JsonSchema + DeserializeOwned,
+{
+    let mut deserializer = serde_json::Deserializer::from_str(raw);
+    serde_path_to_error::deserialize(&mut deserializer)
+        .and_then(|output| {
+            // Trailing characters belong to no field: report the root path.
+            deserializer.end().map_err(|err| {
+                serde_path_to_error::Error::new(serde_path_to_error::Track::new().path(), err)
+            })?;
+            Ok(output)
+        })
+        .map_err(|source| ArtificialError::OutputParse {
+            raw: RawOutput::new(raw),
+            source,
+            schema_name: T::schema_name(),
+        })
+}
+
+/// An output shape given as a JSON Schema at runtime, for outputs that have
+/// no Rust type to derive it from.
+///
+/// Answers are returned as raw [`Value`]s. [`Self::parse`] only checks the
+/// top-level type and the required properties; in
+/// [`SchemaMode::JsonSchemaStrict`] the provider enforces the rest, which
+/// requires the schema to pass [`strict_mode_violations`].
+///
+/// ```
+/// use artificial_core::{generic::GenericMessage, schema_util::{DynamicOutput, SchemaMode}};
+/// use serde_json::json;
+///
+/// let output = DynamicOutput::new(
+///     "ticket",
+///     json!({
+///         "type": "object",
+///         "properties": { "title": { "type": "string" } },
+///         "required": ["title"],
+///         "additionalProperties": false
+///     }),
+/// );
+///
+/// let mut messages: Vec<GenericMessage> = Vec::new();
+/// let format = output.attach(SchemaMode::JsonSchemaStrict, &mut messages);
+/// assert_eq!(format.unwrap()["json_schema"]["name"], "ticket");
+///
+/// assert_eq!(output.parse(r#"{"title":"Printer on fire"}"#)?["title"], "Printer on fire");
+/// assert!(output.parse(r#"{"summary":"Printer on fire"}"#).is_err());
+/// assert!(output.parse("[]").is_err());
+/// # Ok::<(), artificial_core::error::ArtificialError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicOutput {
+    name: String,
+    schema: Value,
+}
+
+impl DynamicOutput {
+    /// `name` identifies the schema towards the provider in strict mode
+    /// (letters, digits, `_` and `-`).
+    pub fn new(name: impl Into<String>, schema: Value) -> Self {
+        Self {
+            name: name.into(),
+            schema,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Like [`SchemaMode::attach`], with this schema.
+    pub fn attach<M>(&self, mode: SchemaMode, messages: &mut Vec<M>) -> Option<Value>
+    where
+        GenericMessage: Into<M>,
+    {
+        match mode {
+            SchemaMode::JsonSchemaStrict => {
+                Some(strict_response_format(&self.name, self.schema.clone()))
+            }
+            SchemaMode::JsonObject => {
+                messages.push(schema_instructions(&self.schema).into());
+                Some(serde_json::json!({ "type": "json_object" }))
+            }
+            SchemaMode::None => None,
+        }
+    }
+
+    /// Parse an answer and check its top-level shape.
+    ///
+    /// # Errors
+    ///
+    /// [`ArtificialError::Serialization`] if `answer` is no JSON,
+    /// [`ArtificialError::Invalid`] if it has the wrong type or lacks a
+    /// required property.
+    pub fn parse(&self, answer: &str) -> Result<Value> {
+        let value: Value = serde_json::from_str(answer)?;
+        if let Some(expected) = self.schema.get("type").and_then(Value::as_str) {
+            if !has_type(&value, expected) {
+                return Err(ArtificialError::Invalid(format!(
+                    "`{}` output is not of type `{expected}`",
+                    self.name
+                )));
+            }
+        }
+        let required = self.schema.get("required").and_then(Value::as_array);
+        for field in required.into_iter().flatten().filter_map(Value::as_str) {
+            if value.get(field).is_none() {
+                return Err(ArtificialError::Invalid(format!(
+                    "`{}` output lacks required property `{field}`",
+                    self.name
+                )));
+            }
+        }
+        Ok(value)
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Check a JSON Schema against the rules OpenAI-style *strict* structured
+/// outputs impose and return one human-readable message per violation.
+///
+/// The rules checked are:
+///
+/// * the root must be an `object` and carry a `title` (used as schema name),
+/// * every object must set `additionalProperties: false`,
+/// * every property of an object must be listed in `required`
+///   (model optional fields as `Option<T>` with `#[schemars(required)]`),
+/// * no unresolved `$ref` pointers may remain.
+///
+/// An empty vector means the schema can be sent with `strict: true`.
+///
+/// ```
+/// use artificial_core::schema_util::{derive_response_schema, strict_mode_violations};
+/// use schemars::JsonSchema;
+///
+/// #[derive(JsonSchema)]
+/// #[serde(deny_unknown_fields)]
+/// struct Strict { bar: String }
+///
+/// #[derive(JsonSchema)]
+/// struct Loose { bar: Option<String> }
+///
+/// assert!(strict_mode_violations(&derive_response_schema::<Strict>()).is_empty());
+/// assert_eq!(strict_mode_violations(&derive_response_schema::<Loose>()).len(), 2);
+/// ```
+pub fn strict_mode_violations(schema: &Value) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    if schema.get("title").and_then(Value::as_str).is_none() {
+        violations.push("root schema has no `title`".to_string());
+    }
+    if !is_object_schema(schema) {
+        violations.push("root schema must be of type `object`".to_string());
+    }
+
+    collect_strict_violations(schema, "$", &mut violations);
+    violations
+}
+
+fn is_object_schema(schema: &Value) -> bool {
+    match schema.get("type") {
+        Some(Value::String(t)) => t == "object",
+        Some(Value::Array(types)) => types.iter().any(|t| t == "object"),
+        _ => false,
+    }
+}
+
+fn collect_strict_violations(schema: &Value, path: &str, violations: &mut Vec<String>) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+
+    if map.contains_key("$ref") {
+        violations.push(format!("{path}: unresolved `$ref`"));
+    }
+
+    if let Some(Value::Object(properties)) = map.get("properties") {
+        if map.get("additionalProperties") != Some(&Value::Bool(false)) {
+            violations.push(format!("{path}: `additionalProperties` must be `false`"));
+        }
+
+        let required: Vec<&str> = map
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        for name in properties.keys() {
+            if !required.contains(&name.as_str()) {
+                violations.push(format!("{path}: property `{name}` is not required"));
+            }
+        }
+
+        for (name, sub) in properties {
+            collect_strict_violations(sub, &format!("{path}.{name}"), violations);
+        }
+    }
+
+    for keyword in ["items", "anyOf", "oneOf", "allOf"] {
+        match map.get(keyword) {
+            Some(Value::Array(subs)) => {
+                for (idx, sub) in subs.iter().enumerate() {
+                    collect_strict_violations(sub, &format!("{path}.{keyword}[{idx}]"), violations);
+                }
+            }
+            Some(sub @ Value::Object(_)) => {
+                collect_strict_violations(sub, &format!("{path}.{keyword}"), violations);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Render a system message that describes `schema` in prose.
+///
+/// Used for models that only support `json_object` mode: the provider then
+/// guarantees *some* JSON, and this message tells the model *which* JSON.
+///
+/// ```
+/// use artificial_core::schema_util::schema_instructions;
+/// use serde_json::json;
+///
+/// let message = schema_instructions(&json!({ "type": "object" }));
+/// assert!(message.content.unwrap().contains("JSON"));
+/// ```
+pub fn schema_instructions(schema: &Value) -> GenericMessage {
+    let pretty = serde_json::to_string_pretty(schema).expect("schema is serialisable JSON");
+    GenericMessage::new(
+        format!(
+            "Respond with a single JSON object that validates against the following \
+             JSON Schema. Do not wrap it in Markdown and do not add any text before \
+             or after it.\n\n```json\n{pretty}\n```"
+        ),
+        GenericRole::System,
+    )
+}
@@ -0,0 +1,378 @@
+//! Answer reuse for paraphrased questions.
+//!
+//! FAQ-like traffic asks the same few questions in countless wordings. A
+//! [`SemanticCacheProvider`] embeds the final user message of each request
+//! and answers from a previous response when an earlier question is close
+//! enough by cosine similarity—"How do I reset my password?" and "password
+//! reset, how?" share one provider call.
+//!
+//! Only the final user message is compared. Everything else—model, system
+//! prompt, earlier turns, tools and generation settings—has to match
+//! exactly, so a cached answer is never served into a different
+//! conversation or to a differently configured request.
+//!
+//! ```rust,no_run
+//! use artificial_core::{model::{Model, OpenAiModel}, semantic_cache::SemanticCacheProvider};
+//! # fn wrap<B, E>(backend: B, embedder: E) -> SemanticCacheProvider<B, E> {
+//! let backend = SemanticCacheProvider::new(
+//!     backend,
+//!     embedder,
+//!     Model::OpenAi(OpenAiModel::TextEmbedding3Small),
+//! )
+//! .with_threshold(0.93)
+//! .with_max_entries(10_000);
+//! # backend }
+//! ```
+//!
+//! # Structured outputs
+//!
+//! A structured answer holds values taken from the question—an amount, a
+//! date, a name—and is wrong for a question that merely sounds alike. By
+//! default requests with a `response_format` are therefore answered from the
+//! cache only for the identical question; opt in to similarity matching with
+//! [`SemanticCacheProvider::with_structured_threshold`].
+//!
+//! Lookups cost one embedding call per request unless the question was seen
+//! verbatim. Embedding errors never fail a request; the cache is bypassed
+//! instead. Cached answers carry no usage report.
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::Result,
+    generic::{GenericChatCompletionResponse, GenericMessage, GenericRole, ResponseContent},
+    model::Model,
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, EmbeddingProvider,
+        HealthCheckProvider,
+    },
+    singleflight::request_key,
+};
+
+struct Entry {
+    /// [`request_key`] of everything but the question.
+    scope: String,
+    question: String,
+    embedding: Vec<f32>,
+    response: GenericChatCompletionResponse<GenericMessage>,
+    stored: Instant,
+}
+
+/// [`ChatCompletionProvider`] wrapper that reuses answers to similar
+/// questions, see the [module docs](self).
+pub struct SemanticCacheProvider<B, E> {
+    inner: B,
+    embedder: E,
+    embedding_model: Model,
+    threshold: f32,
+    structured_threshold: Option<f32>,
+    max_entries: usize,
+    ttl: Option<Duration>,
+    entries: Mutex<VecDeque<Entry>>,
+}
+
+impl<B, E> SemanticCacheProvider<B, E> {
+    /// Cache answers of `inner`, comparing questions by their `embedder`
+    /// vectors of `embedding_model`.
+    pub fn new(inner: B, embedder: E, embedding_model: Model) -> Self {
+        Self {
+            inner,
+            embedder,
+            embedding_model,
+            threshold: 0.95,
+            structured_threshold: None,
+            max_entries: 1_000,
+            ttl: None,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Minimum cosine similarity of a cache hit. Defaults to 0.95; lower
+    /// values save more calls and risk answering a different question.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Allow similarity matches for requests with a `response_format` at
+    /// `threshold`, which should be well above [`Self::with_threshold`].
+    pub fn with_structured_threshold(mut self, threshold: f32) -> Self {
+        self.structured_threshold = Some(threshold);
+        self
+    }
+
+    /// Number of answers kept; the oldest are dropped first. Defaults to
+    /// 1000. Lookups compare against every entry.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Stop serving answers older than `ttl`. By default answers are kept
+    /// until evicted.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("semantic cache poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .expect("semantic cache poisoned")
+            .clear();
+    }
+
+    /// The cached answer to `question` in `scope`, compared by `embedding`
+    /// when given and verbatim otherwise.
+    fn lookup(
+        &self,
+        scope: &str,
+        question: &str,
+        embedding: Option<(&[f32], f32)>,
+    ) -> Option<GenericChatCompletionResponse<GenericMessage>> {
+        let mut entries = self.entries.lock().expect("semantic cache poisoned");
+        if let Some(ttl) = self.ttl {
+            entries.retain(|entry| entry.stored.elapsed() < ttl);
+        }
+
+        let candidates = entries.iter().filter(|entry| entry.scope == scope);
+        let best = match embedding {
+            None => candidates.rev().find(|entry| entry.question == question),
+            Some((embedding, threshold)) => candidates
+                .map(|entry| (entry, cosine_similarity(&entry.embedding, embedding)))
+                .filter(|(_, similarity)| *similarity >= threshold)
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(entry, _)| entry),
+        }?;
+
+        Some(GenericChatCompletionResponse {
+            content: best.response.content.clone(),
+            usage: None,
+            metadata: best.response.metadata.clone(),
+        })
+    }
+
+    fn store(&self, entry: Entry) {
+        let mut entries = self.entries.lock().expect("semantic cache poisoned");
+        while entries.len() >= self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+impl<B, E> ChatCompletionProvider for SemanticCacheProvider<B, E>
+where
+    B: ChatCompletionProvider,
+    E: EmbeddingProvider,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let params = params.map_messages(Into::<GenericMessage>::into);
+
+        Box::pin(async move {
+            let Some((scope, question)) = split_question(&params) else {
+                return self.inner.chat_complete(params).await;
+            };
+
+            if let Some(response) = self.lookup(&scope, &question, None) {
+                return Ok(response);
+            }
+
+            let threshold = match params.response_format {
+                Some(_) => self.structured_threshold,
+                None => Some(self.threshold),
+            };
+            let embedding = self.embed(&question).await;
+            if let (Some(embedding), Some(threshold)) = (&embedding, threshold) {
+                if let Some(response) = self.lookup(&scope, &question, Some((embedding, threshold)))
+                {
+                    return Ok(response);
+                }
+            }
+
+            let response = self.inner.chat_complete(params).await?;
+            if let (Some(embedding), ResponseContent::Finished(_)) = (embedding, &response.content)
+            {
+                self.store(Entry {
+                    scope,
+                    question,
+                    embedding,
+                    response: GenericChatCompletionResponse {
+                        content: response.content.clone(),
+                        usage: None,
+                        metadata: response.metadata.clone(),
+                    },
+                    stored: Instant::now(),
+                });
+            }
+            Ok(response)
+        })
+    }
+}
+
+impl<B, E> SemanticCacheProvider<B, E>
+where
+    E: EmbeddingProvider,
+{
+    async fn embed(&self, question: &str) -> Option<Vec<f32>> {
+        match self
+            .embedder
+            .embed(vec![question.to_owned()], self.embedding_model.clone())
+            .await
+        {
+            Ok(response) => response.embeddings.into_iter().next(),
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %_err, "embedding for the semantic cache failed");
+                None
+            }
+        }
+    }
+}
+
+impl<B: HealthCheckProvider, E: Send + Sync> HealthCheckProvider for SemanticCacheProvider<B, E> {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+/// The final user message and the key of everything before and around it.
+/// `None` if the request does not end with a plain text user question.
+fn split_question(params: &ChatCompleteParameters<GenericMessage>) -> Option<(String, String)> {
+    let (last, earlier) = params.messages.split_last()?;
+    if last.role != GenericRole::User || last.tool_calls.is_some() || !last.attachments.is_empty() {
+        return None;
+    }
+    let question = last.content.clone()?;
+
+    let mut scope = params.clone();
+    scope.messages = earlier.to_vec();
+    Some((request_key(&scope)?, question))
+}
+
+/// Cosine of the angle between two embeddings, `-1.0..=1.0`; `0.0` if
+/// their lengths differ or either is zero.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    match norm(a) * norm(b) {
+        0.0 => 0.0,
+        norms => dot / norms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::OpenAiModel, testing::MockProvider};
+
+    /// Answers "answer 1", "answer 2", … and embeds the questions of the
+    /// tests by the topics they mention.
+    fn mock() -> MockProvider {
+        let mock = MockProvider::new();
+        for call in 1..=3 {
+            mock.push_text(format!("answer {call}"));
+        }
+        mock.set_embedding("how to reset my password", vec![1.0, 0.0, 1.0])
+            .set_embedding("password reset?", vec![1.0, 0.0, 1.0])
+            .set_embedding("where is my invoice", vec![0.0, 1.0, 0.0])
+            .set_embedding("reset password for bob", vec![1.0, 0.0, 1.0])
+            .set_embedding("reset password for alice", vec![1.0, 0.0, 1.0]);
+        mock
+    }
+
+    fn ask(question: &str) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(question.into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    async fn answer(
+        cache: &SemanticCacheProvider<MockProvider, MockProvider>,
+        params: ChatCompleteParameters<GenericMessage>,
+    ) -> String {
+        let response = cache.chat_complete(params).await.unwrap();
+        response.content.expect_finished().content.unwrap()
+    }
+
+    fn cache(mock: &MockProvider) -> SemanticCacheProvider<MockProvider, MockProvider> {
+        SemanticCacheProvider::new(
+            mock.clone(),
+            mock.clone(),
+            Model::OpenAi(OpenAiModel::TextEmbedding3Small),
+        )
+    }
+
+    #[tokio::test]
+    async fn similar_questions_share_an_answer() {
+        let mock = mock();
+        let cache = cache(&mock);
+
+        assert_eq!(
+            answer(&cache, ask("how to reset my password")).await,
+            "answer 1"
+        );
+        assert_eq!(answer(&cache, ask("password reset?")).await, "answer 1");
+        assert_eq!(answer(&cache, ask("where is my invoice")).await, "answer 2");
+
+        let mut other_model = ask("password reset?");
+        other_model.model = Model::OpenAi(OpenAiModel::Gpt4o);
+        assert_eq!(answer(&cache, other_model).await, "answer 3");
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn structured_requests_need_the_identical_question() {
+        let mock = mock();
+        let cache = cache(&mock);
+        let structured = |question| {
+            ask(question).with_response_format(serde_json::json!({ "type": "json_object" }))
+        };
+
+        assert_eq!(
+            answer(&cache, structured("reset password for bob")).await,
+            "answer 1"
+        );
+        assert_eq!(
+            answer(&cache, structured("reset password for bob")).await,
+            "answer 1"
+        );
+        assert_eq!(
+            answer(&cache, structured("reset password for alice")).await,
+            "answer 2"
+        );
+        assert_eq!(mock.requests().len(), 2);
+    }
+}
@@ -0,0 +1,392 @@
+//! Collapse identical concurrent requests into one provider call.
+//!
+//! Batch pipelines often contain duplicate inputs.  Without coordination
+//! every duplicate is sent to the provider, multiplying cost and rate-limit
+//! pressure.  [`SingleFlightProvider`] keys each request by its complete
+//! content (messages, model, tools, sampling and response format): the first
+//! caller dispatches it, callers arriving while it is in flight wait and
+//! receive a copy of the same answer.
+//!
+//! Only *concurrent* duplicates are merged; once the call finished the next
+//! identical request is sent again.  Waiters get the answer without a usage
+//! report, so token accounting reflects what was actually billed.  A failed
+//! call fails all of them with the same
+//! [`ArtificialError::Shared`] error, so retries and classification treat
+//! every duplicate alike.
+//!
+//! Cancellation is per caller: the shared call is dispatched without a
+//! [`CancellationToken`](crate::cancel::CancellationToken), and each caller
+//! races only its own token. If the dispatching caller is cancelled, a
+//! waiting duplicate takes over and sends the request itself.
+//!
+//! ```rust,no_run
+//! use artificial_core::singleflight::SingleFlightProvider;
+//! # fn wrap<B>(backend: B) -> SingleFlightProvider<B> {
+//! let backend = SingleFlightProvider::new(backend);
+//! # backend }
+//! ```
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    cancel::CancellationToken,
+    error::{ArtificialError, Result},
+    generic::{GenericChatCompletionResponse, GenericMessage},
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+    },
+};
+
+type SharedResult =
+    std::result::Result<GenericChatCompletionResponse<GenericMessage>, Arc<ArtificialError>>;
+
+#[derive(Default)]
+struct Flight {
+    result: Option<SharedResult>,
+    waiters: Vec<Waker>,
+    /// Number of live [`Wait`]s.
+    waiting: usize,
+    /// The dispatching caller gave up; the next waiter to look takes over.
+    orphaned: bool,
+}
+
+type InFlight = Mutex<HashMap<String, Arc<Mutex<Flight>>>>;
+
+/// [`ChatCompletionProvider`] wrapper that deduplicates in-flight requests.
+pub struct SingleFlightProvider<B> {
+    inner: B,
+    in_flight: InFlight,
+}
+
+impl<B> SingleFlightProvider<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B> ChatCompletionProvider for SingleFlightProvider<B>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let mut params = params.map_messages(Into::<GenericMessage>::into);
+
+        Box::pin(async move {
+            let Some(key) = request_key(&params) else {
+                return self.inner.chat_complete(params).await;
+            };
+            let token = params.cancellation.take();
+
+            let (flight, leader) = {
+                let mut in_flight = self.in_flight.lock().expect("single-flight map poisoned");
+                match in_flight.get(&key) {
+                    Some(flight) => (Arc::clone(flight), false),
+                    None => {
+                        let flight = Arc::new(Mutex::new(Flight::default()));
+                        in_flight.insert(key.clone(), Arc::clone(&flight));
+                        (flight, true)
+                    }
+                }
+            };
+
+            if !leader {
+                let wait = Wait::new(&self.in_flight, &key, Arc::clone(&flight));
+                if let Some(response) = cancellable(token.as_ref(), wait).await? {
+                    return Ok(response);
+                }
+            }
+
+            let mut guard = LeaderGuard {
+                in_flight: &self.in_flight,
+                key,
+                flight,
+                result: None,
+            };
+            match cancellable(token.as_ref(), self.inner.chat_complete(params)).await {
+                // Our own cancellation: the guard hands the flight over.
+                Err(ArtificialError::Cancelled) if token.is_some_and(|t| t.is_cancelled()) => {
+                    Err(ArtificialError::Cancelled)
+                }
+                Ok(response) => {
+                    guard.result = Some(Ok(GenericChatCompletionResponse {
+                        content: response.content.clone(),
+                        usage: None,
+                        metadata: response.metadata.clone(),
+                    }));
+                    Ok(response)
+                }
+                Err(err) => {
+                    let err = Arc::new(err);
+                    guard.result = Some(Err(Arc::clone(&err)));
+                    Err(ArtificialError::Shared(err))
+                }
+            }
+        })
+    }
+}
+
+impl<B: HealthCheckProvider> HealthCheckProvider for SingleFlightProvider<B> {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+/// Everything that influences the answer, serialised. `None` disables
+/// deduplication for the request.
+pub(crate) fn request_key(params: &ChatCompleteParameters<GenericMessage>) -> Option<String> {
+    crate::canonical::to_string(&(
+        params.model.as_ref(),
+        &params.messages,
+        &params.tools,
+        (params.temperature, params.top_p),
+        (
+            params.max_tokens,
+            &params.stop,
+            params.presence_penalty,
+            params.frequency_penalty,
+            params.seed,
+            params.reasoning_effort,
+        ),
+        &params.response_format,
+        &params.cache_key,
+    ))
+    .ok()
+}
+
+async fn cancellable<F, T>(token: Option<&CancellationToken>, future: F) -> Result<T>
+where
+    F: Future<Output = Result<T>> + Unpin,
+{
+    match token {
+        Some(token) => token.run(future).await,
+        None => future.await,
+    }
+}
+
+/// Publishes the leader's result and unregisters the flight. A leader that
+/// is dropped without a result hands the flight to a waiter instead, so
+/// waiters never hang and never fail for a cancellation that is not theirs.
+struct LeaderGuard<'a> {
+    in_flight: &'a InFlight,
+    key: String,
+    flight: Arc<Mutex<Flight>>,
+    result: Option<SharedResult>,
+}
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().expect("single-flight map poisoned");
+        let mut flight = self.flight.lock().expect("flight poisoned");
+        match self.result.take() {
+            Some(result) => {
+                in_flight.remove(&self.key);
+                flight.result = Some(result);
+            }
+            None if flight.waiting > 0 => flight.orphaned = true,
+            None => {
+                in_flight.remove(&self.key);
+            }
+        }
+        for waker in flight.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Waits for the leader's result; yields `Ok(None)` when the caller has to
+/// take over the flight.
+struct Wait<'a> {
+    in_flight: &'a InFlight,
+    key: &'a str,
+    flight: Arc<Mutex<Flight>>,
+}
+
+impl<'a> Wait<'a> {
+    fn new(in_flight: &'a InFlight, key: &'a str, flight: Arc<Mutex<Flight>>) -> Self {
+        flight.lock().expect("flight poisoned").waiting += 1;
+        Self {
+            in_flight,
+            key,
+            flight,
+        }
+    }
+}
+
+impl Future for Wait<'_> {
+    type Output = Result<Option<GenericChatCompletionResponse<GenericMessage>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut flight = self.flight.lock().expect("flight poisoned");
+        if let Some(result) = &flight.result {
+            return Poll::Ready(result.clone().map(Some).map_err(ArtificialError::Shared));
+        }
+        if flight.orphaned {
+            flight.orphaned = false;
+            return Poll::Ready(Ok(None));
+        }
+        if !flight.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            flight.waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().expect("single-flight map poisoned");
+        let mut flight = self.flight.lock().expect("flight poisoned");
+        flight.waiting -= 1;
+        // The last waiter of an orphaned flight leaves: nobody will lead it.
+        if flight.orphaned && flight.waiting == 0 {
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        generic::{GenericRole, GenericUsageReport, ResponseContent},
+        model::{Model, OpenAiModel},
+    };
+
+    /// Counts calls and yields once so concurrent callers overlap; answers
+    /// `"empty"` with [`ArtificialError::EmptyResponse`].
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    impl ChatCompletionProvider for Counting {
+        type Message = GenericMessage;
+
+        fn chat_complete<'s, M>(
+            &'s self,
+            params: ChatCompleteParameters<M>,
+        ) -> Pin<
+            Box<
+                dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>>
+                    + Send
+                    + 's,
+            >,
+        >
+        where
+            M: Into<Self::Message> + Clone + Send + Sync + 's,
+        {
+            let last: GenericMessage = params.messages.last().cloned().unwrap().into();
+            Box::pin(async move {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                if last.content.as_deref() == Some("empty") {
+                    return Err(ArtificialError::EmptyResponse);
+                }
+                Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(GenericMessage::new(
+                        last.content.unwrap_or_default(),
+                        GenericRole::Assistant,
+                    )),
+                    usage: Some(GenericUsageReport {
+                        prompt_tokens: 1,
+                        completion_tokens: 1,
+                        total_tokens: 2,
+                    }),
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    fn params(text: &str) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new(text.into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn concurrent_duplicates_share_one_call() {
+        let provider = SingleFlightProvider::new(Counting::default());
+
+        let (a, b, c) = tokio::join!(
+            provider.chat_complete(params("same")),
+            provider.chat_complete(params("same")),
+            provider.chat_complete(params("other")),
+        );
+
+        assert_eq!(provider.inner().0.load(Ordering::SeqCst), 2);
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert!(a.usage.is_some() && b.usage.is_none());
+        match (a.content, b.content, c.unwrap().content) {
+            (
+                ResponseContent::Finished(a),
+                ResponseContent::Finished(b),
+                ResponseContent::Finished(c),
+            ) => {
+                assert_eq!(a.content, b.content);
+                assert_eq!(c.content.as_deref(), Some("other"));
+            }
+            _ => panic!("expected finished answers"),
+        }
+
+        // Finished flights are forgotten: the next duplicate is sent again.
+        provider.chat_complete(params("same")).await.unwrap();
+        assert_eq!(provider.inner().0.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn a_waiter_takes_over_when_the_leader_is_cancelled() {
+        let provider = SingleFlightProvider::new(Counting::default());
+        let token = CancellationToken::new();
+
+        let mut leader = provider.chat_complete(params("same").with_cancellation(token.clone()));
+        let mut waiter = provider.chat_complete(params("same"));
+        assert!(futures_util::poll!(&mut leader).is_pending());
+        assert!(futures_util::poll!(&mut waiter).is_pending());
+        token.cancel();
+
+        let (leader, waiter) = tokio::join!(leader, waiter);
+        assert!(matches!(leader, Err(ArtificialError::Cancelled)));
+        assert!(waiter.unwrap().usage.is_some());
+        assert_eq!(provider.inner().0.load(Ordering::SeqCst), 2);
+        assert!(provider.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn duplicates_fail_with_the_leaders_error() {
+        let provider = SingleFlightProvider::new(Counting::default());
+
+        let (a, b) = tokio::join!(
+            provider.chat_complete(params("empty")),
+            provider.chat_complete(params("empty")),
+        );
+
+        assert_eq!(provider.inner().0.load(Ordering::SeqCst), 1);
+        for err in [a.unwrap_err(), b.unwrap_err()] {
+            assert!(matches!(err.root(), ArtificialError::EmptyResponse));
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! Incremental check that streamed text is still a valid JSON prefix.
+
+/// Feeds on JSON fragments and reports as soon as the accumulated text can
+/// no longer become a valid JSON value.
+///
+/// Used for streamed tool-call arguments: a model producing garbage can be
+/// stopped (or retried) at the first bad byte instead of at the end of the
+/// message.
+///
+/// ```rust
+/// use artificial_core::stream::JsonPrefixValidator;
+///
+/// let mut args = JsonPrefixValidator::new();
+/// assert!(args.push(r#"{"city": "Ber"#));
+/// assert!(!args.is_complete());
+/// assert!(args.push(r#"lin"}"#));
+/// assert!(args.is_complete());
+///
+/// let mut garbage = JsonPrefixValidator::new();
+/// assert!(!garbage.push(r#"{"city" "Berlin"}"#));
+/// assert_eq!(garbage.error(), Some("expected ':' at byte 8"));
+/// ```
+///
+/// Numbers are checked loosely (only their character set), everything else
+/// strictly. [`super::PartialJson`] runs on the same state machine.
+#[derive(Debug, Clone, Default)]
+pub struct JsonPrefixValidator {
+    stack: Vec<Container>,
+    state: State,
+    offset: usize,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Container {
+    Object,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum State {
+    /// A value must follow.
+    #[default]
+    Value,
+    /// A value or, for an empty array, `]`.
+    ValueOrClose,
+    Key,
+    /// A key or, for an empty object, `}`.
+    KeyOrClose,
+    Colon,
+    /// A value just ended inside a container.
+    AfterValue,
+    String {
+        key: bool,
+        escape: Escape,
+    },
+    Number,
+    Literal {
+        word: &'static [u8],
+        matched: usize,
+    },
+    /// The top-level value is complete.
+    Done,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Escape {
+    None,
+    Backslash,
+    /// Hex digits of a `\u` escape still expected.
+    Unicode(u8),
+}
+
+/// Structure of the text as the validator sees it, for consumers that need
+/// more than a yes or no. Offsets are byte offsets into the whole text.
+pub(crate) trait Observer {
+    /// A value starts at the current character.
+    fn value_start(&mut self) {}
+    /// The `{` or `[` at `offset` opened `container`.
+    fn open(&mut self, _container: Container, _offset: usize) {}
+    /// The innermost container was closed; [`Self::value_end`] follows.
+    fn close(&mut self) {}
+    /// An object key starts with the quote at `offset`.
+    fn key_start(&mut self, _offset: usize) {}
+    /// The object key ends with the quote at `offset`.
+    fn key_end(&mut self, _offset: usize) {}
+    /// A value ended; nothing before `end` changes anymore.
+    fn value_end(&mut self, _end: usize) {}
+}
+
+impl Observer for () {}
+
+impl JsonPrefixValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `fragment`; `false` once the text cannot become valid JSON.
+    pub fn push(&mut self, fragment: &str) -> bool {
+        self.push_observed(fragment, &mut ())
+    }
+
+    /// [`Self::push`], reporting the structure to `observer`.
+    pub(crate) fn push_observed<O: Observer>(&mut self, fragment: &str, observer: &mut O) -> bool {
+        for c in fragment.chars() {
+            if self.error.is_some() {
+                break;
+            }
+            if let Err(expected) = self.step(c, observer) {
+                self.error = Some(format!("expected {expected} at byte {}", self.offset));
+            }
+            self.offset += c.len_utf8();
+        }
+        self.error.is_none()
+    }
+
+    /// Why the text is malformed, if it is.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Whether the text so far is one complete JSON value.
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+            && match self.state {
+                State::Done => true,
+                // A top-level number has no terminator.
+                State::Number => self.stack.is_empty(),
+                _ => false,
+            }
+    }
+
+    /// Bytes of an unfinished escape sequence at the end of the text if a
+    /// string value is being written.
+    pub(crate) fn open_string(&self) -> Option<usize> {
+        match self.state {
+            State::String { key: false, escape } => Some(match escape {
+                Escape::None => 0,
+                Escape::Backslash => 1,
+                Escape::Unicode(left) => 6 - left as usize,
+            }),
+            _ => None,
+        }
+    }
+
+    fn step<O: Observer>(&mut self, c: char, observer: &mut O) -> Result<(), &'static str> {
+        match self.state {
+            State::String { key, escape } => return self.string(c, key, escape, observer),
+            State::Literal { word, matched } => {
+                if word.get(matched) != Some(&(c as u8)) || !c.is_ascii() {
+                    return Err("a literal");
+                }
+                self.state = match matched + 1 == word.len() {
+                    true => self.value_done(observer, self.offset + 1),
+                    false => State::Literal {
+                        word,
+                        matched: matched + 1,
+                    },
+                };
+                return Ok(());
+            }
+            State::Number => {
+                if c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E') {
+                    return Ok(());
+                }
+                self.state = self.value_done(observer, self.offset);
+            }
+            _ => {}
+        }
+
+        if c.is_ascii_whitespace() {
+            return Ok(());
+        }
+
+        match self.state {
+            State::Value | State::ValueOrClose => {
+                if c == ']' && matches!(self.state, State::ValueOrClose) {
+                    return self.close(Container::Array, observer);
+                }
+                if matches!(c, '{' | '[' | '"' | '-' | '0'..='9' | 't' | 'f' | 'n') {
+                    observer.value_start();
+                }
+                self.state = match c {
+                    '{' => {
+                        self.stack.push(Container::Object);
+                        observer.open(Container::Object, self.offset);
+                        State::KeyOrClose
+                    }
+                    '[' => {
+                        self.stack.push(Container::Array);
+                        observer.open(Container::Array, self.offset);
+                        State::ValueOrClose
+                    }
+                    '"' => State::String {
+                        key: false,
+                        escape: Escape::None,
+                    },
+                    '-' | '0'..='9' => State::Number,
+                    't' => literal(b"true"),
+                    'f' => literal(b"false"),
+                    'n' => literal(b"null"),
+                    _ => return Err("a value"),
+                };
+                Ok(())
+            }
+            State::Key | State::KeyOrClose => match c {
+                '"' => {
+                    observer.key_start(self.offset);
+                    self.state = State::String {
+                        key: true,
+                        escape: Escape::None,
+                    };
+                    Ok(())
+                }
+                '}' if matches!(self.state, State::KeyOrClose) => {
+                    self.close(Container::Object, observer)
+                }
+                _ => Err("an object key"),
+            },
+            State::Colon => match c {
+                ':' => {
+                    self.state = State::Value;
+                    Ok(())
+                }
+                _ => Err("':'"),
+            },
+            State::AfterValue => match (c, self.stack.last()) {
+                (',', Some(Container::Object)) => {
+                    self.state = State::Key;
+                    Ok(())
+                }
+                (',', Some(Container::Array)) => {
+                    self.state = State::Value;
+                    Ok(())
+                }
+                ('}', _) => self.close(Container::Object, observer),
+                (']', _) => self.close(Container::Array, observer),
+                _ => Err("',' or a closing bracket"),
+            },
+            State::Done => Err("end of input"),
+            State::String { .. } | State::Literal { .. } | State::Number => unreachable!(),
+        }
+    }
+
+    fn string<O: Observer>(
+        &mut self,
+        c: char,
+        key: bool,
+        escape: Escape,
+        observer: &mut O,
+    ) -> Result<(), &'static str> {
+        let escape = match escape {
+            Escape::Backslash => match c {
+                'u' => Escape::Unicode(4),
+                '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => Escape::None,
+                _ => return Err("an escape sequence"),
+            },
+            Escape::Unicode(left) => match c.is_ascii_hexdigit() {
+                true if left == 1 => Escape::None,
+                true => Escape::Unicode(left - 1),
+                false => return Err("a hex digit"),
+            },
+            Escape::None => match c {
+                '"' => {
+                    self.state = match key {
+                        true => {
+                            observer.key_end(self.offset);
+                            State::Colon
+                        }
+                        false => self.value_done(observer, self.offset + 1),
+                    };
+                    return Ok(());
+                }
+                '\\' => Escape::Backslash,
+                c if (c as u32) < 0x20 => return Err("an escaped control character"),
+                _ => Escape::None,
+            },
+        };
+        self.state = State::String { key, escape };
+        Ok(())
+    }
+
+    fn close<O: Observer>(
+        &mut self,
+        container: Container,
+        observer: &mut O,
+    ) -> Result<(), &'static str> {
+        if self.stack.pop() != Some(container) {
+            return Err(match container {
+                Container::Object => "'}' to match an object",
+                Container::Array => "']' to match an array",
+            });
+        }
+        observer.close();
+        self.state = self.value_done(observer, self.offset + 1);
+        Ok(())
+    }
+
+    /// The state after a value that ends before `end`.
+    fn value_done<O: Observer>(&self, observer: &mut O, end: usize) -> State {
+        observer.value_end(end);
+        match self.stack.is_empty() {
+            true => State::Done,
+            false => State::AfterValue,
+        }
+    }
+}
+
+fn literal(word: &'static [u8]) -> State {
+    State::Literal { word, matched: 1 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(fragments: &[&str]) -> JsonPrefixValidator {
+        let mut validator = JsonPrefixValidator::new();
+        for fragment in fragments {
+            validator.push(fragment);
+        }
+        validator
+    }
+
+    #[test]
+    fn accepts_prefixes_of_valid_json() {
+        let text = r#"{"a": [1, -2.5e3, true, null, {"b": "x\"é"}], "c": {}, "d": []}"#;
+        let mut validator = JsonPrefixValidator::new();
+        for c in text.chars() {
+            assert!(validator.push(&c.to_string()), "{:?}", validator.error());
+        }
+        assert!(validator.is_complete());
+        assert!(check(&["  4", "2"]).is_complete());
+        assert!(!check(&["[1,"]).is_complete());
+    }
+
+    #[test]
+    fn rejects_at_the_first_bad_byte() {
+        for (text, error) in [
+            ("{'a': 1}", "expected an object key at byte 1"),
+            (r#"{"a": 1,}"#, "expected an object key at byte 8"),
+            ("[1 2]", "expected ',' or a closing bracket at byte 3"),
+            ("[1}", "expected '}' to match an object at byte 2"),
+            ("{} x", "expected end of input at byte 3"),
+            ("tru3", "expected a literal at byte 3"),
+            (r#""\x""#, "expected an escape sequence at byte 2"),
+        ] {
+            assert_eq!(check(&[text]).error(), Some(error), "{text}");
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Runtime-agnostic utilities for provider streams.
+//!
+//! The streaming traits hand out raw deltas exactly as the provider produced
+//! them. The adapters in this module reshape those streams for consumers such
+//! as UI layers, or share them between several consumers, without tying the
+//! core crate to a specific async runtime.
+mod json_prefix;
+mod partial_json;
+mod tee;
+mod text;
+mod watchdog;
+
+pub use json_prefix::*;
+pub use partial_json::*;
+pub use tee::*;
+pub use text::*;
+pub use watchdog::*;
@@ -0,0 +1,397 @@
+//! Snapshots of a JSON document that is still being streamed.
+
+use std::{fmt, marker::PhantomData};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::json_prefix::{Container, JsonPrefixValidator, Observer};
+use crate::{error::Result, schema_util::parse_output};
+
+/// Accumulates streamed JSON and turns the text received so far into a
+/// [`Partial`] view.
+///
+/// A snapshot contains everything that is certain already: finished values,
+/// and string values up to the last received character. Values that may
+/// still change their meaning – a number such as `12` that may become
+/// `125`, a half-written `tru`, an object key without its value – are left
+/// out until they are complete.
+///
+/// ```rust
+/// use artificial_core::stream::PartialJson;
+///
+/// let mut json = PartialJson::new();
+/// json.push(r#"{"items": [{"title": "Intro"}, {"title": "Mid"#);
+///
+/// let partial = json.snapshot::<serde_json::Value>().unwrap();
+/// assert_eq!(partial.value()["items"][1]["title"], "Mid");
+/// assert!(partial.is_complete("/items/0"));
+/// assert!(!partial.is_complete("/items/1"));
+/// ```
+///
+/// The text is scanned once, as it arrives, on the state machine of
+/// [`super::JsonPrefixValidator`]; a snapshot only closes the text at the
+/// last certain point and parses it.
+#[derive(Debug, Clone, Default)]
+pub struct PartialJson {
+    text: String,
+    scanner: JsonPrefixValidator,
+    outline: Outline,
+}
+
+impl PartialJson {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, delta: &str) {
+        self.text.push_str(delta);
+        self.scanner.push_observed(
+            delta,
+            &mut Observed {
+                text: &self.text,
+                outline: &mut self.outline,
+            },
+        );
+    }
+
+    /// The raw text received so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The document as far as it is known; `None` before the first value
+    /// starts or once the text is no valid JSON prefix.
+    pub fn snapshot<T>(&self) -> Option<Partial<T>> {
+        if self.scanner.error().is_some() || self.outline.broken {
+            return None;
+        }
+        let (json, open) = if self.scanner.is_complete() {
+            (self.text.clone(), None)
+        } else if let Some(escape) = self.scanner.open_string() {
+            let mut json = self.text[..self.text.len() - escape].to_owned();
+            json.push('"');
+            close(&mut json, &self.outline.frames);
+            (json, Some(open_path(&self.outline.frames)))
+        } else {
+            let (end, frames) = self.outline.safe.as_ref()?;
+            let mut json = self.text[..*end].to_owned();
+            close(&mut json, frames);
+            (json, Some(open_path(frames)))
+        };
+        let value = serde_json::from_str(&json).ok()?;
+        Some(Partial {
+            value,
+            open,
+            output: PhantomData,
+        })
+    }
+}
+
+/// A progressively filled view of an output of type `T`.
+///
+/// Fields are addressed with JSON pointers such as `/items/0/title`. A value
+/// is *complete* once the model has written its last character; only the
+/// values along the path currently being written are incomplete.
+pub struct Partial<T> {
+    value: Value,
+    /// Pointer segments of the innermost value still being written; `None`
+    /// once the document is complete.
+    open: Option<Vec<String>>,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<T> Partial<T> {
+    /// Everything received so far, as JSON.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Whether the whole document has been received.
+    pub fn is_finished(&self) -> bool {
+        self.open.is_none()
+    }
+
+    /// Whether the value at `pointer` exists and will not change anymore.
+    pub fn is_complete(&self, pointer: &str) -> bool {
+        if self.value.pointer(pointer).is_none() {
+            return false;
+        }
+        let Some(open) = &self.open else {
+            return true;
+        };
+        let segments = segments(pointer);
+        !(segments.len() <= open.len() && open.iter().zip(&segments).all(|(a, b)| a == b))
+    }
+
+    /// The value at `pointer` as `V`, once it is complete.
+    pub fn get<V: DeserializeOwned>(&self, pointer: &str) -> Option<V> {
+        if !self.is_complete(pointer) {
+            return None;
+        }
+        serde_json::from_value(self.value.pointer(pointer)?.clone()).ok()
+    }
+
+    /// The string at `pointer` as far as it has been written.
+    pub fn text(&self, pointer: &str) -> Option<&str> {
+        self.value.pointer(pointer)?.as_str()
+    }
+
+    /// The complete items of the array at `pointer`, in order, as `V`.
+    ///
+    /// Stops at the first item that is incomplete or does not deserialize.
+    pub fn items<V: DeserializeOwned>(&self, pointer: &str) -> Vec<V> {
+        let Some(items) = self.value.pointer(pointer).and_then(Value::as_array) else {
+            return Vec::new();
+        };
+        (0..items.len())
+            .map_while(|index| self.get(&format!("{pointer}/{index}")))
+            .collect()
+    }
+}
+
+impl<T: DeserializeOwned + schemars::JsonSchema> Partial<T> {
+    /// The snapshot as `T`; succeeds early if the missing fields have
+    /// defaults (`Option`, `#[serde(default)]`).
+    pub fn parse(&self) -> Result<T> {
+        parse_output(&self.value.to_string())
+    }
+}
+
+impl<T> Clone for Partial<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            open: self.open.clone(),
+            output: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Partial<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.open == other.open
+    }
+}
+
+impl<T> fmt::Debug for Partial<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Partial")
+            .field(
+                "value",
+                &crate::redact::RedactedText(&self.value.to_string()),
+            )
+            .field("open", &self.open)
+            .finish()
+    }
+}
+
+fn segments(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+enum Frame {
+    /// `key` is set once the key is complete, until the value ends.
+    Object { key: Option<String> },
+    /// Number of items started so far, and whether the last is still
+    /// being written.
+    Array { len: usize, writing: bool },
+}
+
+/// The containers around the current position, with their keys and
+/// indices, as far as the scanner got.
+#[derive(Debug, Clone, Default)]
+struct Outline {
+    frames: Vec<Frame>,
+    /// The last cut point: byte offset and the frames to close there.
+    safe: Option<(usize, Vec<Frame>)>,
+    key_start: usize,
+    /// A key the scanner accepted but serde does not, e.g. a lone surrogate.
+    broken: bool,
+}
+
+/// An [`Outline`] updated from the scanner, with the text it refers to.
+struct Observed<'a> {
+    text: &'a str,
+    outline: &'a mut Outline,
+}
+
+impl Observer for Observed<'_> {
+    fn value_start(&mut self) {
+        if let Some(Frame::Array { len, writing }) = self.outline.frames.last_mut() {
+            *len += 1;
+            *writing = true;
+        }
+    }
+
+    fn open(&mut self, container: Container, offset: usize) {
+        let outline = &mut *self.outline;
+        outline.frames.push(match container {
+            Container::Object => Frame::Object { key: None },
+            Container::Array => Frame::Array {
+                len: 0,
+                writing: false,
+            },
+        });
+        outline.safe = Some((offset + 1, outline.frames.clone()));
+    }
+
+    fn close(&mut self) {
+        self.outline.frames.pop();
+    }
+
+    fn key_start(&mut self, offset: usize) {
+        self.outline.key_start = offset;
+    }
+
+    fn key_end(&mut self, offset: usize) {
+        let outline = &mut *self.outline;
+        match serde_json::from_str(&self.text[outline.key_start..=offset]) {
+            Ok(key) => {
+                if let Some(Frame::Object { key: slot }) = outline.frames.last_mut() {
+                    *slot = Some(key);
+                }
+            }
+            Err(_) => outline.broken = true,
+        }
+    }
+
+    fn value_end(&mut self, end: usize) {
+        let outline = &mut *self.outline;
+        match outline.frames.last_mut() {
+            Some(Frame::Object { key }) => *key = None,
+            Some(Frame::Array { writing, .. }) => *writing = false,
+            None => {}
+        }
+        outline.safe = Some((end, outline.frames.clone()));
+    }
+}
+
+fn close(json: &mut String, frames: &[Frame]) {
+    // A cut right after a comma would leave it dangling.
+    let trimmed = json.trim_end().trim_end_matches(',').len();
+    json.truncate(trimmed);
+    for frame in frames.iter().rev() {
+        json.push(match frame {
+            Frame::Object { .. } => '}',
+            Frame::Array { .. } => ']',
+        });
+    }
+}
+
+/// Pointer segments of the innermost value being written.
+fn open_path(frames: &[Frame]) -> Vec<String> {
+    let mut path = Vec::new();
+    for frame in frames {
+        match frame {
+            Frame::Object { key: Some(key) } => path.push(key.clone()),
+            Frame::Array { len, writing: true } => path.push((len - 1).to_string()),
+            _ => break,
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshots(text: &str) -> Vec<Option<Value>> {
+        let mut json = PartialJson::new();
+        text.chars()
+            .map(|c| {
+                json.push(&c.to_string());
+                json.snapshot::<Value>().map(|p| p.value().clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_prefix_closes_into_a_subset_of_the_result() {
+        let text = r#"{"title": "A \"quoted\" été", "n": -12.5e3, "ok": true,
+            "tags": ["x", "y"], "nested": {"empty": [], "none": null}}"#;
+        let full: Value = serde_json::from_str(text).unwrap();
+        let snapshots = snapshots(text);
+        assert_eq!(snapshots.last().unwrap().as_ref(), Some(&full));
+        for snapshot in snapshots.into_iter().flatten() {
+            for (key, value) in snapshot.as_object().unwrap() {
+                match (value, &full[key]) {
+                    (Value::String(part), Value::String(whole)) => {
+                        assert!(whole.starts_with(part.as_str()))
+                    }
+                    (Value::Array(_) | Value::Object(_), _) => {}
+                    (part, whole) => assert_eq!(part, whole, "{key}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unfinished_scalars_and_keys_are_left_out() {
+        let mut json = PartialJson::new();
+        json.push(r#"{"count": 12"#);
+        assert_eq!(
+            json.snapshot::<Value>().unwrap().value(),
+            &serde_json::json!({})
+        );
+        json.push(r#", "ti"#);
+        let partial = json.snapshot::<Value>().unwrap();
+        assert_eq!(partial.value(), &serde_json::json!({"count": 12}));
+        assert!(partial.is_complete("/count"));
+        json.push(r#"tle": "Hel"#);
+        assert_eq!(
+            json.snapshot::<Value>().unwrap().text("/title"),
+            Some("Hel")
+        );
+    }
+
+    #[test]
+    fn completed_items_are_available_while_the_list_grows() {
+        #[derive(serde::Deserialize, PartialEq, Debug)]
+        struct Item {
+            name: String,
+        }
+
+        let mut json = PartialJson::new();
+        json.push(r#"{"items": [{"name": "a"}, {"name": "b"}, {"na"#);
+        let partial = json.snapshot::<Value>().unwrap();
+        let items: Vec<Item> = partial.items("/items");
+        assert_eq!(
+            items,
+            [Item { name: "a".into() }, Item { name: "b".into() }]
+        );
+        assert!(!partial.is_finished());
+
+        json.push(r#"me": "c"}"#);
+        let partial = json.snapshot::<Value>().unwrap();
+        assert_eq!(partial.items::<Item>("/items").len(), 3);
+
+        json.push("]}");
+        let partial = json.snapshot::<Value>().unwrap();
+        assert!(partial.is_finished());
+        assert_eq!(partial.items::<Item>("/items").len(), 3);
+    }
+
+    #[test]
+    fn unfinished_escapes_are_cut_off() {
+        let mut json = PartialJson::new();
+        json.push(r#"{"t": "a\"#);
+        assert_eq!(json.snapshot::<Value>().unwrap().text("/t"), Some("a"));
+        json.push("u00");
+        assert_eq!(json.snapshot::<Value>().unwrap().text("/t"), Some("a"));
+        json.push("e9");
+        assert_eq!(json.snapshot::<Value>().unwrap().text("/t"), Some("aé"));
+    }
+
+    #[test]
+    fn invalid_text_has_no_snapshot() {
+        let mut json = PartialJson::new();
+        json.push(r#"{"a" 1}"#);
+        assert!(json.snapshot::<Value>().is_none());
+    }
+}
@@ -0,0 +1,239 @@
+//! Fan a single provider stream out to several consumers.
+//!
+//! Provider streams are single-consumer.  [`tee`] splits one into `n`
+//! [`TeeBranch`]es that each observe every item, e.g. one for the UI, one
+//! for a logger and one collecting the final message:
+//!
+//! ```rust,no_run
+//! use artificial_core::stream::tee;
+//! # use artificial_core::{error::Result, generic::StreamEvent};
+//! # use futures_core::Stream;
+//! # fn fan_out<S: Stream<Item = Result<StreamEvent>> + Unpin>(events: S) {
+//! let mut branches = tee(events, 3, 64).into_iter();
+//! let (ui, log, collector) = (
+//!     branches.next().unwrap(),
+//!     branches.next().unwrap(),
+//!     branches.next().unwrap(),
+//! );
+//! # }
+//! ```
+//!
+//! # Buffering
+//!
+//! Every branch owns a queue of at most `capacity` items.  When one branch
+//! falls `capacity` items behind, the source is not polled any further until
+//! that branch catches up—memory stays bounded and the slowest consumer sets
+//! the pace.  Dropping a branch removes it from the fan-out.
+//!
+//! # Errors
+//!
+//! [`ArtificialError`] is not `Clone`, so an error from the source reaches
+//! every branch as [`ArtificialError::Shared`]; [`ArtificialError::root`]
+//! still tells which error it was.
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures_core::Stream;
+
+use crate::error::{ArtificialError, Result};
+
+struct Slot<T> {
+    queue: VecDeque<std::result::Result<T, Arc<ArtificialError>>>,
+    waker: Option<Waker>,
+    open: bool,
+}
+
+struct Shared<S, T> {
+    source: S,
+    finished: bool,
+    capacity: usize,
+    slots: Vec<Slot<T>>,
+}
+
+impl<S, T> Shared<S, T> {
+    fn wake_others(&mut self, except: usize) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            if i != except {
+                if let Some(waker) = slot.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+}
+
+/// One consumer side of a [`tee`]d stream.
+pub struct TeeBranch<S, T> {
+    index: usize,
+    shared: Arc<Mutex<Shared<S, T>>>,
+}
+
+/// Split `source` into `branches` streams that all yield every item.
+///
+/// `capacity` (at least 1) bounds how far a branch may lag behind the
+/// fastest one.
+pub fn tee<S, T>(source: S, branches: usize, capacity: usize) -> Vec<TeeBranch<S, T>>
+where
+    S: Stream<Item = Result<T>> + Unpin,
+    T: Clone,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        source,
+        finished: false,
+        capacity: capacity.max(1),
+        slots: (0..branches)
+            .map(|_| Slot {
+                queue: VecDeque::new(),
+                waker: None,
+                open: true,
+            })
+            .collect(),
+    }));
+
+    (0..branches)
+        .map(|index| TeeBranch {
+            index,
+            shared: shared.clone(),
+        })
+        .collect()
+}
+
+impl<S, T> Stream for TeeBranch<S, T>
+where
+    S: Stream<Item = Result<T>> + Unpin,
+    T: Clone,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let index = self.index;
+        let mut guard = self.shared.lock().expect("tee state poisoned");
+        let shared = &mut *guard;
+
+        if let Some(item) = shared.slots[index].queue.pop_front() {
+            // Room was made; a source-blocked sibling may continue.
+            shared.wake_others(index);
+            return Poll::Ready(Some(item.map_err(ArtificialError::Shared)));
+        }
+        if shared.finished {
+            return Poll::Ready(None);
+        }
+
+        let capacity = shared.capacity;
+        let lagging = shared
+            .slots
+            .iter()
+            .any(|slot| slot.open && slot.queue.len() >= capacity);
+        if lagging {
+            shared.slots[index].waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        match Pin::new(&mut shared.source).poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                let item = item.map_err(Arc::new);
+                for (i, slot) in shared.slots.iter_mut().enumerate() {
+                    if i != index && slot.open {
+                        slot.queue.push_back(item.clone());
+                    }
+                }
+                shared.wake_others(index);
+                Poll::Ready(Some(item.map_err(ArtificialError::Shared)))
+            }
+            Poll::Ready(None) => {
+                shared.finished = true;
+                shared.wake_others(index);
+                Poll::Ready(None)
+            }
+            Poll::Pending => {
+                shared.slots[index].waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<S, T> Drop for TeeBranch<S, T> {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            let slot = &mut shared.slots[self.index];
+            slot.open = false;
+            slot.queue.clear();
+            slot.waker = None;
+            // The dropped branch may have been the one registered with the
+            // source, or the one everybody was waiting for.
+            shared.wake_others(self.index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use super::*;
+
+    struct Numbers(VecDeque<Result<u32>>);
+
+    impl Stream for Numbers {
+        type Item = Result<u32>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.pop_front())
+        }
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn every_branch_sees_every_item() {
+        let source =
+            Numbers(vec![Ok(1), Ok(2), Err(ArtificialError::Invalid("bad".into()))].into());
+        let mut branches = tee(source, 2, 8);
+        let mut second = branches.pop().unwrap();
+        let mut first = branches.pop().unwrap();
+
+        for branch in [&mut first, &mut second] {
+            assert_eq!(next(branch).await.unwrap().unwrap(), 1);
+            assert_eq!(next(branch).await.unwrap().unwrap(), 2);
+            let err = next(branch).await.unwrap().unwrap_err();
+            assert!(err.to_string().contains("invalid: bad"));
+            assert!(matches!(err.root(), ArtificialError::Invalid(_)));
+            assert!(next(branch).await.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_branch_bounds_the_fast_one() {
+        let source = Numbers((1..=5).map(Ok).collect());
+        let mut branches = tee(source, 2, 2);
+        let mut slow = branches.pop().unwrap();
+        let mut fast = branches.pop().unwrap();
+
+        let waker = std::task::Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        assert!(matches!(
+            Pin::new(&mut fast).poll_next(&mut cx),
+            Poll::Ready(Some(Ok(1)))
+        ));
+        assert!(matches!(
+            Pin::new(&mut fast).poll_next(&mut cx),
+            Poll::Ready(Some(Ok(2)))
+        ));
+        assert!(Pin::new(&mut fast).poll_next(&mut cx).is_pending());
+
+        assert_eq!(next(&mut slow).await.unwrap().unwrap(), 1);
+        assert_eq!(next(&mut fast).await.unwrap().unwrap(), 3);
+
+        drop(slow);
+        assert_eq!(next(&mut fast).await.unwrap().unwrap(), 4);
+        assert_eq!(next(&mut fast).await.unwrap().unwrap(), 5);
+        assert!(next(&mut fast).await.is_none());
+    }
+}
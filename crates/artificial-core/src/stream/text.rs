@@ -0,0 +1,399 @@
+//! Combinators over text-delta streams.
+//!
+//! Providers emit deltas at token granularity—half words, a lone backtick, a
+//! comma.  [`TextStreamExt`] turns such a stream into nicely shaped chunks:
+//!
+//! * [`TextStreamExt::rechunk_words`] / [`TextStreamExt::rechunk_sentences`]
+//!   only emit complete words or sentences,
+//! * [`TextStreamExt::markdown_safe`] never emits half a code fence,
+//! * [`TextStreamExt::mask_words`] replaces blocked words with `*`,
+//! * [`TextStreamExt::paced`] enforces a minimum delay between chunks.
+//!
+//! Buffered text is always flushed when the inner stream ends, so no output
+//! is lost.  Errors are passed through immediately.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use artificial_core::stream::TextStreamExt;
+//! # use futures_core::Stream;
+//! # fn shape<S>(deltas: S)
+//! # where S: Stream<Item = artificial_core::error::Result<String>> + Unpin + Send {
+//! let shaped = deltas
+//!     .markdown_safe()
+//!     .paced(Duration::from_millis(30), |d| async move {
+//!         // e.g. `tokio::time::sleep(d).await`
+//! #       let _ = d;
+//!     });
+//! # }
+//! ```
+use std::{
+    collections::HashSet,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+
+use crate::error::Result;
+
+/// Decides how much of the buffered text may be emitted.
+///
+/// Implement this to build custom re-chunking strategies and plug them in
+/// with [`TextStreamExt::chunked`].
+pub trait Chunker {
+    /// Number of leading **bytes** of `pending` that are ready to be emitted.
+    /// Must fall on a `char` boundary.
+    fn ready(&mut self, pending: &str) -> usize;
+
+    /// Last chance to rewrite a chunk before it is emitted.
+    fn finish(&mut self, chunk: String) -> String {
+        chunk
+    }
+}
+
+/// Emits everything up to (and including) the last whitespace.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Words;
+
+impl Chunker for Words {
+    fn ready(&mut self, pending: &str) -> usize {
+        pending
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0)
+    }
+}
+
+/// Emits complete sentences: text up to a `.`, `!`, `?` or newline that is
+/// followed by whitespace.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sentences;
+
+impl Chunker for Sentences {
+    fn ready(&mut self, pending: &str) -> usize {
+        let mut ready = 0;
+        let mut previous = None;
+        for (i, c) in pending.char_indices() {
+            let ends_sentence = matches!(previous, Some('.' | '!' | '?')) && c.is_whitespace();
+            if ends_sentence || c == '\n' {
+                ready = i + c.len_utf8();
+            }
+            previous = Some(c);
+        }
+        ready
+    }
+}
+
+/// Holds back fenced code blocks until they are closed and never splits a
+/// run of backticks, so a renderer never sees half a fence.
+#[derive(Debug, Clone, Copy)]
+pub struct MarkdownSafe {
+    at_line_start: bool,
+}
+
+impl Default for MarkdownSafe {
+    fn default() -> Self {
+        Self {
+            at_line_start: true,
+        }
+    }
+}
+
+impl Chunker for MarkdownSafe {
+    fn ready(&mut self, pending: &str) -> usize {
+        let mut safe = 0;
+        let mut in_fence = false;
+        let mut line_start = 0;
+        let mut first_line = true;
+
+        while line_start < pending.len() {
+            let is_line_start = !first_line || self.at_line_start;
+            let rest = &pending[line_start..];
+
+            let Some(newline) = rest.find('\n') else {
+                // Incomplete last line.
+                if !in_fence {
+                    let trimmed = rest.trim_start();
+                    let maybe_fence =
+                        is_line_start && (trimmed.starts_with("```") || "```".starts_with(trimmed));
+                    if !maybe_fence {
+                        safe = line_start + rest.trim_end_matches('`').len();
+                    }
+                }
+                break;
+            };
+
+            let line = &rest[..newline];
+            if is_line_start && line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            line_start += newline + 1;
+            if !in_fence {
+                safe = line_start;
+            }
+            first_line = false;
+        }
+
+        if safe > 0 {
+            self.at_line_start = pending[..safe].ends_with('\n');
+        }
+        safe
+    }
+}
+
+/// Word-aligned chunking that replaces blocked words (case-insensitive,
+/// whole words only) with asterisks of the same length.
+#[derive(Debug, Clone, Default)]
+pub struct MaskWords {
+    blocked: HashSet<String>,
+}
+
+impl MaskWords {
+    pub fn new<I, W>(words: I) -> Self
+    where
+        I: IntoIterator<Item = W>,
+        W: AsRef<str>,
+    {
+        Self {
+            blocked: words
+                .into_iter()
+                .map(|w| w.as_ref().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl Chunker for MaskWords {
+    fn ready(&mut self, pending: &str) -> usize {
+        Words.ready(pending)
+    }
+
+    fn finish(&mut self, chunk: String) -> String {
+        let mut out = String::with_capacity(chunk.len());
+        let mut word = String::new();
+
+        let flush = |word: &mut String, out: &mut String| {
+            if self.blocked.contains(&word.to_lowercase()) {
+                out.extend(std::iter::repeat_n('*', word.chars().count()));
+            } else {
+                out.push_str(word);
+            }
+            word.clear();
+        };
+
+        for c in chunk.chars() {
+            if c.is_alphanumeric() || c == '\'' {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut out);
+                out.push(c);
+            }
+        }
+        flush(&mut word, &mut out);
+        out
+    }
+}
+
+/// Stream adapter returned by [`TextStreamExt::chunked`] and friends.
+pub struct Chunked<S, C> {
+    inner: S,
+    chunker: C,
+    buffer: String,
+    done: bool,
+}
+
+impl<S, C> Stream for Chunked<S, C>
+where
+    S: Stream<Item = Result<String>> + Unpin,
+    C: Chunker + Unpin,
+{
+    type Item = Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.done {
+                if this.buffer.is_empty() {
+                    return Poll::Ready(None);
+                }
+                let rest = std::mem::take(&mut this.buffer);
+                return Poll::Ready(Some(Ok(this.chunker.finish(rest))));
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(delta)) => {
+                    this.buffer.push_str(&delta);
+                    let ready = this.chunker.ready(&this.buffer);
+                    if ready > 0 {
+                        let chunk: String = this.buffer.drain(..ready).collect();
+                        return Poll::Ready(Some(Ok(this.chunker.finish(chunk))));
+                    }
+                }
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => this.done = true,
+            }
+        }
+    }
+}
+
+type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+type Sleeper = Arc<dyn Fn(Duration) -> Sleep + Send + Sync>;
+
+/// Stream adapter returned by [`TextStreamExt::paced`].
+pub struct Paced<S> {
+    inner: S,
+    interval: Duration,
+    sleeper: Sleeper,
+    sleep: Option<Sleep>,
+}
+
+impl<S> Stream for Paced<S>
+where
+    S: Stream + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(sleep) = this.sleep.as_mut() {
+            ready!(sleep.as_mut().poll(cx));
+            this.sleep = None;
+        }
+
+        let item = ready!(Pin::new(&mut this.inner).poll_next(cx));
+        if item.is_some() && !this.interval.is_zero() {
+            this.sleep = Some((this.sleeper)(this.interval));
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Shaping combinators for any stream of text deltas.
+pub trait TextStreamExt: Stream<Item = Result<String>> + Sized {
+    /// Re-chunk using a custom [`Chunker`].
+    fn chunked<C: Chunker>(self, chunker: C) -> Chunked<Self, C> {
+        Chunked {
+            inner: self,
+            chunker,
+            buffer: String::new(),
+            done: false,
+        }
+    }
+
+    /// Emit whole words only.
+    fn rechunk_words(self) -> Chunked<Self, Words> {
+        self.chunked(Words)
+    }
+
+    /// Emit whole sentences only.
+    fn rechunk_sentences(self) -> Chunked<Self, Sentences> {
+        self.chunked(Sentences)
+    }
+
+    /// Never emit half a code fence or a dangling run of backticks.
+    fn markdown_safe(self) -> Chunked<Self, MarkdownSafe> {
+        self.chunked(MarkdownSafe::default())
+    }
+
+    /// Mask the given words with asterisks.
+    fn mask_words<I, W>(self, words: I) -> Chunked<Self, MaskWords>
+    where
+        I: IntoIterator<Item = W>,
+        W: AsRef<str>,
+    {
+        self.chunked(MaskWords::new(words))
+    }
+
+    /// Wait at least `interval` between two chunks.
+    ///
+    /// The core crate is runtime-agnostic, so the caller supplies the timer,
+    /// e.g. `|d| tokio::time::sleep(d)`.
+    fn paced<F, Fut>(self, interval: Duration, sleep: F) -> Paced<Self>
+    where
+        F: Fn(Duration) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Paced {
+            inner: self,
+            interval,
+            sleeper: Arc::new(move |d| Box::pin(sleep(d))),
+            sleep: None,
+        }
+    }
+}
+
+impl<S> TextStreamExt for S where S: Stream<Item = Result<String>> {}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, future::poll_fn};
+
+    use super::*;
+
+    struct Deltas(VecDeque<Result<String>>);
+
+    impl Stream for Deltas {
+        type Item = Result<String>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.pop_front())
+        }
+    }
+
+    fn deltas(parts: &[&str]) -> Deltas {
+        Deltas(parts.iter().map(|p| Ok(p.to_string())).collect())
+    }
+
+    async fn collect<S: Stream<Item = Result<String>> + Unpin>(mut stream: S) -> Vec<String> {
+        let mut out = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            out.push(chunk.unwrap());
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn rechunks_words_and_flushes_tail() {
+        let chunks =
+            collect(deltas(&["Hel", "lo wo", "rld, how", " are", " you"]).rechunk_words()).await;
+        assert_eq!(chunks, ["Hello ", "world, ", "how ", "are ", "you"]);
+    }
+
+    #[tokio::test]
+    async fn rechunks_sentences() {
+        let chunks = collect(deltas(&["One. T", "wo! Three"]).rechunk_sentences()).await;
+        assert_eq!(chunks, ["One. ", "Two! ", "Three"]);
+    }
+
+    #[tokio::test]
+    async fn holds_code_fences_until_closed() {
+        let chunks = collect(
+            deltas(&[
+                "Intro `x",
+                "` and\n`",
+                "``rust\nfn main",
+                "() {}\n``",
+                "`\nDone",
+            ])
+            .markdown_safe(),
+        )
+        .await;
+        assert_eq!(
+            chunks,
+            ["Intro `x", "` and\n", "```rust\nfn main() {}\n```\nDone"]
+        );
+    }
+
+    #[tokio::test]
+    async fn masks_blocked_words_across_deltas() {
+        let chunks = collect(deltas(&["what the he", "ck, Heck"]).mask_words(["heck"])).await;
+        assert_eq!(chunks.concat(), "what the ****, ****");
+    }
+}
@@ -0,0 +1,389 @@
+//! Find streams that stay open for too long.
+//!
+//! A response stream that is neither drained nor dropped keeps its HTTP
+//! connection—and whatever the consumer holds on to—alive indefinitely.
+//! [`StreamWatchdog`] keeps a registry of the streams it wraps, so leaks show
+//! up as entries that never go away:
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use artificial_core::stream::StreamWatchdog;
+//! # use artificial_core::provider::StreamingChatProvider;
+//! # fn wrap<B: StreamingChatProvider>(backend: B) {
+//! let watchdog = StreamWatchdog::new();
+//! let _monitor = watchdog.spawn_monitor(Duration::from_secs(300), Duration::from_secs(30), |stream| {
+//!     eprintln!("{} open for {:?}", stream.task_name(), stream.age());
+//! });
+//! let provider = watchdog.provider(backend);
+//! # }
+//! ```
+//!
+//! The monitor is a named plain thread (`artificial-stream-watchdog`), so it
+//! works with any runtime and shows up as such in debuggers; it ends once
+//! every clone of the watchdog is gone. Without a monitor, poll
+//! [`StreamWatchdog::overdue`] from an existing housekeeping task.
+//!
+//! # tokio-console
+//!
+//! Every watched stream has a [`Watched::task_name`], the same one its
+//! [`OpenStream`] reports. Pass it to `tokio::task::Builder::name` when
+//! spawning the task that drains the stream, and a leaked stream in a report
+//! can be found in tokio-console by name. With the `tracing` feature each
+//! stream is also polled inside an `artificial.stream` span carrying its id,
+//! label and, once dropped, the number of items it yielded.
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+use futures_core::Stream;
+
+use crate::{
+    error::Result,
+    generic::{GenericChatCompletionResponse, GenericMessage, StreamingEventsProvider},
+    layer::ProviderLayer,
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, HealthCheckProvider,
+        StreamingChatProvider,
+    },
+};
+
+/// A stream registered with a [`StreamWatchdog`].
+#[derive(Debug, Clone)]
+pub struct OpenStream {
+    pub id: u64,
+    /// What the stream belongs to; the model for provider streams.
+    pub label: String,
+    pub opened_at: Instant,
+    /// Items yielded so far; a count that stopped moving hints at a
+    /// consumer that gave up without dropping the stream.
+    pub items: u64,
+}
+
+impl OpenStream {
+    pub fn age(&self) -> Duration {
+        self.opened_at.elapsed()
+    }
+
+    /// Same as [`Watched::task_name`] of the stream.
+    pub fn task_name(&self) -> String {
+        task_name(self.id, &self.label)
+    }
+}
+
+fn task_name(id: u64, label: &str) -> String {
+    format!("artificial-stream-{id} ({label})")
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    next_id: u64,
+    open: BTreeMap<u64, Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    stream: OpenStream,
+    reported: bool,
+}
+
+/// Registry of open streams; clones share it.
+#[derive(Debug, Clone, Default)]
+pub struct StreamWatchdog {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl StreamWatchdog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track `stream` until it is dropped.
+    pub fn watch<S: Stream>(&self, label: impl Into<String>, stream: S) -> Watched<S> {
+        let label = label.into();
+        let mut registry = self.registry();
+        let id = registry.next_id;
+        registry.next_id += 1;
+        let name = task_name(id, &label);
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "artificial.stream",
+            artificial.stream.id = id,
+            artificial.stream.label = %label,
+            artificial.stream.items = tracing::field::Empty,
+        );
+        registry.open.insert(
+            id,
+            Entry {
+                stream: OpenStream {
+                    id,
+                    label,
+                    opened_at: Instant::now(),
+                    items: 0,
+                },
+                reported: false,
+            },
+        );
+        Watched {
+            inner: Box::pin(stream),
+            id,
+            name,
+            registry: Arc::downgrade(&self.registry),
+            #[cfg(feature = "tracing")]
+            span,
+        }
+    }
+
+    /// Wrap a backend so every stream it opens is watched.
+    pub fn provider<B>(&self, inner: B) -> WatchedProvider<B> {
+        WatchedProvider {
+            inner,
+            watchdog: self.clone(),
+        }
+    }
+
+    /// All streams currently open, oldest first.
+    pub fn open_streams(&self) -> Vec<OpenStream> {
+        self.registry()
+            .open
+            .values()
+            .map(|entry| entry.stream.clone())
+            .collect()
+    }
+
+    /// Streams open for longer than `threshold`.
+    pub fn overdue(&self, threshold: Duration) -> Vec<OpenStream> {
+        self.open_streams()
+            .into_iter()
+            .filter(|stream| stream.age() > threshold)
+            .collect()
+    }
+
+    /// Check every `interval` on a background thread and hand each stream
+    /// open longer than `threshold` to `report`, once per stream.
+    ///
+    /// Fails only if the thread cannot be created; the streams are watched
+    /// either way and [`Self::overdue`] keeps working.
+    pub fn spawn_monitor<F>(
+        &self,
+        threshold: Duration,
+        interval: Duration,
+        report: F,
+    ) -> io::Result<thread::JoinHandle<()>>
+    where
+        F: Fn(&OpenStream) + Send + 'static,
+    {
+        let registry = Arc::downgrade(&self.registry);
+        thread::Builder::new()
+            .name("artificial-stream-watchdog".into())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                let Some(registry) = registry.upgrade() else {
+                    return;
+                };
+                let overdue: Vec<OpenStream> = {
+                    let mut registry = registry.lock().expect("stream watchdog poisoned");
+                    registry
+                        .open
+                        .values_mut()
+                        .filter(|entry| !entry.reported && entry.stream.age() > threshold)
+                        .map(|entry| {
+                            entry.reported = true;
+                            entry.stream.clone()
+                        })
+                        .collect()
+                };
+                overdue.iter().for_each(&report);
+            })
+    }
+
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().expect("stream watchdog poisoned")
+    }
+}
+
+impl<B> ProviderLayer<B> for StreamWatchdog {
+    type Provider = WatchedProvider<B>;
+
+    fn layer(&self, inner: B) -> WatchedProvider<B> {
+        self.provider(inner)
+    }
+}
+
+/// Stream returned by [`StreamWatchdog::watch`]; leaves the registry when
+/// dropped.
+pub struct Watched<S> {
+    inner: Pin<Box<S>>,
+    id: u64,
+    name: String,
+    registry: Weak<Mutex<Registry>>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<S> Watched<S> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// `artificial-stream-<id> (<label>)`, for naming the task that drains
+    /// the stream.
+    pub fn task_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S: Stream> Stream for Watched<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = &mut *self;
+        #[cfg(feature = "tracing")]
+        let _entered = this.span.enter();
+        let polled = this.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(_)) = &polled {
+            if let Some(registry) = this.registry.upgrade() {
+                let mut registry = registry.lock().expect("stream watchdog poisoned");
+                if let Some(entry) = registry.open.get_mut(&this.id) {
+                    entry.stream.items += 1;
+                }
+            }
+        }
+        polled
+    }
+}
+
+impl<S> Drop for Watched<S> {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            if let Ok(mut registry) = registry.lock() {
+                let _entry = registry.open.remove(&self.id);
+                #[cfg(feature = "tracing")]
+                if let Some(entry) = _entry {
+                    self.span
+                        .record("artificial.stream.items", entry.stream.items);
+                }
+            }
+        }
+    }
+}
+
+/// Backend wrapper from [`StreamWatchdog::provider`]; streams are labelled
+/// with the requested model.
+pub struct WatchedProvider<B> {
+    inner: B,
+    watchdog: StreamWatchdog,
+}
+
+impl<B> WatchedProvider<B> {
+    pub fn watchdog(&self) -> &StreamWatchdog {
+        &self.watchdog
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B: StreamingChatProvider> StreamingChatProvider for WatchedProvider<B> {
+    type Message = B::Message;
+    type Delta<'s>
+        = Watched<B::Delta<'s>>
+    where
+        Self: 's;
+
+    fn chat_complete_stream<'s, M>(&'s self, params: ChatCompleteParameters<M>) -> Self::Delta<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let label = params.model.as_ref().to_owned();
+        self.watchdog
+            .watch(label, self.inner.chat_complete_stream(params))
+    }
+}
+
+impl<B: StreamingEventsProvider> StreamingEventsProvider for WatchedProvider<B> {
+    type EventStream<'s>
+        = Watched<B::EventStream<'s>>
+    where
+        Self: 's;
+
+    fn chat_complete_events_stream<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Self::EventStream<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let label = params.model.as_ref().to_owned();
+        self.watchdog
+            .watch(label, self.inner.chat_complete_events_stream(params))
+    }
+}
+
+impl<B: ChatCompletionProvider> ChatCompletionProvider for WatchedProvider<B> {
+    type Message = B::Message;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        self.inner.chat_complete(params)
+    }
+}
+
+impl<B: HealthCheckProvider> HealthCheckProvider for WatchedProvider<B> {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{
+        generic::GenericRole,
+        model::{Model, OpenAiModel},
+        testing::{MockProvider, MockReply},
+    };
+
+    #[tokio::test]
+    async fn streams_are_tracked_until_dropped() {
+        let mock = MockProvider::new();
+        mock.push(MockReply::chunks(["a", "b"]));
+        let provider = StreamWatchdog::new().provider(mock);
+
+        let mut stream = provider.chat_complete_stream(ChatCompleteParameters::new(
+            vec![GenericMessage::new("hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        ));
+        stream.next().await.unwrap().unwrap();
+
+        let open = provider.watchdog().open_streams();
+        assert_eq!(open.len(), 1);
+        assert_eq!((open[0].label.as_str(), open[0].items), ("gpt-4o-mini", 1));
+        assert_eq!(open[0].task_name(), stream.task_name());
+        assert_eq!(stream.task_name(), "artificial-stream-0 (gpt-4o-mini)");
+        assert_eq!(provider.watchdog().overdue(Duration::ZERO).len(), 1);
+        assert!(provider
+            .watchdog()
+            .overdue(Duration::from_secs(60))
+            .is_empty());
+
+        drop(stream);
+        assert!(provider.watchdog().open_streams().is_empty());
+    }
+}
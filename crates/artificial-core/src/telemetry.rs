@@ -0,0 +1,330 @@
+//! Structured `tracing` spans for LLM calls (feature `tracing`).
+//!
+//! [`crate::ArtificialClient`] opens one span per template execution, chat
+//! completion and prompt stream. Streaming chat providers open their own,
+//! because the client hands their streams through unchanged. Every span is
+//! named `artificial.request` and carries the attributes of the OpenTelemetry
+//! GenAI semantic conventions, plus a few of its own:
+//!
+//! | Field                                | Value                                   |
+//! |--------------------------------------|-----------------------------------------|
+//! | `gen_ai.operation.name`              | `chat`                                  |
+//! | `gen_ai.system`                      | `openai`, `xai`, `deepseek` or `_OTHER` |
+//! | `gen_ai.request.model`               | the requested model                     |
+//! | `gen_ai.usage.input_tokens`          | prompt tokens, once reported            |
+//! | `gen_ai.usage.output_tokens`         | completion tokens, once reported        |
+//! | `error.type`                         | e.g. `backend`, `serialization`         |
+//! | `artificial.template`                | [`crate::template::PromptTemplate::name`] |
+//! | `artificial.latency_ms`              | until the answer or the end of the stream |
+//! | `artificial.time_to_first_chunk_ms`  | streams only                            |
+//! | `artificial.retries`                 | HTTP retries, recorded by the provider  |
+//!
+//! With the `otel` feature the spans also set `otel.name` (`chat gpt-4o`),
+//! `otel.kind` and `otel.status_code`, which `tracing-opentelemetry` turns
+//! into the span name, kind and status of the exported span. Install that
+//! layer with the exporter of your choice; this crate depends on `tracing`
+//! only.
+//!
+//! Provider crates use [`request_span`], [`trace_response`] and
+//! [`trace_stream`] to emit the same spans.
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use futures_core::Stream;
+use tracing::{field::Empty, Instrument, Span};
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{GenericChatCompletionResponse, GenericUsageReport, StreamEvent},
+    model::Model,
+    provider::PromptStreamEvent,
+};
+
+/// A new `artificial.request` span for `operation` on `model`.
+pub fn request_span(operation: &'static str, model: &Model, template: Option<&str>) -> Span {
+    #[cfg(feature = "otel")]
+    let span = tracing::info_span!(
+        "artificial.request",
+        otel.name = format!("{operation} {}", model.as_ref()),
+        otel.kind = "client",
+        otel.status_code = Empty,
+        gen_ai.operation.name = operation,
+        gen_ai.system = system(model),
+        gen_ai.request.model = model.as_ref(),
+        gen_ai.usage.input_tokens = Empty,
+        gen_ai.usage.output_tokens = Empty,
+        error.type = Empty,
+        artificial.template = template,
+        artificial.latency_ms = Empty,
+        artificial.time_to_first_chunk_ms = Empty,
+        artificial.retries = Empty,
+    );
+    #[cfg(not(feature = "otel"))]
+    let span = tracing::info_span!(
+        "artificial.request",
+        gen_ai.operation.name = operation,
+        gen_ai.system = system(model),
+        gen_ai.request.model = model.as_ref(),
+        gen_ai.usage.input_tokens = Empty,
+        gen_ai.usage.output_tokens = Empty,
+        error.type = Empty,
+        artificial.template = template,
+        artificial.latency_ms = Empty,
+        artificial.time_to_first_chunk_ms = Empty,
+        artificial.retries = Empty,
+    );
+    span
+}
+
+/// `gen_ai.system` of the provider serving `model`.
+fn system(model: &Model) -> &'static str {
+    match model {
+        Model::OpenAi(_) => "openai",
+        Model::XAi(_) => "xai",
+        Model::DeepSeek(_) => "deepseek",
+        Model::Custom(_) => "_OTHER",
+    }
+}
+
+/// Low-cardinality name of the error variant, for `error.type`.
+fn error_type(err: &ArtificialError) -> &'static str {
+    match err.root() {
+        ArtificialError::BackendNotConfigured { .. } => "backend_not_configured",
+        ArtificialError::ModelNotSupported { .. } => "model_not_supported",
+        ArtificialError::Serialization(_) => "serialization",
+        ArtificialError::OutputParse { .. } => "output_parse",
+        ArtificialError::Backend(_) => "backend",
+        ArtificialError::StreamDisconnected { .. } => "stream_disconnected",
+        ArtificialError::EmptyResponse => "empty_response",
+        ArtificialError::ToolLoopDetected { .. } => "tool_loop_detected",
+        ArtificialError::PolicyViolation { .. } => "policy_violation",
+        ArtificialError::Cancelled => "cancelled",
+        ArtificialError::InvalidRequest(_) => "invalid_request",
+        ArtificialError::Invalid(_) => "invalid",
+        ArtificialError::Other(_)
+        | ArtificialError::WithContext { .. }
+        | ArtificialError::Shared(_) => "other",
+    }
+}
+
+pub fn record_usage(span: &Span, usage: &GenericUsageReport) {
+    span.record("gen_ai.usage.input_tokens", usage.prompt_tokens);
+    span.record("gen_ai.usage.output_tokens", usage.completion_tokens);
+}
+
+/// Record latency since `started` and, for errors, the error type.
+pub fn record_outcome<T>(span: &Span, started: Instant, result: &Result<T>) {
+    span.record(
+        "artificial.latency_ms",
+        started.elapsed().as_millis() as u64,
+    );
+    match result {
+        Ok(_) => {
+            #[cfg(feature = "otel")]
+            span.record("otel.status_code", "OK");
+        }
+        Err(err) => {
+            span.record("error.type", error_type(err));
+            #[cfg(feature = "otel")]
+            span.record("otel.status_code", "ERROR");
+        }
+    }
+}
+
+/// Run `response` inside `span` and record its usage and outcome.
+pub async fn trace_response<T, F>(
+    span: Span,
+    response: F,
+) -> Result<GenericChatCompletionResponse<T>>
+where
+    F: Future<Output = Result<GenericChatCompletionResponse<T>>>,
+{
+    let started = Instant::now();
+    let result = response.instrument(span.clone()).await;
+    if let Some(usage) = result.as_ref().ok().and_then(|r| r.usage.as_ref()) {
+        record_usage(&span, usage);
+    }
+    record_outcome(&span, started, &result);
+    result
+}
+
+/// Items of traced streams that may carry a usage report.
+pub trait StreamUsage {
+    fn usage(&self) -> Option<&GenericUsageReport> {
+        None
+    }
+}
+
+impl StreamUsage for String {}
+
+impl<T> StreamUsage for PromptStreamEvent<T> {}
+
+impl StreamUsage for StreamEvent {
+    fn usage(&self) -> Option<&GenericUsageReport> {
+        match self {
+            StreamEvent::Usage(usage) => Some(usage),
+            _ => None,
+        }
+    }
+}
+
+/// Poll `stream` inside `span`, recording time to the first chunk, usage
+/// and outcome.
+pub fn trace_stream<S>(span: Span, stream: S) -> Traced<S> {
+    Traced {
+        inner: stream,
+        span,
+        started: Instant::now(),
+        first_chunk: true,
+        failed: false,
+    }
+}
+
+/// Stream returned by [`trace_stream`].
+pub struct Traced<S> {
+    inner: S,
+    span: Span,
+    started: Instant,
+    first_chunk: bool,
+    failed: bool,
+}
+
+impl<S, T> Stream for Traced<S>
+where
+    S: Stream<Item = Result<T>> + Unpin,
+    T: StreamUsage,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let polled = {
+            let _entered = this.span.enter();
+            Pin::new(&mut this.inner).poll_next(cx)
+        };
+        match &polled {
+            Poll::Ready(Some(Ok(item))) => {
+                if this.first_chunk {
+                    this.first_chunk = false;
+                    this.span.record(
+                        "artificial.time_to_first_chunk_ms",
+                        this.started.elapsed().as_millis() as u64,
+                    );
+                }
+                if let Some(usage) = item.usage() {
+                    record_usage(&this.span, usage);
+                }
+            }
+            Poll::Ready(Some(Err(err))) => {
+                this.failed = true;
+                this.span.record("error.type", error_type(err));
+            }
+            Poll::Ready(None) => {
+                this.span.record(
+                    "artificial.latency_ms",
+                    this.started.elapsed().as_millis() as u64,
+                );
+                #[cfg(feature = "otel")]
+                this.span
+                    .record("otel.status_code", if this.failed { "ERROR" } else { "OK" });
+            }
+            Poll::Pending => {}
+        }
+        polled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        fmt::Debug,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    use crate::{
+        generic::{GenericMessage, GenericRole},
+        model::OpenAiModel,
+        provider::{ChatCompleteParameters, ChatCompletionProvider},
+        testing::{MockProvider, MockReply},
+        ArtificialClient,
+    };
+
+    use super::*;
+
+    /// Collects the fields of every span into one map.
+    #[derive(Clone, Default)]
+    struct Fields(Arc<Mutex<BTreeMap<String, String>>>);
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().into(), value.into());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            let value = format!("{value:?}");
+            self.0.lock().unwrap().insert(field.name().into(), value);
+        }
+    }
+
+    impl Subscriber for Fields {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            span.record(&mut self.clone());
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, values: &span::Record<'_>) {
+            values.record(&mut self.clone());
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn client_records_model_and_usage() {
+        let mock = MockProvider::new();
+        mock.push(MockReply::text("hi").with_usage(12, 3));
+        mock.push_error(ArtificialError::EmptyResponse);
+        let client = ArtificialClient::new(mock);
+        let params = ChatCompleteParameters::new(
+            vec![GenericMessage::new("hello".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        );
+
+        let fields = Fields::default();
+        let _guard = tracing::subscriber::set_default(fields.clone());
+        client.chat_complete(params.clone()).await.unwrap();
+
+        let recorded = fields.0.lock().unwrap().clone();
+        assert_eq!(recorded["gen_ai.system"], "openai");
+        assert_eq!(recorded["gen_ai.request.model"], "gpt-4o-mini");
+        assert_eq!(recorded["gen_ai.usage.input_tokens"], "12");
+        assert_eq!(recorded["gen_ai.usage.output_tokens"], "3");
+        assert!(recorded.contains_key("artificial.latency_ms"));
+
+        client.chat_complete(params).await.unwrap_err();
+        assert_eq!(fields.0.lock().unwrap()["error.type"], "empty_response");
+    }
+}
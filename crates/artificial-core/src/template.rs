@@ -48,7 +48,12 @@ use std::any::Any;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::model::Model;
+use crate::{
+    error::Result,
+    model::Model,
+    provider::{ReasoningEffort, Temperature, ThinkingBudget},
+    schema_util::SchemaMode,
+};
 
 /// High-level description of a prompt.
 ///
@@ -67,6 +72,172 @@ pub trait PromptTemplate: IntoPrompt {
     /// Logical model identifier.  The back-end will map this to its own naming
     /// scheme (`"gpt-4o-mini"`, `"claude-3-haiku"`, …).
     const MODEL: Model;
+
+    /// Human-readable template name used in errors, logs and reports.
+    ///
+    /// Defaults to the fully qualified Rust type name; override it if you
+    /// prefer a shorter or more stable identifier.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Semantic version of the template's wording, e.g. `"1.4.0"`.
+    ///
+    /// [`crate::ArtificialClient`] copies it into
+    /// [`crate::generic::ResponseMetadata::template_version`] so answers can
+    /// be traced back to the prompt that produced them; register the
+    /// template in a [`crate::registry::PromptRegistry`] to look it up
+    /// again. `None` (default) leaves the template unversioned.
+    fn version() -> Option<&'static str> {
+        None
+    }
+
+    /// Key sent along with every request of this template so the provider
+    /// routes them to the same prompt cache. `None` (default) sends no hint.
+    fn cache_key() -> Option<&'static str> {
+        None
+    }
+
+    /// Clean up a parsed answer before it is returned, e.g. trim strings,
+    /// normalise casing or clamp scores.
+    ///
+    /// [`crate::ArtificialClient`] applies it to every finished answer of
+    /// this template, so the cleanup lives next to the template instead of
+    /// at each call site. Defaults to the identity.
+    ///
+    /// ```rust
+    /// # use artificial_core::{generic::GenericMessage, model::*, template::*};
+    /// #[derive(serde::Deserialize, schemars::JsonSchema)]
+    /// struct Score { value: f32 }
+    ///
+    /// struct RateAnswer;
+    /// # impl IntoPrompt for RateAnswer { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+    ///
+    /// impl PromptTemplate for RateAnswer {
+    ///     type Output = Score;
+    ///     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    ///
+    ///     fn postprocess(score: Score) -> Score {
+    ///         Score { value: score.value.clamp(0.0, 1.0) }
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(RateAnswer::postprocess(Score { value: 1.7 }).value, 1.0);
+    /// ```
+    fn postprocess(output: Self::Output) -> Self::Output {
+        output
+    }
+
+    /// Generation settings that belong to the template rather than to the
+    /// call site, e.g. a low temperature for extraction tasks. Defaults to
+    /// the provider defaults.
+    ///
+    /// A temperature scheduled through
+    /// [`crate::provider::ExecutionPolicy`] takes precedence.
+    ///
+    /// ```rust
+    /// # use artificial_core::{generic::GenericMessage, model::*, template::*};
+    /// # struct ExtractInvoice;
+    /// # impl IntoPrompt for ExtractInvoice { type Message = GenericMessage; fn into_prompt(self) -> Vec<GenericMessage> { vec![] } }
+    /// impl PromptTemplate for ExtractInvoice {
+    ///     type Output = serde_json::Value;
+    ///     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    ///
+    ///     fn params() -> TemplateParams {
+    ///         TemplateParams::new().with_temperature(0.0).with_max_tokens(512)
+    ///     }
+    /// }
+    /// ```
+    fn params() -> TemplateParams {
+        TemplateParams::default()
+    }
+}
+
+/// Generation settings declared by [`PromptTemplate::params`]. `None`
+/// leaves the provider default.
+///
+/// `temperature` is a plain number so declaring params cannot fail; it is
+/// checked with [`Self::checked_temperature`] when a request is built.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateParams {
+    pub temperature: Option<f64>,
+    /// Upper bound for generated tokens (including reasoning tokens).
+    pub max_tokens: Option<u32>,
+    pub seed: Option<i64>,
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Trade reasoning effort for latency when attempts time out; takes
+    /// precedence over `reasoning_effort` on backends that support it.
+    pub thinking_budget: Option<ThinkingBudget>,
+    /// How the output schema is attached; `None` picks the best mode of the
+    /// model.
+    pub schema_mode: Option<SchemaMode>,
+}
+
+impl TemplateParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_reasoning_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(effort);
+        self
+    }
+
+    pub fn with_thinking_budget(mut self, budget: ThinkingBudget) -> Self {
+        self.thinking_budget = Some(budget);
+        self
+    }
+
+    /// Force `mode`, e.g. [`SchemaMode::JsonObject`] for a model that rejects
+    /// `json_schema` but is not known to do so.
+    pub fn with_schema_mode(mut self, mode: SchemaMode) -> Self {
+        self.schema_mode = Some(mode);
+        self
+    }
+
+    /// `temperature` as a validated [`Temperature`].
+    ///
+    /// # Errors
+    ///
+    /// [`crate::error::ArtificialError::InvalidRequest`] if it is out of
+    /// range.
+    pub fn checked_temperature(&self) -> Result<Option<Temperature>> {
+        self.temperature.map(Temperature::new).transpose()
+    }
+
+    /// The schema mode to use with `model`.
+    pub fn schema_mode_for(&self, model: &Model) -> SchemaMode {
+        self.schema_mode
+            .unwrap_or_else(|| SchemaMode::for_model(model))
+    }
+}
+
+/// A template whose leading messages (system prompt, instructions, few-shot
+/// examples) are identical for every instance.
+///
+/// The static prefix can be sent ahead of time with
+/// [`crate::ArtificialClient::warm`] so that the first real request already
+/// hits the provider's prompt cache.  The prefix **must** be a byte-identical
+/// prefix of what [`IntoPrompt::into_prompt`] renders, otherwise the cache
+/// never matches.
+pub trait WarmablePrompt: PromptTemplate {
+    /// The messages every rendering of this template starts with.
+    fn static_prefix() -> Vec<Self::Message>;
 }
 
 /// Converts a value into a series of chat messages.
@@ -83,6 +254,89 @@ pub trait IntoPrompt {
     fn into_prompt(self) -> Vec<Self::Message>;
 }
 
+/// Renders a prompt **without consuming it**.
+///
+/// Every `IntoPrompt + Clone` type implements it by cloning; implement it by
+/// hand for templates that are expensive or impossible to clone. Any
+/// `&T` with `T: RenderPrompt` is itself a prompt (and a [`PromptTemplate`]
+/// if `T` is one), so the same value can be executed again for retries or
+/// A/B runs:
+///
+/// ```rust,no_run
+/// # use artificial_core::{provider::PromptExecutionProvider, template::PromptTemplate};
+/// # async fn run<B: PromptExecutionProvider, P: PromptTemplate + Clone + Send + Sync>(backend: B, prompt: P)
+/// # where P::Message: Into<B::Message> {
+/// let first = backend.prompt_execute(&prompt).await;
+/// if first.is_err() {
+///     let second = backend.prompt_execute(&prompt).await;
+/// }
+/// # }
+/// ```
+///
+/// Providers keep consuming the template through [`IntoPrompt`]: they render
+/// it once per execution, and their repair and retry loops resend the
+/// rendered messages. `RenderPrompt` is for callers that execute the *same
+/// template value* more than once.
+pub trait RenderPrompt {
+    type Message: Send + Sync;
+
+    /// Return **all** messages in the desired order, leaving `self` intact.
+    fn render(&self) -> Vec<Self::Message>;
+}
+
+impl<T: IntoPrompt + Clone> RenderPrompt for T {
+    type Message = T::Message;
+
+    fn render(&self) -> Vec<Self::Message> {
+        self.clone().into_prompt()
+    }
+}
+
+impl<T: RenderPrompt + ?Sized> IntoPrompt for &T {
+    type Message = T::Message;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        self.render()
+    }
+}
+
+impl<T> PromptTemplate for &T
+where
+    T: PromptTemplate + RenderPrompt<Message = <T as IntoPrompt>::Message>,
+{
+    type Output = T::Output;
+    const MODEL: Model = T::MODEL;
+
+    fn name() -> &'static str {
+        T::name()
+    }
+
+    fn version() -> Option<&'static str> {
+        T::version()
+    }
+
+    fn cache_key() -> Option<&'static str> {
+        T::cache_key()
+    }
+
+    fn postprocess(output: Self::Output) -> Self::Output {
+        T::postprocess(output)
+    }
+
+    fn params() -> TemplateParams {
+        T::params()
+    }
+}
+
+impl<T> WarmablePrompt for &T
+where
+    T: WarmablePrompt + RenderPrompt<Message = <T as IntoPrompt>::Message>,
+{
+    fn static_prefix() -> Vec<Self::Message> {
+        T::static_prefix()
+    }
+}
+
 /// Convenience implementation so a single [`crate::generic::GenericMessage`]
 /// can be passed directly to the client without wrapping it in a struct.
 impl IntoPrompt for crate::generic::GenericMessage {
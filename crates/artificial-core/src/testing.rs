@@ -0,0 +1,843 @@
+//! In-memory stand-in for a provider.
+//!
+//! Enable the `test-util` feature to use [`MockProvider`] in your own tests.
+//! It implements every provider trait of this crate, answers with replies
+//! queued up front and records what it was asked, so code built on
+//! [`crate::ArtificialClient`] can be tested without network access:
+//!
+//! ```rust
+//! # #[cfg(feature = "test-util")]
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! use artificial_core::{
+//!     generic::{GenericMessage, GenericRole},
+//!     model::{Model, OpenAiModel},
+//!     provider::PromptExecutionProvider,
+//!     template::{IntoPrompt, PromptTemplate},
+//!     testing::MockProvider,
+//!     ArtificialClient,
+//! };
+//!
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct Sentiment { positive: bool }
+//!
+//! struct Classify(&'static str);
+//! impl IntoPrompt for Classify {
+//!     type Message = GenericMessage;
+//!     fn into_prompt(self) -> Vec<GenericMessage> {
+//!         vec![GenericMessage::new(self.0.into(), GenericRole::User)]
+//!     }
+//! }
+//! impl PromptTemplate for Classify {
+//!     type Output = Sentiment;
+//!     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+//! }
+//!
+//! let mock = MockProvider::new();
+//! mock.push_json(serde_json::json!({ "positive": true }));
+//!
+//! let client = ArtificialClient::new(mock.clone());
+//! let answer = client.prompt_execute(Classify("I love it")).await.unwrap();
+//!
+//! assert!(answer.content.expect_finished().positive);
+//! assert_eq!(mock.last_request().unwrap().messages[0].content.as_deref(), Some("I love it"));
+//! # }
+//! # #[cfg(not(feature = "test-util"))]
+//! # fn main() {}
+//! ```
+//!
+//! Replies are consumed in order by whichever method is called next; each
+//! method adapts the reply to its own return type (a text reply is a
+//! finished message for `chat_complete`, a JSON document to parse for
+//! `prompt_execute`, a single chunk for streams, one reply per choice for
+//! `chat_complete_n`, …). Calls without a queued reply fail with [`ArtificialError::Other`].
+//!
+//! Embeddings and moderation verdicts are looked up by text instead, see
+//! [`MockProvider::set_embedding`] and [`MockProvider::flag`], so they do
+//! not depend on how often or in which order the code under test asks.
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    error::{ArtificialError, Result},
+    generic::{
+        GenericChatCompletionResponse, GenericFunctionCall, GenericFunctionCallIntent,
+        GenericMessage, GenericRole, GenericUsageReport, ResponseContent, StreamEvent,
+        StreamingEventsProvider,
+    },
+    model::Model,
+    provider::{
+        BackendHealth, BoxedPromptStream, BoxedResponseFut, ChatCompleteParameters,
+        ChatCompletionProvider, Choice, EmbeddingProvider, EmbeddingResponse, HealthCheckProvider,
+        HealthStatus, ModerationProvider, ModerationResult, MultiChoiceProvider,
+        MultiChoiceResponse, PromptExecutionProvider, PromptStreamEvent, PromptStreamingProvider,
+        PromptWarmingProvider, StreamingChatProvider, TranscriptionProvider, TranscriptionRequest,
+        TranscriptionResult,
+    },
+    schema_util::parse_output,
+    template::{IntoPrompt, PromptTemplate},
+};
+
+/// A canned answer of [`MockProvider`].
+#[derive(Debug)]
+pub struct MockReply {
+    kind: ReplyKind,
+    usage: Option<GenericUsageReport>,
+}
+
+#[derive(Debug)]
+enum ReplyKind {
+    Chunks(Vec<String>),
+    ToolCalls(Vec<GenericFunctionCallIntent>),
+    Events(Vec<StreamEvent>),
+    Error(ArtificialError),
+}
+
+impl MockReply {
+    /// A plain answer; for templates, the JSON text of the output.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::chunks([text.into()])
+    }
+
+    /// An answer streamed in the given pieces; non-streaming calls see the
+    /// concatenation.
+    pub fn chunks<S: Into<String>>(chunks: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            kind: ReplyKind::Chunks(chunks.into_iter().map(Into::into).collect()),
+            usage: None,
+        }
+    }
+
+    /// A request to call the given tools; ids are `call-0`, `call-1`, ….
+    pub fn tool_calls<S: Into<String>>(
+        calls: impl IntoIterator<Item = (S, serde_json::Value)>,
+    ) -> Self {
+        let calls = calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, arguments))| GenericFunctionCallIntent {
+                id: format!("call-{index}"),
+                function: GenericFunctionCall {
+                    name: name.into(),
+                    arguments,
+                },
+            })
+            .collect();
+        Self {
+            kind: ReplyKind::ToolCalls(calls),
+            usage: None,
+        }
+    }
+
+    /// Raw events for [`StreamingEventsProvider`]; other calls fail.
+    pub fn events(events: impl IntoIterator<Item = StreamEvent>) -> Self {
+        Self {
+            kind: ReplyKind::Events(events.into_iter().collect()),
+            usage: None,
+        }
+    }
+
+    pub fn error(error: ArtificialError) -> Self {
+        Self {
+            kind: ReplyKind::Error(error),
+            usage: None,
+        }
+    }
+
+    pub fn with_usage(mut self, prompt_tokens: i64, completion_tokens: i64) -> Self {
+        self.usage = Some(GenericUsageReport {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        });
+        self
+    }
+
+    fn into_content(self) -> Result<ResponseContent<String>> {
+        match self.kind {
+            ReplyKind::Chunks(chunks) => Ok(ResponseContent::Finished(chunks.concat())),
+            ReplyKind::ToolCalls(calls) => Ok(ResponseContent::ToolCalls(
+                GenericMessage::new_tool_call(String::new(), calls),
+            )),
+            ReplyKind::Events(_) => Err(ArtificialError::Other(
+                "MockProvider: event replies can only be streamed as events".into(),
+            )),
+            ReplyKind::Error(err) => Err(err),
+        }
+    }
+}
+
+/// Scripted provider for tests, see the [module docs](self).
+///
+/// Cloning is cheap; all clones share the queue and the recorded requests,
+/// so keep a clone around after moving one into a client.
+#[derive(Debug, Clone, Default)]
+pub struct MockProvider {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    replies: VecDeque<MockReply>,
+    requests: Vec<ChatCompleteParameters<GenericMessage>>,
+    transcriptions: Vec<TranscriptionRequest>,
+    embeddings: HashMap<String, Vec<f32>>,
+    embedded: Vec<String>,
+    /// Patterns and the categories inputs containing them are flagged for.
+    flags: Vec<(String, Vec<String>)>,
+    moderated: Vec<String>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `reply` behind all replies queued so far.
+    pub fn push(&self, reply: MockReply) -> &Self {
+        self.state().replies.push_back(reply);
+        self
+    }
+
+    pub fn push_text(&self, text: impl Into<String>) -> &Self {
+        self.push(MockReply::text(text))
+    }
+
+    /// Queue `value` serialized as JSON, e.g. a template output.
+    pub fn push_json(&self, value: impl Serialize) -> &Self {
+        let text = serde_json::to_string(&value).expect("mock reply serializes");
+        self.push(MockReply::text(text))
+    }
+
+    pub fn push_tool_calls<S: Into<String>>(
+        &self,
+        calls: impl IntoIterator<Item = (S, serde_json::Value)>,
+    ) -> &Self {
+        self.push(MockReply::tool_calls(calls))
+    }
+
+    pub fn push_error(&self, error: ArtificialError) -> &Self {
+        self.push(MockReply::error(error))
+    }
+
+    /// Answer [`EmbeddingProvider::embed`] calls for `text` with `vector`.
+    /// Texts without a vector fail the whole call.
+    pub fn set_embedding(&self, text: impl Into<String>, vector: Vec<f32>) -> &Self {
+        self.state().embeddings.insert(text.into(), vector);
+        self
+    }
+
+    /// Flag every moderation input containing `pattern` for `categories`.
+    /// Inputs matching no pattern pass.
+    pub fn flag<S: Into<String>>(
+        &self,
+        pattern: impl Into<String>,
+        categories: impl IntoIterator<Item = S>,
+    ) -> &Self {
+        let categories = categories.into_iter().map(Into::into).collect();
+        self.state().flags.push((pattern.into(), categories));
+        self
+    }
+
+    /// Replies not consumed yet.
+    pub fn remaining(&self) -> usize {
+        self.state().replies.len()
+    }
+
+    /// Every request received so far, oldest first. Template executions are
+    /// recorded with their rendered messages, model and
+    /// [`PromptTemplate::params`].
+    pub fn requests(&self) -> Vec<ChatCompleteParameters<GenericMessage>> {
+        self.state().requests.clone()
+    }
+
+    pub fn last_request(&self) -> Option<ChatCompleteParameters<GenericMessage>> {
+        self.state().requests.last().cloned()
+    }
+
+    pub fn transcriptions(&self) -> Vec<TranscriptionRequest> {
+        self.state().transcriptions.clone()
+    }
+
+    /// Every text passed to [`EmbeddingProvider::embed`], oldest first.
+    pub fn embedded(&self) -> Vec<String> {
+        self.state().embedded.clone()
+    }
+
+    /// Every input passed to [`ModerationProvider::moderate`], oldest first.
+    pub fn moderated(&self) -> Vec<String> {
+        self.state().moderated.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock provider poisoned")
+    }
+
+    /// Record `params` and take the next reply.
+    fn next(&self, params: ChatCompleteParameters<GenericMessage>) -> Result<MockReply> {
+        let mut state = self.state();
+        state.requests.push(params);
+        state
+            .replies
+            .pop_front()
+            .ok_or_else(|| ArtificialError::Other("MockProvider: no reply queued".into()))
+    }
+
+    fn next_for_template<P>(&self, prompt: P) -> Result<MockReply>
+    where
+        P: PromptTemplate,
+        <P as IntoPrompt>::Message: Into<GenericMessage>,
+    {
+        let messages = prompt.into_prompt().into_iter().map(Into::into).collect();
+        let template = P::params();
+        let mut params = ChatCompleteParameters::new(messages, P::MODEL);
+        params.temperature = template.checked_temperature()?;
+        params.max_tokens = template.max_tokens;
+        params.seed = template.seed;
+        params.reasoning_effort = template.reasoning_effort;
+        params.cache_key = P::cache_key().map(str::to_owned);
+        self.next(params)
+    }
+
+    fn chat_response(
+        &self,
+        params: ChatCompleteParameters<GenericMessage>,
+    ) -> Result<GenericChatCompletionResponse<GenericMessage>> {
+        let reply = self.next(params)?;
+        let usage = reply.usage.clone();
+        let content = match reply.into_content()? {
+            ResponseContent::Finished(text) => {
+                ResponseContent::Finished(GenericMessage::new(text, GenericRole::Assistant))
+            }
+            ResponseContent::ToolCalls(message) => ResponseContent::ToolCalls(message),
+        };
+        Ok(GenericChatCompletionResponse {
+            content,
+            usage,
+            metadata: Default::default(),
+        })
+    }
+
+    /// The next `n` replies as the choices of one request.
+    fn multi_choice_response(
+        &self,
+        params: ChatCompleteParameters<GenericMessage>,
+        n: u32,
+    ) -> Result<MultiChoiceResponse> {
+        let mut replies = vec![self.next(params)?];
+        for _ in 1..n {
+            let reply =
+                self.state().replies.pop_front().ok_or_else(|| {
+                    ArtificialError::Other("MockProvider: no reply queued".into())
+                })?;
+            replies.push(reply);
+        }
+
+        let mut usage: Option<GenericUsageReport> = None;
+        let mut choices = Vec::with_capacity(replies.len());
+        for reply in replies {
+            if let Some(reply_usage) = &reply.usage {
+                let total = usage.get_or_insert(GenericUsageReport {
+                    prompt_tokens: 0,
+                    completion_tokens: 0,
+                    total_tokens: 0,
+                });
+                total.prompt_tokens += reply_usage.prompt_tokens;
+                total.completion_tokens += reply_usage.completion_tokens;
+                total.total_tokens += reply_usage.total_tokens;
+            }
+            choices.push(match reply.into_content()? {
+                ResponseContent::Finished(text) => Choice {
+                    message: GenericMessage::new(text, GenericRole::Assistant),
+                    finish_reason: Some("stop".into()),
+                },
+                ResponseContent::ToolCalls(message) => Choice {
+                    message,
+                    finish_reason: Some("tool_calls".into()),
+                },
+            });
+        }
+        Ok(MultiChoiceResponse {
+            choices,
+            usage,
+            metadata: Default::default(),
+        })
+    }
+}
+
+fn parse_content<T: JsonSchema + serde::de::DeserializeOwned>(
+    content: ResponseContent<String>,
+) -> Result<ResponseContent<T>> {
+    Ok(match content {
+        ResponseContent::Finished(text) => ResponseContent::Finished(parse_output(&text)?),
+        ResponseContent::ToolCalls(message) => ResponseContent::ToolCalls(message),
+    })
+}
+
+/// Items of a mocked stream, yielded one per poll.
+pub struct MockStream<T> {
+    items: VecDeque<T>,
+}
+
+impl<T> MockStream<T> {
+    fn new(items: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            items: items.into_iter().collect(),
+        }
+    }
+}
+
+// Items are never pinned in place.
+impl<T> Unpin for MockStream<T> {}
+
+impl<T> Stream for MockStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Poll::Ready(self.get_mut().items.pop_front())
+    }
+}
+
+impl ChatCompletionProvider for MockProvider {
+    type Message = GenericMessage;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let response = self.chat_response(params.map_messages(Into::into));
+        Box::pin(async move { response })
+    }
+}
+
+impl MultiChoiceProvider for MockProvider {
+    type Message = GenericMessage;
+
+    /// Records one request and answers with the next `n` replies.
+    fn chat_complete_n<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+        n: u32,
+    ) -> Pin<Box<dyn Future<Output = Result<MultiChoiceResponse>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let response = self.multi_choice_response(params.map_messages(Into::into), n);
+        Box::pin(async move { response })
+    }
+}
+
+impl StreamingChatProvider for MockProvider {
+    type Message = GenericMessage;
+
+    type Delta<'s>
+        = MockStream<Result<String>>
+    where
+        Self: 's;
+
+    fn chat_complete_stream<'s, M>(&'s self, params: ChatCompleteParameters<M>) -> Self::Delta<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        match self.next(params.map_messages(Into::into)) {
+            Ok(MockReply {
+                kind: ReplyKind::Chunks(chunks),
+                ..
+            }) => MockStream::new(chunks.into_iter().map(Ok)),
+            Ok(MockReply {
+                kind: ReplyKind::ToolCalls(_),
+                ..
+            }) => MockStream::new([]),
+            Ok(reply) => MockStream::new([reply.into_content().map(|_| String::new())]),
+            Err(err) => MockStream::new([Err(err)]),
+        }
+    }
+}
+
+impl StreamingEventsProvider for MockProvider {
+    type EventStream<'s>
+        = MockStream<Result<StreamEvent>>
+    where
+        Self: 's;
+
+    fn chat_complete_events_stream<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Self::EventStream<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let reply = match self.next(params.map_messages(Into::into)) {
+            Ok(reply) => reply,
+            Err(err) => return MockStream::new([Err(err)]),
+        };
+        let mut events = match reply.kind {
+            ReplyKind::Events(events) => return MockStream::new(events.into_iter().map(Ok)),
+            ReplyKind::Error(err) => return MockStream::new([Err(err)]),
+            ReplyKind::Chunks(chunks) => chunks.into_iter().map(StreamEvent::TextDelta).collect(),
+            ReplyKind::ToolCalls(calls) => calls
+                .into_iter()
+                .enumerate()
+                .flat_map(|(index, intent)| {
+                    [
+                        StreamEvent::ToolCallStart {
+                            index,
+                            id: Some(intent.id.clone()),
+                            name: Some(intent.function.name.clone()),
+                        },
+                        StreamEvent::ToolCallComplete { index, intent },
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        };
+        events.push(StreamEvent::MessageEnd);
+        events.extend(reply.usage.map(StreamEvent::Usage));
+        MockStream::new(events.into_iter().map(Ok))
+    }
+}
+
+impl PromptExecutionProvider for MockProvider {
+    type Message = GenericMessage;
+
+    fn prompt_execute<'a, 'p, P>(&'a self, prompt: P) -> BoxedResponseFut<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        let reply = self.next_for_template(prompt);
+        Box::pin(async move {
+            let reply = reply?;
+            let usage = reply.usage.clone();
+            Ok(GenericChatCompletionResponse {
+                content: parse_content(reply.into_content()?)?,
+                usage,
+                metadata: Default::default(),
+            })
+        })
+    }
+}
+
+impl PromptStreamingProvider for MockProvider {
+    fn prompt_execute_stream<'a, 'p, P>(&'a self, prompt: P) -> BoxedPromptStream<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        P::Output: Send,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        let events: Vec<Result<PromptStreamEvent<P::Output>>> = match self.next_for_template(prompt)
+        {
+            Ok(MockReply {
+                kind: ReplyKind::Chunks(chunks),
+                ..
+            }) => {
+                let finished = parse_output(&chunks.concat()).map(PromptStreamEvent::Finished);
+                chunks
+                    .into_iter()
+                    .map(|chunk| Ok(PromptStreamEvent::Delta(chunk)))
+                    .chain([finished])
+                    .collect()
+            }
+            Ok(reply) => vec![Err(reply.into_content().err().unwrap_or_else(|| {
+                ArtificialError::Other("MockProvider: tool calls cannot be streamed".into())
+            }))],
+            Err(err) => vec![Err(err)],
+        };
+        Box::pin(MockStream::new(events))
+    }
+}
+
+impl PromptWarmingProvider for MockProvider {
+    type Message = GenericMessage;
+
+    /// Records the request; consumes no reply.
+    fn warm_prefix<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<GenericUsageReport>>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        self.state().requests.push(params.map_messages(Into::into));
+        Box::pin(async { Ok(None) })
+    }
+}
+
+impl TranscriptionProvider for MockProvider {
+    /// Answers with the text of the next reply.
+    fn transcribe<'s>(
+        &'s self,
+        request: TranscriptionRequest,
+    ) -> Pin<Box<dyn Future<Output = Result<TranscriptionResult>> + Send + 's>> {
+        let result = {
+            let mut state = self.state();
+            state.transcriptions.push(request);
+            state
+                .replies
+                .pop_front()
+                .ok_or_else(|| ArtificialError::Other("MockProvider: no reply queued".into()))
+        }
+        .and_then(MockReply::into_content)
+        .and_then(ResponseContent::into_finished)
+        .map(|text| TranscriptionResult {
+            text,
+            language: None,
+            duration_seconds: None,
+            segments: None,
+            metadata: None,
+        });
+        Box::pin(async move { result })
+    }
+}
+
+impl EmbeddingProvider for MockProvider {
+    /// Answers with the vectors set by [`MockProvider::set_embedding`].
+    fn embed<'s>(
+        &'s self,
+        texts: Vec<String>,
+        _model: Model,
+    ) -> Pin<Box<dyn Future<Output = Result<EmbeddingResponse>> + Send + 's>> {
+        let result = {
+            let mut state = self.state();
+            state.embedded.extend(texts.iter().cloned());
+            texts
+                .iter()
+                .map(|text| {
+                    state.embeddings.get(text).cloned().ok_or_else(|| {
+                        ArtificialError::Other(format!("MockProvider: no embedding for {text:?}"))
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        }
+        .map(|embeddings| EmbeddingResponse {
+            embeddings,
+            usage: None,
+        });
+        Box::pin(async move { result })
+    }
+}
+
+impl ModerationProvider for MockProvider {
+    /// Flags inputs by the patterns set with [`MockProvider::flag`].
+    fn moderate<'s>(
+        &'s self,
+        inputs: Vec<String>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<ModerationResult>>> + Send + 's>> {
+        let mut state = self.state();
+        let results = inputs
+            .iter()
+            .map(|input| {
+                let categories: Vec<String> = state
+                    .flags
+                    .iter()
+                    .filter(|(pattern, _)| input.contains(pattern.as_str()))
+                    .flat_map(|(_, categories)| categories.iter().cloned())
+                    .collect();
+                ModerationResult {
+                    flagged: !categories.is_empty(),
+                    scores: categories.iter().map(|c| (c.clone(), 1.0)).collect(),
+                    categories,
+                }
+            })
+            .collect();
+        state.moderated.extend(inputs);
+        Box::pin(async move { Ok(results) })
+    }
+}
+
+impl HealthCheckProvider for MockProvider {
+    /// Always healthy.
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        Box::pin(async {
+            vec![BackendHealth {
+                backend: "mock".into(),
+                status: HealthStatus::Healthy,
+                latency: Duration::ZERO,
+            }]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::{
+        model::{Model, OpenAiModel},
+        provider::PartialStreamEvent,
+        tools::ToolRegistry,
+        ArtificialClient,
+    };
+
+    fn params() -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new("hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn replies_in_order_and_records_requests() {
+        let mock = MockProvider::new();
+        mock.push(MockReply::text("hello").with_usage(3, 2))
+            .push_error(ArtificialError::EmptyResponse);
+
+        let response = mock.chat_complete(params()).await.unwrap();
+        assert_eq!(response.content.text(), Some("hello"));
+        assert_eq!(response.usage.unwrap().total_tokens, 5);
+        assert!(matches!(
+            mock.chat_complete(params()).await,
+            Err(ArtificialError::EmptyResponse)
+        ));
+        assert!(mock.chat_complete(params()).await.is_err());
+        assert_eq!(mock.requests().len(), 3);
+        assert_eq!(mock.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn drives_a_tool_loop_through_the_client() {
+        let mock = MockProvider::new();
+        mock.push_tool_calls([("echo", serde_json::json!({ "text": "hi" }))])
+            .push_text("done");
+        let registry = ToolRegistry::new().with_tool(
+            crate::generic::GenericFunctionSpec {
+                name: "echo".into(),
+                description: "Echo.".into(),
+                parameters: serde_json::json!({ "type": "object" }),
+            },
+            |args| async move { Ok(args["text"].to_string()) },
+        );
+
+        let outcome = ArtificialClient::new(mock.clone())
+            .chat_complete_with_tools(params(), &registry)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.message.content.as_deref(), Some("done"));
+        let last = mock.last_request().unwrap();
+        assert_eq!(last.messages.last().unwrap().role, GenericRole::Tool);
+        assert_eq!(last.tools.unwrap()[0].name, "echo");
+    }
+
+    #[tokio::test]
+    async fn streams_chunks_and_events() {
+        let mock = MockProvider::new();
+        mock.push(MockReply::chunks(["Hel", "lo"]))
+            .push(MockReply::chunks(["Hi"]).with_usage(1, 1));
+
+        let text: Vec<String> = mock
+            .chat_complete_stream(params())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(text, ["Hel", "lo"]);
+
+        let events: Vec<StreamEvent> = mock
+            .chat_complete_events_stream(params())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(matches!(&events[0], StreamEvent::TextDelta(t) if t == "Hi"));
+        assert!(matches!(events[1], StreamEvent::MessageEnd));
+        assert!(matches!(events[2], StreamEvent::Usage(_)));
+    }
+
+    #[tokio::test]
+    async fn answers_each_choice_with_a_reply() {
+        let mock = MockProvider::new();
+        mock.push_text("yes").push_text("no").push_text("left over");
+
+        let response = mock.chat_complete_n(params(), 2).await.unwrap();
+        let answers: Vec<_> = response
+            .choices
+            .iter()
+            .map(|choice| choice.message.content.as_deref())
+            .collect();
+        assert_eq!(answers, [Some("yes"), Some("no")]);
+        assert_eq!(mock.requests().len(), 1);
+        assert_eq!(mock.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn embeds_known_texts_only() {
+        let mock = MockProvider::new();
+        mock.set_embedding("cat", vec![1.0, 0.0]);
+        let model = Model::OpenAi(OpenAiModel::TextEmbedding3Small);
+
+        let response = mock.embed(vec!["cat".into()], model.clone()).await.unwrap();
+        assert_eq!(response.embeddings, [vec![1.0, 0.0]]);
+        assert!(mock.embed(vec!["dog".into()], model).await.is_err());
+        assert_eq!(mock.embedded(), ["cat", "dog"]);
+    }
+
+    #[tokio::test]
+    async fn partial_streams_yield_completed_items_early() {
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        struct Outline {
+            chapters: Vec<String>,
+        }
+
+        struct Plan;
+        impl IntoPrompt for Plan {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<GenericMessage> {
+                vec![GenericMessage::new("plan".into(), GenericRole::User)]
+            }
+        }
+        impl PromptTemplate for Plan {
+            type Output = Outline;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+        }
+
+        let mock = MockProvider::new();
+        mock.push(MockReply::chunks([
+            r#"{"chapters": ["In"#,
+            r#"tro", "Ma"#,
+            r#"in""#,
+            "]}",
+        ]));
+
+        let events: Vec<_> = mock
+            .prompt_execute_stream_partial(Plan)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let completed: Vec<Vec<String>> = events
+            .iter()
+            .filter_map(|event| match event {
+                PartialStreamEvent::Partial(partial) => Some(partial.items("/chapters")),
+                PartialStreamEvent::Finished(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            completed,
+            [
+                vec![],
+                vec!["Intro"],
+                vec!["Intro", "Main"],
+                vec!["Intro", "Main"]
+            ]
+        );
+        let Some(PartialStreamEvent::Finished(outline)) = events.last() else {
+            panic!("stream did not finish");
+        };
+        assert_eq!(outline.chapters, ["Intro", "Main"]);
+    }
+}
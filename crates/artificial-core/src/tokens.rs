@@ -0,0 +1,166 @@
+//! Estimate how many tokens a prompt will cost before sending it.
+//!
+//! Context-length errors only surface once the provider rejects a request.
+//! A [`TokenCounter`] lets you measure rendered messages up front and compare
+//! them with [`crate::capability::ModelCapabilities::context_window`]:
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_core::model::{Model, OpenAiModel};
+//! use artificial_core::tokens::{counter_for, TokenCounter};
+//!
+//! let model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+//! let messages = vec![GenericMessage::new("Hello there!".into(), GenericRole::User)];
+//!
+//! let tokens = counter_for(&model).count_messages(&messages);
+//! let window = model.capabilities().context_window.unwrap();
+//! assert!(tokens < window as usize);
+//! ```
+//!
+//! Without the `tiktoken` feature counts are a character-based
+//! approximation; enable it for exact BPE counts on OpenAI models.
+use crate::{generic::GenericMessage, model::Model};
+
+/// Counts tokens of text and chat messages for one tokenizer.
+pub trait TokenCounter: Send + Sync {
+    /// Number of tokens `text` encodes to.
+    fn count_text(&self, text: &str) -> usize;
+
+    /// Number of prompt tokens `messages` cost, including the per-message
+    /// framing chat APIs add around every turn.
+    fn count_messages(&self, messages: &[GenericMessage]) -> usize {
+        // Framing as documented for OpenAI chat models: every message is
+        // wrapped in a few special tokens and the reply is primed with 3.
+        const PER_MESSAGE: usize = 3;
+        const REPLY_PRIMING: usize = 3;
+
+        let mut total = REPLY_PRIMING;
+        for message in messages {
+            total += PER_MESSAGE + self.count_text(&message.role.to_string());
+            if let Some(content) = &message.content {
+                total += self.count_text(content);
+            }
+            if let Some(name) = &message.name {
+                total += 1 + self.count_text(name);
+            }
+            if let Some(tool_calls) = &message.tool_calls {
+                for call in tool_calls {
+                    total += self.count_text(&call.function.name);
+                    total += self.count_text(&call.function.arguments.to_string());
+                }
+            }
+        }
+        total
+    }
+}
+
+/// Tokenizer-free estimate based on the average length of a token.
+///
+/// Good enough for budget checks with some headroom; English prose averages
+/// about four characters per token.
+#[derive(Debug, Clone, Copy)]
+pub struct ApproxCounter {
+    chars_per_token: f32,
+}
+
+impl Default for ApproxCounter {
+    fn default() -> Self {
+        Self {
+            chars_per_token: 4.0,
+        }
+    }
+}
+
+impl ApproxCounter {
+    /// Use a different ratio, e.g. a lower one for code or non-Latin text.
+    pub fn with_chars_per_token(mut self, chars_per_token: f32) -> Self {
+        self.chars_per_token = chars_per_token.max(0.1);
+        self
+    }
+}
+
+impl TokenCounter for ApproxCounter {
+    fn count_text(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.chars_per_token).ceil() as usize
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+pub use tiktoken::TiktokenCounter;
+
+#[cfg(feature = "tiktoken")]
+mod tiktoken {
+    use super::TokenCounter;
+    use crate::model::Model;
+
+    /// Exact counts with the BPE tokenizers used by OpenAI models.
+    ///
+    /// The tables are built once per process and shared, so constructing a
+    /// counter is cheap.
+    #[derive(Clone, Copy)]
+    pub struct TiktokenCounter {
+        bpe: &'static tiktoken_rs::CoreBPE,
+    }
+
+    impl TiktokenCounter {
+        /// `o200k_base`, used by GPT-4o, GPT-4.1, GPT-5 and the o-series.
+        pub fn o200k() -> Self {
+            Self {
+                bpe: tiktoken_rs::o200k_base_singleton(),
+            }
+        }
+
+        /// `cl100k_base`, used by GPT-4 and GPT-3.5 and a reasonable proxy
+        /// for other providers.
+        pub fn cl100k() -> Self {
+            Self {
+                bpe: tiktoken_rs::cl100k_base_singleton(),
+            }
+        }
+
+        /// The tokenizer `model` uses, or the closest available one.
+        pub fn for_model(model: &Model) -> Self {
+            match model {
+                Model::OpenAi(_) => Self::o200k(),
+                _ => Self::cl100k(),
+            }
+        }
+    }
+
+    impl TokenCounter for TiktokenCounter {
+        fn count_text(&self, text: &str) -> usize {
+            self.bpe.encode_with_special_tokens(text).len()
+        }
+    }
+}
+
+/// The most accurate counter available for `model` with the enabled
+/// features.
+pub fn counter_for(model: &Model) -> Box<dyn TokenCounter> {
+    #[cfg(feature = "tiktoken")]
+    {
+        Box::new(TiktokenCounter::for_model(model))
+    }
+    #[cfg(not(feature = "tiktoken"))]
+    {
+        let _ = model;
+        Box::new(ApproxCounter::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::GenericRole;
+
+    #[test]
+    fn approx_counts_include_message_framing() {
+        let counter = ApproxCounter::default();
+        assert_eq!(counter.count_text("abcdefgh"), 2);
+        assert_eq!(counter.count_text("abcdefghi"), 3);
+
+        let messages = vec![GenericMessage::new("abcdefgh".into(), GenericRole::User)];
+        // priming + framing + "user" + content
+        assert_eq!(counter.count_messages(&messages), 3 + 3 + 1 + 2);
+    }
+}
@@ -0,0 +1,249 @@
+//! Keep large tool results from flooding the context window.
+//!
+//! A single tool call that returns a whole database table or web page can
+//! exhaust the model's context.  [`ToolOutputPager`] sits between your tool
+//! handlers and the conversation: results that fit the token budget pass
+//! through unchanged, larger ones are either cut off or split into pages.
+//! With [`OversizePolicy::Paginate`] the model is told how to fetch the rest
+//! through the synthetic [`GET_MORE_TOOL`].
+//!
+//! With a [`ToolRegistry`] nothing else is needed:
+//! [`ToolRegistry::with_output_budget`] runs every result through a pager,
+//! offers [`GET_MORE_TOOL`] to the model and answers its calls.  Without a
+//! registry, register the spec next to your own tools and route the calls
+//! yourself:
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericFunctionCall, GenericFunctionCallIntent};
+//! use artificial_core::tool_output::ToolOutputPager;
+//!
+//! let mut pager = ToolOutputPager::new(10);
+//! let tools = vec![/* your specs */ ToolOutputPager::tool_spec()];
+//!
+//! let huge = "row\n".repeat(50);
+//! let first = pager.tool_message("call_1", huge);
+//! assert!(first.content.unwrap().contains("page 1 of"));
+//!
+//! // later the model asks for the next page
+//! let call = GenericFunctionCallIntent {
+//!     id: "call_2".into(),
+//!     function: GenericFunctionCall {
+//!         name: "get_more_tool_output".into(),
+//!         arguments: serde_json::json!({ "result_id": "call_1", "page": 2 }),
+//!     },
+//! };
+//! assert!(pager.handles(&call));
+//! let second = pager.get_more(&call);
+//! assert!(second.content.unwrap().contains("page 2 of"));
+//! # let _ = tools;
+//! ```
+//!
+//! [`ToolRegistry`]: crate::tools::ToolRegistry
+//! [`ToolRegistry::with_output_budget`]: crate::tools::ToolRegistry::with_output_budget
+use std::collections::HashMap;
+
+use crate::generic::{GenericFunctionCallIntent, GenericFunctionSpec, GenericMessage, GenericRole};
+
+/// Name of the synthetic tool the model calls to fetch further pages.
+pub const GET_MORE_TOOL: &str = "get_more_tool_output";
+
+/// Rough characters-per-token ratio used to turn the budget into a length.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// What to do with a tool result that exceeds the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizePolicy {
+    /// Keep the first page and drop the rest.
+    Truncate,
+    /// Keep every page and let the model fetch them via [`GET_MORE_TOOL`].
+    #[default]
+    Paginate,
+}
+
+/// Applies a token budget to tool results and serves follow-up pages.
+#[derive(Debug, Clone)]
+pub struct ToolOutputPager {
+    max_tokens: usize,
+    policy: OversizePolicy,
+    pages: HashMap<String, Vec<String>>,
+}
+
+impl ToolOutputPager {
+    /// Budget each tool message to roughly `max_tokens` tokens.
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            policy: OversizePolicy::default(),
+            pages: HashMap::new(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: OversizePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> OversizePolicy {
+        self.policy
+    }
+
+    /// Spec of [`GET_MORE_TOOL`]; add it to the request's tools when
+    /// paginating.
+    pub fn tool_spec() -> GenericFunctionSpec {
+        GenericFunctionSpec {
+            name: GET_MORE_TOOL.to_string(),
+            description: "Fetch another page of a tool result that was too large to show at once."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "result_id": {
+                        "type": "string",
+                        "description": "The result id mentioned in the paginated tool output"
+                    },
+                    "page": {
+                        "type": "integer",
+                        "description": "1-based page number"
+                    }
+                },
+                "required": ["result_id", "page"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    /// Build the tool message answering `tool_call_id`, truncating or
+    /// paginating `content` if it exceeds the budget.
+    pub fn tool_message(
+        &mut self,
+        tool_call_id: impl Into<String>,
+        content: String,
+    ) -> GenericMessage {
+        let tool_call_id = tool_call_id.into();
+        let body = self.apply(&tool_call_id, content);
+        GenericMessage::new(body, GenericRole::Tool).with_tool_call_id(tool_call_id)
+    }
+
+    /// The body of [`Self::tool_message`].
+    pub(crate) fn apply(&mut self, tool_call_id: &str, content: String) -> String {
+        let mut pages = split_pages(&content, self.max_tokens * CHARS_PER_TOKEN);
+        if pages.len() <= 1 {
+            return content;
+        }
+        match self.policy {
+            OversizePolicy::Truncate => format!(
+                "{}\n\n[Output truncated: showing about {} of {} tokens.]",
+                pages.swap_remove(0),
+                self.max_tokens,
+                content.len() / CHARS_PER_TOKEN,
+            ),
+            OversizePolicy::Paginate => {
+                let body = render_page(tool_call_id, &pages, 1);
+                self.pages.insert(tool_call_id.to_string(), pages);
+                body
+            }
+        }
+    }
+
+    /// `true` if `call` targets [`GET_MORE_TOOL`] and must be answered with
+    /// [`ToolOutputPager::get_more`].
+    pub fn handles(&self, call: &GenericFunctionCallIntent) -> bool {
+        call.function.name == GET_MORE_TOOL
+    }
+
+    /// Answer a [`GET_MORE_TOOL`] call. Invalid ids or page numbers produce
+    /// an explanatory tool message instead of an error, so the model can
+    /// correct itself.
+    pub fn get_more(&self, call: &GenericFunctionCallIntent) -> GenericMessage {
+        GenericMessage::new(self.page(call), GenericRole::Tool).with_tool_call_id(&call.id)
+    }
+
+    /// The body of [`Self::get_more`].
+    pub(crate) fn page(&self, call: &GenericFunctionCallIntent) -> String {
+        let args = &call.function.arguments;
+        let result_id = args.get("result_id").and_then(|v| v.as_str());
+        let page = args.get("page").and_then(|v| v.as_u64());
+
+        match (result_id, page) {
+            (Some(id), Some(page)) => match self.pages.get(id) {
+                Some(pages) if page >= 1 && page as usize <= pages.len() => {
+                    render_page(id, pages, page as usize)
+                }
+                Some(pages) => format!(
+                    "Page {page} does not exist; result `{id}` has {} pages.",
+                    pages.len()
+                ),
+                None => format!("No paginated result with id `{id}`."),
+            },
+            _ => "Expected arguments `result_id` (string) and `page` (integer).".to_string(),
+        }
+    }
+
+    /// Forget the stored pages of a result, e.g. once the turn is over.
+    pub fn release(&mut self, result_id: &str) {
+        self.pages.remove(result_id);
+    }
+}
+
+fn render_page(result_id: &str, pages: &[String], page: usize) -> String {
+    let total = pages.len();
+    let hint = if page < total {
+        format!(
+            " Call `{GET_MORE_TOOL}` with {{\"result_id\": \"{result_id}\", \"page\": {}}} for more.",
+            page + 1
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "{}\n\n[Result `{result_id}`: page {page} of {total}.{hint}]",
+        pages[page - 1]
+    )
+}
+
+/// Split `content` into chunks of at most `max_chars` bytes, preferring line
+/// breaks in the second half of a chunk and never cutting a UTF-8 character.
+fn split_pages(content: &str, max_chars: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut rest = content;
+
+    while rest.len() > max_chars {
+        let mut end = max_chars;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if let Some(newline) = rest[..end].rfind('\n').filter(|&n| n >= end / 2) {
+            end = newline + 1;
+        }
+        if end == 0 {
+            // A single character wider than the budget.
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        pages.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() || pages.is_empty() {
+        pages.push(rest.to_string());
+    }
+
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_keeps_first_page_only() {
+        let mut pager = ToolOutputPager::new(2).with_policy(OversizePolicy::Truncate);
+
+        let small = pager.tool_message("a", "tiny".into());
+        assert_eq!(small.content.as_deref(), Some("tiny"));
+
+        let message = pager.tool_message("b", "ä".repeat(20));
+        let content = message.content.unwrap();
+        assert!(content.starts_with("ääää\n\n[Output truncated"));
+        assert_eq!(message.tool_call_id.as_deref(), Some("b"));
+        assert!(pager.pages.is_empty());
+    }
+}
@@ -0,0 +1,819 @@
+//! Register tools once, let the client drive the calling loop.
+//!
+//! Without help, every tool-using application repeats the same loop: send
+//! the chat, check for [`ResponseContent::ToolCalls`], run each call, append
+//! the assistant message and the tool results, send again.  A
+//! [`ToolRegistry`] maps tool names to async handlers, and
+//! [`ArtificialClient::chat_complete_with_tools`] runs that loop until the
+//! model produces a final answer:
+//!
+//! ```rust,no_run
+//! use artificial_core::{ArtificialClient, tools::ToolRegistry};
+//! use artificial_core::generic::{GenericFunctionSpec, GenericMessage, GenericRole};
+//! use artificial_core::model::{Model, OpenAiModel};
+//! use artificial_core::provider::{ChatCompleteParameters, ChatCompletionProvider};
+//! # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(client: ArtificialClient<B>) -> artificial_core::error::Result<()> {
+//! let registry = ToolRegistry::new().with_tool(
+//!     GenericFunctionSpec {
+//!         name: "current_weather".into(),
+//!         description: "Current temperature in °C for a city.".into(),
+//!         parameters: serde_json::json!({
+//!             "type": "object",
+//!             "properties": { "city": { "type": "string" } },
+//!             "required": ["city"],
+//!             "additionalProperties": false
+//!         }),
+//!     },
+//!     |args| async move { Ok(format!("{{\"city\": {}, \"celsius\": 21}}", args["city"])) },
+//! );
+//!
+//! let params = ChatCompleteParameters::new(
+//!     vec![GenericMessage::new("Weather in Berlin?".into(), GenericRole::User)],
+//!     Model::OpenAi(OpenAiModel::Gpt4oMini),
+//! );
+//! let outcome = client.chat_complete_with_tools(params, &registry).await?;
+//! println!("{}", outcome.message.content.unwrap_or_default());
+//! # Ok(()) }
+//! ```
+//!
+//! Handler errors and calls to unknown tools are reported back to the model
+//! as the tool result, so it can correct itself instead of aborting the run.
+//! A model that keeps repeating the same call with the same arguments is
+//! stopped with [`ArtificialError::ToolLoopDetected`] before it burns
+//! through [`ToolRegistry::with_max_rounds`].
+//!
+//! Results that would flood the context window are cut down with
+//! [`ToolRegistry::with_output_budget`].
+//!
+//! Tools with a typed argument struct implement [`TypedTool`] instead; their
+//! parameter schema is derived from the struct, so it cannot drift from the
+//! code that consumes it.
+//!
+//! [`ArtificialClient::chat_complete_with_tools`]: crate::ArtificialClient::chat_complete_with_tools
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    agent_events::{AgentEvent, AgentEventKind, AgentEventSink},
+    error::{ArtificialError, Result},
+    generic::{
+        GenericFunctionCallIntent, GenericFunctionSpec, GenericMessage, GenericRole,
+        GenericUsageReport, ResponseContent,
+    },
+    provider::{ChatCompleteParameters, ChatCompletionProvider},
+    schema_util::derive_response_schema,
+    tool_output::{OversizePolicy, ToolOutputPager, GET_MORE_TOOL},
+};
+
+pub type ToolFuture = Pin<Box<dyn Future<Output = Result<String>> + Send>>;
+type Handler = Box<dyn Fn(serde_json::Value) -> ToolFuture + Send + Sync>;
+
+struct RegisteredTool {
+    spec: GenericFunctionSpec,
+    handler: Handler,
+}
+
+/// A tool whose arguments `I` and result `O` are Rust types.
+///
+/// ```rust
+/// use artificial_core::{error::Result, tools::{ToolRegistry, TypedTool}};
+/// use schemars::JsonSchema;
+/// use serde::Deserialize;
+/// # use std::{future::Future, pin::Pin};
+///
+/// /// The city to look up.
+/// #[derive(Deserialize, JsonSchema)]
+/// #[serde(deny_unknown_fields)]
+/// struct WeatherArgs { city: String }
+///
+/// struct CurrentWeather;
+///
+/// impl TypedTool<WeatherArgs, f64> for CurrentWeather {
+///     fn name(&self) -> &str { "current_weather" }
+///     fn description(&self) -> &str { "Current temperature in °C for a city." }
+///     fn call(&self, args: WeatherArgs) -> Pin<Box<dyn Future<Output = Result<f64>> + Send + '_>> {
+///         Box::pin(async move { Ok(if args.city == "Berlin" { 21.0 } else { 25.0 }) })
+///     }
+/// }
+///
+/// let spec = CurrentWeather.spec();
+/// assert_eq!(spec.parameters["required"], serde_json::json!(["city"]));
+///
+/// let registry = ToolRegistry::new().with_typed_tool(CurrentWeather);
+/// assert!(registry.contains("current_weather"));
+/// ```
+pub trait TypedTool<I, O>: Send + Sync + 'static
+where
+    I: JsonSchema + DeserializeOwned + Send + 'static,
+    O: Serialize,
+{
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Run the tool with already parsed arguments.
+    fn call(&self, input: I) -> Pin<Box<dyn Future<Output = Result<O>> + Send + '_>>;
+
+    /// Spec with `parameters` derived from `I`.
+    fn spec(&self) -> GenericFunctionSpec {
+        let mut parameters = derive_response_schema::<I>();
+        if let Some(object) = parameters.as_object_mut() {
+            object.remove("$schema");
+        }
+        GenericFunctionSpec {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters,
+        }
+    }
+}
+
+/// Tools available to a chat, keyed by name.
+pub struct ToolRegistry {
+    tools: BTreeMap<String, RegisteredTool>,
+    max_rounds: usize,
+    max_repeated_calls: usize,
+    output: Option<Mutex<ToolOutputPager>>,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self {
+            tools: BTreeMap::new(),
+            max_rounds: 8,
+            max_repeated_calls: 3,
+            output: None,
+        }
+    }
+
+    /// Register `handler` for `spec.name`; it receives the call's JSON
+    /// arguments and returns the tool result sent to the model. A later
+    /// registration with the same name replaces the earlier one.
+    pub fn register<F, Fut>(&mut self, spec: GenericFunctionSpec, handler: F)
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let handler: Handler = Box::new(move |args| Box::pin(handler(args)));
+        self.tools
+            .insert(spec.name.clone(), RegisteredTool { spec, handler });
+    }
+
+    /// Builder variant of [`Self::register`].
+    pub fn with_tool<F, Fut>(mut self, spec: GenericFunctionSpec, handler: F) -> Self
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        self.register(spec, handler);
+        self
+    }
+
+    /// Register a [`TypedTool`]. Arguments that do not deserialize into `I`
+    /// are reported back to the model like any other tool error; the result
+    /// is sent as JSON.
+    pub fn register_typed<T, I, O>(&mut self, tool: T)
+    where
+        T: TypedTool<I, O>,
+        I: JsonSchema + DeserializeOwned + Send + 'static,
+        O: Serialize + 'static,
+    {
+        let spec = tool.spec();
+        let tool = Arc::new(tool);
+        self.register(spec, move |args| {
+            let tool = Arc::clone(&tool);
+            async move {
+                let input: I = serde_json::from_value(args)?;
+                let output = tool.call(input).await?;
+                Ok(serde_json::to_string(&output)?)
+            }
+        });
+    }
+
+    /// Builder variant of [`Self::register_typed`].
+    pub fn with_typed_tool<T, I, O>(mut self, tool: T) -> Self
+    where
+        T: TypedTool<I, O>,
+        I: JsonSchema + DeserializeOwned + Send + 'static,
+        O: Serialize + 'static,
+    {
+        self.register_typed(tool);
+        self
+    }
+
+    /// Upper bound of model round-trips per run. Defaults to 8.
+    pub fn with_max_rounds(mut self, max_rounds: usize) -> Self {
+        self.max_rounds = max_rounds.max(1);
+        self
+    }
+
+    pub fn max_rounds(&self) -> usize {
+        self.max_rounds
+    }
+
+    /// How often a run may call the same tool with identical arguments
+    /// before it fails with [`ArtificialError::ToolLoopDetected`]. Defaults
+    /// to 3.
+    pub fn with_max_repeated_calls(mut self, max_repeated_calls: usize) -> Self {
+        self.max_repeated_calls = max_repeated_calls.max(1);
+        self
+    }
+
+    pub fn max_repeated_calls(&self) -> usize {
+        self.max_repeated_calls
+    }
+
+    /// Keep every tool result to roughly `max_tokens` tokens, see
+    /// [`crate::tool_output`]. With [`OversizePolicy::Paginate`] the
+    /// registry also offers [`GET_MORE_TOOL`] and answers its calls from
+    /// the pages it kept; those stay around as long as the registry.
+    pub fn with_output_budget(mut self, max_tokens: usize, policy: OversizePolicy) -> Self {
+        self.output = Some(Mutex::new(
+            ToolOutputPager::new(max_tokens).with_policy(policy),
+        ));
+        self
+    }
+
+    /// Specs of all registered tools, for [`ChatCompleteParameters::tools`].
+    pub fn specs(&self) -> Vec<GenericFunctionSpec> {
+        let mut specs: Vec<_> = self.tools.values().map(|tool| tool.spec.clone()).collect();
+        if self.paginates() {
+            specs.push(ToolOutputPager::tool_spec());
+        }
+        specs
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name) || (name == GET_MORE_TOOL && self.paginates())
+    }
+
+    fn paginates(&self) -> bool {
+        self.output.as_ref().is_some_and(|pager| {
+            pager.lock().expect("tool output pager poisoned").policy() == OversizePolicy::Paginate
+        })
+    }
+
+    /// Execute `call` and wrap the outcome in the tool message answering it.
+    pub async fn execute(&self, call: &GenericFunctionCallIntent) -> GenericMessage {
+        let (content, _) = self.run(call).await;
+        GenericMessage::new(content, GenericRole::Tool).with_tool_call_id(&call.id)
+    }
+
+    /// The content for the model and whether it reports an error.
+    async fn run(&self, call: &GenericFunctionCallIntent) -> (String, bool) {
+        if let Some(pager) = &self.output {
+            let pager = pager.lock().expect("tool output pager poisoned");
+            if pager.policy() == OversizePolicy::Paginate && pager.handles(call) {
+                return (pager.page(call), false);
+            }
+        }
+        match self.tools.get(&call.function.name) {
+            Some(tool) => match (tool.handler)(call.function.arguments.clone()).await {
+                Ok(content) => match &self.output {
+                    Some(pager) => (
+                        pager
+                            .lock()
+                            .expect("tool output pager poisoned")
+                            .apply(&call.id, content),
+                        false,
+                    ),
+                    None => (content, false),
+                },
+                Err(err) => (format!("Error: {err}"), true),
+            },
+            None => (
+                format!(
+                    "Error: unknown tool `{}`. Available tools: {}.",
+                    call.function.name,
+                    self.tools.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+                true,
+            ),
+        }
+    }
+}
+
+/// Result of a tool-calling run.
+#[derive(Debug, Clone)]
+pub struct ToolRunOutcome {
+    /// The model's final answer.
+    pub message: GenericMessage,
+    /// The full history: the request messages, every assistant and tool
+    /// turn, and the final answer.
+    pub messages: Vec<GenericMessage>,
+    /// Usage summed over all rounds.
+    pub usage: Option<GenericUsageReport>,
+    /// Number of model round-trips.
+    pub rounds: usize,
+}
+
+/// Serializable state of a tool-calling run between two steps.
+///
+/// Save it (e.g. as JSON) to survive restarts or to wait for a human to
+/// approve [`Self::pending_calls`], then continue with
+/// [`crate::ArtificialClient::resume_with_tools`] or
+/// [`crate::ArtificialClient::step_with_tools`]. The request settings
+/// (model, extra tools, temperature, …) are not part of the checkpoint; the
+/// resuming process passes them again.
+///
+/// ```rust,no_run
+/// # use artificial_core::{ArtificialClient, tools::{AgentCheckpoint, ToolRegistry}, generic::GenericMessage, provider::*};
+/// # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(
+/// #     client: ArtificialClient<B>, params: ChatCompleteParameters<GenericMessage>, registry: ToolRegistry,
+/// # ) -> artificial_core::error::Result<()> {
+/// let mut checkpoint = AgentCheckpoint::new(params.messages.clone());
+/// loop {
+///     if let Some(outcome) = client.step_with_tools(&params, &mut checkpoint, &registry).await? {
+///         println!("{:?}", outcome.message.content);
+///         break;
+///     }
+///     if !checkpoint.pending_calls.is_empty() {
+///         // Persist and ask for approval before the tools run.
+///         std::fs::write("run.json", serde_json::to_vec(&checkpoint)?).unwrap();
+///         # break;
+///     }
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentCheckpoint {
+    /// The conversation so far, including every assistant and tool turn.
+    pub messages: Vec<GenericMessage>,
+    /// Calls the model requested that have not been executed yet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending_calls: Vec<GenericFunctionCallIntent>,
+    /// Model round-trips so far.
+    pub step: usize,
+    /// Usage summed over all round-trips so far.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<GenericUsageReport>,
+    /// How often each (tool, arguments) pair was requested, for
+    /// [`ToolRegistry::with_max_repeated_calls`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub call_counts: BTreeMap<String, usize>,
+}
+
+impl AgentCheckpoint {
+    /// A run that has not started yet.
+    pub fn new(messages: Vec<GenericMessage>) -> Self {
+        Self {
+            messages,
+            ..Self::default()
+        }
+    }
+}
+
+/// The loop behind [`crate::ArtificialClient::chat_complete_with_tools`].
+pub(crate) async fn run_tool_loop<B>(
+    backend: &B,
+    params: &ChatCompleteParameters<GenericMessage>,
+    checkpoint: &mut AgentCheckpoint,
+    registry: &ToolRegistry,
+    events: &dyn AgentEventSink,
+) -> Result<ToolRunOutcome>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    loop {
+        if let Some(outcome) = step_tool_run(backend, params, checkpoint, registry, events).await? {
+            return Ok(outcome);
+        }
+    }
+}
+
+/// Advance the run by one step: execute the pending calls if there are any,
+/// otherwise ask the model. `Some` once the model has answered.
+pub(crate) async fn step_tool_run<B>(
+    backend: &B,
+    params: &ChatCompleteParameters<GenericMessage>,
+    checkpoint: &mut AgentCheckpoint,
+    registry: &ToolRegistry,
+    events: &dyn AgentEventSink,
+) -> Result<Option<ToolRunOutcome>>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    let result = match checkpoint.pending_calls.is_empty() {
+        true => call_model(backend, params, checkpoint, registry, events).await,
+        false => {
+            execute_pending(checkpoint, registry, events).await;
+            Ok(None)
+        }
+    };
+    if let Err(err) = &result {
+        events.emit(AgentEvent::new(
+            checkpoint.step,
+            AgentEventKind::Error {
+                message: err.to_string(),
+            },
+        ));
+    }
+    result
+}
+
+async fn call_model<B>(
+    backend: &B,
+    params: &ChatCompleteParameters<GenericMessage>,
+    checkpoint: &mut AgentCheckpoint,
+    registry: &ToolRegistry,
+    events: &dyn AgentEventSink,
+) -> Result<Option<ToolRunOutcome>>
+where
+    B: ChatCompletionProvider,
+    GenericMessage: Into<B::Message>,
+{
+    if checkpoint.step >= registry.max_rounds {
+        return Err(ArtificialError::Other(format!(
+            "model still requested tools after {} rounds",
+            registry.max_rounds
+        )));
+    }
+    checkpoint.step += 1;
+    let step = checkpoint.step;
+    events.emit(AgentEvent::new(step, AgentEventKind::StepStarted));
+
+    let mut request = params.clone();
+    request.messages = checkpoint.messages.clone();
+    let mut tools = request.tools.take().unwrap_or_default();
+    tools.retain(|tool| !registry.contains(&tool.name));
+    tools.extend(registry.specs());
+    request.tools = Some(tools);
+
+    let response = backend.chat_complete(request).await?;
+    events.emit(AgentEvent::new(
+        step,
+        AgentEventKind::ModelCalled {
+            model: params.model.as_ref().to_string(),
+            tool_calls: match &response.content {
+                ResponseContent::ToolCalls(message) => {
+                    message.tool_calls.as_ref().map_or(0, Vec::len)
+                }
+                ResponseContent::Finished(_) => 0,
+            },
+            usage: response.usage.clone(),
+        },
+    ));
+    if let Some(next) = response.usage {
+        checkpoint.usage = Some(match checkpoint.usage.take() {
+            None => next,
+            Some(total) => GenericUsageReport {
+                prompt_tokens: total.prompt_tokens + next.prompt_tokens,
+                completion_tokens: total.completion_tokens + next.completion_tokens,
+                total_tokens: total.total_tokens + next.total_tokens,
+            },
+        });
+    }
+
+    match response.content {
+        ResponseContent::Finished(message) => {
+            events.emit(AgentEvent::new(
+                step,
+                AgentEventKind::FinalAnswer {
+                    message: message.clone(),
+                },
+            ));
+            checkpoint.messages.push(message.clone());
+            Ok(Some(ToolRunOutcome {
+                message,
+                messages: checkpoint.messages.clone(),
+                usage: checkpoint.usage.clone(),
+                rounds: step,
+            }))
+        }
+        ResponseContent::ToolCalls(message) => {
+            let calls = message.tool_calls.clone().unwrap_or_default();
+            for call in &calls {
+                let count = checkpoint.call_counts.entry(call_key(call)).or_default();
+                *count += 1;
+                if *count > registry.max_repeated_calls {
+                    return Err(ArtificialError::ToolLoopDetected {
+                        tool: call.function.name.clone(),
+                        calls: *count,
+                    });
+                }
+            }
+            checkpoint.messages.push(message);
+            checkpoint.pending_calls = calls;
+            Ok(None)
+        }
+    }
+}
+
+async fn execute_pending(
+    checkpoint: &mut AgentCheckpoint,
+    registry: &ToolRegistry,
+    events: &dyn AgentEventSink,
+) {
+    let step = checkpoint.step;
+    for call in std::mem::take(&mut checkpoint.pending_calls) {
+        events.emit(AgentEvent::new(
+            step,
+            AgentEventKind::ToolInvoked {
+                call_id: call.id.clone(),
+                tool: call.function.name.clone(),
+                arguments: call.function.arguments.clone(),
+            },
+        ));
+        let (content, is_error) = registry.run(&call).await;
+        events.emit(AgentEvent::new(
+            step,
+            AgentEventKind::ToolResult {
+                call_id: call.id.clone(),
+                tool: call.function.name.clone(),
+                content: content.clone(),
+                is_error,
+            },
+        ));
+        checkpoint
+            .messages
+            .push(GenericMessage::new(content, GenericRole::Tool).with_tool_call_id(&call.id));
+    }
+}
+
+/// Identifies a call by tool name and arguments; the call id differs on
+/// every round and is ignored. Stable across processes, so it can live in a
+/// checkpoint.
+fn call_key(call: &GenericFunctionCallIntent) -> String {
+    format!("{}:{}", call.function.name, call.function.arguments)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        generic::{GenericChatCompletionResponse, GenericFunctionCall},
+        model::{Model, OpenAiModel},
+        ArtificialClient,
+    };
+
+    /// Requests `echo` once, then answers with the tool result it received.
+    struct EchoOnce(Mutex<Vec<Vec<GenericMessage>>>);
+
+    impl ChatCompletionProvider for EchoOnce {
+        type Message = GenericMessage;
+
+        fn chat_complete<'s, M>(
+            &'s self,
+            params: ChatCompleteParameters<M>,
+        ) -> Pin<
+            Box<
+                dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>>
+                    + Send
+                    + 's,
+            >,
+        >
+        where
+            M: Into<Self::Message> + Clone + Send + Sync + 's,
+        {
+            let messages: Vec<GenericMessage> =
+                params.messages.into_iter().map(Into::into).collect();
+            self.0.lock().unwrap().push(messages.clone());
+            Box::pin(async move {
+                let content = match messages.last().unwrap().role {
+                    GenericRole::Tool => ResponseContent::Finished(GenericMessage::new(
+                        messages.last().unwrap().content.clone().unwrap(),
+                        GenericRole::Assistant,
+                    )),
+                    _ => echo_call(),
+                };
+                Ok(GenericChatCompletionResponse {
+                    content,
+                    usage: Some(GenericUsageReport {
+                        prompt_tokens: 2,
+                        completion_tokens: 1,
+                        total_tokens: 3,
+                    }),
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    fn echo_call() -> ResponseContent<GenericMessage> {
+        ResponseContent::ToolCalls(GenericMessage::new_tool_call(
+            "call-1".into(),
+            vec![GenericFunctionCallIntent {
+                id: "call-1".into(),
+                function: GenericFunctionCall {
+                    name: "echo".into(),
+                    arguments: serde_json::json!({ "text": "hi" }),
+                },
+            }],
+        ))
+    }
+
+    fn echo_registry() -> ToolRegistry {
+        ToolRegistry::new().with_tool(
+            GenericFunctionSpec {
+                name: "echo".into(),
+                description: "Echo the text.".into(),
+                parameters: serde_json::json!({ "type": "object" }),
+            },
+            |args| async move { Ok(args["text"].as_str().unwrap_or_default().to_uppercase()) },
+        )
+    }
+
+    /// Requests `echo` with the same arguments forever.
+    struct Stuck;
+
+    impl ChatCompletionProvider for Stuck {
+        type Message = GenericMessage;
+
+        fn chat_complete<'s, M>(
+            &'s self,
+            _params: ChatCompleteParameters<M>,
+        ) -> Pin<
+            Box<
+                dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>>
+                    + Send
+                    + 's,
+            >,
+        >
+        where
+            M: Into<Self::Message> + Clone + Send + Sync + 's,
+        {
+            Box::pin(async {
+                Ok(GenericChatCompletionResponse {
+                    content: echo_call(),
+                    usage: None,
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn executes_tools_until_the_model_finishes() {
+        let registry = echo_registry();
+        let client = ArtificialClient::new(EchoOnce(Mutex::new(Vec::new())));
+        let params = ChatCompleteParameters::new(
+            vec![GenericMessage::new("echo hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        );
+
+        let outcome = client
+            .chat_complete_with_tools(params, &registry)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.message.content.as_deref(), Some("HI"));
+        assert_eq!(outcome.rounds, 2);
+        assert_eq!(outcome.usage.unwrap().total_tokens, 6);
+        let roles: Vec<_> = outcome.messages.iter().map(|m| m.role).collect();
+        assert_eq!(
+            roles,
+            [
+                GenericRole::User,
+                GenericRole::Assistant,
+                GenericRole::Tool,
+                GenericRole::Assistant
+            ]
+        );
+        assert_eq!(client.backend().0.lock().unwrap()[1].len(), 3);
+    }
+
+    #[tokio::test]
+    async fn repeated_identical_calls_abort_the_run() {
+        let registry = echo_registry().with_max_repeated_calls(2);
+        let params = ChatCompleteParameters::new(
+            vec![GenericMessage::new("echo hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        );
+
+        let err = ArtificialClient::new(Stuck)
+            .chat_complete_with_tools(params, &registry)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ArtificialError::ToolLoopDetected { ref tool, calls: 3 } if tool == "echo"
+        ));
+    }
+
+    #[tokio::test]
+    async fn observed_runs_emit_step_events() {
+        use crate::agent_events::MemoryEventLog;
+
+        let log = MemoryEventLog::new();
+        let params = ChatCompleteParameters::new(
+            vec![GenericMessage::new("echo hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        );
+        ArtificialClient::new(EchoOnce(Mutex::new(Vec::new())))
+            .chat_complete_with_tools_observed(params, &echo_registry(), &log)
+            .await
+            .unwrap();
+
+        let kinds: Vec<_> = log
+            .events()
+            .iter()
+            .map(|e| {
+                let json = serde_json::to_value(e).unwrap();
+                format!("{}:{}", e.step, json["type"].as_str().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "1:step_started",
+                "1:model_called",
+                "1:tool_invoked",
+                "1:tool_result",
+                "2:step_started",
+                "2:model_called",
+                "2:final_answer"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn checkpoints_survive_a_serde_roundtrip() {
+        let registry = echo_registry();
+        let params = ChatCompleteParameters::new(
+            vec![GenericMessage::new("echo hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        );
+        let first = ArtificialClient::new(EchoOnce(Mutex::new(Vec::new())));
+        let mut checkpoint = AgentCheckpoint::new(params.messages.clone());
+        let done = first
+            .step_with_tools(&params, &mut checkpoint, &registry)
+            .await
+            .unwrap();
+        assert!(done.is_none());
+        assert_eq!(checkpoint.pending_calls.len(), 1);
+
+        let saved = serde_json::to_string(&checkpoint).unwrap();
+        let restored: AgentCheckpoint = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored.step, 1);
+        assert_eq!(restored.pending_calls[0].function.name, "echo");
+
+        let second = ArtificialClient::new(EchoOnce(Mutex::new(Vec::new())));
+        let outcome = second
+            .resume_with_tools(&params, restored, &registry)
+            .await
+            .unwrap();
+        assert_eq!(outcome.message.content.as_deref(), Some("HI"));
+        assert_eq!(outcome.rounds, 2);
+        assert_eq!(outcome.usage.unwrap().total_tokens, 6);
+        assert_eq!(second.backend().0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn output_budget_pages_results_and_serves_the_rest() {
+        let registry = echo_registry().with_output_budget(2, OversizePolicy::Paginate);
+        assert!(registry.contains(GET_MORE_TOOL));
+        assert!(registry
+            .specs()
+            .iter()
+            .any(|spec| spec.name == GET_MORE_TOOL));
+
+        let call = |id: &str, name: &str, arguments| GenericFunctionCallIntent {
+            id: id.into(),
+            function: GenericFunctionCall {
+                name: name.into(),
+                arguments,
+            },
+        };
+        let first = registry
+            .execute(&call(
+                "call-1",
+                "echo",
+                serde_json::json!({ "text": "abcdefghijkl" }),
+            ))
+            .await;
+        assert!(first
+            .content
+            .unwrap()
+            .starts_with("ABCDEFGH\n\n[Result `call-1`: page 1 of 2."));
+
+        let more = registry
+            .execute(&call(
+                "call-2",
+                GET_MORE_TOOL,
+                serde_json::json!({ "result_id": "call-1", "page": 2 }),
+            ))
+            .await;
+        assert_eq!(more.tool_call_id.as_deref(), Some("call-2"));
+        assert_eq!(
+            more.content.as_deref(),
+            Some("IJKL\n\n[Result `call-1`: page 2 of 2.]")
+        );
+    }
+}
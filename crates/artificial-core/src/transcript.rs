@@ -0,0 +1,337 @@
+//! Step-by-step reconstruction of a tool-calling run.
+//!
+//! An [`AgentEvent`] log is complete but flat. A [`Transcript`] groups it
+//! back into model round-trips – which model ran, what it cost, which tools
+//! it called with which arguments and what they returned – and prints that
+//! as a readable timeline:
+//!
+//! ```rust
+//! use artificial_core::agent_events::{AgentEvent, AgentEventKind};
+//! use artificial_core::transcript::Transcript;
+//! use artificial_core::usage::TokenPrice;
+//!
+//! let log = r#"
+//! {"step":1,"at_ms":0,"type":"step_started"}
+//! {"step":1,"at_ms":420,"type":"model_called","model":"gpt-4o-mini","tool_calls":1,"usage":{"prompt_tokens":120,"completion_tokens":18,"total_tokens":138}}
+//! {"step":1,"at_ms":421,"type":"tool_invoked","call_id":"c1","tool":"weather","arguments":{"city":"Berlin"}}
+//! {"step":1,"at_ms":480,"type":"tool_result","call_id":"c1","tool":"weather","content":"32°C","is_error":false}
+//! "#;
+//!
+//! let transcript = Transcript::from_jsonl(log)?
+//!     .with_price("gpt-4o-mini", TokenPrice::per_million(0.15, 0.60));
+//! let step = &transcript.steps()[0];
+//! assert_eq!(step.tools[0].result.as_deref(), Some("32°C"));
+//! println!("{transcript}");
+//! // ── step 1 · gpt-4o-mini · 120 in / 18 out · $0.000029 · 480 ms
+//! //   → weather {"city":"Berlin"}
+//! //   ← 32°C
+//! # Ok::<(), artificial_core::error::ArtificialError>(())
+//! ```
+//!
+//! Record the events of a live run with
+//! [`crate::agent_events::MemoryEventLog`], persist them as JSON lines and
+//! rebuild the transcript later with [`Transcript::from_jsonl`].
+use std::fmt;
+
+use crate::{
+    agent_events::{AgentEvent, AgentEventKind},
+    error::Result,
+    generic::{GenericMessage, GenericUsageReport},
+    usage::{PricingTable, TokenPrice},
+};
+
+/// A run grouped into its model round-trips.
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    steps: Vec<TranscriptStep>,
+    pricing: PricingTable,
+}
+
+/// One model round-trip and the tool calls it triggered.
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptStep {
+    pub step: usize,
+    pub model: Option<String>,
+    pub usage: Option<GenericUsageReport>,
+    /// Timestamp of the first event of the step.
+    pub started_at_ms: u64,
+    /// Time between the first and the last event of the step.
+    pub duration_ms: u64,
+    pub tools: Vec<ToolExchange>,
+    pub answer: Option<GenericMessage>,
+    pub error: Option<String>,
+}
+
+/// A tool call and, if it completed, its result.
+#[derive(Debug, Clone)]
+pub struct ToolExchange {
+    pub call_id: String,
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    pub result: Option<String>,
+    pub is_error: bool,
+}
+
+impl Transcript {
+    /// Group `events` by step. Events may come in any order of steps, but
+    /// within a step they are expected in emission order.
+    pub fn from_events(events: impl IntoIterator<Item = AgentEvent>) -> Self {
+        let mut steps: Vec<TranscriptStep> = Vec::new();
+        for event in events {
+            let index = match steps.iter().position(|s| s.step == event.step) {
+                Some(index) => index,
+                None => {
+                    steps.push(TranscriptStep {
+                        step: event.step,
+                        started_at_ms: event.at_ms,
+                        ..TranscriptStep::default()
+                    });
+                    steps.len() - 1
+                }
+            };
+            steps[index].record(event);
+        }
+        steps.sort_by_key(|s| s.step);
+        Self {
+            steps,
+            pricing: PricingTable::new(),
+        }
+    }
+
+    /// Parse a JSON-lines event log; blank lines are skipped.
+    pub fn from_jsonl(log: &str) -> Result<Self> {
+        let events = log
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<Vec<AgentEvent>, _>>()?;
+        Ok(Self::from_events(events))
+    }
+
+    /// Price tokens of `model` (as reported in the events) to show costs.
+    pub fn with_price(mut self, model: impl Into<String>, price: TokenPrice) -> Self {
+        self.pricing = self.pricing.with_price(model, price);
+        self
+    }
+
+    /// Price tokens with a whole table, e.g. the one of a
+    /// [`crate::usage::UsageTracker`].
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    pub fn steps(&self) -> &[TranscriptStep] {
+        &self.steps
+    }
+
+    /// The run as it looked after `step`: every step up to and including it.
+    pub fn until(&self, step: usize) -> &[TranscriptStep] {
+        let end = self.steps.partition_point(|s| s.step <= step);
+        &self.steps[..end]
+    }
+
+    /// Cost of `step`, if its model is priced and it reported usage.
+    pub fn cost(&self, step: &TranscriptStep) -> Option<f64> {
+        self.pricing
+            .cost(step.model.as_deref()?, step.usage.as_ref()?)
+    }
+
+    pub fn total_usage(&self) -> GenericUsageReport {
+        let mut total = GenericUsageReport {
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+        };
+        for usage in self.steps.iter().filter_map(|s| s.usage.as_ref()) {
+            total.prompt_tokens += usage.prompt_tokens;
+            total.completion_tokens += usage.completion_tokens;
+            total.total_tokens += usage.total_tokens;
+        }
+        total
+    }
+
+    /// Sum of all priced steps; `None` if no step could be priced.
+    pub fn total_cost(&self) -> Option<f64> {
+        self.steps
+            .iter()
+            .filter_map(|s| self.cost(s))
+            .reduce(|a, b| a + b)
+    }
+
+    fn write_step(&self, f: &mut fmt::Formatter<'_>, step: &TranscriptStep) -> fmt::Result {
+        write!(f, "── step {}", step.step)?;
+        if let Some(model) = &step.model {
+            write!(f, " · {model}")?;
+        }
+        if let Some(usage) = &step.usage {
+            write!(
+                f,
+                " · {} in / {} out",
+                usage.prompt_tokens, usage.completion_tokens
+            )?;
+        }
+        if let Some(cost) = self.cost(step) {
+            write!(f, " · ${cost:.6}")?;
+        }
+        writeln!(f, " · {} ms", step.duration_ms)?;
+
+        for tool in &step.tools {
+            writeln!(f, "  → {} {}", tool.tool, tool.arguments)?;
+            match (&tool.result, tool.is_error) {
+                (Some(result), false) => writeln!(f, "  ← {result}")?,
+                (Some(result), true) => writeln!(f, "  ✗ {result}")?,
+                (None, _) => writeln!(f, "  … no result")?,
+            }
+        }
+        if let Some(answer) = &step.answer {
+            writeln!(f, "  = {}", answer.content.as_deref().unwrap_or_default())?;
+        }
+        if let Some(error) = &step.error {
+            writeln!(f, "  ! {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TranscriptStep {
+    fn record(&mut self, event: AgentEvent) {
+        self.duration_ms = event.at_ms.saturating_sub(self.started_at_ms);
+        match event.kind {
+            AgentEventKind::StepStarted => {}
+            AgentEventKind::ModelCalled { model, usage, .. } => {
+                self.model = Some(model);
+                self.usage = usage;
+            }
+            AgentEventKind::ToolInvoked {
+                call_id,
+                tool,
+                arguments,
+            } => self.tools.push(ToolExchange {
+                call_id,
+                tool,
+                arguments,
+                result: None,
+                is_error: false,
+            }),
+            AgentEventKind::ToolResult {
+                call_id,
+                content,
+                is_error,
+                ..
+            } => {
+                if let Some(exchange) = self.tools.iter_mut().find(|t| t.call_id == call_id) {
+                    exchange.result = Some(content);
+                    exchange.is_error = is_error;
+                }
+            }
+            AgentEventKind::FinalAnswer { message } => self.answer = Some(message),
+            AgentEventKind::Error { message } => self.error = Some(message),
+        }
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            self.write_step(f, step)?;
+        }
+        let total = self.total_usage();
+        write!(
+            f,
+            "total: {} steps · {} in / {} out",
+            self.steps.len(),
+            total.prompt_tokens,
+            total.completion_tokens
+        )?;
+        if let Some(cost) = self.total_cost() {
+            write!(f, " · ${cost:.6}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::GenericRole;
+
+    fn event(step: usize, at_ms: u64, kind: AgentEventKind) -> AgentEvent {
+        AgentEvent { step, at_ms, kind }
+    }
+
+    #[test]
+    fn groups_events_into_steps() {
+        let usage = GenericUsageReport {
+            prompt_tokens: 1000,
+            completion_tokens: 100,
+            total_tokens: 1100,
+        };
+        let events = vec![
+            event(1, 10, AgentEventKind::StepStarted),
+            event(
+                1,
+                30,
+                AgentEventKind::ModelCalled {
+                    model: "gpt-4o-mini".into(),
+                    tool_calls: 1,
+                    usage: Some(usage.clone()),
+                },
+            ),
+            event(
+                1,
+                31,
+                AgentEventKind::ToolInvoked {
+                    call_id: "c1".into(),
+                    tool: "echo".into(),
+                    arguments: serde_json::json!({ "text": "hi" }),
+                },
+            ),
+            event(
+                1,
+                35,
+                AgentEventKind::ToolResult {
+                    call_id: "c1".into(),
+                    tool: "echo".into(),
+                    content: "HI".into(),
+                    is_error: false,
+                },
+            ),
+            event(2, 40, AgentEventKind::StepStarted),
+            event(
+                2,
+                60,
+                AgentEventKind::ModelCalled {
+                    model: "gpt-4o-mini".into(),
+                    tool_calls: 0,
+                    usage: Some(usage),
+                },
+            ),
+            event(
+                2,
+                60,
+                AgentEventKind::FinalAnswer {
+                    message: GenericMessage::new("HI".into(), GenericRole::Assistant),
+                },
+            ),
+        ];
+
+        let transcript = Transcript::from_events(events)
+            .with_price("gpt-4o-mini", TokenPrice::per_million(1.0, 10.0));
+
+        assert_eq!(transcript.steps().len(), 2);
+        assert_eq!(transcript.steps()[0].duration_ms, 25);
+        assert_eq!(transcript.until(1).len(), 1);
+        assert_eq!(transcript.total_usage().total_tokens, 2200);
+        assert!((transcript.total_cost().unwrap() - 0.004).abs() < 1e-9);
+        assert_eq!(
+            transcript.to_string(),
+            "── step 1 · gpt-4o-mini · 1000 in / 100 out · $0.002000 · 25 ms\n\
+             \x20 → echo {\"text\":\"hi\"}\n\
+             \x20 ← HI\n\
+             ── step 2 · gpt-4o-mini · 1000 in / 100 out · $0.002000 · 20 ms\n\
+             \x20 = HI\n\
+             total: 2 steps · 2000 in / 200 out · $0.004000"
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! Token accounting and cost estimation across many calls.
+//!
+//! [`UsageTracker`] wraps any [`ChatCompletionProvider`] and adds the
+//! [`GenericUsageReport`] of every response to a shared [`UsageLedger`],
+//! grouped by model. With a [`PricingTable`] the ledger also estimates what
+//! the tokens cost:
+//!
+//! ```rust
+//! use artificial_core::usage::{PricingTable, TokenPrice, UsageTracker};
+//! # fn wrap<B>(backend: B) {
+//! let provider = UsageTracker::new(backend).with_pricing(
+//!     PricingTable::new().with_price("gpt-4o-mini", TokenPrice::per_million(0.15, 0.60)),
+//! );
+//! let ledger = provider.ledger();
+//!
+//! // … run the batch through `ArtificialClient::new(provider)` …
+//!
+//! let total = ledger.total();
+//! println!("{} requests, {} tokens", total.requests, total.total_tokens);
+//! if let Some(usd) = ledger.cost() {
+//!     println!("≈ ${usd:.4}");
+//! }
+//! # }
+//! ```
+//!
+//! Prices change and differ per account, so the table is always supplied by
+//! the caller; models without a price are counted but not costed.
+//!
+//! Embeddings and event streams are counted as well. A stream is recorded
+//! once it ends or is dropped, with the [`StreamEvent::Usage`] it carried;
+//! backends that do not report usage while streaming only add to
+//! [`ModelUsage::requests`].
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+
+use crate::{
+    error::Result,
+    generic::{
+        GenericChatCompletionResponse, GenericMessage, GenericUsageReport, StreamEvent,
+        StreamingEventsProvider,
+    },
+    model::Model,
+    provider::{
+        BackendHealth, ChatCompleteParameters, ChatCompletionProvider, EmbeddingProvider,
+        EmbeddingResponse, HealthCheckProvider,
+    },
+};
+
+/// Cost of one token for a model, in an arbitrary currency (usually USD).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl TokenPrice {
+    /// Prices as listed by most providers: per one million tokens.
+    pub fn per_million(prompt: f64, completion: f64) -> Self {
+        Self {
+            prompt: prompt / 1_000_000.0,
+            completion: completion / 1_000_000.0,
+        }
+    }
+
+    pub fn cost(&self, usage: &GenericUsageReport) -> f64 {
+        usage.prompt_tokens as f64 * self.prompt + usage.completion_tokens as f64 * self.completion
+    }
+}
+
+/// Token prices by model identifier (as in [`crate::model::Model::as_ref`]).
+#[derive(Debug, Clone, Default)]
+pub struct PricingTable {
+    prices: HashMap<String, TokenPrice>,
+}
+
+impl PricingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_price(mut self, model: impl Into<String>, price: TokenPrice) -> Self {
+        self.prices.insert(model.into(), price);
+        self
+    }
+
+    pub fn price(&self, model: &str) -> Option<TokenPrice> {
+        self.prices.get(model).copied()
+    }
+
+    /// Cost of `usage` on `model`, `None` if the model is not priced.
+    pub fn cost(&self, model: &str, usage: &GenericUsageReport) -> Option<f64> {
+        Some(self.price(model)?.cost(usage))
+    }
+}
+
+/// Accumulated usage of one model (or of all of them, see
+/// [`UsageLedger::total`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModelUsage {
+    /// Responses received, including those without a usage report.
+    pub requests: u64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+}
+
+impl ModelUsage {
+    fn add(&mut self, other: &ModelUsage) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+
+    /// The token counts as a report, e.g. for [`PricingTable::cost`].
+    pub fn report(&self) -> GenericUsageReport {
+        GenericUsageReport {
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            total_tokens: self.total_tokens,
+        }
+    }
+}
+
+/// Shared usage totals.
+///
+/// Cloning is cheap; all clones share the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct UsageLedger {
+    inner: Arc<Mutex<LedgerState>>,
+}
+
+#[derive(Debug, Default)]
+struct LedgerState {
+    models: BTreeMap<String, ModelUsage>,
+    pricing: PricingTable,
+}
+
+impl UsageLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the prices used by [`Self::cost`] and [`Self::cost_of`].
+    pub fn set_pricing(&self, pricing: PricingTable) {
+        self.state().pricing = pricing;
+    }
+
+    /// Count one response of `model`.
+    pub fn record(&self, model: &str, usage: Option<&GenericUsageReport>) {
+        let mut state = self.state();
+        let entry = state.models.entry(model.to_owned()).or_default();
+        entry.requests += 1;
+        if let Some(usage) = usage {
+            entry.prompt_tokens += usage.prompt_tokens;
+            entry.completion_tokens += usage.completion_tokens;
+            entry.total_tokens += usage.total_tokens;
+        }
+    }
+
+    /// Usage by model identifier.
+    pub fn per_model(&self) -> BTreeMap<String, ModelUsage> {
+        self.state().models.clone()
+    }
+
+    /// Usage summed over all models.
+    pub fn total(&self) -> ModelUsage {
+        let mut total = ModelUsage::default();
+        for usage in self.state().models.values() {
+            total.add(usage);
+        }
+        total
+    }
+
+    /// Estimated cost of `model`, `None` if it is not priced.
+    pub fn cost_of(&self, model: &str) -> Option<f64> {
+        let state = self.state();
+        state
+            .pricing
+            .cost(model, &state.models.get(model)?.report())
+    }
+
+    /// Estimated cost of all priced models; `None` if none is priced.
+    pub fn cost(&self) -> Option<f64> {
+        let state = self.state();
+        state
+            .models
+            .iter()
+            .filter_map(|(model, usage)| state.pricing.cost(model, &usage.report()))
+            .reduce(|a, b| a + b)
+    }
+
+    /// Clear all counters (keeps the pricing), e.g. between batch jobs.
+    pub fn reset(&self) {
+        self.state().models.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LedgerState> {
+        self.inner.lock().expect("usage ledger poisoned")
+    }
+}
+
+/// [`ChatCompletionProvider`] wrapper that counts tokens into a
+/// [`UsageLedger`].
+pub struct UsageTracker<B> {
+    inner: B,
+    ledger: UsageLedger,
+}
+
+impl<B> UsageTracker<B> {
+    /// Wrap `inner` with a fresh ledger.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            ledger: UsageLedger::new(),
+        }
+    }
+
+    /// Count into an existing ledger, e.g. one shared by several backends.
+    pub fn with_ledger(mut self, ledger: UsageLedger) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    pub fn with_pricing(self, pricing: PricingTable) -> Self {
+        self.ledger.set_pricing(pricing);
+        self
+    }
+
+    /// A handle on the totals; it stays valid after the tracker moved into a
+    /// client.
+    pub fn ledger(&self) -> UsageLedger {
+        self.ledger.clone()
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B> ChatCompletionProvider for UsageTracker<B>
+where
+    B: ChatCompletionProvider,
+{
+    type Message = B::Message;
+
+    fn chat_complete<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<
+        Box<dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>> + Send + 's>,
+    >
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let model = params.model.as_ref().to_string();
+        Box::pin(async move {
+            let response = self.inner.chat_complete(params).await?;
+            self.ledger.record(&model, response.usage.as_ref());
+            Ok(response)
+        })
+    }
+}
+
+impl<B> StreamingEventsProvider for UsageTracker<B>
+where
+    B: StreamingEventsProvider,
+{
+    type EventStream<'s>
+        = Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send + 's>>
+    where
+        Self: 's;
+
+    fn chat_complete_events_stream<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Self::EventStream<'s>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let model = params.model.as_ref().to_string();
+        Box::pin(Counted {
+            source: Box::pin(self.inner.chat_complete_events_stream(params)),
+            ledger: &self.ledger,
+            model: Some(model),
+            usage: None,
+        })
+    }
+}
+
+impl<B> EmbeddingProvider for UsageTracker<B>
+where
+    B: EmbeddingProvider,
+{
+    fn embed<'s>(
+        &'s self,
+        texts: Vec<String>,
+        model: Model,
+    ) -> Pin<Box<dyn Future<Output = Result<EmbeddingResponse>> + Send + 's>> {
+        let name = model.as_ref().to_string();
+        Box::pin(async move {
+            let response = self.inner.embed(texts, model).await?;
+            self.ledger.record(&name, response.usage.as_ref());
+            Ok(response)
+        })
+    }
+}
+
+impl<B> HealthCheckProvider for UsageTracker<B>
+where
+    B: HealthCheckProvider,
+{
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        self.inner.check_health()
+    }
+}
+
+/// Passes a stream through and records it in the ledger when it ends.
+struct Counted<'s, S> {
+    source: S,
+    ledger: &'s UsageLedger,
+    /// Taken when the stream is recorded.
+    model: Option<String>,
+    usage: Option<GenericUsageReport>,
+}
+
+impl<S> Counted<'_, S> {
+    fn finish(&mut self) {
+        if let Some(model) = self.model.take() {
+            self.ledger.record(&model, self.usage.as_ref());
+        }
+    }
+}
+
+impl<S> Stream for Counted<'_, S>
+where
+    S: Stream<Item = Result<StreamEvent>> + Unpin,
+{
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = match Pin::new(&mut this.source).poll_next(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        match &item {
+            Some(Ok(StreamEvent::Usage(usage))) => this.usage = Some(usage.clone()),
+            Some(_) => {}
+            None => this.finish(),
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl<S> Drop for Counted<'_, S> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic::{GenericRole, ResponseContent},
+        model::{Model, OpenAiModel},
+    };
+
+    /// Reports 1000 prompt and 100 completion tokens per call.
+    struct Fixed;
+
+    impl ChatCompletionProvider for Fixed {
+        type Message = GenericMessage;
+
+        fn chat_complete<'s, M>(
+            &'s self,
+            _params: ChatCompleteParameters<M>,
+        ) -> Pin<
+            Box<
+                dyn Future<Output = Result<GenericChatCompletionResponse<GenericMessage>>>
+                    + Send
+                    + 's,
+            >,
+        >
+        where
+            M: Into<Self::Message> + Clone + Send + Sync + 's,
+        {
+            Box::pin(async {
+                Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(GenericMessage::new(
+                        "ok".into(),
+                        GenericRole::Assistant,
+                    )),
+                    usage: Some(GenericUsageReport {
+                        prompt_tokens: 1000,
+                        completion_tokens: 100,
+                        total_tokens: 1100,
+                    }),
+                    metadata: Default::default(),
+                })
+            })
+        }
+    }
+
+    fn params(model: OpenAiModel) -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new("hi".into(), GenericRole::User)],
+            Model::OpenAi(model),
+        )
+    }
+
+    #[tokio::test]
+    async fn accumulates_usage_and_cost_per_model() {
+        let provider = UsageTracker::new(Fixed).with_pricing(
+            PricingTable::new().with_price("gpt-4o-mini", TokenPrice::per_million(1.0, 10.0)),
+        );
+        let ledger = provider.ledger();
+
+        provider
+            .chat_complete(params(OpenAiModel::Gpt4oMini))
+            .await
+            .unwrap();
+        provider
+            .chat_complete(params(OpenAiModel::Gpt4oMini))
+            .await
+            .unwrap();
+        provider
+            .chat_complete(params(OpenAiModel::Gpt4o))
+            .await
+            .unwrap();
+
+        let per_model = ledger.per_model();
+        assert_eq!(per_model["gpt-4o-mini"].requests, 2);
+        assert_eq!(per_model["gpt-4o-mini"].total_tokens, 2200);
+        assert_eq!(ledger.total().requests, 3);
+        assert!((ledger.cost_of("gpt-4o-mini").unwrap() - 0.004).abs() < 1e-9);
+        assert_eq!(ledger.cost_of("gpt-4o"), None);
+        assert!((ledger.cost().unwrap() - 0.004).abs() < 1e-9);
+
+        ledger.reset();
+        assert_eq!(ledger.total(), ModelUsage::default());
+    }
+
+    #[tokio::test]
+    async fn streams_are_counted_when_they_end() {
+        use futures_util::StreamExt;
+
+        use crate::testing::{MockProvider, MockReply};
+
+        let mock = MockProvider::new();
+        mock.push(MockReply::chunks(["Hel", "lo"]).with_usage(10, 2));
+        mock.push_text("unfinished");
+        let provider = UsageTracker::new(mock);
+        let ledger = provider.ledger();
+
+        let events: Vec<_> = provider
+            .chat_complete_events_stream(params(OpenAiModel::Gpt4oMini))
+            .collect()
+            .await;
+        assert_eq!(events.len(), 4);
+        assert_eq!(ledger.total().total_tokens, 12);
+
+        let mut stream = provider.chat_complete_events_stream(params(OpenAiModel::Gpt4oMini));
+        stream.next().await;
+        drop(stream);
+        assert_eq!(ledger.total().requests, 2);
+        assert_eq!(ledger.total().total_tokens, 12);
+    }
+}
@@ -0,0 +1,229 @@
+//! Startup checks for [`PromptTemplate`]s.
+//!
+//! Schema problems (a missing `deny_unknown_fields`, an optional field that
+//! strict mode rejects, …) normally only show up when the provider answers
+//! the first live request with a 400.  [`TemplateValidator`] moves that
+//! discovery to application startup:
+//!
+//! 1. every registered template is **rendered** with sample data,
+//! 2. the JSON Schema of its `Output` is **derived**,
+//! 3. the schema is run through the **strict-mode** checker
+//!    ([`crate::schema_util::strict_mode_violations`]).
+//!
+//! All problems are collected and reported at once instead of failing on the
+//! first one.
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_core::model::{Model, OpenAiModel};
+//! use artificial_core::template::{IntoPrompt, PromptTemplate};
+//! use artificial_core::validation::TemplateValidator;
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! #[serde(deny_unknown_fields)]
+//! struct Answer { text: String }
+//!
+//! struct Ask(String);
+//!
+//! impl IntoPrompt for Ask {
+//!     type Message = GenericMessage;
+//!     fn into_prompt(self) -> Vec<Self::Message> {
+//!         vec![GenericMessage::new(self.0, GenericRole::User)]
+//!     }
+//! }
+//!
+//! impl PromptTemplate for Ask {
+//!     type Output = Answer;
+//!     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+//! }
+//!
+//! TemplateValidator::new()
+//!     .register(Ask("sample question".into()))
+//!     .validate()
+//!     .into_result()
+//!     .expect("templates are valid");
+//! ```
+use std::{any::TypeId, fmt};
+
+use crate::{
+    error::{ArtificialError, Result},
+    schema_util::{derive_response_schema, strict_mode_violations},
+    template::PromptTemplate,
+};
+
+/// A single problem found while validating a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateProblem {
+    /// Name of the offending template.
+    pub template: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.template, self.message)
+    }
+}
+
+/// Result of [`TemplateValidator::validate`].
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// Number of templates that were checked.
+    pub checked: usize,
+    pub problems: Vec<TemplateProblem>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Convert the report into an error listing **every** problem, one per
+    /// line, so it can be bubbled up from `main` with `?`.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_ok() {
+            return Ok(());
+        }
+
+        let lines: Vec<String> = self.problems.iter().map(ToString::to_string).collect();
+        Err(ArtificialError::Invalid(format!(
+            "{} template problem(s) found:\n{}",
+            self.problems.len(),
+            lines.join("\n")
+        )))
+    }
+}
+
+type Check = Box<dyn FnOnce() -> Vec<TemplateProblem>>;
+
+/// Registry of templates that should be checked at startup.
+#[derive(Default)]
+pub struct TemplateValidator {
+    checks: Vec<Check>,
+}
+
+impl TemplateValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a template together with a **sample** instance used for
+    /// rendering.  The sample should resemble real input closely enough to
+    /// exercise all fragments.
+    pub fn register<P>(mut self, sample: P) -> Self
+    where
+        P: PromptTemplate + 'static,
+    {
+        self.checks.push(Box::new(move || check_template(sample)));
+        self
+    }
+
+    /// Run all registered checks and collect the problems.
+    pub fn validate(self) -> ValidationReport {
+        let checked = self.checks.len();
+        let problems = self.checks.into_iter().flat_map(|check| check()).collect();
+
+        ValidationReport { checked, problems }
+    }
+}
+
+fn check_template<P>(sample: P) -> Vec<TemplateProblem>
+where
+    P: PromptTemplate + 'static,
+{
+    let template = P::name();
+    let mut problems = Vec::new();
+
+    if sample.into_prompt().is_empty() {
+        problems.push(TemplateProblem {
+            template,
+            message: "rendered prompt contains no messages".into(),
+        });
+    }
+
+    // Raw JSON output is requested in `json_object` mode; there is no schema
+    // to check.
+    if TypeId::of::<P::Output>() == TypeId::of::<serde_json::Value>() {
+        return problems;
+    }
+
+    let schema = derive_response_schema::<P::Output>();
+    problems.extend(
+        strict_mode_violations(&schema)
+            .into_iter()
+            .map(|message| TemplateProblem {
+                template,
+                message: format!("strict schema: {message}"),
+            }),
+    );
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        generic::{GenericMessage, GenericRole},
+        model::{Model, OpenAiModel},
+        template::IntoPrompt,
+    };
+
+    #[derive(Deserialize, JsonSchema)]
+    struct LooseOutput {
+        #[allow(dead_code)]
+        maybe: Option<String>,
+    }
+
+    struct Empty;
+
+    impl IntoPrompt for Empty {
+        type Message = GenericMessage;
+        fn into_prompt(self) -> Vec<Self::Message> {
+            Vec::new()
+        }
+    }
+
+    impl PromptTemplate for Empty {
+        type Output = LooseOutput;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    }
+
+    struct Raw;
+
+    impl IntoPrompt for Raw {
+        type Message = GenericMessage;
+        fn into_prompt(self) -> Vec<Self::Message> {
+            vec![GenericMessage::new("hi".into(), GenericRole::User)]
+        }
+    }
+
+    impl PromptTemplate for Raw {
+        type Output = serde_json::Value;
+        const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    }
+
+    #[test]
+    fn reports_all_problems_of_all_templates() {
+        let report = TemplateValidator::new()
+            .register(Empty)
+            .register(Raw)
+            .validate();
+
+        assert_eq!(report.checked, 2);
+        // empty prompt + missing additionalProperties + optional property
+        assert_eq!(report.problems.len(), 3);
+        assert!(report
+            .problems
+            .iter()
+            .all(|p| p.template.ends_with("Empty")));
+
+        let err = report.into_result().expect_err("report has problems");
+        assert!(err.to_string().contains("3 template problem(s)"));
+    }
+}
@@ -2,14 +2,17 @@ use std::{env, sync::Arc};
 
 use artificial_core::error::{ArtificialError, Result};
 
-use crate::client::{HttpTimeoutConfig, OpenAiClient, RetryPolicy};
+use crate::{
+    client::{HttpTimeoutConfig, OpenAiClient, RateLimitSnapshot, RetryPolicy},
+    rate_limit::RateLimiter,
+};
 
 /// Thin wrapper that wires the HTTP client [`OpenAiClient`] into a value that
 /// implements [`artificial_core::backend::Backend`].
 ///
 /// Think of it as the **service locator** for the OpenAI back-end:
 ///
-/// * stores the API key (and optionally a custom base URL in the future),
+/// * stores the API key and base URL (OpenAI or a compatible provider),
 /// * owns a shareable, connection-pooled `reqwest::Client`,
 /// * provides a fluent [`OpenAiAdapterBuilder`] so callers don’t have to juggle
 ///   `Option<String>` manually.
@@ -21,7 +24,27 @@ pub struct OpenAiAdapter {
     pub(crate) client: Arc<OpenAiClient>,
 }
 
-impl OpenAiAdapter {}
+impl OpenAiAdapter {
+    /// Rate-limit headers of the latest response received with this
+    /// adapter's key, `None` before the first one (or if the server sends
+    /// none).
+    ///
+    /// Lets schedulers and dashboards read the remaining headroom without
+    /// waiting for a `429`:
+    ///
+    /// ```rust,no_run
+    /// # fn check(adapter: &artificial_openai::OpenAiAdapter) {
+    /// if let Some(snapshot) = adapter.rate_limit_snapshot() {
+    ///     if snapshot.token_headroom().is_some_and(|h| h < 0.1) {
+    ///         // back off before the provider does it for us
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn rate_limit_snapshot(&self) -> Option<RateLimitSnapshot> {
+        self.client.rate_limit_snapshot()
+    }
+}
 
 /// Builder-style configuration for constructing [`OpenAiAdapter`].
 ///
@@ -42,8 +65,16 @@ pub struct OpenAiAdapterOptions {
     pub(crate) api_key: Option<String>,
     pub(crate) retry: Option<RetryPolicy>,
     pub(crate) timeouts: Option<HttpTimeoutConfig>,
+    pub(crate) base_url: Option<String>,
+    /// Name of the variable the key is expected in, for error messages.
+    pub(crate) api_key_env: Option<&'static str>,
+    pub(crate) azure: Option<(String, String, String)>,
+    pub(crate) rate_limiter: Option<RateLimiter>,
 }
 
+const XAI_BASE_URL: &str = "https://api.x.ai/v1";
+const DEEPSEEK_BASE_URL: &str = "https://api.deepseek.com/v1";
+
 impl OpenAiAdapterOptions {
     /// Create an *empty* builder. Remember to supply an API key manually.
     pub fn new() -> Self {
@@ -53,14 +84,45 @@ impl OpenAiAdapterOptions {
     /// Convenience constructor that tries to load the `OPENAI_API_KEY`
     /// environment variable.
     ///
+    /// If `OPENAI_BASE_URL` is set, requests go to that endpoint instead of
+    /// `https://api.openai.com/v1`.
+    ///
     /// # Panics
     ///
     /// Never panics. Missing keys only surface during [`Self::build`].
     pub fn new_from_env() -> Self {
+        let base_url = env::var("OPENAI_BASE_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty());
+        Self::preset_from_env("OPENAI_API_KEY", base_url.as_deref())
+    }
+
+    /// Preset for xAI (Grok) through its OpenAI-compatible API, reading the
+    /// key from `XAI_API_KEY`.
+    ///
+    /// ```rust,no_run
+    /// use artificial_openai::OpenAiAdapterOptions;
+    ///
+    /// let backend = OpenAiAdapterOptions::xai_from_env()
+    ///     .build()
+    ///     .expect("XAI_API_KEY must be set");
+    /// ```
+    pub fn xai_from_env() -> Self {
+        Self::preset_from_env("XAI_API_KEY", Some(XAI_BASE_URL))
+    }
+
+    /// Preset for DeepSeek through its OpenAI-compatible API, reading the key
+    /// from `DEEPSEEK_API_KEY`.
+    pub fn deepseek_from_env() -> Self {
+        Self::preset_from_env("DEEPSEEK_API_KEY", Some(DEEPSEEK_BASE_URL))
+    }
+
+    fn preset_from_env(api_key_env: &'static str, base_url: Option<&str>) -> Self {
         Self {
-            api_key: env::var("OPENAI_API_KEY").ok(),
-            retry: None,
-            timeouts: None,
+            api_key: env::var(api_key_env).ok(),
+            base_url: base_url.map(str::to_owned),
+            api_key_env: Some(api_key_env),
+            ..Self::default()
         }
     }
 
@@ -70,6 +132,26 @@ impl OpenAiAdapterOptions {
         self
     }
 
+    /// Send requests to another server speaking the OpenAI wire protocol,
+    /// e.g. vLLM, a LiteLLM proxy or OpenRouter.
+    ///
+    /// `base_url` is the prefix in front of `/chat/completions`, usually
+    /// ending in `/v1`.
+    ///
+    /// ```rust,no_run
+    /// use artificial_openai::OpenAiAdapterOptions;
+    ///
+    /// let backend = OpenAiAdapterOptions::new()
+    ///     .with_api_key("unused")
+    ///     .with_base_url("http://localhost:8000/v1")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
     /// Set a retry policy for OpenAI HTTP calls.
     pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
         self.retry = Some(retry);
@@ -82,15 +164,50 @@ impl OpenAiAdapterOptions {
         self
     }
 
+    /// Delay requests before they would hit the rate limits instead of
+    /// waiting for `429`s. See [`RateLimiter`].
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Target an Azure OpenAI deployment instead of api.openai.com.
+    ///
+    /// `endpoint` is the resource URL (`https://{resource}.openai.azure.com`),
+    /// `deployment` the name of the model deployment. The API key is sent in
+    /// the `api-key` header as Azure expects.
+    ///
+    /// ```rust,no_run
+    /// use artificial_openai::OpenAiAdapterOptions;
+    ///
+    /// let backend = OpenAiAdapterOptions::new()
+    ///     .with_api_key(std::env::var("AZURE_OPENAI_API_KEY").unwrap())
+    ///     .with_azure("https://my-resource.openai.azure.com", "gpt-4o-mini", "2024-10-21")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn with_azure(
+        mut self,
+        endpoint: impl Into<String>,
+        deployment: impl Into<String>,
+        api_version: impl Into<String>,
+    ) -> Self {
+        self.azure = Some((endpoint.into(), deployment.into(), api_version.into()));
+        self
+    }
+
     /// Finalise the builder and return a ready-to-use adapter.
     ///
     /// # Errors
     ///
     /// * [`ArtificialError::Invalid`] – if the API key is missing.
     pub fn build(self) -> Result<OpenAiAdapter> {
-        let api_key = self.api_key.ok_or(ArtificialError::Invalid(
-            "missing env variable: `OPENAI_API_KEY`".into(),
-        ))?;
+        let api_key = self.api_key.ok_or_else(|| {
+            ArtificialError::Invalid(format!(
+                "missing env variable: `{}`",
+                self.api_key_env.unwrap_or("OPENAI_API_KEY")
+            ))
+        })?;
 
         let mut client = if let Some(timeouts) = self.timeouts {
             OpenAiClient::new_with_timeouts(api_key, timeouts)
@@ -100,6 +217,15 @@ impl OpenAiAdapterOptions {
         if let Some(retry) = self.retry {
             client = client.with_retry_policy(retry);
         }
+        if let Some(limiter) = self.rate_limiter {
+            client = client.with_rate_limiter(limiter);
+        }
+        if let Some(base_url) = self.base_url {
+            client = client.with_base_url(base_url);
+        }
+        if let Some((endpoint, deployment, api_version)) = self.azure {
+            client = client.with_azure(endpoint, deployment, api_version);
+        }
 
         Ok(OpenAiAdapter {
             client: Arc::new(client),
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{ChatCompletionRequest, ChatCompletionResponse};
+
+/// Response of `POST /v1/files`.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct FileObject {
+    pub id: String,
+    #[serde(default)]
+    pub bytes: Option<u64>,
+}
+
+/// Request body of `POST /v1/batches`.
+#[derive(Debug, Serialize, Clone)]
+pub struct CreateBatchRequest {
+    pub input_file_id: String,
+    pub endpoint: String,
+    pub completion_window: String,
+}
+
+/// A batch as returned by `POST /v1/batches` and `GET /v1/batches/{id}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchObject {
+    pub id: String,
+    pub status: BatchStatus,
+    #[serde(default)]
+    pub output_file_id: Option<String>,
+    #[serde(default)]
+    pub error_file_id: Option<String>,
+    #[serde(default)]
+    pub request_counts: Option<BatchRequestCounts>,
+    /// Validation errors of the input file, if any.
+    #[serde(default)]
+    pub errors: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Validating,
+    Failed,
+    InProgress,
+    Finalizing,
+    Completed,
+    Expired,
+    Cancelling,
+    Cancelled,
+}
+
+impl BatchStatus {
+    /// Whether the batch will not change any more.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            Self::Failed | Self::Completed | Self::Expired | Self::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct BatchRequestCounts {
+    pub total: u64,
+    pub completed: u64,
+    pub failed: u64,
+}
+
+/// One line of the batch input file.
+#[derive(Debug, Serialize)]
+pub struct BatchInputLine<'a> {
+    pub custom_id: &'a str,
+    pub method: &'static str,
+    pub url: &'static str,
+    pub body: &'a ChatCompletionRequest,
+}
+
+/// One line of the batch output or error file.
+#[derive(Debug, Deserialize)]
+pub struct BatchOutputLine {
+    pub custom_id: String,
+    #[serde(default)]
+    pub response: Option<BatchOutputResponse>,
+    #[serde(default)]
+    pub error: Option<BatchOutputError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchOutputResponse {
+    pub status_code: u16,
+    pub body: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchOutputError {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl BatchOutputResponse {
+    /// The chat completion, for successful requests.
+    pub fn completion(self) -> Result<ChatCompletionResponse, Value> {
+        if (200..300).contains(&self.status_code) {
+            serde_json::from_value(self.body.clone()).map_err(|_| self.body)
+        } else {
+            Err(self.body)
+        }
+    }
+}
@@ -1,18 +1,24 @@
 use artificial_core::error::ArtificialError;
-use artificial_core::generic::{GenericFunctionSpec, GenericMessage, GenericRole};
-use artificial_core::provider::ChatCompleteParameters;
+use artificial_core::generic::{
+    GenericAttachment, GenericFunctionSpec, GenericMessage, GenericRole, ResponseMetadata,
+    SystemRole,
+};
+use artificial_core::provider::{ChatCompleteParameters, Temperature, TopP};
+use artificial_core::redact;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 
-use std::fmt;
+use std::{fmt, time::Duration};
 
 use crate::impl_builder_methods;
-use crate::model_map::map_model;
+use crate::model_map::{map_model, supports_reasoning_effort};
 
 use super::common;
 use super::tools::ToolCall;
 
-#[derive(Debug, Serialize, Clone)]
+/// `Debug` output shows the number of messages rather than their content,
+/// see [`artificial_core::redact`].
+#[derive(Serialize, Clone)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatCompletionMessage>,
@@ -30,6 +36,58 @@ pub struct ChatCompletionRequest {
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_cache_key: Option<String>,
+    /// Per-request override of the client's HTTP timeout; not sent.
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+    /// Whether the retry policy repeats attempts that timed out; not sent.
+    #[serde(skip)]
+    pub retry_on_timeout: bool,
+}
+
+impl fmt::Debug for ChatCompletionRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ChatCompletionRequest");
+        debug.field("model", &self.model);
+        match redact::content_visible() {
+            true => debug.field("messages", &self.messages),
+            false => debug.field(
+                "messages",
+                &format_args!("<{} messages>", self.messages.len()),
+            ),
+        };
+        debug
+            .field("tools", &self.tools)
+            .field("temperature", &self.temperature)
+            .field("top_p", &self.top_p)
+            .field("n", &self.n)
+            .field("response_format", &self.response_format)
+            .field("stream", &self.stream)
+            .field("tool_choice", &self.tool_choice)
+            .field("max_completion_tokens", &self.max_completion_tokens)
+            .field("stop", &self.stop)
+            .field("presence_penalty", &self.presence_penalty)
+            .field("frequency_penalty", &self.frequency_penalty)
+            .field("seed", &self.seed)
+            .field("reasoning_effort", &self.reasoning_effort)
+            .field("prompt_cache_key", &self.prompt_cache_key)
+            .field("timeout", &self.timeout)
+            .field("retry_on_timeout", &self.retry_on_timeout)
+            .finish()
+    }
 }
 
 impl ChatCompletionRequest {
@@ -44,13 +102,24 @@ impl ChatCompletionRequest {
             stream: None,
             tools: None,
             tool_choice: None,
+            max_completion_tokens: None,
+            stop: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            seed: None,
+            reasoning_effort: None,
+            prompt_cache_key: None,
+            timeout: None,
+            retry_on_timeout: true,
         }
     }
 }
 
 impl_builder_methods!(
     ChatCompletionRequest,
-    response_format: serde_json::Value
+    response_format: serde_json::Value,
+    max_completion_tokens: u32,
+    prompt_cache_key: String
 );
 
 impl<M> TryFrom<ChatCompleteParameters<M>> for ChatCompletionRequest
@@ -68,19 +137,57 @@ where
                 )))?
                 .into(),
             messages: value.messages.into_iter().map(Into::into).collect(),
-            tools: value
-                .tools
-                .map(|tools| tools.into_iter().map(Into::into).collect()),
-            temperature: value.temperature,
-            top_p: None,
+            tools: value.tools.map(canonical_tools).transpose()?,
+            temperature: value.temperature.map(Temperature::get),
+            top_p: value.top_p.map(TopP::get),
             n: None,
             response_format: value.response_format,
             stream: None,
             tool_choice: None,
+            max_completion_tokens: value.max_tokens,
+            stop: value.stop,
+            presence_penalty: value.presence_penalty,
+            frequency_penalty: value.frequency_penalty,
+            seed: value.seed,
+            reasoning_effort: value
+                .reasoning_effort
+                .filter(|_| supports_reasoning_effort(&value.model))
+                .map(|effort| effort.as_ref().to_owned()),
+            prompt_cache_key: value.cache_key,
+            timeout: value.timeout,
+            retry_on_timeout: true,
         })
     }
 }
 
+/// Tools deduplicated and sorted by name.
+///
+/// Tool loops and agents resend the same tools every turn, often collected
+/// from maps or registries in varying order. Providers cache the prompt
+/// prefix, which starts with the tools, so an unchanged tool set has to
+/// serialise to the same bytes to hit the cache. Identical duplicates are
+/// dropped; two different tools of the same name are rejected here rather
+/// than by the API.
+fn canonical_tools(tools: Vec<GenericFunctionSpec>) -> Result<Vec<ToolSpec>, ArtificialError> {
+    let mut unique: Vec<GenericFunctionSpec> = Vec::with_capacity(tools.len());
+    for tool in tools {
+        match unique.iter().find(|known| known.name == tool.name) {
+            Some(known)
+                if known.description == tool.description && known.parameters == tool.parameters => {
+            }
+            Some(_) => {
+                return Err(ArtificialError::InvalidRequest(format!(
+                    "tool `{}` is declared twice with different specs",
+                    tool.name
+                )));
+            }
+            None => unique.push(tool),
+        }
+    }
+    unique.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(unique.into_iter().map(Into::into).collect())
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct ToolSpec {
@@ -137,6 +244,30 @@ pub enum MessageRole {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Content {
     Text(String),
+    /// Multi-part content, used when a message carries attachments.
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    File { file: FileReference },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct FileReference {
+    pub file_id: String,
+}
+
+impl From<GenericAttachment> for ContentPart {
+    fn from(value: GenericAttachment) -> Self {
+        match value {
+            GenericAttachment::File { file_id } => ContentPart::File {
+                file: FileReference { file_id },
+            },
+        }
+    }
 }
 
 impl serde::Serialize for Content {
@@ -152,6 +283,7 @@ impl serde::Serialize for Content {
                     serializer.serialize_str(text)
                 }
             }
+            Content::Parts(ref parts) => parts.serialize(serializer),
         }
     }
 }
@@ -190,6 +322,13 @@ impl<'de> Deserialize<'de> for Content {
             {
                 Ok(Content::Text(String::new()))
             }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                Vec::deserialize(de::value::SeqAccessDeserializer::new(seq)).map(Content::Parts)
+            }
         }
 
         deserializer.deserialize_any(ContentVisitor)
@@ -212,6 +351,12 @@ pub struct ChatCompletionMessage {
     pub tool_call_id: Option<String>,
 }
 
+impl SystemRole for ChatCompletionMessage {
+    fn is_system(&self) -> bool {
+        self.role == MessageRole::System
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct ChatCompletionMessageForResponse {
     pub role: MessageRole,
@@ -237,6 +382,8 @@ impl From<ChatCompletionMessageForResponse> for GenericMessage {
                 .map(|calls| calls.into_iter().map(Into::into).collect()),
             name: val.name,
             tool_call_id: val.tool_call_id,
+            reasoning: val.reasoning_content.filter(|r| !r.is_empty()),
+            attachments: Vec::new(),
         }
     }
 }
@@ -262,6 +409,25 @@ pub struct ChatCompletionResponse {
     pub system_fingerprint: Option<String>,
 }
 
+impl ChatCompletionResponse {
+    /// Everything but the content, with the finish reason of the first
+    /// choice.
+    pub fn metadata(&self) -> ResponseMetadata {
+        ResponseMetadata {
+            id: self.id.clone(),
+            model: Some(self.model.clone()),
+            system_fingerprint: self.system_fingerprint.clone(),
+            created: Some(self.created),
+            finish_reason: self
+                .choices
+                .first()
+                .and_then(|choice| choice.finish_reason.as_ref())
+                .map(|reason| reason.as_str().to_owned()),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum FinishReason {
@@ -271,6 +437,17 @@ pub enum FinishReason {
     ToolCalls,
 }
 
+impl FinishReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::ToolCalls => "tool_calls",
+        }
+    }
+}
+
 #[allow(non_camel_case_types, dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct FinishDetails {
@@ -305,7 +482,16 @@ impl From<GenericMessage> for ChatCompletionMessage {
     fn from(value: GenericMessage) -> Self {
         Self {
             role: value.role.into(),
-            content: value.content.map(Content::Text),
+            content: match (value.content, value.attachments) {
+                (content, attachments) if attachments.is_empty() => content.map(Content::Text),
+                (content, attachments) => Some(Content::Parts(
+                    content
+                        .map(|text| ContentPart::Text { text })
+                        .into_iter()
+                        .chain(attachments.into_iter().map(Into::into))
+                        .collect(),
+                )),
+            },
             name: value.name,
             tool_calls: value
                 .tool_calls
@@ -314,3 +500,97 @@ impl From<GenericMessage> for ChatCompletionMessage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reasoning_content_reaches_generic_message() {
+        let message: ChatCompletionMessageForResponse = serde_json::from_str(
+            r#"{"role":"assistant","content":"42","reasoning_content":"6 times 7"}"#,
+        )
+        .unwrap();
+
+        let generic: GenericMessage = message.into();
+        assert_eq!(generic.content.as_deref(), Some("42"));
+        assert_eq!(generic.reasoning.as_deref(), Some("6 times 7"));
+    }
+
+    #[test]
+    fn cache_key_becomes_prompt_cache_key() {
+        let params = ChatCompleteParameters::new(
+            vec![GenericMessage::new("be brief".into(), GenericRole::System)],
+            artificial_core::model::Model::Custom("gpt-4o-mini"),
+        )
+        .with_cache_key("support-bot");
+
+        let request = ChatCompletionRequest::try_from(params).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["prompt_cache_key"], "support-bot");
+        assert!(json.get("max_completion_tokens").is_none());
+    }
+
+    #[test]
+    fn tools_are_deduplicated_and_ordered_by_name() {
+        let tool = |name: &str, description: &str| GenericFunctionSpec {
+            name: name.into(),
+            description: description.into(),
+            parameters: serde_json::json!({ "type": "object" }),
+        };
+        let request = |tools| {
+            let params = ChatCompleteParameters::new(
+                vec![GenericMessage::new("hi".into(), GenericRole::User)],
+                artificial_core::model::Model::Custom("gpt-4o-mini"),
+            )
+            .with_tools(tools);
+            ChatCompletionRequest::try_from(params)
+        };
+
+        let first = request(vec![
+            tool("weather", "Current weather"),
+            tool("calendar", "Upcoming events"),
+            tool("weather", "Current weather"),
+        ])
+        .unwrap();
+        let second = request(vec![
+            tool("calendar", "Upcoming events"),
+            tool("weather", "Current weather"),
+        ])
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&first.tools).unwrap(),
+            serde_json::to_string(&second.tools).unwrap()
+        );
+        assert_eq!(first.tools.unwrap()[0].function.name, "calendar");
+
+        let conflicting = request(vec![
+            tool("weather", "Current weather"),
+            tool("weather", "Forecast"),
+        ]);
+        assert!(matches!(
+            conflicting,
+            Err(ArtificialError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn attachments_become_content_parts() {
+        let message: ChatCompletionMessage =
+            GenericMessage::new("What is the notice period?".into(), GenericRole::User)
+                .with_file("file-abc")
+                .into();
+
+        assert_eq!(
+            serde_json::to_value(&message.content).unwrap(),
+            serde_json::json!([
+                { "type": "text", "text": "What is the notice period?" },
+                { "type": "file", "file": { "file_id": "file-abc" } }
+            ])
+        );
+
+        let json = serde_json::to_string(&message).unwrap();
+        let read_back: ChatCompletionMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(read_back.content, message.content);
+    }
+}
@@ -10,6 +10,10 @@ pub struct ChatCompletionMessageDelta {
     pub role: Option<MessageRole>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    /// Chain-of-thought text of reasoning models served through
+    /// OpenAI-compatible APIs (DeepSeek, xAI).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCallDelta>>,
 }
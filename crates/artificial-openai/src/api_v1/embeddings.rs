@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Request body of `POST /v1/embeddings`.
+#[derive(Debug, Serialize, Clone)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+/// Response of `POST /v1/embeddings`.
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingResponse {
+    pub data: Vec<EmbeddingObject>,
+    pub usage: EmbeddingUsage,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingObject {
+    /// Position of the input this vector belongs to.
+    pub index: usize,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: i64,
+    pub total_tokens: i64,
+}
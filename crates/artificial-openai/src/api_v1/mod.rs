@@ -1,9 +1,17 @@
 mod audio_transcription;
+mod batches;
 mod chat_completion;
 mod chat_completion_stream;
 mod common;
+mod embeddings;
+mod models;
+mod moderations;
 mod tools;
 
 pub use audio_transcription::*;
+pub use batches::*;
 pub use chat_completion::*;
 pub use chat_completion_stream::*;
+pub use embeddings::*;
+pub use models::*;
+pub use moderations::*;
@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+/// Response of `GET /v1/models`.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ModelListResponse {
+    pub object: String,
+    pub data: Vec<ModelObject>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ModelObject {
+    pub id: String,
+    #[serde(default)]
+    pub owned_by: Option<String>,
+}
@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+use artificial_core::provider::ModerationResult;
+use serde::{Deserialize, Serialize};
+
+/// Request body of `POST /v1/moderations`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ModerationRequest {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+/// Response of `POST /v1/moderations`.
+#[derive(Debug, Deserialize)]
+pub struct ModerationResponse {
+    pub results: Vec<ModerationObject>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationObject {
+    pub flagged: bool,
+    #[serde(default)]
+    pub categories: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub category_scores: BTreeMap<String, f64>,
+}
+
+impl From<ModerationObject> for ModerationResult {
+    fn from(value: ModerationObject) -> Self {
+        Self {
+            flagged: value.flagged,
+            categories: value
+                .categories
+                .into_iter()
+                .filter_map(|(category, hit)| hit.then_some(category))
+                .collect(),
+            scores: value.category_scores,
+        }
+    }
+}
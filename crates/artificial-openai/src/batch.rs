@@ -0,0 +1,322 @@
+//! Offline execution through the OpenAI Batch API.
+//!
+//! Batches run within 24 hours at half the price of regular requests, which
+//! makes them the right tool for nightly jobs over thousands of documents.
+//! [`BatchExecutor`] collects [`PromptTemplate`] executions, uploads them as
+//! a JSONL file, polls until the batch is done and parses every answer into
+//! the template's output, keyed by the id given to [`BatchExecutor::push`]:
+//!
+//! ```rust,no_run
+//! use artificial_core::{generic::*, model::*, template::*};
+//! use artificial_openai::OpenAiAdapterBuilder;
+//!
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct Invoice { total_cents: u64 }
+//!
+//! struct ExtractInvoice(String);
+//! impl IntoPrompt for ExtractInvoice {
+//!     type Message = GenericMessage;
+//!     fn into_prompt(self) -> Vec<GenericMessage> {
+//!         vec![GenericMessage::new(self.0, GenericRole::User)]
+//!     }
+//! }
+//! impl PromptTemplate for ExtractInvoice {
+//!     type Output = Invoice;
+//!     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+//! }
+//!
+//! # async fn run(documents: Vec<(String, String)>) -> artificial_core::error::Result<()> {
+//! let adapter = OpenAiAdapterBuilder::new_from_env().build()?;
+//! let mut batch = adapter.batch::<Invoice>();
+//! for (id, text) in documents {
+//!     batch.push(id, ExtractInvoice(text))?;
+//! }
+//! for (id, result) in batch.run().await? {
+//!     match result {
+//!         Ok(response) => println!("{id}: {}", response.content.expect_finished().total_cents),
+//!         Err(err) => eprintln!("{id}: {err}"),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A batch outlives the process that submitted it. Keep the id returned by
+//! [`BatchExecutor::submit`] and pick up the results later with
+//! [`BatchExecutor::wait`] and [`BatchExecutor::results`] on a fresh
+//! executor of the same output type.
+//!
+//! Batched requests are answered once: [`artificial_core::provider::ExecutionPolicy`]
+//! repairs and retries do not apply, and postprocessing runs per template as
+//! usual.
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    sync::Arc,
+    time::Duration,
+};
+
+use artificial_core::{
+    error::{ArtificialError, Result},
+    generic::{GenericChatCompletionResponse, ResponseContent},
+    schema_util::parse_output,
+    template::PromptTemplate,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+pub use crate::api_v1::{BatchObject, BatchRequestCounts, BatchStatus};
+use crate::{
+    OpenAiAdapter,
+    api_v1::{
+        BatchInputLine, BatchOutputLine, ChatCompletionMessage, ChatCompletionRequest,
+        CreateBatchRequest,
+    },
+    client::OpenAiClient,
+    error::OpenAiError,
+    provider_impl_prompt::{first_choice_content, structured_request},
+};
+
+/// Results of a batch by the id each request was pushed with.
+pub type BatchResults<T> = BTreeMap<String, Result<GenericChatCompletionResponse<T>>>;
+
+const ENDPOINT: &str = "/v1/chat/completions";
+const COMPLETION_WINDOW: &str = "24h";
+
+struct BatchEntry<T> {
+    custom_id: String,
+    request: ChatCompletionRequest,
+    postprocess: fn(T) -> T,
+}
+
+/// Collects prompt executions with output `T` and runs them as one batch.
+pub struct BatchExecutor<T> {
+    client: Arc<OpenAiClient>,
+    entries: Vec<BatchEntry<T>>,
+    /// Position in `entries` by custom id.
+    index: HashMap<String, usize>,
+    poll_interval: Duration,
+    _output: PhantomData<fn() -> T>,
+}
+
+impl OpenAiAdapter {
+    /// Start a batch of prompts answering with `T`.
+    pub fn batch<T>(&self) -> BatchExecutor<T> {
+        BatchExecutor {
+            client: Arc::clone(&self.client),
+            entries: Vec::new(),
+            index: HashMap::new(),
+            poll_interval: Duration::from_secs(30),
+            _output: PhantomData,
+        }
+    }
+}
+
+impl<T> BatchExecutor<T>
+where
+    T: JsonSchema + DeserializeOwned + 'static,
+{
+    /// How often [`Self::wait`] asks for the batch status. Default 30s.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Add `prompt` under `custom_id`, which must be unique in the batch.
+    pub fn push<P>(&mut self, custom_id: impl Into<String>, prompt: P) -> Result<&mut Self>
+    where
+        P: PromptTemplate<Output = T>,
+        P::Message: Into<ChatCompletionMessage>,
+    {
+        let custom_id = custom_id.into();
+        if self.index.contains_key(&custom_id) {
+            return Err(ArtificialError::InvalidRequest(format!(
+                "duplicate batch request id `{custom_id}`"
+            )));
+        }
+
+        let messages = prompt.into_prompt().into_iter().map(Into::into).collect();
+        let request = structured_request::<T>(messages, &P::MODEL, P::cache_key(), &P::params())?;
+        self.index.insert(custom_id.clone(), self.entries.len());
+        self.entries.push(BatchEntry {
+            custom_id,
+            request,
+            postprocess: P::postprocess,
+        });
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The batch input file: one request per line.
+    pub fn to_jsonl(&self) -> Result<Vec<u8>> {
+        let mut jsonl = Vec::new();
+        for entry in &self.entries {
+            serde_json::to_writer(
+                &mut jsonl,
+                &BatchInputLine {
+                    custom_id: &entry.custom_id,
+                    method: "POST",
+                    url: ENDPOINT,
+                    body: &entry.request,
+                },
+            )?;
+            jsonl.push(b'\n');
+        }
+        Ok(jsonl)
+    }
+
+    /// Upload the requests and start the batch.
+    pub async fn submit(&self) -> Result<BatchObject> {
+        if self.entries.is_empty() {
+            return Err(ArtificialError::InvalidRequest("batch is empty".into()));
+        }
+        let file = self
+            .client
+            .upload_file(self.to_jsonl()?, "batch.jsonl", "batch")
+            .await?;
+        Ok(self
+            .client
+            .create_batch(CreateBatchRequest {
+                input_file_id: file.id,
+                endpoint: ENDPOINT.into(),
+                completion_window: COMPLETION_WINDOW.into(),
+            })
+            .await?)
+    }
+
+    /// Poll until the batch with `batch_id` is completed, failed, expired
+    /// or cancelled.
+    pub async fn wait(&self, batch_id: &str) -> Result<BatchObject> {
+        loop {
+            let batch = self.client.retrieve_batch(batch_id).await?;
+            if batch.status.is_terminal() {
+                return Ok(batch);
+            }
+            self.client.sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Download and parse the answers of a finished `batch`.
+    ///
+    /// Expired and cancelled batches yield what was done; requests pushed to
+    /// this executor without an answer are reported as errors. An output
+    /// line that cannot be decoded fails only the request it belongs to.
+    pub async fn results(&self, batch: &BatchObject) -> Result<BatchResults<T>> {
+        if batch.status == BatchStatus::Failed {
+            return Err(ArtificialError::Invalid(format!(
+                "batch {} failed: {}",
+                batch.id,
+                batch.errors.clone().unwrap_or_default()
+            )));
+        }
+
+        let mut results = BTreeMap::new();
+        for file_id in [&batch.output_file_id, &batch.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            let content = self.client.file_content(file_id).await?;
+            for line in content.split(|b| *b == b'\n') {
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                let line: BatchOutputLine = match serde_json::from_slice(line) {
+                    Ok(line) => line,
+                    Err(err) => {
+                        // Lines without a readable id end up as missing
+                        // results below.
+                        if let Some(custom_id) = custom_id_of(line) {
+                            results.insert(custom_id, Err(err.into()));
+                        }
+                        continue;
+                    }
+                };
+                let postprocess = self
+                    .index
+                    .get(&line.custom_id)
+                    .map_or(identity::<T> as fn(T) -> T, |&i| {
+                        self.entries[i].postprocess
+                    });
+                results.insert(line.custom_id.clone(), parse_line(line, postprocess));
+            }
+        }
+
+        for entry in &self.entries {
+            if !results.contains_key(&entry.custom_id) {
+                results.insert(
+                    entry.custom_id.clone(),
+                    Err(ArtificialError::Other(format!(
+                        "no result for `{}`, batch {} ended as {:?}",
+                        entry.custom_id, batch.id, batch.status
+                    ))),
+                );
+            }
+        }
+        Ok(results)
+    }
+
+    /// [`Self::submit`], [`Self::wait`] and [`Self::results`] in one go.
+    pub async fn run(self) -> Result<BatchResults<T>> {
+        let batch = self.submit().await?;
+        let batch = self.wait(&batch.id).await?;
+        self.results(&batch).await
+    }
+}
+
+fn identity<T>(value: T) -> T {
+    value
+}
+
+/// The `custom_id` of an output line that does not decode as a whole.
+fn custom_id_of(line: &[u8]) -> Option<String> {
+    #[derive(serde::Deserialize)]
+    struct Id {
+        custom_id: String,
+    }
+    serde_json::from_slice::<Id>(line)
+        .ok()
+        .map(|id| id.custom_id)
+}
+
+fn parse_line<T: JsonSchema + DeserializeOwned>(
+    line: BatchOutputLine,
+    postprocess: fn(T) -> T,
+) -> Result<GenericChatCompletionResponse<T>> {
+    let response = match (line.response, line.error) {
+        (Some(response), _) => response,
+        (None, error) => {
+            let error = error.unwrap_or(crate::api_v1::BatchOutputError {
+                code: None,
+                message: None,
+            });
+            return Err(OpenAiError::Format(format!(
+                "batch request failed: {}: {}",
+                error.code.unwrap_or_default(),
+                error.message.unwrap_or_default()
+            ))
+            .into());
+        }
+    };
+
+    let status = response.status_code;
+    let completion = response.completion().map_err(|body| OpenAiError::Api {
+        status: reqwest::StatusCode::from_u16(status)
+            .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR),
+        body: body.to_string(),
+    })?;
+    let (content, usage, metadata) = first_choice_content(completion)?;
+    let output = parse_output::<T>(&content)?;
+
+    Ok(GenericChatCompletionResponse {
+        content: ResponseContent::Finished(postprocess(output)),
+        usage: Some(usage),
+        metadata,
+    })
+}
@@ -4,18 +4,25 @@ use futures_core::Stream;
 use futures_util::StreamExt;
 use reqwest::{
     Client as HttpClient,
-    header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue},
+    header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue},
+};
+use serde::Serialize;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
-use std::time::Duration;
 
 use artificial_core::provider::{TranscriptionRequest, TranscriptionResult};
 
 use crate::{
     api_v1::{
-        AudioTranscriptionResponse, ChatCompletionChunkResponse, ChatCompletionRequest,
-        ChatCompletionResponse,
+        AudioTranscriptionResponse, BatchObject, ChatCompletionChunkResponse,
+        ChatCompletionRequest, ChatCompletionResponse, CreateBatchRequest, EmbeddingRequest,
+        EmbeddingResponse, FileObject, ModelListResponse, ModerationRequest, ModerationResponse,
     },
     error::{OpenAiError, OpenAiRateLimitHeaders},
+    rate_limit::RateLimiter,
+    sleep::Sleeper,
 };
 
 fn parse_retry_after_seconds(headers: &reqwest::header::HeaderMap) -> Duration {
@@ -42,6 +49,17 @@ fn header_string(headers: &reqwest::header::HeaderMap, name: &str) -> Option<Str
         .map(|s| s.to_string())
 }
 
+fn parse_rate_limit_headers(headers: &reqwest::header::HeaderMap) -> OpenAiRateLimitHeaders {
+    OpenAiRateLimitHeaders {
+        limit_requests: header_u32(headers, "x-ratelimit-limit-requests"),
+        remaining_requests: header_u32(headers, "x-ratelimit-remaining-requests"),
+        reset_requests: header_string(headers, "x-ratelimit-reset-requests"),
+        limit_tokens: header_u32(headers, "x-ratelimit-limit-tokens"),
+        remaining_tokens: header_u32(headers, "x-ratelimit-remaining-tokens"),
+        reset_tokens: header_string(headers, "x-ratelimit-reset-tokens"),
+    }
+}
+
 fn extract_rate_limit_info(
     headers: &reqwest::header::HeaderMap,
 ) -> (Option<Duration>, Option<String>, OpenAiRateLimitHeaders) {
@@ -50,14 +68,7 @@ fn extract_rate_limit_info(
         if d.as_secs() > 0 { Some(d) } else { None }
     };
 
-    let info = OpenAiRateLimitHeaders {
-        limit_requests: header_u32(headers, "x-ratelimit-limit-requests"),
-        remaining_requests: header_u32(headers, "x-ratelimit-remaining-requests"),
-        reset_requests: header_string(headers, "x-ratelimit-reset-requests"),
-        limit_tokens: header_u32(headers, "x-ratelimit-limit-tokens"),
-        remaining_tokens: header_u32(headers, "x-ratelimit-remaining-tokens"),
-        reset_tokens: header_string(headers, "x-ratelimit-reset-tokens"),
-    };
+    let info = parse_rate_limit_headers(headers);
 
     // Prefer request reset, fall back to token reset.
     let reset_at = info
@@ -106,6 +117,8 @@ pub struct RetryPolicy {
     pub base_delay: Duration,
     pub max_delay: Duration,
     pub respect_retry_after: bool,
+    /// Awaited between attempts, see [`Sleeper`].
+    pub sleeper: Sleeper,
 }
 
 impl Default for RetryPolicy {
@@ -115,6 +128,7 @@ impl Default for RetryPolicy {
             base_delay: Duration::from_millis(500),
             max_delay: Duration::from_secs(30),
             respect_retry_after: true,
+            sleeper: Sleeper::default(),
         }
     }
 }
@@ -153,8 +167,50 @@ impl Default for HttpTimeoutConfig {
     }
 }
 
+/// The rate-limit headers of the most recent response, see
+/// [`crate::OpenAiAdapter::rate_limit_snapshot`].
+#[derive(Debug, Clone)]
+pub struct RateLimitSnapshot {
+    pub headers: OpenAiRateLimitHeaders,
+    /// When the response carrying the headers arrived.
+    pub observed_at: Instant,
+}
+
+impl RateLimitSnapshot {
+    /// Time since the headers were received; remaining counts recover
+    /// while it grows.
+    pub fn age(&self) -> Duration {
+        self.observed_at.elapsed()
+    }
+
+    /// Fraction of the request limit still available, `0.0..=1.0`.
+    pub fn request_headroom(&self) -> Option<f32> {
+        headroom(
+            self.headers.remaining_requests?,
+            self.headers.limit_requests?,
+        )
+    }
+
+    /// Fraction of the token limit still available, `0.0..=1.0`.
+    pub fn token_headroom(&self) -> Option<f32> {
+        headroom(self.headers.remaining_tokens?, self.headers.limit_tokens?)
+    }
+}
+
+fn headroom(remaining: u32, limit: u32) -> Option<f32> {
+    (limit > 0).then(|| (remaining as f32 / limit as f32).min(1.0))
+}
+
 const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 
+/// Azure OpenAI routes requests by deployment instead of by model and
+/// versions its API through a query parameter.
+#[derive(Clone, Debug)]
+struct AzureDeployment {
+    deployment: String,
+    api_version: String,
+}
+
 /// Minimal HTTP client for OpenAI’s *chat/completions* endpoint.
 ///
 /// * Non-streaming only (one request ▶ one response).
@@ -168,6 +224,10 @@ pub struct OpenAiClient {
     base: String,
     retry: RetryPolicy,
     timeouts: HttpTimeoutConfig,
+    azure: Option<AzureDeployment>,
+    /// Shared by clones, so the adapter sees what every request observed.
+    rate_limits: Arc<Mutex<Option<RateLimitSnapshot>>>,
+    limiter: Option<RateLimiter>,
 }
 
 impl OpenAiClient {
@@ -211,30 +271,144 @@ impl OpenAiClient {
             base: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_owned()),
             retry: RetryPolicy::default(),
             timeouts,
+            azure: None,
+            rate_limits: Arc::default(),
+            limiter: None,
         }
     }
 
+    /// Point the client at another OpenAI-compatible endpoint.
+    pub(crate) fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base = base_url.into().trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Send requests to an Azure OpenAI resource: URLs take the form
+    /// `{endpoint}/openai/deployments/{deployment}/…?api-version=…` and the
+    /// key travels in the `api-key` header.
+    pub(crate) fn with_azure(
+        mut self,
+        endpoint: impl Into<String>,
+        deployment: impl Into<String>,
+        api_version: impl Into<String>,
+    ) -> Self {
+        self.base = format!("{}/openai", endpoint.into().trim_end_matches('/'));
+        self.azure = Some(AzureDeployment {
+            deployment: deployment.into(),
+            api_version: api_version.into(),
+        });
+        self
+    }
+
+    /// URL of the API resource at `path` (e.g. `chat/completions`).
+    fn endpoint(&self, path: &str) -> String {
+        match &self.azure {
+            None => format!("{}/{path}", self.base),
+            // Models, files and batches are per resource, not per deployment.
+            Some(azure)
+                if path == "models" || path.starts_with("files") || path.starts_with("batches") =>
+            {
+                format!("{}/{path}?api-version={}", self.base, azure.api_version)
+            }
+            Some(azure) => format!(
+                "{}/deployments/{}/{path}?api-version={}",
+                self.base, azure.deployment, azure.api_version
+            ),
+        }
+    }
+
+    fn auth_header(&self) -> (HeaderName, HeaderValue) {
+        match self.azure {
+            None => (
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", self.api_key)).unwrap(),
+            ),
+            Some(_) => (
+                HeaderName::from_static("api-key"),
+                HeaderValue::from_str(&self.api_key).unwrap(),
+            ),
+        }
+    }
+
+    /// The latest rate-limit headers received with this key, if any.
+    pub fn rate_limit_snapshot(&self) -> Option<RateLimitSnapshot> {
+        self.rate_limits
+            .lock()
+            .expect("rate limit snapshot poisoned")
+            .clone()
+    }
+
+    /// Remember the rate-limit headers of `headers`; responses without any
+    /// (e.g. from compatible servers) keep the previous snapshot.
+    fn observe_rate_limits(&self, headers: &HeaderMap) {
+        let parsed = parse_rate_limit_headers(headers);
+        if parsed.is_empty() {
+            return;
+        }
+        if let Some(limiter) = &self.limiter {
+            limiter.observe(&parsed);
+        }
+        *self
+            .rate_limits
+            .lock()
+            .expect("rate limit snapshot poisoned") = Some(RateLimitSnapshot {
+            headers: parsed,
+            observed_at: Instant::now(),
+        });
+    }
+
     /// Allow callers to override the default retry policy.
     pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
         self.retry = retry;
         self
     }
 
+    /// Delay requests proactively to stay within the rate limits.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    // Internal: wait until the rate limiter admits a body of `body_bytes`.
+    async fn throttle(&self, body_bytes: usize) {
+        let Some(limiter) = &self.limiter else {
+            return;
+        };
+        let delay = limiter.reserve(body_bytes);
+        if !delay.is_zero() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                delay_ms = delay.as_millis() as u64,
+                "delaying request to stay within rate limits"
+            );
+            self.retry.sleeper.sleep(delay).await;
+        }
+    }
+
     // Internal: send POST with retry/backoff handling.
-    async fn post_json_with_retry(
+    async fn post_json_with_retry<R: Serialize + ?Sized>(
         &self,
         url: String,
         headers: HeaderMap,
-        request: &ChatCompletionRequest,
+        request: &R,
         request_timeout: Option<Duration>,
+        retry_on_timeout: bool,
     ) -> Result<reqwest::Response, OpenAiError> {
+        // Serialized once: the same bytes are sent on every attempt and
+        // measured by the rate limiter.
+        let body = bytes::Bytes::from(serde_json::to_vec(request)?);
+        let mut headers = headers;
+        headers
+            .entry(CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static("application/json"));
         let mut attempt: u32 = 0;
         loop {
+            self.throttle(body.len()).await;
             let mut req = self
                 .http
                 .post(url.clone())
                 .headers(headers.clone())
-                .json(request);
+                .body(body.clone());
             if let Some(timeout) = request_timeout {
                 req = req.timeout(timeout);
             }
@@ -242,6 +416,7 @@ impl OpenAiClient {
 
             match res {
                 Ok(resp) => {
+                    self.observe_rate_limits(resp.headers());
                     let status = resp.status();
                     if status.is_success() {
                         #[cfg(feature = "tracing")]
@@ -275,9 +450,10 @@ impl OpenAiClient {
                             );
                             log_rate_limit_tight(resp.headers(), "retrying");
                         }
-                        // Blocking sleep to avoid introducing a new async runtime dependency.
-                        std::thread::sleep(delay);
+                        self.retry.sleeper.sleep(delay).await;
                         attempt += 1;
+                        #[cfg(feature = "tracing")]
+                        tracing::Span::current().record("artificial.retries", attempt);
                         continue;
                     } else {
                         let status = resp.status();
@@ -310,9 +486,11 @@ impl OpenAiClient {
                 }
                 Err(err) => {
                     // Retry on transport errors up to max_retries.
-                    if attempt < self.retry.max_retries
-                        && (err.is_timeout() || err.is_connect() || !err.is_status())
-                    {
+                    let transient = match err.is_timeout() {
+                        true => retry_on_timeout,
+                        false => err.is_connect() || !err.is_status(),
+                    };
+                    if attempt < self.retry.max_retries && transient {
                         let delay = self.retry.backoff_for(attempt);
                         #[cfg(feature = "tracing")]
                         {
@@ -322,8 +500,10 @@ impl OpenAiClient {
                                 "retrying after transport error"
                             );
                         }
-                        std::thread::sleep(delay);
+                        self.retry.sleeper.sleep(delay).await;
                         attempt += 1;
+                        #[cfg(feature = "tracing")]
+                        tracing::Span::current().record("artificial.retries", attempt);
                         continue;
                     } else {
                         return Err(OpenAiError::Http(err));
@@ -341,14 +521,18 @@ impl OpenAiClient {
         // Build headers once.
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.api_key)).unwrap(),
-        );
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
 
-        let url = format!("{}/chat/completions", self.base);
+        let url = self.endpoint("chat/completions");
         let resp = self
-            .post_json_with_retry(url, headers, &request, self.timeouts.request_timeout)
+            .post_json_with_retry(
+                url,
+                headers,
+                &request,
+                request.timeout.or(self.timeouts.request_timeout),
+                request.retry_on_timeout,
+            )
             .await?;
 
         let bytes = resp.bytes().await?;
@@ -369,25 +553,36 @@ impl OpenAiClient {
         // 2) headers (incl. SSE accept)
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.api_key)).unwrap(),
-        );
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
         headers.insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
 
-        let url = format!("{}/chat/completions", self.base);
+        let url = self.endpoint("chat/completions");
 
         // 3) async stream wrapper
         try_stream! {
             let resp = self
-                .post_json_with_retry(url, headers, &request, self.timeouts.stream_timeout)
+                .post_json_with_retry(
+                    url,
+                    headers,
+                    &request,
+                    request.timeout.or(self.timeouts.stream_timeout),
+                    request.retry_on_timeout,
+                )
                 .await?;
 
             let mut bytes_stream = resp.bytes_stream();
             let mut buf = Vec::new();
+            let mut bytes_received: u64 = 0;
 
             while let Some(chunk) = bytes_stream.next().await {
-                let chunk = chunk?;
+                // Errors while reading the body mean the connection went away
+                // after the provider accepted the request (reset, GOAWAY, …).
+                let chunk = chunk.map_err(|source| OpenAiError::StreamDisconnected {
+                    bytes_received,
+                    source,
+                })?;
+                bytes_received += chunk.len() as u64;
                 buf.extend_from_slice(&chunk);
 
                 while let Some(pos) = buf.windows(2).position(|w| w == b"\n\n") {
@@ -406,6 +601,181 @@ impl OpenAiClient {
         }
     }
 
+    /// Embed `request.input` via `POST /embeddings`.
+    ///
+    /// The API accepts at most 2048 texts per call; the
+    /// [`artificial_core::provider::EmbeddingProvider`] implementation of
+    /// [`crate::OpenAiAdapter`] splits larger inputs.
+    pub async fn embeddings(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, OpenAiError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
+
+        let url = self.endpoint("embeddings");
+        let resp = self
+            .post_json_with_retry(url, headers, &request, self.timeouts.request_timeout, true)
+            .await?;
+
+        let bytes = resp.bytes().await?;
+        let parsed: EmbeddingResponse = serde_json::from_slice(&bytes)?;
+        Ok(parsed)
+    }
+
+    /// Classify `request.input` against OpenAI's usage policies via
+    /// `POST /moderations`.
+    pub async fn moderations(
+        &self,
+        request: ModerationRequest,
+    ) -> Result<ModerationResponse, OpenAiError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
+
+        let url = self.endpoint("moderations");
+        let resp = self
+            .post_json_with_retry(url, headers, &request, self.timeouts.request_timeout, true)
+            .await?;
+
+        let bytes = resp.bytes().await?;
+        let parsed: ModerationResponse = serde_json::from_slice(&bytes)?;
+        Ok(parsed)
+    }
+
+    /// Upload `bytes` as a file for `purpose` (e.g. `"batch"`) via
+    /// `POST /files`.
+    pub async fn upload_file(
+        &self,
+        bytes: Vec<u8>,
+        filename: &str,
+        purpose: &str,
+    ) -> Result<FileObject, OpenAiError> {
+        use reqwest::multipart::{Form, Part};
+        let mut headers = HeaderMap::new();
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
+
+        let part = Part::bytes(bytes).file_name(filename.to_owned());
+        let form = Form::new()
+            .text("purpose", purpose.to_owned())
+            .part("file", part);
+
+        let url = self.endpoint("files");
+        let mut req = self.http.post(url).headers(headers).multipart(form);
+        if let Some(timeout) = self.timeouts.request_timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = self.error_for_status(req.send().await?).await?;
+
+        let bytes = resp.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Raw content of a file via `GET /files/{id}/content`.
+    pub async fn file_content(&self, file_id: &str) -> Result<Vec<u8>, OpenAiError> {
+        let resp = self.get(&format!("files/{file_id}/content")).await?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    /// Start a batch via `POST /batches`.
+    pub async fn create_batch(
+        &self,
+        request: CreateBatchRequest,
+    ) -> Result<BatchObject, OpenAiError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
+
+        let url = self.endpoint("batches");
+        let resp = self
+            .post_json_with_retry(url, headers, &request, self.timeouts.request_timeout, true)
+            .await?;
+
+        let bytes = resp.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Current state of a batch via `GET /batches/{id}`.
+    pub async fn retrieve_batch(&self, batch_id: &str) -> Result<BatchObject, OpenAiError> {
+        let resp = self.get(&format!("batches/{batch_id}")).await?;
+        let bytes = resp.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Wait for `delay` with the retry policy's [`Sleeper`].
+    pub(crate) async fn sleep(&self, delay: Duration) {
+        self.retry.sleeper.sleep(delay).await;
+    }
+
+    // Internal: authenticated GET without retries.
+    async fn get(&self, path: &str) -> Result<reqwest::Response, OpenAiError> {
+        let mut headers = HeaderMap::new();
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
+
+        let mut req = self.http.get(self.endpoint(path)).headers(headers);
+        if let Some(timeout) = self.timeouts.request_timeout {
+            req = req.timeout(timeout);
+        }
+        self.error_for_status(req.send().await?).await
+    }
+
+    // Internal: record rate limits and turn error statuses into errors.
+    async fn error_for_status(
+        &self,
+        resp: reqwest::Response,
+    ) -> Result<reqwest::Response, OpenAiError> {
+        self.observe_rate_limits(resp.headers());
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let headers_map = resp.headers().clone();
+        let body = resp.text().await.unwrap_or_default();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let (retry_after, reset_at, headers) = extract_rate_limit_info(&headers_map);
+            return Err(OpenAiError::RateLimited {
+                status,
+                body,
+                retry_after,
+                reset_at,
+                headers,
+            });
+        }
+        Err(OpenAiError::Api { status, body })
+    }
+
+    /// List the models available to the API key via `GET /models`.
+    ///
+    /// The call is free of charge, which makes it a good liveness probe.
+    pub async fn list_models(&self) -> Result<ModelListResponse, OpenAiError> {
+        let mut headers = HeaderMap::new();
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
+
+        let url = self.endpoint("models");
+        let mut req = self.http.get(url).headers(headers);
+        if let Some(timeout) = self.timeouts.request_timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req.send().await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(OpenAiError::Api { status, body });
+        }
+
+        let bytes = resp.bytes().await?;
+        let parsed: ModelListResponse = serde_json::from_slice(&bytes)?;
+        Ok(parsed)
+    }
+
     /// Perform an audio transcription via OpenAI `/audio/transcriptions`.
     pub async fn audio_transcription(
         &self,
@@ -422,10 +792,8 @@ impl OpenAiClient {
 
         use reqwest::multipart::{Form, Part};
         let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.api_key)).unwrap(),
-        );
+        let (name, value) = self.auth_header();
+        headers.insert(name, value);
 
         let filename = request.filename.unwrap_or_else(|| "audio.wav".to_string());
         let file_part = Part::bytes(request.audio)
@@ -447,12 +815,13 @@ impl OpenAiClient {
             form = form.text("prompt", prompt);
         }
 
-        let url = format!("{}/audio/transcriptions", self.base);
+        let url = self.endpoint("audio/transcriptions");
         let mut req = self.http.post(url).headers(headers).multipart(form);
         if let Some(timeout) = self.timeouts.request_timeout {
             req = req.timeout(timeout);
         }
         let resp = req.send().await?;
+        self.observe_rate_limits(resp.headers());
 
         if !resp.status().is_success() {
             let status = resp.status();
@@ -590,6 +959,48 @@ mod tests {
         assert_eq!(first.choices.len(), 1);
     }
 
+    #[tokio::test]
+    async fn connection_reset_mid_stream_is_classified() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind tcp listener");
+        let addr = listener.local_addr().expect("listener addr");
+        let frame = format!(
+            "data: {}\n\n",
+            r#"{"id":"x","object":"chat.completion.chunk","created":0,"model":"gpt-4o-mini","choices":[{"index":0,"delta":{"content":"par"},"finish_reason":null}]}"#
+        );
+        let frame_len = frame.len() as u64;
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept connection");
+            let mut req_buf = [0_u8; 8192];
+            let _ = stream.read(&mut req_buf);
+            // Announce far more body than we send, then hang up.
+            let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: 100000\r\n\r\n";
+            stream.write_all(head.as_bytes()).expect("write head");
+            stream.write_all(frame.as_bytes()).expect("write frame");
+            let _ = stream.flush();
+        });
+
+        let client = OpenAiClient::with_http_and_timeouts(
+            "test-key",
+            reqwest::Client::new(),
+            Some(format!("http://{addr}")),
+            HttpTimeoutConfig::default(),
+        );
+
+        let mut stream = Box::pin(client.chat_completion_stream(sample_request()));
+        stream
+            .next()
+            .await
+            .expect("first chunk")
+            .expect("first chunk parses");
+        match stream.next().await {
+            Some(Err(OpenAiError::StreamDisconnected { bytes_received, .. })) => {
+                assert_eq!(bytes_received, frame_len)
+            }
+            other => panic!("unexpected item: {other:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn audio_transcription_parses_text_response() {
         let base_url = run_single_response_server(
@@ -623,6 +1034,57 @@ mod tests {
         assert_eq!(result.duration_seconds, Some(1.25));
     }
 
+    #[tokio::test]
+    async fn list_models_parses_model_ids() {
+        let base_url = run_single_response_server(
+            Duration::from_millis(0),
+            r#"{"object":"list","data":[{"id":"gpt-4o-mini","object":"model","owned_by":"openai"}]}"#.to_string(),
+            "application/json",
+        );
+
+        let client = OpenAiClient::with_http_and_timeouts(
+            "test-key",
+            reqwest::Client::new(),
+            Some(base_url),
+            HttpTimeoutConfig::default(),
+        );
+
+        let models = client.list_models().await.expect("listing should succeed");
+        assert_eq!(models.data.len(), 1);
+        assert_eq!(models.data[0].id, "gpt-4o-mini");
+    }
+
+    #[tokio::test]
+    async fn azure_uses_deployment_urls_and_api_key_header() {
+        use wiremock::{
+            Mock, MockServer, ResponseTemplate,
+            matchers::{header, method, path, query_param},
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/chat-prod/chat/completions"))
+            .and(query_param("api-version", "2024-10-21"))
+            .and(header("api-key", "azure-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"id":"x","object":"chat.completion","created":0,"model":"gpt-4o-mini","choices":[{"index":0,"message":{"role":"assistant","content":"ok"},"finish_reason":"stop","finish_details":null}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2},"system_fingerprint":null}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OpenAiClient::new("azure-key").with_azure(
+            format!("{}/", server.uri()),
+            "chat-prod",
+            "2024-10-21",
+        );
+
+        client
+            .chat_completion(sample_request())
+            .await
+            .expect("azure request should match");
+    }
+
     #[tokio::test]
     async fn audio_transcription_rejects_empty_audio() {
         let client = OpenAiClient::new("test-key");
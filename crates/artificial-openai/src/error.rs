@@ -5,7 +5,7 @@ use reqwest::StatusCode;
 use std::time::Duration;
 
 /// Headers conveying rate limit information returned by OpenAI.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct OpenAiRateLimitHeaders {
     pub limit_requests: Option<u32>,
     pub remaining_requests: Option<u32>,
@@ -15,6 +15,18 @@ pub struct OpenAiRateLimitHeaders {
     pub reset_tokens: Option<String>,
 }
 
+impl OpenAiRateLimitHeaders {
+    /// `true` if the response carried none of the headers.
+    pub fn is_empty(&self) -> bool {
+        self.limit_requests.is_none()
+            && self.remaining_requests.is_none()
+            && self.reset_requests.is_none()
+            && self.limit_tokens.is_none()
+            && self.remaining_tokens.is_none()
+            && self.reset_tokens.is_none()
+    }
+}
+
 /// High-level error type covering every failure mode the client can hit.
 #[derive(Debug, thiserror::Error)]
 pub enum OpenAiError {
@@ -36,6 +48,17 @@ pub enum OpenAiError {
     #[error("OpenAI returned non-success status {status}: {body}")]
     Api { status: StatusCode, body: String },
 
+    /// The connection broke while a streaming response was being read.
+    ///
+    /// Unlike [`Self::Api`] the provider accepted the request; the stream was
+    /// cut by the network (connection reset, HTTP/2 GOAWAY, proxy timeout).
+    #[error("stream disconnected after {bytes_received} bytes: {source}")]
+    StreamDisconnected {
+        bytes_received: u64,
+        #[source]
+        source: reqwest::Error,
+    },
+
     #[error("OpenAI format error: {0}")]
     Format(String),
 
@@ -43,9 +66,41 @@ pub enum OpenAiError {
     Unknown(String),
 }
 
+/// Whether `err` is an HTTP attempt that ran into its timeout.
+pub fn is_timeout(err: &ArtificialError) -> bool {
+    match err.root() {
+        ArtificialError::Backend(source) => matches!(
+            source.downcast_ref::<OpenAiError>(),
+            Some(OpenAiError::Http(err)) if err.is_timeout()
+        ),
+        _ => false,
+    }
+}
+
+/// Whether `err` is a `429` from the OpenAI API, e.g. as the overload check
+/// of [`artificial_core::concurrency::ConcurrencyLimited::with_overload_check`].
+pub fn is_rate_limited(err: &ArtificialError) -> bool {
+    match err.root() {
+        ArtificialError::Backend(source) => matches!(
+            source.downcast_ref::<OpenAiError>(),
+            Some(OpenAiError::RateLimited { .. })
+        ),
+        _ => false,
+    }
+}
+
 impl From<OpenAiError> for ArtificialError {
     fn from(value: OpenAiError) -> Self {
-        ArtificialError::Backend(Box::new(value))
+        match value {
+            OpenAiError::StreamDisconnected {
+                bytes_received,
+                source,
+            } => ArtificialError::StreamDisconnected {
+                bytes_received,
+                source: Box::new(source),
+            },
+            other => ArtificialError::Backend(Box::new(other)),
+        }
     }
 }
 
@@ -0,0 +1,253 @@
+//! Fine-tuning datasets from recorded traffic.
+//!
+//! Expensive agents are good teachers: every tool-calling run a
+//! [`RecordingProvider`](artificial_core::recorder::RecordingProvider) sees is
+//! a worked example of which tool to call with which arguments.
+//! [`FineTuningExporter`] turns those [`Interaction`]s into the JSONL format
+//! of the OpenAI fine-tuning API, including assistant `tool_calls`, `tool`
+//! results and the `tools` that were offered:
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_core::recorder::{Interaction, InteractionOutcome};
+//! use artificial_openai::fine_tuning::FineTuningExporter;
+//!
+//! let interaction = Interaction {
+//!     model: "gpt-4o".into(),
+//!     messages: vec![GenericMessage::new("Hi!".into(), GenericRole::User)],
+//!     tools: None,
+//!     temperature: None,
+//!     response_format: None,
+//!     outcome: InteractionOutcome::Finished {
+//!         message: GenericMessage::new("Hello!".into(), GenericRole::Assistant),
+//!     },
+//!     usage: None,
+//!     latency_ms: 800,
+//!     recorded_at_ms: 0,
+//! };
+//!
+//! let mut exporter = FineTuningExporter::new();
+//! exporter.push(&interaction)?;
+//! let line: serde_json::Value = serde_json::from_slice(&exporter.to_jsonl()?)?;
+//! assert_eq!(
+//!     line,
+//!     serde_json::json!({ "messages": [
+//!         { "role": "user", "content": "Hi!" },
+//!         { "role": "assistant", "content": "Hello!" },
+//!     ] })
+//! );
+//! # Ok::<(), artificial_core::error::ArtificialError>(())
+//! ```
+//!
+//! A tool loop records one interaction per model round-trip, each repeating
+//! the conversation so far. The exporter keeps only the longest of them, so
+//! every run ends up as **one** example. Failed interactions are skipped.
+//! Upload the result with `purpose = "fine-tune"`.
+use std::collections::HashMap;
+
+use artificial_core::{canonical, error::Result, recorder::Interaction};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::api_v1::{ChatCompletionMessage, ToolSpec};
+
+/// One line of a fine-tuning file.
+#[derive(Debug, Clone, Serialize)]
+struct Example {
+    messages: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<ToolSpec>>,
+}
+
+/// Collects recorded interactions as chat fine-tuning examples.
+#[derive(Debug, Clone, Default)]
+pub struct FineTuningExporter {
+    /// In push order; `None` once a longer example continued it.
+    examples: Vec<Option<Example>>,
+    /// Positions of the kept examples by hash of their first message, so a
+    /// push only compares examples of the same conversation.
+    by_start: HashMap<u64, Vec<usize>>,
+    len: usize,
+}
+
+impl FineTuningExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the conversation of `interaction`, followed by its answer.
+    ///
+    /// Returns `false` if the interaction failed or an earlier example
+    /// already continues its conversation.
+    pub fn push(&mut self, interaction: &Interaction) -> Result<bool> {
+        let Some(answer) = interaction.outcome.message() else {
+            return Ok(false);
+        };
+        let messages = interaction
+            .messages
+            .iter()
+            .chain([answer])
+            .map(|message| to_json(ChatCompletionMessage::from(message.clone())))
+            .collect::<Result<Vec<_>>>()?;
+
+        let examples = &mut self.examples;
+        let conversation = self
+            .by_start
+            .entry(canonical::hash(&messages[0])?)
+            .or_default();
+        if conversation
+            .iter()
+            .any(|&i| messages_of(examples, i).starts_with(&messages))
+        {
+            return Ok(false);
+        }
+        conversation.retain(|&i| {
+            let continued = messages.starts_with(messages_of(examples, i));
+            if continued {
+                examples[i] = None;
+                self.len -= 1;
+            }
+            !continued
+        });
+
+        conversation.push(examples.len());
+        examples.push(Some(Example {
+            messages,
+            tools: interaction
+                .tools
+                .clone()
+                .map(|tools| tools.into_iter().map(Into::into).collect()),
+        }));
+        self.len += 1;
+        Ok(true)
+    }
+
+    /// Add every interaction, see [`Self::push`].
+    pub fn extend<'i>(
+        &mut self,
+        interactions: impl IntoIterator<Item = &'i Interaction>,
+    ) -> Result<&mut Self> {
+        for interaction in interactions {
+            self.push(interaction)?;
+        }
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The training file: one example per line.
+    pub fn to_jsonl(&self) -> Result<Vec<u8>> {
+        let mut jsonl = Vec::new();
+        for example in self.examples.iter().flatten() {
+            serde_json::to_writer(&mut jsonl, example)?;
+            jsonl.push(b'\n');
+        }
+        Ok(jsonl)
+    }
+}
+
+/// Messages of the example at `i`, none if it was continued.
+fn messages_of(examples: &[Option<Example>], i: usize) -> &[Value] {
+    examples[i]
+        .as_ref()
+        .map_or(&[], |example| example.messages.as_slice())
+}
+
+/// The message as the API expects it in a training file, without the
+/// `null` fields a request tolerates.
+fn to_json(message: ChatCompletionMessage) -> Result<Value> {
+    let mut value = serde_json::to_value(message)?;
+    if let Value::Object(fields) = &mut value {
+        fields.retain(|_, field| !field.is_null());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use artificial_core::{
+        generic::{
+            GenericFunctionCall, GenericFunctionCallIntent, GenericFunctionSpec, GenericMessage,
+            GenericRole,
+        },
+        recorder::InteractionOutcome,
+    };
+
+    use super::*;
+
+    fn interaction(messages: Vec<GenericMessage>, outcome: InteractionOutcome) -> Interaction {
+        Interaction {
+            model: "gpt-4o".into(),
+            messages,
+            tools: Some(vec![GenericFunctionSpec {
+                name: "weather".into(),
+                description: "Current weather of a city.".into(),
+                parameters: serde_json::json!({ "type": "object" }),
+            }]),
+            temperature: None,
+            response_format: None,
+            outcome,
+            usage: None,
+            latency_ms: 0,
+            recorded_at_ms: 0,
+        }
+    }
+
+    #[test]
+    fn a_tool_loop_becomes_one_example() {
+        let question = GenericMessage::new("Weather in Rome?".into(), GenericRole::User);
+        let mut call = GenericMessage::new(String::new(), GenericRole::Assistant);
+        call.content = None;
+        call.tool_calls = Some(vec![GenericFunctionCallIntent {
+            id: "call-1".into(),
+            function: GenericFunctionCall {
+                name: "weather".into(),
+                arguments: serde_json::json!({ "city": "Rome" }),
+            },
+        }]);
+        let mut result = GenericMessage::new("31°C".into(), GenericRole::Tool);
+        result.tool_call_id = Some("call-1".into());
+        let answer = GenericMessage::new("It is 31°C.".into(), GenericRole::Assistant);
+
+        let step_one = interaction(
+            vec![question.clone()],
+            InteractionOutcome::ToolCalls {
+                message: call.clone(),
+            },
+        );
+        let step_two = interaction(
+            vec![question, call, result],
+            InteractionOutcome::Finished { message: answer },
+        );
+        let failed = interaction(
+            vec![],
+            InteractionOutcome::Failed {
+                error: "timeout".into(),
+            },
+        );
+
+        let mut exporter = FineTuningExporter::new();
+        exporter.extend([&step_one, &step_two, &failed]).unwrap();
+        assert_eq!(exporter.len(), 1);
+        assert!(!exporter.push(&step_one).unwrap());
+
+        let jsonl = exporter.to_jsonl().unwrap();
+        let example: Value = serde_json::from_slice(&jsonl).unwrap();
+        let messages = example["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1]["tool_calls"][0]["function"]["name"], "weather");
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            r#"{"city":"Rome"}"#
+        );
+        assert!(messages[1].get("content").is_none());
+        assert_eq!(messages[2]["tool_call_id"], "call-1");
+        assert_eq!(example["tools"][0]["function"]["name"], "weather");
+    }
+}
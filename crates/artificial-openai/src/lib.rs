@@ -2,11 +2,25 @@ mod adapter;
 mod model_map;
 mod provider_impl_chat;
 mod provider_impl_chat_stream;
+mod provider_impl_embedding;
+mod provider_impl_health;
+mod provider_impl_moderation;
+mod provider_impl_multi_choice;
 mod provider_impl_prompt;
+mod provider_impl_prompt_stream;
 mod provider_impl_transcription;
+mod provider_impl_warm;
 
 pub use adapter::{OpenAiAdapter, OpenAiAdapterBuilder, OpenAiAdapterOptions};
 mod api_v1;
+pub mod batch;
 mod client;
-pub use client::{HttpTimeoutConfig, RetryPolicy};
+pub use client::{HttpTimeoutConfig, RateLimitSnapshot, RetryPolicy};
 pub mod error;
+pub mod fine_tuning;
+mod rate_limit;
+pub use rate_limit::RateLimiter;
+mod sleep;
+pub use sleep::{SleepFuture, Sleeper};
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
@@ -1,4 +1,4 @@
-use artificial_core::model::{Model, OpenAiModel};
+use artificial_core::model::{DeepSeekModel, Model, OpenAiModel, XAiModel};
 
 const GPT5: &str = "gpt-5";
 const GPT5_1: &str = "gpt-5.1";
@@ -24,14 +24,20 @@ const GPT4_O: &str = "gpt-4o";
 const O3: &str = "o3";
 const O3_MINI: &str = "o3-mini";
 const O4_MINI: &str = "o4-mini";
+const TEXT_EMBEDDING_3_SMALL: &str = "text-embedding-3-small";
+const TEXT_EMBEDDING_3_LARGE: &str = "text-embedding-3-large";
+const GROK_4: &str = "grok-4";
+const GROK_3: &str = "grok-3";
+const GROK_3_MINI: &str = "grok-3-mini";
+const DEEPSEEK_CHAT: &str = "deepseek-chat";
+const DEEPSEEK_REASONER: &str = "deepseek-reasoner";
 
 pub(crate) fn map_model(model: &Model) -> Option<&'static str> {
-    if let Model::Custom(custom) = *model {
-        return Some(custom);
-    }
-
-    let Model::OpenAi(openai_model) = model else {
-        return None;
+    let openai_model = match model {
+        Model::OpenAi(openai_model) => openai_model,
+        Model::XAi(xai_model) => return Some(map_xai_model(xai_model)),
+        Model::DeepSeek(deepseek_model) => return Some(map_deepseek_model(deepseek_model)),
+        Model::Custom(custom) => return Some(custom),
     };
 
     match openai_model {
@@ -40,6 +46,8 @@ pub(crate) fn map_model(model: &Model) -> Option<&'static str> {
         OpenAiModel::O3 => Some(O3),
         OpenAiModel::O3Mini => Some(O3_MINI),
         OpenAiModel::O4Mini => Some(O4_MINI),
+        OpenAiModel::TextEmbedding3Small => Some(TEXT_EMBEDDING_3_SMALL),
+        OpenAiModel::TextEmbedding3Large => Some(TEXT_EMBEDDING_3_LARGE),
         OpenAiModel::Gpt5 => Some(GPT5),
         OpenAiModel::Gpt5Nano => Some(GPT5_NANO),
         OpenAiModel::Gpt5Mini => Some(GPT5_MINI),
@@ -61,3 +69,37 @@ pub(crate) fn map_model(model: &Model) -> Option<&'static str> {
         OpenAiModel::Gpt4_1Nano => Some(GPT4_1_NANO),
     }
 }
+
+/// Whether the model accepts `reasoning_effort`. Sending it to other
+/// models is rejected by the API, so the setting is dropped for them.
+/// Custom models are trusted to know what they accept.
+pub(crate) fn supports_reasoning_effort(model: &Model) -> bool {
+    match model {
+        Model::OpenAi(model) => !matches!(
+            model,
+            OpenAiModel::Gpt4o
+                | OpenAiModel::Gpt4oMini
+                | OpenAiModel::Gpt4_1
+                | OpenAiModel::Gpt4_1Mini
+                | OpenAiModel::Gpt4_1Nano
+        ),
+        Model::XAi(model) => matches!(model, XAiModel::Grok3Mini),
+        Model::DeepSeek(_) => false,
+        Model::Custom(_) => true,
+    }
+}
+
+fn map_xai_model(model: &XAiModel) -> &'static str {
+    match model {
+        XAiModel::Grok4 => GROK_4,
+        XAiModel::Grok3 => GROK_3,
+        XAiModel::Grok3Mini => GROK_3_MINI,
+    }
+}
+
+fn map_deepseek_model(model: &DeepSeekModel) -> &'static str {
+    match model {
+        DeepSeekModel::DeepSeekChat => DEEPSEEK_CHAT,
+        DeepSeekModel::DeepSeekReasoner => DEEPSEEK_REASONER,
+    }
+}
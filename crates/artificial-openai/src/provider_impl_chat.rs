@@ -1,13 +1,17 @@
 use std::sync::Arc;
 
 use artificial_core::{
+    error::{ArtificialError, ErrorContext},
     generic::{GenericChatCompletionResponse, GenericUsageReport, ResponseContent},
     provider::{ChatCompleteParameters, ChatCompletionProvider},
 };
 
 use crate::{
     OpenAiAdapter,
-    api_v1::{ChatCompletionMessage, FinishReason},
+    api_v1::{
+        ChatCompletionMessage, ChatCompletionMessageForResponse, ChatCompletionRequest,
+        FinishReason,
+    },
     error::OpenAiError,
 };
 
@@ -33,12 +37,22 @@ impl ChatCompletionProvider for OpenAiAdapter {
         M: Into<Self::Message> + Clone + Send + Sync + 's,
     {
         let client = Arc::clone(&self.client);
+        let cancellation = params.cancellation.clone();
 
-        Box::pin(async move {
-            let request = params.try_into()?;
+        let completion = Box::pin(async move {
+            let request: ChatCompletionRequest = params.try_into()?;
 
-            let mut response = client.chat_completion(request).await?;
+            let mut context = ErrorContext::new().with_model(&request.model);
+            if let Some(fingerprint) = ErrorContext::fingerprint_of(&request.messages) {
+                context = context.with_fingerprint(fingerprint);
+            }
+
+            let mut response = client
+                .chat_completion(request)
+                .await
+                .map_err(|err| ArtificialError::from(err).with_context(context.clone()))?;
 
+            let metadata = response.metadata();
             let usage_report = GenericUsageReport {
                 prompt_tokens: response.usage.prompt_tokens as i64,
                 completion_tokens: response.usage.completion_tokens as i64,
@@ -46,29 +60,65 @@ impl ChatCompletionProvider for OpenAiAdapter {
             };
 
             let Some(first_choice) = response.choices.pop() else {
-                return Err(OpenAiError::Format("response has no choices".into()).into());
+                return Err(ArtificialError::from(OpenAiError::Format(
+                    "response has no choices".into(),
+                ))
+                .with_context(context));
+            };
+
+            // Some OpenAI-compatible servers answer tool calls with `stop`;
+            // the calls are what matters.
+            let wants_tools = match &first_choice.finish_reason {
+                Some(FinishReason::ToolCalls) => true,
+                None | Some(FinishReason::Stop) => has_tool_calls(&first_choice.message),
+                Some(_) => false,
             };
+            if wants_tools {
+                return Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::ToolCalls(first_choice.message.into()),
+                    usage: Some(usage_report),
+                    metadata,
+                });
+            }
 
             match &first_choice.finish_reason {
-                Some(FinishReason::ToolCalls) => {
-                    let response = GenericChatCompletionResponse {
-                        content: ResponseContent::ToolCalls(first_choice.message.into()),
-                        usage: Some(usage_report),
-                    };
-                    Ok(response)
+                None | Some(FinishReason::Stop) if is_empty(&first_choice.message) => {
+                    Err(ArtificialError::EmptyResponse.with_context(context))
                 }
                 None | Some(FinishReason::Stop) => {
                     let response = GenericChatCompletionResponse {
                         content: ResponseContent::Finished(first_choice.message.into()),
                         usage: Some(usage_report),
+                        metadata,
                     };
                     Ok(response)
                 }
-                Some(other) => Err(OpenAiError::Format(format!(
+                Some(other) => Err(ArtificialError::from(OpenAiError::Format(format!(
                     "unhandled finish reason on API: {other:?}"
-                ))
-                .into()),
+                )))
+                .with_context(context)),
             }
-        })
+        });
+
+        match cancellation {
+            Some(token) => Box::pin(token.run(completion)),
+            None => completion,
+        }
     }
 }
+
+fn has_tool_calls(message: &ChatCompletionMessageForResponse) -> bool {
+    message
+        .tool_calls
+        .as_ref()
+        .is_some_and(|calls| !calls.is_empty())
+}
+
+/// No text worth showing and nothing to execute.
+fn is_empty(message: &ChatCompletionMessageForResponse) -> bool {
+    message
+        .content
+        .as_deref()
+        .is_none_or(|content| content.trim().is_empty())
+        && !has_tool_calls(message)
+}
@@ -4,12 +4,13 @@ use crate::OpenAiAdapter;
 use crate::api_v1::ChatCompletionMessage;
 use crate::api_v1::ChatCompletionRequest;
 use crate::api_v1::FinishReason;
-use artificial_core::error::{ArtificialError, Result};
+use artificial_core::error::{ArtificialError, ErrorContext, Result};
 use artificial_core::generic::{GenericFunctionCall, GenericFunctionCallIntent, StreamEvent};
 use artificial_core::provider::StreamingEventsProvider;
 use artificial_core::provider::{ChatCompleteParameters, StreamingChatProvider};
+use artificial_core::stream::JsonPrefixValidator;
 use futures_core::stream::Stream;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 impl StreamingChatProvider for OpenAiAdapter {
     type Message = ChatCompletionMessage;
@@ -24,18 +25,21 @@ impl StreamingChatProvider for OpenAiAdapter {
         M: Into<Self::Message> + Clone + Send + Sync + 's,
     {
         let client = self.client.clone();
+        let cancellation = params.cancellation.clone();
+        #[cfg(feature = "tracing")]
+        let span = artificial_core::telemetry::request_span("chat", &params.model, None);
 
-        Box::pin(async_stream::try_stream! {
+        let stream = Box::pin(async_stream::try_stream! {
         use futures_util::StreamExt;
 
         let request: ChatCompletionRequest = params.try_into()?;
-
+        let context = stream_context(&request);
 
             let stream = client.chat_completion_stream(request);
             futures_util::pin_mut!(stream);
 
             while let Some(chunk) = stream.next().await {
-                let chunk = chunk.map_err(ArtificialError::from)?;
+                let chunk = chunk.map_err(|err| ArtificialError::from(err).with_context(context.clone()))?;
                 for choice in chunk.choices {
                     if let Some(text) = choice.delta.content {
                         yield text;
@@ -43,7 +47,15 @@ impl StreamingChatProvider for OpenAiAdapter {
                 }
             }
 
-        })
+        });
+
+        let stream: Self::Delta<'s> = match cancellation {
+            Some(token) => Box::pin(token.guard(stream)),
+            None => stream,
+        };
+        #[cfg(feature = "tracing")]
+        let stream = Box::pin(artificial_core::telemetry::trace_stream(span, stream));
+        stream
     }
 }
 
@@ -61,26 +73,39 @@ impl StreamingEventsProvider for OpenAiAdapter {
         M: Into<Self::Message> + Clone + Send + Sync + 's,
     {
         let client = self.client.clone();
+        let cancellation = params.cancellation.clone();
+        #[cfg(feature = "tracing")]
+        let span = artificial_core::telemetry::request_span("chat", &params.model, None);
 
-        Box::pin(async_stream::try_stream! {
+        let stream = Box::pin(async_stream::try_stream! {
             use futures_util::StreamExt;
 
             let request: ChatCompletionRequest = params.try_into()?;
+            let context = stream_context(&request);
 
             // Track tool-call argument fragments and first-seen id/name per tool index.
             let mut tool_args: HashMap<usize, String> = HashMap::new();
             let mut tool_seen: HashMap<usize, (Option<String>, Option<String>)> = HashMap::new();
+            // Arguments are checked while they stream so garbage is reported early.
+            let mut tool_json: HashMap<usize, JsonPrefixValidator> = HashMap::new();
+            let mut malformed: HashSet<usize> = HashSet::new();
 
             let stream = client.chat_completion_stream(request);
             futures_util::pin_mut!(stream);
 
             while let Some(chunk) = stream.next().await {
-                let chunk = chunk.map_err(ArtificialError::from)?;
+                let chunk = chunk.map_err(|err| ArtificialError::from(err).with_context(context.clone()))?;
 
                 for choice in chunk.choices {
                     // Process only the first choice to match current non-streaming behavior.
                     if choice.index != 0 { continue; }
 
+                    // Reasoning deltas precede the answer text.
+                    if let Some(delta) = choice.delta.reasoning_content
+                        && !delta.is_empty() {
+                            yield StreamEvent::ReasoningDelta(delta);
+                        }
+
                     // Text deltas
                     if let Some(delta) = choice.delta.content
                         && !delta.is_empty() {
@@ -122,12 +147,25 @@ impl StreamingEventsProvider for OpenAiAdapter {
                                 if let Some(arguments) = func.arguments {
                                     let buf = tool_args.entry(tc.index).or_default();
                                     buf.push_str(&arguments);
+                                    let validator = tool_json.entry(tc.index).or_default();
+                                    let valid = malformed.contains(&tc.index) || validator.push(&arguments);
+                                    let reason = validator.error().map(str::to_owned);
+                                    let buf = buf.clone();
                                     if !arguments.is_empty() {
                                         yield StreamEvent::ToolCallArgumentsDelta {
                                             index: tc.index,
                                             arguments_fragment: arguments,
                                         };
                                     }
+                                    if !valid {
+                                        malformed.insert(tc.index);
+                                        yield StreamEvent::ToolCallMalformed {
+                                            index: tc.index,
+                                            name: entry.1.clone(),
+                                            arguments: buf,
+                                            reason: reason.unwrap_or_default(),
+                                        };
+                                    }
                                 }
                             }
                         }
@@ -139,6 +177,9 @@ impl StreamingEventsProvider for OpenAiAdapter {
                             FinishReason::ToolCalls => {
                                 // Finalize tool calls by parsing accumulated argument buffers.
                                 for (index, buf) in tool_args.iter() {
+                                    if malformed.contains(index) {
+                                        continue;
+                                    }
                                     let (id_opt, name_opt) = tool_seen
                                         .get(index)
                                         .cloned()
@@ -146,7 +187,7 @@ impl StreamingEventsProvider for OpenAiAdapter {
 
                                     let name = name_opt.unwrap_or_else(|| "tool".to_string());
                                     let args_json: serde_json::Value = serde_json::from_str(buf)
-                                        .map_err(|e| ArtificialError::Invalid(format!("invalid tool arguments JSON: {e}")))?;
+                                        .map_err(|e| ArtificialError::Invalid(format!("invalid tool arguments JSON: {e}")).with_context(context.clone()))?;
 
                                     let intent = GenericFunctionCallIntent {
                                         id: id_opt.unwrap_or_else(|| format!("toolcall-{index}")),
@@ -167,6 +208,23 @@ impl StreamingEventsProvider for OpenAiAdapter {
                     }
                 }
             }
-        })
+        });
+
+        let stream: Self::EventStream<'s> = match cancellation {
+            Some(token) => Box::pin(token.guard(stream)),
+            None => stream,
+        };
+        #[cfg(feature = "tracing")]
+        let stream = Box::pin(artificial_core::telemetry::trace_stream(span, stream));
+        stream
+    }
+}
+
+/// Error context for a streaming request: model and message fingerprint.
+fn stream_context(request: &ChatCompletionRequest) -> ErrorContext {
+    let context = ErrorContext::new().with_model(&request.model);
+    match ErrorContext::fingerprint_of(&request.messages) {
+        Some(fingerprint) => context.with_fingerprint(fingerprint),
+        None => context,
     }
 }
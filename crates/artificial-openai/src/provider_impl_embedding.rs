@@ -0,0 +1,66 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use artificial_core::{
+    error::{ArtificialError, ErrorContext, Result},
+    generic::GenericUsageReport,
+    model::Model,
+    provider::{EmbeddingProvider, EmbeddingResponse},
+};
+
+use crate::{OpenAiAdapter, api_v1::EmbeddingRequest, model_map::map_model};
+
+/// Most inputs `/v1/embeddings` accepts in one request.
+const MAX_INPUTS_PER_REQUEST: usize = 2048;
+
+/// Inputs are sent in batches of 2048, one request after the other; the
+/// usage of all batches is summed up.
+impl EmbeddingProvider for OpenAiAdapter {
+    fn embed<'s>(
+        &'s self,
+        texts: Vec<String>,
+        model: Model,
+    ) -> Pin<Box<dyn Future<Output = Result<EmbeddingResponse>> + Send + 's>> {
+        let client = Arc::clone(&self.client);
+
+        Box::pin(async move {
+            let model = map_model(&model).ok_or(ArtificialError::InvalidRequest(format!(
+                "backend does not support selected model: {model:?}"
+            )))?;
+            let context = ErrorContext::new().with_model(model);
+
+            let mut embeddings = Vec::with_capacity(texts.len());
+            let mut usage = GenericUsageReport {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            };
+            for batch in texts.chunks(MAX_INPUTS_PER_REQUEST) {
+                let request = EmbeddingRequest {
+                    model: model.to_owned(),
+                    input: batch.to_vec(),
+                };
+                let mut response = client
+                    .embeddings(request)
+                    .await
+                    .map_err(|err| ArtificialError::from(err).with_context(context.clone()))?;
+
+                if response.data.len() != batch.len() {
+                    return Err(ArtificialError::Invalid(format!(
+                        "expected {} embeddings, got {}",
+                        batch.len(),
+                        response.data.len()
+                    )));
+                }
+                response.data.sort_by_key(|object| object.index);
+                embeddings.extend(response.data.into_iter().map(|object| object.embedding));
+                usage.prompt_tokens += response.usage.prompt_tokens;
+                usage.total_tokens += response.usage.total_tokens;
+            }
+
+            Ok(EmbeddingResponse {
+                embeddings,
+                usage: Some(usage),
+            })
+        })
+    }
+}
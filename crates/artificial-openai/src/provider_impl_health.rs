@@ -0,0 +1,27 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Instant};
+
+use artificial_core::provider::{BackendHealth, HealthCheckProvider, HealthStatus};
+
+use crate::OpenAiAdapter;
+
+/// Probes the backend via `GET /models`, which is free and does not consume
+/// any completion tokens.
+impl HealthCheckProvider for OpenAiAdapter {
+    fn check_health<'s>(&'s self) -> Pin<Box<dyn Future<Output = Vec<BackendHealth>> + Send + 's>> {
+        let client = Arc::clone(&self.client);
+
+        Box::pin(async move {
+            let started = Instant::now();
+            let status = match client.list_models().await {
+                Ok(_) => HealthStatus::Healthy,
+                Err(err) => HealthStatus::Unhealthy(err.to_string()),
+            };
+
+            vec![BackendHealth {
+                backend: "openai".into(),
+                status,
+                latency: started.elapsed(),
+            }]
+        })
+    }
+}
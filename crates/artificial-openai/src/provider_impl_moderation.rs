@@ -0,0 +1,40 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use artificial_core::{
+    error::{ArtificialError, ErrorContext, Result},
+    provider::{ModerationProvider, ModerationResult},
+};
+
+use crate::{OpenAiAdapter, api_v1::ModerationRequest};
+
+/// Model used for `/v1/moderations`; free of charge and multi-modal.
+const MODERATION_MODEL: &str = "omni-moderation-latest";
+
+impl ModerationProvider for OpenAiAdapter {
+    fn moderate<'s>(
+        &'s self,
+        inputs: Vec<String>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<ModerationResult>>> + Send + 's>> {
+        let client = Arc::clone(&self.client);
+
+        Box::pin(async move {
+            let expected = inputs.len();
+            let request = ModerationRequest {
+                model: MODERATION_MODEL.to_owned(),
+                input: inputs,
+            };
+            let response = client.moderations(request).await.map_err(|err| {
+                ArtificialError::from(err)
+                    .with_context(ErrorContext::new().with_model(MODERATION_MODEL))
+            })?;
+
+            if response.results.len() != expected {
+                return Err(ArtificialError::Invalid(format!(
+                    "expected {expected} moderation results, got {}",
+                    response.results.len()
+                )));
+            }
+            Ok(response.results.into_iter().map(Into::into).collect())
+        })
+    }
+}
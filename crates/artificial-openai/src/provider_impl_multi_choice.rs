@@ -0,0 +1,79 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use artificial_core::{
+    error::{ArtificialError, ErrorContext, Result},
+    generic::GenericUsageReport,
+    provider::{ChatCompleteParameters, Choice, MultiChoiceProvider, MultiChoiceResponse},
+};
+
+use crate::{
+    OpenAiAdapter,
+    api_v1::{ChatCompletionMessage, ChatCompletionRequest},
+};
+
+/// Uses the `n` parameter of `/v1/chat/completions`, so all choices come
+/// from one request. Choices are returned as-is, including truncated or
+/// filtered ones; check [`Choice::finish_reason`].
+impl MultiChoiceProvider for OpenAiAdapter {
+    type Message = ChatCompletionMessage;
+
+    fn chat_complete_n<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+        n: u32,
+    ) -> Pin<Box<dyn Future<Output = Result<MultiChoiceResponse>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let client = Arc::clone(&self.client);
+        let cancellation = params.cancellation.clone();
+
+        let completion = Box::pin(async move {
+            if n == 0 {
+                return Err(ArtificialError::InvalidRequest(
+                    "chat_complete_n needs at least one choice".into(),
+                ));
+            }
+            let mut request: ChatCompletionRequest = params.try_into()?;
+            request.n = Some(n as i64);
+
+            let mut context = ErrorContext::new().with_model(&request.model);
+            if let Some(fingerprint) = ErrorContext::fingerprint_of(&request.messages) {
+                context = context.with_fingerprint(fingerprint);
+            }
+
+            let response = client
+                .chat_completion(request)
+                .await
+                .map_err(|err| ArtificialError::from(err).with_context(context))?;
+
+            let metadata = response.metadata();
+            let usage = GenericUsageReport {
+                prompt_tokens: response.usage.prompt_tokens as i64,
+                completion_tokens: response.usage.completion_tokens as i64,
+                total_tokens: response.usage.total_tokens as i64,
+            };
+            let choices = response
+                .choices
+                .into_iter()
+                .map(|choice| Choice {
+                    finish_reason: choice
+                        .finish_reason
+                        .map(|reason| reason.as_str().to_owned()),
+                    message: choice.message.into(),
+                })
+                .collect();
+
+            Ok(MultiChoiceResponse {
+                choices,
+                usage: Some(usage),
+                metadata,
+            })
+        });
+
+        match cancellation {
+            Some(token) => Box::pin(token.run(completion)),
+            None => completion,
+        }
+    }
+}
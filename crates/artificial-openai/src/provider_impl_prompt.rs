@@ -1,19 +1,31 @@
-use std::{any::Any, future::Future, pin::Pin, sync::Arc};
+use std::{
+    any::Any,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use artificial_core::{
-    error::{ArtificialError, Result},
-    generic::{GenericChatCompletionResponse, GenericUsageReport, ResponseContent},
-    provider::PromptExecutionProvider,
-    template::{IntoPrompt, PromptTemplate},
+    error::{ArtificialError, ErrorContext, Result},
+    generic::{
+        GenericChatCompletionResponse, GenericMessage, GenericRole, GenericUsageReport,
+        ResponseContent, ResponseMetadata,
+    },
+    model::Model,
+    provider::{ExecutionPolicy, PromptExecutionProvider, ThinkingBudget},
+    schema_util::parse_output,
+    template::{IntoPrompt, PromptTemplate, TemplateParams},
 };
-use schemars::{JsonSchema, SchemaGenerator, r#gen::SchemaSettings};
-use serde_json::json;
+use schemars::JsonSchema;
+use serde::Deserialize;
 
 use crate::{
     OpenAiAdapter,
-    api_v1::{ChatCompletionMessage, ChatCompletionRequest, FinishReason},
-    error::OpenAiError,
-    model_map::map_model,
+    api_v1::{ChatCompletionMessage, ChatCompletionRequest, ChatCompletionResponse, FinishReason},
+    client::OpenAiClient,
+    error::{OpenAiError, is_timeout},
+    model_map::{map_model, supports_reasoning_effort},
 };
 
 /// Implementation of [`ChatCompletionProvider`] for the [`OpenAiAdapter`].
@@ -43,6 +55,29 @@ impl PromptExecutionProvider for OpenAiAdapter {
         &'a self,
         prompt: P,
     ) -> Pin<Box<dyn Future<Output = Result<GenericChatCompletionResponse<P::Output>>> + Send + 'p>>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        self.prompt_execute_with_policy(prompt, ExecutionPolicy::default())
+    }
+
+    /// Supports [`ExecutionPolicy::max_repairs`]: answers that do not
+    /// deserialize into `P::Output` are sent back together with the parse
+    /// error, asking the model for a corrected reply.
+    ///
+    /// With [`ExecutionPolicy::output_repair`] set, fences, surrounding prose
+    /// and trailing commas are fixed locally first; only answers that still
+    /// fail to parse cost a repair round-trip.
+    ///
+    /// A [`ThinkingBudget`] applies to models that accept a reasoning effort
+    /// and bounds all attempts together.
+    fn prompt_execute_with_policy<'a, 'p, P>(
+        &'a self,
+        prompt: P,
+        policy: ExecutionPolicy,
+    ) -> Pin<Box<dyn Future<Output = Result<GenericChatCompletionResponse<P::Output>>> + Send + 'p>>
     where
         'a: 'p,
         P: PromptTemplate + Send + Sync + 'p,
@@ -50,100 +85,253 @@ impl PromptExecutionProvider for OpenAiAdapter {
     {
         let client = Arc::clone(&self.client);
 
-        let messages = prompt.into_prompt().into_iter().map(Into::into).collect();
+        let messages: Vec<ChatCompletionMessage> =
+            prompt.into_prompt().into_iter().map(Into::into).collect();
+
+        let mut context = ErrorContext::new()
+            .with_template(P::name())
+            .with_model(P::MODEL.as_ref());
+        if let Some(fingerprint) = ErrorContext::fingerprint_of(&messages) {
+            context = context.with_fingerprint(fingerprint);
+        }
 
         Box::pin(async move {
-            let response_format = derive_response_format::<P::Output>()?;
-
-            let model = map_model(&P::MODEL).ok_or(ArtificialError::InvalidRequest(format!(
-                "backend does not support selected model: {:?}",
-                P::MODEL
-            )))?;
-
-            let request =
-                ChatCompletionRequest::new(model.into(), messages).response_format(response_format);
-
-            let response = client.chat_completion(request).await?;
-
-            let usage_report = GenericUsageReport {
-                prompt_tokens: response.usage.prompt_tokens as i64,
-                completion_tokens: response.usage.completion_tokens as i64,
-                total_tokens: response.usage.total_tokens as i64,
-            };
-
-            let Some(first_choice) = response.choices.first() else {
-                return Err(OpenAiError::Format("response has no choices".into()).into());
-            };
-
-            match &first_choice.finish_reason {
-                None | Some(FinishReason::Stop) => {
-                    let content =
-                        first_choice
-                            .message
-                            .content
-                            .as_ref()
-                            .ok_or(OpenAiError::Format(
-                                "invalid response: empty content".into(),
-                            ))?;
-                    let content = serde_json::from_str(content.as_str())?;
-                    let response = GenericChatCompletionResponse {
-                        content: ResponseContent::Finished(content),
-                        usage: Some(usage_report),
-                    };
-                    Ok(response)
+            execute::<P::Output>(
+                &client,
+                messages,
+                &P::MODEL,
+                P::cache_key(),
+                &P::params(),
+                &policy,
+                &context,
+            )
+            .await
+        })
+    }
+}
+
+/// Send a structured completion request and parse the first choice into
+/// `T`, repairing malformed answers as allowed by `policy`.
+async fn execute<T>(
+    client: &OpenAiClient,
+    mut messages: Vec<ChatCompletionMessage>,
+    requested_model: &Model,
+    cache_key: Option<&str>,
+    params: &TemplateParams,
+    policy: &ExecutionPolicy,
+    context: &ErrorContext,
+) -> Result<GenericChatCompletionResponse<T>>
+where
+    T: JsonSchema + for<'de> Deserialize<'de> + Any,
+{
+    let started = Instant::now();
+    let mut usage: Option<GenericUsageReport> = None;
+    let mut attempt = 1;
+    let mut retried_empty = false;
+    loop {
+        let model = policy.model_for(attempt).unwrap_or(requested_model);
+        let attempt_context = context.clone().with_model(model).with_attempt(attempt);
+
+        let request = structured_request::<T>(
+            messages.clone(),
+            model,
+            cache_key,
+            &TemplateParams {
+                temperature: policy.temperature_for(attempt).or(params.temperature),
+                ..params.clone()
+            },
+        )
+        .map_err(|err| err.with_context(attempt_context.clone()))?;
+
+        let budget = policy
+            .thinking_budget
+            .or(params.thinking_budget)
+            .filter(|_| supports_reasoning_effort(model));
+        let result = match budget {
+            Some(budget) => {
+                let remaining = budget.total.saturating_sub(started.elapsed());
+                request_within_budget(client, request, &budget, remaining).await
+            }
+            None => request_content(client, request).await,
+        };
+        let (content, attempt_usage, metadata) = match result {
+            Err(ArtificialError::EmptyResponse) if policy.retry_on_empty && !retried_empty => {
+                retried_empty = true;
+                continue;
+            }
+            other => other.map_err(|err| err.with_context(attempt_context.clone()))?,
+        };
+        usage = Some(add_usage(usage, attempt_usage));
+
+        let (content, repairs) = match &policy.output_repair {
+            Some(output_repair) => {
+                let repaired = output_repair.repair(&content);
+                #[cfg(feature = "tracing")]
+                if repaired.is_repaired() {
+                    tracing::debug!(attempt, repairs = ?repaired.repairs, "repaired structured output locally");
                 }
-                Some(other) => Err(OpenAiError::Format(format!(
-                    "unhandled finish reason on API: {other:?}"
-                ))
-                .into()),
+                (repaired.text, repaired.repairs)
             }
-        })
+            None => (content, Vec::new()),
+        };
+
+        match parse_output::<T>(&content) {
+            Ok(output) => {
+                return Ok(GenericChatCompletionResponse {
+                    content: ResponseContent::Finished(output),
+                    usage,
+                    metadata: ResponseMetadata {
+                        repairs,
+                        ..metadata
+                    },
+                });
+            }
+            Err(err) if attempt <= policy.max_repairs => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(attempt, error = %err, "repairing malformed structured output");
+
+                messages.push(GenericMessage::new(content, GenericRole::Assistant).into());
+                messages
+                    .push(GenericMessage::new(repair_instruction(&err), GenericRole::User).into());
+                attempt += 1;
+            }
+            Err(err) => return Err(err.with_context(attempt_context)),
+        }
     }
 }
 
-/// Produce the `response_format` object expected by OpenAI.
-///
-/// * If `T == serde_json::Value` we ask for an *unstructured* JSON blob.
-/// * Otherwise we inline a full JSON Schema generated by `schemars`.
-fn derive_response_format<T>() -> Result<serde_json::Value>
+fn repair_instruction(err: &ArtificialError) -> String {
+    format!(
+        "Your previous reply could not be parsed: {err}. \
+         Reply again with only the corrected JSON, matching the required schema exactly."
+    )
+}
+
+fn add_usage(total: Option<GenericUsageReport>, next: GenericUsageReport) -> GenericUsageReport {
+    match total {
+        None => next,
+        Some(total) => GenericUsageReport {
+            prompt_tokens: total.prompt_tokens + next.prompt_tokens,
+            completion_tokens: total.completion_tokens + next.completion_tokens,
+            total_tokens: total.total_tokens + next.total_tokens,
+        },
+    }
+}
+
+/// Build the request for a structured answer of type `T`: resolve the
+/// model, attach the output schema as the template's [`TemplateParams`]
+/// demand and copy the generation settings.
+pub(crate) fn structured_request<T>(
+    mut messages: Vec<ChatCompletionMessage>,
+    requested_model: &Model,
+    cache_key: Option<&str>,
+    params: &TemplateParams,
+) -> Result<ChatCompletionRequest>
 where
     T: JsonSchema + Any,
 {
-    let requested_type = std::any::TypeId::of::<T>();
-    let json_value_type = std::any::TypeId::of::<serde_json::Value>();
+    let model = map_model(requested_model).ok_or(ArtificialError::InvalidRequest(format!(
+        "backend does not support selected model: {requested_model:?}"
+    )))?;
+    let response_format = params
+        .schema_mode_for(requested_model)
+        .attach::<T, _>(&mut messages)?;
 
-    // Fast-path: caller wants raw JSON.
-    if requested_type.eq(&json_value_type) {
-        return Ok(json!({ "type": "json_object" }));
+    let mut request = ChatCompletionRequest::new(model.into(), messages);
+    if let Some(response_format) = response_format {
+        request = request.response_format(response_format);
+    }
+    if let Some(cache_key) = cache_key {
+        request = request.prompt_cache_key(cache_key.to_owned());
     }
+    apply_template_params(request, requested_model, params)
+}
 
-    // Generate inline schema (no $ref) for strict validation.
-    let schema_json = {
-        let mut settings = SchemaSettings::draft07();
-        settings.inline_subschemas = true;
+/// Perform `request` within `remaining` time, repeating attempts that time
+/// out with less reasoning effort as `budget` allows.
+async fn request_within_budget(
+    client: &OpenAiClient,
+    mut request: ChatCompletionRequest,
+    budget: &ThinkingBudget,
+    remaining: Duration,
+) -> Result<(String, GenericUsageReport, ResponseMetadata)> {
+    let started = Instant::now();
+    let efforts = budget.efforts();
+    let mut attempt = 0;
+    loop {
+        request.reasoning_effort = Some(efforts[attempt].as_ref().to_owned());
+        request.timeout =
+            Some(budget.attempt_timeout(remaining.saturating_sub(started.elapsed()), attempt));
+        request.retry_on_timeout = false;
 
-        let mut generator = SchemaGenerator::new(settings);
-        let root_schema = generator.root_schema_for::<T>();
+        match request_content(client, request.clone()).await {
+            Err(err) if is_timeout(&err) && attempt + 1 < efforts.len() => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    effort = efforts[attempt + 1].as_ref(),
+                    "attempt timed out, retrying with less reasoning effort"
+                );
+                attempt += 1;
+            }
+            other => return other,
+        }
+    }
+}
+
+/// Perform one request and return the raw text content of the first choice.
+async fn request_content(
+    client: &OpenAiClient,
+    request: ChatCompletionRequest,
+) -> Result<(String, GenericUsageReport, ResponseMetadata)> {
+    let response = client.chat_completion(request).await?;
+    first_choice_content(response)
+}
 
-        serde_json::to_value(root_schema)?
+/// The text of the first choice, provided the model finished regularly.
+pub(crate) fn first_choice_content(
+    response: ChatCompletionResponse,
+) -> Result<(String, GenericUsageReport, ResponseMetadata)> {
+    let metadata = response.metadata();
+    let usage_report = GenericUsageReport {
+        prompt_tokens: response.usage.prompt_tokens as i64,
+        completion_tokens: response.usage.completion_tokens as i64,
+        total_tokens: response.usage.total_tokens as i64,
     };
 
-    // Extract a human-readable title for the schema.
-    let schema_title = schema_json
-        .as_object()
-        .and_then(|o| o.get("title"))
-        .and_then(|t| t.as_str())
-        .map(str::to_owned)
-        .ok_or(ArtificialError::InvalidRequest(
-            "json schema has no title".into(),
-        ))?;
-
-    Ok(json!({
-        "type": "json_schema",
-        "json_schema": {
-            "strict": true,
-            "name": schema_title,
-            "schema": schema_json,
+    let Some(first_choice) = response.choices.into_iter().next() else {
+        return Err(OpenAiError::Format("response has no choices".into()).into());
+    };
+
+    match &first_choice.finish_reason {
+        None | Some(FinishReason::Stop) => match first_choice.message.content {
+            Some(content) if !content.trim().is_empty() => Ok((content, usage_report, metadata)),
+            _ => Err(ArtificialError::EmptyResponse),
+        },
+        Some(other) => {
+            Err(OpenAiError::Format(format!("unhandled finish reason on API: {other:?}")).into())
         }
-    }))
+    }
+}
+
+/// Copy the template's generation settings onto `request`.
+///
+/// Fails with [`ArtificialError::InvalidRequest`] for an out-of-range
+/// temperature.
+pub(crate) fn apply_template_params(
+    mut request: ChatCompletionRequest,
+    model: &Model,
+    params: &TemplateParams,
+) -> Result<ChatCompletionRequest> {
+    if let Some(temperature) = params.checked_temperature()? {
+        request.temperature = Some(temperature.get());
+    }
+    request.max_completion_tokens = params.max_tokens.or(request.max_completion_tokens);
+    request.seed = params.seed.or(request.seed);
+    if let Some(effort) = params
+        .reasoning_effort
+        .filter(|_| supports_reasoning_effort(model))
+    {
+        request.reasoning_effort = Some(effort.as_ref().to_owned());
+    }
+    Ok(request)
 }
@@ -0,0 +1,87 @@
+use artificial_core::{
+    error::{ArtificialError, ErrorContext},
+    provider::{BoxedPromptStream, PromptStreamEvent, PromptStreamingProvider},
+    schema_util::parse_output,
+    template::{IntoPrompt, PromptTemplate},
+};
+
+use crate::{
+    OpenAiAdapter,
+    api_v1::{ChatCompletionMessage, ChatCompletionRequest},
+    model_map::map_model,
+    provider_impl_prompt::apply_template_params,
+};
+
+/// Streams the structured answer as it is generated and parses the
+/// accumulated text once the model is done.
+impl PromptStreamingProvider for OpenAiAdapter {
+    fn prompt_execute_stream<'a, 'p, P>(&'a self, prompt: P) -> BoxedPromptStream<'p, P::Output>
+    where
+        'a: 'p,
+        P: PromptTemplate + Send + Sync + 'p,
+        P::Output: Send,
+        <P as IntoPrompt>::Message: Into<Self::Message>,
+    {
+        let client = self.client.clone();
+        let messages: Vec<ChatCompletionMessage> =
+            prompt.into_prompt().into_iter().map(Into::into).collect();
+
+        let mut context = ErrorContext::new()
+            .with_template(P::name())
+            .with_model(P::MODEL.as_ref());
+        if let Some(fingerprint) = ErrorContext::fingerprint_of(&messages) {
+            context = context.with_fingerprint(fingerprint);
+        }
+
+        Box::pin(async_stream::try_stream! {
+            use futures_util::StreamExt;
+
+            let model = map_model(&P::MODEL).ok_or(ArtificialError::InvalidRequest(format!(
+                "backend does not support selected model: {:?}",
+                P::MODEL
+            )))?;
+            let mut messages = messages;
+            let params = P::params();
+            let response_format = params
+                .schema_mode_for(&P::MODEL)
+                .attach::<P::Output, _>(&mut messages)
+                .map_err(|err| err.with_context(context.clone()))?;
+
+            let mut request = ChatCompletionRequest::new(model.into(), messages);
+            if let Some(response_format) = response_format {
+                request = request.response_format(response_format);
+            }
+            if let Some(cache_key) = P::cache_key() {
+                request = request.prompt_cache_key(cache_key.to_owned());
+            }
+            request = apply_template_params(request, &P::MODEL, &params)
+                .map_err(|err| err.with_context(context.clone()))?;
+
+            let stream = client.chat_completion_stream(request);
+            futures_util::pin_mut!(stream);
+
+            let mut content = String::new();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|err| ArtificialError::from(err).with_context(context.clone()))?;
+                for choice in chunk.choices {
+                    if choice.index != 0 {
+                        continue;
+                    }
+                    if let Some(delta) = choice.delta.content
+                        && !delta.is_empty()
+                    {
+                        content.push_str(&delta);
+                        yield PromptStreamEvent::Delta(delta);
+                    }
+                }
+            }
+
+            if content.trim().is_empty() {
+                Err(ArtificialError::EmptyResponse.with_context(context.clone()))?;
+            }
+            let output = parse_output::<P::Output>(&content)
+                .map_err(|err| err.with_context(context.clone()))?;
+            yield PromptStreamEvent::Finished(output);
+        })
+    }
+}
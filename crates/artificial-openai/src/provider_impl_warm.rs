@@ -0,0 +1,44 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use artificial_core::{
+    error::{ArtificialError, ErrorContext, Result},
+    generic::GenericUsageReport,
+    provider::{ChatCompleteParameters, PromptWarmingProvider},
+};
+
+use crate::{
+    OpenAiAdapter,
+    api_v1::{ChatCompletionMessage, ChatCompletionRequest},
+};
+
+/// OpenAI caches prompt prefixes automatically; warming is a regular request
+/// capped at one completion token, tagged with `prompt_cache_key`.
+impl PromptWarmingProvider for OpenAiAdapter {
+    type Message = ChatCompletionMessage;
+
+    fn warm_prefix<'s, M>(
+        &'s self,
+        params: ChatCompleteParameters<M>,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<GenericUsageReport>>> + Send + 's>>
+    where
+        M: Into<Self::Message> + Clone + Send + Sync + 's,
+    {
+        let client = Arc::clone(&self.client);
+
+        Box::pin(async move {
+            let request = ChatCompletionRequest::try_from(params)?.max_completion_tokens(1);
+            let context = ErrorContext::new().with_model(&request.model);
+
+            let response = client
+                .chat_completion(request)
+                .await
+                .map_err(|err| ArtificialError::from(err).with_context(context))?;
+
+            Ok(Some(GenericUsageReport {
+                prompt_tokens: response.usage.prompt_tokens as i64,
+                completion_tokens: response.usage.completion_tokens as i64,
+                total_tokens: response.usage.total_tokens as i64,
+            }))
+        })
+    }
+}
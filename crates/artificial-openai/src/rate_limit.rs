@@ -0,0 +1,115 @@
+//! Client-side rate limiting for the OpenAI API.
+//!
+//! Retrying on `429` works, but every rejected request costs a round trip
+//! and a backoff. [`RateLimiter`] keeps two token buckets—requests and
+//! tokens per minute—and delays a request *before* it is sent when it would
+//! exceed either. The buckets follow the `x-ratelimit-*` headers of every
+//! response, so the limiter learns the account's actual limits and notices
+//! other processes spending the same budget:
+//!
+//! ```rust,no_run
+//! use artificial_openai::{OpenAiAdapterBuilder, RateLimiter};
+//!
+//! let backend = OpenAiAdapterBuilder::new_from_env()
+//!     // Start conservatively; the headers correct the numbers.
+//!     .with_rate_limiter(RateLimiter::new().with_requests_per_minute(500))
+//!     .build()
+//!     .expect("OPENAI_API_KEY must be set");
+//! ```
+//!
+//! Token costs are estimated from the request size before sending, at four
+//! bytes per token. Waiting uses the retry policy's [`crate::Sleeper`].
+//!
+//! The buckets live in an [`artificial_core::quota::QuotaManager`]; pass one
+//! with a store via [`RateLimiter::with_quota`] to keep budgets across
+//! restarts, or clone the limiter to share it between adapters using the
+//! same key.
+use std::time::Duration;
+
+use artificial_core::quota::QuotaManager;
+
+use crate::error::OpenAiRateLimitHeaders;
+
+const REQUESTS: &str = "requests";
+const TOKENS: &str = "tokens";
+/// Window of the limits OpenAI reports in its headers.
+const WINDOW: Duration = Duration::from_secs(60);
+const BYTES_PER_TOKEN: usize = 4;
+
+/// Token buckets for requests and tokens per minute, synced with the
+/// provider's rate-limit headers. Clones share the buckets.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    quota: QuotaManager,
+    follow_headers: bool,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    /// A limiter without limits of its own; it starts throttling once the
+    /// first response reported the account's limits.
+    pub fn new() -> Self {
+        Self {
+            quota: QuotaManager::new(),
+            follow_headers: true,
+        }
+    }
+
+    pub fn with_requests_per_minute(self, requests: u32) -> Self {
+        self.quota
+            .sync(REQUESTS, requests as f64, WINDOW, requests as f64);
+        self
+    }
+
+    pub fn with_tokens_per_minute(self, tokens: u32) -> Self {
+        self.quota
+            .sync(TOKENS, tokens as f64, WINDOW, tokens as f64);
+        self
+    }
+
+    /// Keep the buckets in `quota`, e.g. one backed by a
+    /// [`artificial_core::quota::QuotaStore`]. Limits set before are lost.
+    pub fn with_quota(mut self, quota: QuotaManager) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Stick to the configured limits and ignore the response headers.
+    pub fn with_fixed_limits(mut self) -> Self {
+        self.follow_headers = false;
+        self
+    }
+
+    pub fn quota(&self) -> &QuotaManager {
+        &self.quota
+    }
+
+    /// Reserve one request with a body of `body_bytes` and return how long
+    /// to wait before sending it.
+    pub(crate) fn reserve(&self, body_bytes: usize) -> Duration {
+        let tokens = body_bytes.div_ceil(BYTES_PER_TOKEN) as f64;
+        let requests = self.quota.reserve(REQUESTS, 1.0);
+        requests.max(self.quota.reserve(TOKENS, tokens))
+    }
+
+    /// Sync the buckets with the headers of a response.
+    pub(crate) fn observe(&self, headers: &OpenAiRateLimitHeaders) {
+        if !self.follow_headers {
+            return;
+        }
+        if let (Some(limit), Some(remaining)) = (headers.limit_requests, headers.remaining_requests)
+        {
+            self.quota
+                .sync(REQUESTS, limit as f64, WINDOW, remaining as f64);
+        }
+        if let (Some(limit), Some(remaining)) = (headers.limit_tokens, headers.remaining_tokens) {
+            self.quota
+                .sync(TOKENS, limit as f64, WINDOW, remaining as f64);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Async delays for the retry backoff.
+//!
+//! The delay is pluggable through [`Sleeper`]. With the default `tokio`
+//! feature it is `tokio::time::sleep`, on the runtime reqwest already needs.
+//! Builds without the feature, for other runtimes, fall back to a helper
+//! thread per delay that fires the wake-up. Either way the executor thread
+//! is free while a request backs off.
+use std::{fmt, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+pub type SleepFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Produces the future awaited between retry attempts.
+///
+/// ```rust
+/// use artificial_openai::{RetryPolicy, Sleeper};
+///
+/// // e.g. for a runtime other than Tokio
+/// let policy = RetryPolicy {
+///     sleeper: Sleeper::new(|delay| Box::pin(async move {
+///         # let _ = delay;
+///         // my_runtime::sleep(delay).await
+///     })),
+///     ..RetryPolicy::default()
+/// };
+/// # let _ = policy;
+/// ```
+#[derive(Clone)]
+pub struct Sleeper(Arc<dyn Fn(Duration) -> SleepFuture + Send + Sync>);
+
+impl Sleeper {
+    pub fn new(sleep: impl Fn(Duration) -> SleepFuture + Send + Sync + 'static) -> Self {
+        Self(Arc::new(sleep))
+    }
+
+    pub fn sleep(&self, delay: Duration) -> SleepFuture {
+        (self.0)(delay)
+    }
+}
+
+impl Default for Sleeper {
+    #[cfg(feature = "tokio")]
+    fn default() -> Self {
+        Self::new(|delay| Box::pin(tokio::time::sleep(delay)))
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    fn default() -> Self {
+        Self::new(|delay| Box::pin(thread_timer::sleep(delay)))
+    }
+}
+
+impl fmt::Debug for Sleeper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sleeper")
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+mod thread_timer {
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll, Waker},
+        thread,
+        time::Duration,
+    };
+
+    #[derive(Default)]
+    struct State {
+        fired: bool,
+        waker: Option<Waker>,
+    }
+
+    /// Completes after `delay`; the waiting happens on a helper thread. If
+    /// no thread can be spawned the delay is skipped rather than failing the
+    /// request.
+    pub(super) fn sleep(delay: Duration) -> impl Future<Output = ()> + Send {
+        let state = Arc::new(Mutex::new(State::default()));
+        if delay.is_zero() {
+            state.lock().unwrap().fired = true;
+        } else {
+            let timer = Arc::clone(&state);
+            let spawned = thread::Builder::new()
+                .name("artificial-openai-backoff".into())
+                .spawn(move || {
+                    thread::sleep(delay);
+                    let mut state = timer.lock().unwrap();
+                    state.fired = true;
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                });
+            if spawned.is_err() {
+                state.lock().unwrap().fired = true;
+            }
+        }
+        Timer(state)
+    }
+
+    struct Timer(Arc<Mutex<State>>);
+
+    impl Future for Timer {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let mut state = self.0.lock().unwrap();
+            if state.fired {
+                return Poll::Ready(());
+            }
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
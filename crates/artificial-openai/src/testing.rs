@@ -0,0 +1,1485 @@
+//! Offline stand-in for the OpenAI HTTP API.
+//!
+//! Enable the `test-util` feature to use [`MockOpenAiServer`] in your own
+//! integration tests.  It runs a local [`wiremock`] server that speaks the
+//! subset of the OpenAI protocol this crate uses—chat completions (plain and
+//! SSE-streamed), tool calls, embeddings, moderation, batches, model listing and rate limiting—and hands out
+//! an [`OpenAiAdapter`] already pointed at it.
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "test-util")]
+//! # async fn demo() {
+//! use artificial_core::{
+//!     generic::{GenericMessage, GenericRole, ResponseContent},
+//!     model::{Model, OpenAiModel},
+//!     provider::{ChatCompleteParameters, ChatCompletionProvider},
+//! };
+//! use artificial_openai::testing::MockOpenAiServer;
+//!
+//! let server = MockOpenAiServer::start().await;
+//! server.mock_text("Hello from the mock").await;
+//!
+//! let adapter = server.adapter();
+//! let response = adapter
+//!     .chat_complete(ChatCompleteParameters::new(
+//!         vec![GenericMessage::new("hi".into(), GenericRole::User)],
+//!         Model::OpenAi(OpenAiModel::Gpt4oMini),
+//!     ))
+//!     .await
+//!     .unwrap();
+//! assert!(matches!(response.content, ResponseContent::Finished(_)));
+//! # }
+//! ```
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use wiremock::{
+    Match, Mock, MockServer, Request, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::{OpenAiAdapter, OpenAiAdapterOptions, RetryPolicy};
+
+const MOCK_MODEL: &str = "gpt-4o-mini";
+
+/// Local mock of the OpenAI API.
+///
+/// Mocks registered later never shadow earlier ones; register the most
+/// specific behaviour (e.g. a transient 429) first.
+pub struct MockOpenAiServer {
+    server: MockServer,
+}
+
+impl MockOpenAiServer {
+    /// Start a server on a random local port.
+    pub async fn start() -> Self {
+        Self {
+            server: MockServer::start().await,
+        }
+    }
+
+    /// Base URL including the `/v1` prefix.
+    pub fn base_url(&self) -> String {
+        format!("{}/v1", self.server.uri())
+    }
+
+    /// Adapter options pointing at this server, with a dummy API key and a
+    /// retry policy that backs off in milliseconds instead of seconds.
+    pub fn options(&self) -> OpenAiAdapterOptions {
+        OpenAiAdapterOptions::new()
+            .with_api_key("test-key")
+            .with_base_url(self.base_url())
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(10),
+                respect_retry_after: true,
+                ..RetryPolicy::default()
+            })
+    }
+
+    /// Ready-to-use adapter talking to this server.
+    pub fn adapter(&self) -> OpenAiAdapter {
+        self.options()
+            .build()
+            .expect("mock options carry an api key")
+    }
+
+    /// Answer non-streaming chat completions with a raw response `body`.
+    pub async fn mock_completion(&self, body: Value) {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(Streaming(false))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer non-streaming chat completions with an assistant text message.
+    pub async fn mock_text(&self, content: &str) {
+        self.mock_completion(completion(
+            json!({ "role": "assistant", "content": content }),
+            "stop",
+        ))
+        .await;
+    }
+
+    /// Answer non-streaming chat completions with tool calls; each entry is
+    /// `(function name, arguments)`.
+    pub async fn mock_tool_calls(&self, calls: &[(&str, Value)]) {
+        self.mock_tool_calls_with_reason(calls, "tool_calls").await;
+    }
+
+    /// Like [`Self::mock_tool_calls`] with a custom `finish_reason`, for
+    /// servers that report `stop` on tool calls.
+    pub async fn mock_tool_calls_with_reason(&self, calls: &[(&str, Value)], finish_reason: &str) {
+        let tool_calls: Vec<Value> = calls
+            .iter()
+            .enumerate()
+            .map(|(i, (name, arguments))| {
+                json!({
+                    "id": format!("call_{i}"),
+                    "type": "function",
+                    "function": { "name": name, "arguments": arguments.to_string() },
+                })
+            })
+            .collect();
+
+        self.mock_completion(completion(
+            json!({ "role": "assistant", "content": null, "tool_calls": tool_calls }),
+            finish_reason,
+        ))
+        .await;
+    }
+
+    /// Answer streaming chat completions with the given SSE `chunks`, in
+    /// order, followed by `[DONE]`.  Build chunks with [`Self::chunk`].
+    pub async fn mock_stream(&self, chunks: Vec<Value>) {
+        let mut body = String::new();
+        for chunk in chunks {
+            body.push_str(&format!("data: {chunk}\n\n"));
+        }
+        body.push_str("data: [DONE]\n\n");
+
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(Streaming(true))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(body),
+            )
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer streaming chat completions with text `deltas`.
+    pub async fn mock_text_stream(&self, deltas: &[&str]) {
+        let mut chunks = vec![Self::chunk(json!({ "role": "assistant" }), None)];
+        chunks.extend(
+            deltas
+                .iter()
+                .map(|d| Self::chunk(json!({ "content": d }), None)),
+        );
+        chunks.push(Self::chunk(json!({}), Some("stop")));
+        self.mock_stream(chunks).await;
+    }
+
+    /// A single streaming chunk carrying `delta` for choice 0.
+    pub fn chunk(delta: Value, finish_reason: Option<&str>) -> Value {
+        json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": MOCK_MODEL,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        })
+    }
+
+    /// Reject the next `times` chat completion requests with `429` and the
+    /// usual rate-limit headers.  Takes precedence over all other mocks.
+    pub async fn mock_rate_limited(&self, times: u64, retry_after_secs: u64) {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", retry_after_secs.to_string().as_str())
+                    .insert_header("x-ratelimit-limit-requests", "60")
+                    .insert_header("x-ratelimit-remaining-requests", "0")
+                    .insert_header("x-ratelimit-reset-requests", "1s")
+                    .set_body_json(json!({
+                        "error": { "message": "Rate limit reached", "type": "requests" }
+                    })),
+            )
+            .up_to_n_times(times)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer `GET /models` with the given model ids.
+    pub async fn mock_models(&self, ids: &[&str]) {
+        let data: Vec<Value> = ids
+            .iter()
+            .map(|id| json!({ "id": id, "object": "model", "owned_by": "openai" }))
+            .collect();
+
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "object": "list", "data": data })),
+            )
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer `POST /embeddings` with one vector `[position, input length]`
+    /// per input, listed in reverse order, and one prompt token per input.
+    pub async fn mock_embeddings(&self) {
+        Mock::given(method("POST"))
+            .and(path("/v1/embeddings"))
+            .respond_with(|request: &Request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+                let inputs = body["input"].as_array().cloned().unwrap_or_default();
+                let data: Vec<Value> = inputs
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(index, input)| {
+                        let len = input.as_str().map_or(0, str::len);
+                        json!({ "object": "embedding", "index": index, "embedding": [index, len] })
+                    })
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "object": "list",
+                    "data": data,
+                    "model": "text-embedding-3-small",
+                    "usage": { "prompt_tokens": inputs.len(), "total_tokens": inputs.len() },
+                }))
+            })
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer `POST /moderations`, flagging every input that contains
+    /// `trigger` for `category`.
+    pub async fn mock_moderation(&self, trigger: &str, category: &str) {
+        let (trigger, category) = (trigger.to_owned(), category.to_owned());
+        Mock::given(method("POST"))
+            .and(path("/v1/moderations"))
+            .respond_with(move |request: &Request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+                let results: Vec<Value> = body["input"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|input| {
+                        let flagged = input.as_str().is_some_and(|s| s.contains(&trigger));
+                        json!({
+                            "flagged": flagged,
+                            "categories": { category.clone(): flagged },
+                            "category_scores": { category.clone(): if flagged { 0.9 } else { 0.01 } },
+                        })
+                    })
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "id": "modr-mock",
+                    "model": "omni-moderation-latest",
+                    "results": results,
+                }))
+            })
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Run the Batch API: the upload and batch creation succeed, the batch
+    /// is `in_progress` on the first poll and `completed` afterwards. Each
+    /// of `answers` is `(custom id, assistant content)`; the ids in `failed`
+    /// end up in the error file with a `400`.
+    pub async fn mock_batch(&self, answers: &[(&str, &str)], failed: &[&str]) {
+        let batch = |status: &str| {
+            json!({
+                "id": "batch-mock",
+                "object": "batch",
+                "status": status,
+                "output_file_id": (status == "completed").then_some("file-output"),
+                "error_file_id": (status == "completed").then_some("file-errors"),
+                "request_counts": {
+                    "total": answers.len() + failed.len(),
+                    "completed": answers.len(),
+                    "failed": failed.len(),
+                },
+            })
+        };
+        let jsonl = |lines: Vec<Value>| {
+            lines
+                .iter()
+                .map(|line| format!("{line}\n"))
+                .collect::<String>()
+        };
+
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(
+                    json!({ "id": "file-input", "object": "file", "purpose": "batch" }),
+                ),
+            )
+            .mount(&self.server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/batches"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("validating")))
+            .mount(&self.server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/batches/batch-mock"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("in_progress")))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/batches/batch-mock"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("completed")))
+            .mount(&self.server)
+            .await;
+
+        let output = answers
+            .iter()
+            .map(|(id, content)| batch_answer(id, content))
+            .collect();
+        let errors = failed
+            .iter()
+            .map(|id| {
+                json!({
+                    "id": format!("req-{id}"),
+                    "custom_id": id,
+                    "response": {
+                        "status_code": 400,
+                        "body": { "error": { "message": "invalid request", "type": "invalid_request_error" } },
+                    },
+                    "error": null,
+                })
+            })
+            .collect();
+        for (file, lines) in [("file-output", output), ("file-errors", errors)] {
+            Mock::given(method("GET"))
+                .and(path(format!("/v1/files/{file}/content")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(jsonl(lines)))
+                .mount(&self.server)
+                .await;
+        }
+    }
+
+    /// JSON bodies of all requests received so far.
+    pub async fn received_bodies(&self) -> Vec<Value> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|r| serde_json::from_slice(&r.body).ok())
+            .collect()
+    }
+}
+
+/// A successful line of a batch output file.
+fn batch_answer(custom_id: &str, content: &str) -> Value {
+    json!({
+        "id": format!("req-{custom_id}"),
+        "custom_id": custom_id,
+        "response": {
+            "status_code": 200,
+            "body": completion(json!({ "role": "assistant", "content": content }), "stop"),
+        },
+        "error": null,
+    })
+}
+
+fn completion(message: Value, finish_reason: &str) -> Value {
+    json!({
+        "id": "chatcmpl-mock",
+        "object": "chat.completion",
+        "created": 0,
+        "model": MOCK_MODEL,
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": finish_reason,
+            "finish_details": null,
+        }],
+        "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 },
+        "system_fingerprint": "fp_mock",
+    })
+}
+
+/// Matches on the `stream` flag of the request body.
+struct Streaming(bool);
+
+impl Match for Streaming {
+    fn matches(&self, request: &Request) -> bool {
+        let stream = serde_json::from_slice::<Value>(&request.body)
+            .ok()
+            .and_then(|body| body.get("stream").and_then(Value::as_bool))
+            .unwrap_or(false);
+        stream == self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use artificial_core::{
+        generic::{GenericMessage, GenericRole, ResponseContent, StreamEvent},
+        model::{Model, OpenAiModel},
+        provider::{
+            ChatCompleteParameters, ChatCompletionProvider, EmbeddingProvider, HealthCheckProvider,
+            MultiChoiceProvider, ReasoningEffort, StreamingEventsProvider,
+        },
+    };
+    use futures_util::StreamExt;
+
+    use artificial_core::{error::ArtificialError, moderation::ModeratedProvider};
+
+    use super::*;
+    use crate::error::OpenAiError;
+
+    fn params() -> ChatCompleteParameters<GenericMessage> {
+        ChatCompleteParameters::new(
+            vec![GenericMessage::new("hi".into(), GenericRole::User)],
+            Model::OpenAi(OpenAiModel::Gpt4oMini),
+        )
+    }
+
+    #[tokio::test]
+    async fn plain_completion() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_text("hello").await;
+
+        let response = server.adapter().chat_complete(params()).await.unwrap();
+        assert_eq!(response.content.text(), Some("hello"));
+        assert_eq!(response.usage.unwrap().total_tokens, 15);
+        assert_eq!(response.metadata.id.as_deref(), Some("chatcmpl-mock"));
+        assert_eq!(response.metadata.model.as_deref(), Some(MOCK_MODEL));
+        assert_eq!(
+            response.metadata.system_fingerprint.as_deref(),
+            Some("fp_mock")
+        );
+        assert_eq!(response.metadata.finish_reason.as_deref(), Some("stop"));
+    }
+
+    #[tokio::test]
+    async fn multiple_choices_keep_their_finish_reasons() {
+        let server = MockOpenAiServer::start().await;
+        let mut body = completion(json!({ "role": "assistant", "content": "4" }), "stop");
+        body["choices"].as_array_mut().unwrap().push(json!({
+            "index": 1,
+            "message": { "role": "assistant", "content": "fo" },
+            "finish_reason": "length",
+            "finish_details": null,
+        }));
+        server.mock_completion(body).await;
+
+        let response = server.adapter().chat_complete_n(params(), 2).await.unwrap();
+        let answers: Vec<_> = response
+            .choices
+            .iter()
+            .map(|c| (c.message.content.as_deref(), c.finish_reason.as_deref()))
+            .collect();
+        assert_eq!(
+            answers,
+            [(Some("4"), Some("stop")), (Some("fo"), Some("length"))]
+        );
+        assert!(response.choices[0].is_complete());
+        assert!(!response.choices[1].is_complete());
+        assert_eq!(server.received_bodies().await[0]["n"], 2);
+
+        let err = server
+            .adapter()
+            .chat_complete_n(params(), 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ArtificialError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn generation_parameters_reach_the_request() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_text("hello").await;
+
+        let params = params()
+            .with_max_tokens(64)
+            .with_stop(["\n\n"])
+            .with_presence_penalty(0.5)
+            .with_frequency_penalty(-0.5)
+            .with_seed(7);
+        server.adapter().chat_complete(params).await.unwrap();
+
+        let body = &server.received_bodies().await[0];
+        assert_eq!(body["max_completion_tokens"], 64);
+        assert_eq!(body["stop"], json!(["\n\n"]));
+        assert_eq!(body["presence_penalty"], 0.5);
+        assert_eq!(body["frequency_penalty"], -0.5);
+        assert_eq!(body["seed"], 7);
+    }
+
+    #[tokio::test]
+    async fn reasoning_effort_only_reaches_reasoning_models() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_text("hello").await;
+
+        let adapter = server.adapter();
+        let mut reasoning = params().with_reasoning_effort(ReasoningEffort::Low);
+        reasoning.model = Model::OpenAi(OpenAiModel::O4Mini);
+        adapter.chat_complete(reasoning).await.unwrap();
+        adapter
+            .chat_complete(params().with_reasoning_effort(ReasoningEffort::Low))
+            .await
+            .unwrap();
+
+        let bodies = server.received_bodies().await;
+        assert_eq!(bodies[0]["reasoning_effort"], "low");
+        assert!(bodies[1].get("reasoning_effort").is_none());
+    }
+
+    #[tokio::test]
+    async fn non_streaming_tool_calls() {
+        let server = MockOpenAiServer::start().await;
+        server
+            .mock_tool_calls(&[("lookup", json!({ "q": "rust" }))])
+            .await;
+
+        let response = server.adapter().chat_complete(params()).await.unwrap();
+        let calls = response.content.tool_calls();
+        assert_eq!(calls[0].function.name, "lookup");
+        assert_eq!(calls[0].function.arguments, json!({ "q": "rust" }));
+    }
+
+    #[tokio::test]
+    async fn tool_calls_with_stop_reason() {
+        let server = MockOpenAiServer::start().await;
+        server
+            .mock_tool_calls_with_reason(&[("lookup", json!({ "q": "rust" }))], "stop")
+            .await;
+
+        let response = server.adapter().chat_complete(params()).await.unwrap();
+        assert_eq!(response.content.tool_calls()[0].function.name, "lookup");
+    }
+
+    #[tokio::test]
+    async fn interleaved_streamed_tool_calls() {
+        let server = MockOpenAiServer::start().await;
+        let tc = |index: usize, id: Option<&str>, name: Option<&str>, args: &str| {
+            MockOpenAiServer::chunk(
+                json!({ "tool_calls": [{
+                    "index": index,
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": name, "arguments": args },
+                }] }),
+                None,
+            )
+        };
+        server
+            .mock_stream(vec![
+                // id first, name in a later chunk; fragments of both calls interleave.
+                tc(0, Some("call_a"), None, ""),
+                tc(1, Some("call_b"), Some("second"), "{\"n\":"),
+                tc(0, None, Some("first"), "{\"x\""),
+                tc(1, None, None, "2}"),
+                tc(0, None, None, ":1}"),
+                MockOpenAiServer::chunk(json!({}), Some("tool_calls")),
+            ])
+            .await;
+
+        let adapter = server.adapter();
+        let events: Vec<StreamEvent> = adapter
+            .chat_complete_events_stream(params())
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        let mut complete: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::ToolCallComplete { index, intent } => Some((*index, intent.clone())),
+                _ => None,
+            })
+            .collect();
+        complete.sort_by_key(|(index, _)| *index);
+
+        assert_eq!(complete.len(), 2);
+        assert_eq!(complete[0].1.id, "call_a");
+        assert_eq!(complete[0].1.function.name, "first");
+        assert_eq!(complete[0].1.function.arguments, json!({ "x": 1 }));
+        assert_eq!(complete[1].1.id, "call_b");
+        assert_eq!(complete[1].1.function.arguments, json!({ "n": 2 }));
+        assert!(matches!(events.last(), Some(StreamEvent::MessageEnd)));
+    }
+
+    #[tokio::test]
+    async fn malformed_tool_arguments_are_reported_early() {
+        let server = MockOpenAiServer::start().await;
+        let tc = |fragment: &str| {
+            MockOpenAiServer::chunk(
+                json!({ "tool_calls": [{
+                    "index": 0, "id": "call_a", "type": "function",
+                    "function": { "name": "lookup", "arguments": fragment }
+                }] }),
+                None,
+            )
+        };
+        server
+            .mock_stream(vec![
+                tc("{\"q\" "),
+                tc("rust}"),
+                tc("more"),
+                MockOpenAiServer::chunk(json!({}), Some("tool_calls")),
+            ])
+            .await;
+
+        let events: Vec<StreamEvent> = server
+            .adapter()
+            .chat_complete_events_stream(params())
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        let malformed: Vec<_> = events
+            .iter()
+            .enumerate()
+            .filter_map(|(at, e)| match e {
+                StreamEvent::ToolCallMalformed {
+                    name,
+                    arguments,
+                    reason,
+                    ..
+                } => Some((at, name.clone(), arguments.clone(), reason.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(malformed.len(), 1);
+        let (at, name, arguments, reason) = &malformed[0];
+        assert_eq!(name.as_deref(), Some("lookup"));
+        assert_eq!(arguments, "{\"q\" rust}");
+        assert_eq!(reason, "expected ':' at byte 5");
+        // Reported before the third fragment arrived.
+        assert!(events[*at + 1..].iter().any(|e| matches!(
+            e,
+            StreamEvent::ToolCallArgumentsDelta { arguments_fragment, .. } if arguments_fragment == "more"
+        )));
+        assert!(
+            !events
+                .iter()
+                .any(|e| matches!(e, StreamEvent::ToolCallComplete { .. }))
+        );
+        assert!(matches!(events.last(), Some(StreamEvent::MessageEnd)));
+    }
+
+    #[tokio::test]
+    async fn text_stream_preserves_delta_order() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_text_stream(&["Hel", "lo", "!"]).await;
+
+        let adapter = server.adapter();
+        let text: String = adapter
+            .chat_complete_events_stream(params())
+            .filter_map(|e| async move {
+                match e.unwrap() {
+                    StreamEvent::TextDelta(delta) => Some(delta),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+        assert_eq!(text, "Hello!");
+    }
+
+    #[tokio::test]
+    async fn transient_rate_limit_is_retried() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_rate_limited(1, 0).await;
+        server.mock_text("after retry").await;
+
+        let response = server.adapter().chat_complete(params()).await.unwrap();
+        assert!(matches!(response.content, ResponseContent::Finished(_)));
+        assert_eq!(server.received_bodies().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_headers_are_kept_in_a_snapshot() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_rate_limited(1, 0).await;
+        server.mock_text("after retry").await;
+        let adapter = server.adapter();
+        assert!(adapter.rate_limit_snapshot().is_none());
+
+        adapter.chat_complete(params()).await.unwrap();
+
+        // The successful retry carries no headers, so the 429's stay.
+        let snapshot = adapter.rate_limit_snapshot().unwrap();
+        assert_eq!(snapshot.headers.limit_requests, Some(60));
+        assert_eq!(snapshot.request_headroom(), Some(0.0));
+        assert_eq!(snapshot.token_headroom(), None);
+    }
+
+    #[tokio::test]
+    async fn embeddings_are_batched_and_kept_in_input_order() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_embeddings().await;
+
+        let texts: Vec<String> = (0..2050).map(|i| "x".repeat(i % 7)).collect();
+        let response = server
+            .adapter()
+            .embed(
+                texts.clone(),
+                Model::OpenAi(OpenAiModel::TextEmbedding3Small),
+            )
+            .await
+            .unwrap();
+
+        let bodies = server.received_bodies().await;
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["model"], "text-embedding-3-small");
+        assert_eq!(bodies[1]["input"].as_array().unwrap().len(), 2);
+
+        assert_eq!(response.embeddings.len(), texts.len());
+        assert_eq!(response.embeddings[5], vec![5.0, 5.0]);
+        assert_eq!(response.embeddings[2049], vec![1.0, 5.0]);
+        assert_eq!(response.usage.unwrap().prompt_tokens, 2050);
+    }
+
+    #[tokio::test]
+    async fn moderation_guards_the_chat_completion() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_moderation("forbidden", "illicit").await;
+        server.mock_text("ok").await;
+
+        let adapter = server.adapter();
+        let guarded = ModeratedProvider::new(server.adapter(), adapter);
+        let mut request = params();
+        request.messages[0].content = Some("something forbidden".into());
+
+        let err = guarded.chat_complete(request).await.unwrap_err();
+        assert!(matches!(
+            err.root(),
+            ArtificialError::PolicyViolation { categories } if categories == &["illicit"]
+        ));
+        guarded.chat_complete(params()).await.unwrap();
+
+        let paths: Vec<String> = server
+            .server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.url.path().to_owned())
+            .collect();
+        assert_eq!(
+            paths,
+            ["/v1/moderations", "/v1/moderations", "/v1/chat/completions"]
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_results_are_parsed_and_keyed_by_id() {
+        use artificial_core::template::{IntoPrompt, PromptTemplate};
+
+        #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+        struct Answer {
+            value: u32,
+        }
+
+        struct Ask(&'static str);
+
+        impl IntoPrompt for Ask {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new(self.0.into(), GenericRole::User)]
+            }
+        }
+
+        impl PromptTemplate for Ask {
+            type Output = Answer;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+
+            fn postprocess(answer: Answer) -> Answer {
+                Answer {
+                    value: answer.value.min(10),
+                }
+            }
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server
+            .mock_batch(&[("a", r#"{"value":1}"#), ("b", r#"{"value":99}"#)], &["c"])
+            .await;
+
+        let mut batch = server
+            .adapter()
+            .batch::<Answer>()
+            .with_poll_interval(Duration::from_millis(1));
+        batch.push("a", Ask("one")).unwrap();
+        batch.push("b", Ask("ninety-nine")).unwrap();
+        batch.push("c", Ask("broken")).unwrap();
+        batch.push("d", Ask("lost")).unwrap();
+        batch.push("e", Ask("garbled")).unwrap();
+        assert!(batch.push("a", Ask("again")).is_err());
+
+        // An undecodable line fails only its own request.
+        Mock::given(method("GET"))
+            .and(path("/v1/files/file-output/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "{}\n{}\n{{\"custom_id\":\"e\",\"response\":42}}\nnot json\n",
+                batch_answer("a", r#"{"value":1}"#),
+                batch_answer("b", r#"{"value":99}"#),
+            )))
+            .with_priority(1)
+            .mount(&server.server)
+            .await;
+
+        let input = String::from_utf8(batch.to_jsonl().unwrap()).unwrap();
+        let first: Value = serde_json::from_str(input.lines().next().unwrap()).unwrap();
+        assert_eq!(first["url"], "/v1/chat/completions");
+        assert_eq!(first["body"]["response_format"]["type"], "json_schema");
+
+        let mut results = batch.run().await.unwrap();
+        let value = |r: Result<_, _>| {
+            let response: artificial_core::generic::GenericChatCompletionResponse<Answer> =
+                r.unwrap();
+            response.content.expect_finished().value
+        };
+        assert_eq!(value(results.remove("a").unwrap()), 1);
+        assert_eq!(value(results.remove("b").unwrap()), 10);
+        assert!(
+            results
+                .remove("c")
+                .unwrap()
+                .unwrap_err()
+                .to_string()
+                .contains("400")
+        );
+        assert!(results.remove("d").unwrap().is_err());
+        assert!(matches!(
+            results.remove("e").unwrap(),
+            Err(ArtificialError::Serialization(_))
+        ));
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rate_limits_are_recognised_as_overload() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_rate_limited(10, 0).await;
+
+        let err = server.adapter().chat_complete(params()).await.unwrap_err();
+        assert!(crate::error::is_rate_limited(&err));
+        assert!(!crate::error::is_rate_limited(
+            &artificial_core::error::ArtificialError::EmptyResponse
+        ));
+    }
+
+    #[tokio::test]
+    async fn backoff_goes_through_the_sleeper() {
+        use std::sync::{Arc, Mutex};
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_rate_limited(1, 2).await;
+        server.mock_text("after retry").await;
+
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&delays);
+        let adapter = server
+            .options()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 1,
+                sleeper: crate::Sleeper::new(move |delay| {
+                    recorded.lock().unwrap().push(delay);
+                    Box::pin(async {})
+                }),
+                ..RetryPolicy::default()
+            })
+            .build()
+            .unwrap();
+
+        adapter.chat_complete(params()).await.unwrap();
+        assert_eq!(*delays.lock().unwrap(), [Duration::from_secs(2)]);
+    }
+
+    fn recording_sleeper() -> (
+        crate::Sleeper,
+        std::sync::Arc<std::sync::Mutex<Vec<Duration>>>,
+    ) {
+        let delays = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = std::sync::Arc::clone(&delays);
+        let sleeper = crate::Sleeper::new(move |delay| {
+            recorded.lock().unwrap().push(delay);
+            Box::pin(async {})
+        });
+        (sleeper, delays)
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_delays_requests_over_the_limit() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_text("hello").await;
+
+        let (sleeper, delays) = recording_sleeper();
+        let adapter = server
+            .options()
+            .with_retry_policy(RetryPolicy {
+                sleeper,
+                ..RetryPolicy::default()
+            })
+            .with_rate_limiter(crate::RateLimiter::new().with_requests_per_minute(1))
+            .build()
+            .unwrap();
+
+        adapter.chat_complete(params()).await.unwrap();
+        assert!(delays.lock().unwrap().is_empty());
+        adapter.chat_complete(params()).await.unwrap();
+        let delays = delays.lock().unwrap();
+        assert_eq!(delays.len(), 1);
+        assert!(delays[0] > Duration::from_secs(59), "{delays:?}");
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_learns_limits_from_headers() {
+        let server = MockOpenAiServer::start().await;
+        // 60 requests per minute, none left.
+        server.mock_rate_limited(1, 2).await;
+        server.mock_text("after retry").await;
+
+        let (sleeper, delays) = recording_sleeper();
+        let adapter = server
+            .options()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 1,
+                sleeper,
+                ..RetryPolicy::default()
+            })
+            .with_rate_limiter(crate::RateLimiter::new())
+            .build()
+            .unwrap();
+
+        adapter.chat_complete(params()).await.unwrap();
+        let delays = delays.lock().unwrap();
+        // The retry backoff, then the wait for one request to refill.
+        assert_eq!(delays.len(), 2);
+        assert_eq!(delays[0], Duration::from_secs(2));
+        assert!(delays[1] > Duration::from_millis(900), "{delays:?}");
+    }
+
+    #[tokio::test]
+    async fn per_request_timeout_and_cancellation() {
+        use artificial_core::{
+            cancel::CancellationToken, error::ArtificialError, provider::StreamingChatProvider,
+        };
+
+        let server = MockOpenAiServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(completion(
+                        json!({ "role": "assistant", "content": "late" }),
+                        "stop",
+                    ))
+                    .set_delay(Duration::from_millis(500)),
+            )
+            .mount(&server.server)
+            .await;
+        let adapter = server
+            .options()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 0,
+                ..RetryPolicy::default()
+            })
+            .build()
+            .unwrap();
+
+        let err = adapter
+            .chat_complete(params().with_timeout(Duration::from_millis(50)))
+            .await
+            .unwrap_err();
+        match err.root() {
+            ArtificialError::Backend(inner) => assert!(matches!(
+                inner.downcast_ref::<OpenAiError>(),
+                Some(OpenAiError::Http(e)) if e.is_timeout()
+            )),
+            other => panic!("expected a timeout, got {other}"),
+        }
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            cancel.cancel();
+        });
+        let err = adapter
+            .chat_complete(params().with_cancellation(token.clone()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.root(), ArtificialError::Cancelled));
+
+        let mut stream = adapter.chat_complete_stream(params().with_cancellation(token));
+        assert!(matches!(
+            stream.next().await.unwrap().unwrap_err().root(),
+            ArtificialError::Cancelled
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn persistent_rate_limit_surfaces_headers() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_rate_limited(10, 0).await;
+
+        let err = server.adapter().chat_complete(params()).await.unwrap_err();
+        let artificial_core::error::ArtificialError::Backend(inner) = err.root() else {
+            panic!("expected a backend error, got {err}");
+        };
+        match inner.downcast_ref::<OpenAiError>() {
+            Some(OpenAiError::RateLimited { headers, .. }) => {
+                assert_eq!(headers.remaining_requests, Some(0));
+                assert_eq!(headers.reset_requests.as_deref(), Some("1s"));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn json_object_models_get_schema_instructions() {
+        use artificial_core::{
+            model::DeepSeekModel,
+            provider::PromptExecutionProvider,
+            template::{IntoPrompt, PromptTemplate},
+        };
+
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct Answer {
+            value: u32,
+        }
+
+        struct Ask;
+
+        impl IntoPrompt for Ask {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new(
+                    "pick a number".into(),
+                    GenericRole::User,
+                )]
+            }
+        }
+
+        impl PromptTemplate for Ask {
+            type Output = Answer;
+            const MODEL: Model = Model::DeepSeek(DeepSeekModel::DeepSeekChat);
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_text(r#"{"value":7}"#).await;
+
+        let response = server.adapter().prompt_execute(Ask).await.unwrap();
+        assert_eq!(response.content.expect_finished().value, 7);
+
+        let body = &server.received_bodies().await[0];
+        assert_eq!(body["response_format"], json!({ "type": "json_object" }));
+        let last = body["messages"].as_array().unwrap().last().unwrap();
+        assert_eq!(last["role"], "system");
+        assert!(last["content"].as_str().unwrap().contains("\"value\""));
+    }
+
+    #[tokio::test]
+    async fn schema_mode_overrides_the_model_default() {
+        use artificial_core::{
+            model::OpenAiModel,
+            provider::PromptExecutionProvider,
+            schema_util::SchemaMode,
+            template::{IntoPrompt, PromptTemplate, TemplateParams},
+        };
+
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        struct Answer {
+            value: u32,
+        }
+
+        struct Ask<const JSON_MODE: bool>;
+
+        impl<const JSON_MODE: bool> IntoPrompt for Ask<JSON_MODE> {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new(
+                    "pick a number, as {\"value\": n}".into(),
+                    GenericRole::User,
+                )]
+            }
+        }
+
+        impl<const JSON_MODE: bool> PromptTemplate for Ask<JSON_MODE> {
+            type Output = Answer;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+
+            fn params() -> TemplateParams {
+                TemplateParams::new().with_schema_mode(match JSON_MODE {
+                    true => SchemaMode::JsonObject,
+                    false => SchemaMode::None,
+                })
+            }
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_text(r#"{"value":7}"#).await;
+        let adapter = server.adapter();
+
+        let answer = adapter.prompt_execute(Ask::<true>).await.unwrap();
+        assert_eq!(answer.content.expect_finished().value, 7);
+        adapter.prompt_execute(Ask::<false>).await.unwrap();
+
+        let bodies = server.received_bodies().await;
+        assert_eq!(
+            bodies[0]["response_format"],
+            json!({ "type": "json_object" })
+        );
+        assert_eq!(bodies[0]["messages"].as_array().unwrap().len(), 2);
+        assert!(bodies[1].get("response_format").is_none());
+        assert_eq!(bodies[1]["messages"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn template_params_reach_the_request() {
+        use artificial_core::{
+            model::OpenAiModel,
+            provider::{ExecutionPolicy, PromptExecutionProvider},
+            template::{IntoPrompt, PromptTemplate, TemplateParams},
+        };
+
+        struct Extract;
+
+        impl IntoPrompt for Extract {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new("extract".into(), GenericRole::User)]
+            }
+        }
+
+        impl PromptTemplate for Extract {
+            type Output = Value;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+
+            fn params() -> TemplateParams {
+                TemplateParams::new()
+                    .with_temperature(0.0)
+                    .with_max_tokens(256)
+                    .with_seed(1)
+            }
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_text("{}").await;
+        let adapter = server.adapter();
+
+        adapter.prompt_execute(Extract).await.unwrap();
+        let policy = ExecutionPolicy::new().with_temperature_schedule([0.4]);
+        adapter
+            .prompt_execute_with_policy(Extract, policy)
+            .await
+            .unwrap();
+
+        let bodies = server.received_bodies().await;
+        assert_eq!(bodies[0]["temperature"], 0.0);
+        assert_eq!(bodies[0]["max_completion_tokens"], 256);
+        assert_eq!(bodies[0]["seed"], 1);
+        assert_eq!(bodies[1]["temperature"], 0.4);
+        assert_eq!(bodies[1]["max_completion_tokens"], 256);
+    }
+
+    #[tokio::test]
+    async fn timed_out_attempts_lower_the_reasoning_effort() {
+        use artificial_core::{
+            model::OpenAiModel,
+            provider::{PromptExecutionProvider, ReasoningEffort, ThinkingBudget},
+            template::{IntoPrompt, PromptTemplate, TemplateParams},
+        };
+        use wiremock::matchers::body_partial_json;
+
+        struct Solve;
+
+        impl IntoPrompt for Solve {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new("solve".into(), GenericRole::User)]
+            }
+        }
+
+        impl PromptTemplate for Solve {
+            type Output = Value;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::O4Mini);
+
+            fn params() -> TemplateParams {
+                TemplateParams::new().with_thinking_budget(
+                    ThinkingBudget::new(Duration::from_millis(600))
+                        .with_floor(ReasoningEffort::Medium),
+                )
+            }
+        }
+
+        let server = MockOpenAiServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_partial_json(json!({ "reasoning_effort": "high" })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(completion(
+                        json!({ "role": "assistant", "content": "{}" }),
+                        "stop",
+                    ))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .with_priority(1)
+            .mount(&server.server)
+            .await;
+        server.mock_text(r#"{"effort":"medium"}"#).await;
+
+        let answer = server.adapter().prompt_execute(Solve).await.unwrap();
+        assert_eq!(answer.content.expect_finished()["effort"], "medium");
+
+        let efforts: Vec<Value> = server
+            .received_bodies()
+            .await
+            .into_iter()
+            .map(|body| body["reasoning_effort"].clone())
+            .collect();
+        assert_eq!(efforts, [json!("high"), json!("medium")]);
+    }
+
+    #[tokio::test]
+    async fn malformed_output_is_repaired() {
+        use artificial_core::{
+            provider::{ExecutionPolicy, PromptExecutionProvider},
+            template::{IntoPrompt, PromptTemplate},
+        };
+
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct Answer {
+            value: u32,
+        }
+
+        struct Ask;
+
+        impl IntoPrompt for Ask {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new(
+                    "pick a number".into(),
+                    GenericRole::User,
+                )]
+            }
+        }
+
+        impl PromptTemplate for Ask {
+            type Output = Answer;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_text(r#"{"value":7}"#).await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion(
+                json!({ "role": "assistant", "content": r#"{"value":"seven"}"# }),
+                "stop",
+            )))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server.server)
+            .await;
+
+        let response = server
+            .adapter()
+            .prompt_execute_with_policy(
+                Ask,
+                ExecutionPolicy::new()
+                    .with_max_repairs(1)
+                    .with_temperature_schedule([0.0, 0.5])
+                    .with_model_escalation([Model::OpenAi(OpenAiModel::Gpt4o)]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.content.expect_finished().value, 7);
+        assert_eq!(response.usage.unwrap().total_tokens, 2 * 15);
+
+        let bodies = server.received_bodies().await;
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["temperature"], 0.0);
+        assert_eq!(bodies[1]["temperature"], 0.5);
+        assert_eq!(bodies[1]["model"], "gpt-4o");
+        let repair = bodies[1]["messages"].as_array().unwrap();
+        assert_eq!(repair[1]["content"], r#"{"value":"seven"}"#);
+        assert!(
+            repair[2]["content"]
+                .as_str()
+                .unwrap()
+                .contains("could not be parsed")
+        );
+    }
+
+    #[tokio::test]
+    async fn unparsable_output_keeps_the_raw_answer() {
+        use artificial_core::{
+            provider::PromptExecutionProvider,
+            template::{IntoPrompt, PromptTemplate},
+        };
+
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct Answer {
+            #[allow(dead_code)]
+            values: Vec<u32>,
+        }
+
+        struct Ask;
+
+        impl IntoPrompt for Ask {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new(
+                    "pick two numbers".into(),
+                    GenericRole::User,
+                )]
+            }
+        }
+
+        impl PromptTemplate for Ask {
+            type Output = Answer;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_text(r#"{"values": [3, "four"]}"#).await;
+
+        let Err(err) = server.adapter().prompt_execute(Ask).await else {
+            panic!("the answer should not parse");
+        };
+        assert_eq!(err.context().unwrap().template, Some(Ask::name()));
+        let ArtificialError::OutputParse {
+            raw,
+            source,
+            schema_name,
+        } = err.root()
+        else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(raw.as_str(), r#"{"values": [3, "four"]}"#);
+        assert_eq!(source.path().to_string(), "values[1]");
+        assert_eq!(schema_name, "Answer");
+        if !artificial_core::redact::content_visible() {
+            assert_eq!(format!("{raw:?}"), "<redacted: 23 chars>");
+        }
+    }
+
+    #[tokio::test]
+    async fn fenced_output_is_repaired_locally() {
+        use artificial_core::{
+            output_repair::{OutputRepair, Repair},
+            provider::{ExecutionPolicy, PromptExecutionProvider},
+            template::{IntoPrompt, PromptTemplate},
+        };
+
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct Answer {
+            values: Vec<u32>,
+        }
+
+        struct Ask;
+
+        impl IntoPrompt for Ask {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new(
+                    "pick two numbers".into(),
+                    GenericRole::User,
+                )]
+            }
+        }
+
+        impl PromptTemplate for Ask {
+            type Output = Answer;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server
+            .mock_text("Here you go:\n```json\n{\"values\": [3, 4,]}\n```")
+            .await;
+
+        let response = server
+            .adapter()
+            .prompt_execute_with_policy(
+                Ask,
+                ExecutionPolicy::new().with_output_repair(OutputRepair::new()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response.metadata.repairs,
+            [Repair::StrippedCodeFence, Repair::RemovedTrailingCommas]
+        );
+        assert_eq!(response.content.expect_finished().values, [3, 4]);
+        assert_eq!(server.received_bodies().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn prompt_stream_yields_deltas_then_parsed_output() {
+        use artificial_core::{
+            provider::{PromptStreamEvent, PromptStreamingProvider},
+            template::{IntoPrompt, PromptTemplate},
+        };
+        use futures_util::StreamExt;
+
+        #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+        #[serde(deny_unknown_fields)]
+        struct Answer {
+            value: u32,
+        }
+
+        struct Ask;
+
+        impl IntoPrompt for Ask {
+            type Message = GenericMessage;
+            fn into_prompt(self) -> Vec<Self::Message> {
+                vec![GenericMessage::new(
+                    "pick a number".into(),
+                    GenericRole::User,
+                )]
+            }
+        }
+
+        impl PromptTemplate for Ask {
+            type Output = Answer;
+            const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+        }
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_text_stream(&[r#"{"val"#, r#"ue":7}"#]).await;
+
+        let adapter = server.adapter();
+        let events: Vec<_> = adapter.prompt_execute_stream(Ask).collect().await;
+        let events: Vec<_> = events.into_iter().map(Result::unwrap).collect();
+
+        assert!(matches!(&events[0], PromptStreamEvent::Delta(d) if d == r#"{"val"#));
+        assert!(matches!(&events[1], PromptStreamEvent::Delta(d) if d == r#"ue":7}"#));
+        assert!(matches!(
+            &events[2],
+            PromptStreamEvent::Finished(Answer { value: 7 })
+        ));
+
+        let body = &server.received_bodies().await[0];
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["response_format"]["type"], "json_schema");
+    }
+
+    #[tokio::test]
+    async fn whitespace_answer_is_an_empty_response() {
+        use artificial_core::error::ArtificialError;
+
+        let server = MockOpenAiServer::start().await;
+        server.mock_text("  \n").await;
+
+        let err = server.adapter().chat_complete(params()).await.unwrap_err();
+        assert!(matches!(err.root(), ArtificialError::EmptyResponse));
+    }
+
+    #[tokio::test]
+    async fn health_check_lists_models() {
+        let server = MockOpenAiServer::start().await;
+        server.mock_models(&["gpt-4o-mini"]).await;
+
+        let health = server.adapter().check_health().await;
+        assert!(health[0].status.is_healthy());
+    }
+}
@@ -45,14 +45,24 @@
 //!
 //! The generic parameter `Message` allows back-ends to plug in their own, richer
 //! message types while reusing the same chaining logic.
-use artificial_core::template::IntoPrompt;
+use artificial_core::{
+    generic::{GenericMessage, GenericRole},
+    model::Model,
+    template::IntoPrompt,
+    tokens::{TokenCounter, counter_for},
+};
+
+use crate::{snapshot::PromptSnapshot, transform::MessageTransform};
 
 /// Lightweight container that accumulates messages produced by
 /// [`IntoPrompt`] implementors.
 ///
-/// The single `Vec` field is kept private so the only way to obtain the result
-/// is through [`Self::build`], ensuring the builder API remains fluent.
-pub struct PromptChain<Message>(Vec<Message>);
+/// The fields are kept private so the only way to obtain the result is
+/// through [`Self::build`], ensuring the builder API remains fluent.
+pub struct PromptChain<Message> {
+    messages: Vec<Message>,
+    transforms: Vec<Box<dyn MessageTransform<Message>>>,
+}
 
 impl<Message> Default for PromptChain<Message> {
     fn default() -> Self {
@@ -63,7 +73,10 @@ impl<Message> Default for PromptChain<Message> {
 impl<Message> PromptChain<Message> {
     /// Create an empty chain.
     pub fn new() -> Self {
-        Self(vec![])
+        Self {
+            messages: vec![],
+            transforms: vec![],
+        }
     }
 
     /// Append the messages produced by `with` to the chain.
@@ -81,12 +94,351 @@ impl<Message> PromptChain<Message> {
     ///     .build();
     /// ```
     pub fn with(mut self, with: impl IntoPrompt<Message = Message>) -> Self {
-        self.0.append(&mut with.into_prompt());
+        self.messages.append(&mut with.into_prompt());
+        self
+    }
+
+    /// Append `fragment` only if `condition` holds.
+    ///
+    /// ```rust
+    /// # use artificial_prompt::chain::PromptChain;
+    /// # use artificial_core::generic::{GenericMessage, GenericRole};
+    /// let verbose = false;
+    /// let messages = PromptChain::new()
+    ///     .with(GenericMessage::new("Summarise.".into(), GenericRole::User))
+    ///     .with_if(verbose, GenericMessage::new("Explain each step.".into(), GenericRole::User))
+    ///     .build();
+    /// assert_eq!(messages.len(), 1);
+    /// ```
+    pub fn with_if(self, condition: bool, fragment: impl IntoPrompt<Message = Message>) -> Self {
+        match condition {
+            true => self.with(fragment),
+            false => self,
+        }
+    }
+
+    /// Append `fragment` if there is one.
+    ///
+    /// ```rust
+    /// # use artificial_prompt::chain::PromptChain;
+    /// # use artificial_core::generic::{GenericMessage, GenericRole};
+    /// let profile: Option<&str> = Some("Prefers short answers.");
+    /// let messages = PromptChain::new()
+    ///     .with_opt(profile.map(|p| GenericMessage::new(p.into(), GenericRole::System)))
+    ///     .build();
+    /// assert_eq!(messages.len(), 1);
+    /// ```
+    pub fn with_opt(self, fragment: Option<impl IntoPrompt<Message = Message>>) -> Self {
+        match fragment {
+            Some(fragment) => self.with(fragment),
+            None => self,
+        }
+    }
+
+    /// Append the fragment `f` builds for each item, in order.
+    ///
+    /// ```rust
+    /// # use artificial_prompt::chain::PromptChain;
+    /// # use artificial_core::generic::{GenericMessage, GenericRole};
+    /// let history = [("alice", "hi"), ("bob", "hello")];
+    /// let messages = PromptChain::new()
+    ///     .with_each(history, |(from, text)| {
+    ///         GenericMessage::new(format!("{from}: {text}"), GenericRole::User)
+    ///     })
+    ///     .build();
+    /// assert_eq!(messages.len(), 2);
+    /// ```
+    pub fn with_each<I, F, P>(self, items: I, f: F) -> Self
+    where
+        I: IntoIterator,
+        F: FnMut(I::Item) -> P,
+        P: IntoPrompt<Message = Message>,
+    {
+        items.into_iter().map(f).fold(self, Self::with)
+    }
+
+    /// Rewrite every message with `f` when the chain is built.
+    ///
+    /// Applies to all messages regardless of which fragment produced them or
+    /// whether they were added before or after this call, so cross-cutting
+    /// rules live in one place instead of in each fragment.
+    ///
+    /// ```rust
+    /// # use artificial_prompt::chain::PromptChain;
+    /// # use artificial_core::generic::{GenericMessage, GenericRole};
+    /// let messages = PromptChain::new()
+    ///     .map_messages(|mut message: GenericMessage| {
+    ///         message.name = Some("web".into());
+    ///         message
+    ///     })
+    ///     .with(GenericMessage::new("hi".into(), GenericRole::User))
+    ///     .build();
+    /// assert_eq!(messages[0].name.as_deref(), Some("web"));
+    /// ```
+    pub fn map_messages<F>(self, f: F) -> Self
+    where
+        F: Fn(Message) -> Message + Send + Sync + 'static,
+    {
+        self.with_transform(f)
+    }
+
+    /// Apply `transform` to every message when the chain is built, after
+    /// the transforms added before it. See [`crate::transform`].
+    pub fn with_transform(mut self, transform: impl MessageTransform<Message> + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
         self
     }
 
     /// Consume the builder and return the accumulated messages.
     pub fn build(self) -> Vec<Message> {
-        self.0
+        let Self {
+            messages,
+            transforms,
+        } = self;
+        messages
+            .into_iter()
+            .map(|message| apply_transforms(&transforms, message))
+            .collect()
+    }
+}
+
+fn apply_transforms<Message>(
+    transforms: &[Box<dyn MessageTransform<Message>>],
+    message: Message,
+) -> Message {
+    transforms
+        .iter()
+        .fold(message, |message, transform| transform.transform(message))
+}
+
+/// Rules applied by [`PromptChain::build_normalized_with`].
+///
+/// The default merges and front-loads system messages and drops empty
+/// ones; history is only collapsed on request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    /// Move every system message ahead of the conversation, keeping their
+    /// order.
+    pub system_first: bool,
+    /// Join consecutive system messages into one, separated by a blank line.
+    pub merge_system: bool,
+    /// Drop messages without text, tool calls or attachments. Tool results
+    /// are kept so they still answer their call.
+    pub drop_empty: bool,
+    /// Replace runs of more than this many plain user and assistant
+    /// messages with a single user message holding the transcript. The last
+    /// message of the prompt is never collapsed.
+    pub collapse_history_over: Option<usize>,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            system_first: true,
+            merge_system: true,
+            drop_empty: true,
+            collapse_history_over: None,
+        }
+    }
+}
+
+impl Normalization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_history_collapsed_over(mut self, max_messages: usize) -> Self {
+        self.collapse_history_over = Some(max_messages);
+        self
+    }
+
+    /// Apply the rules to `messages`.
+    pub fn apply(&self, messages: Vec<GenericMessage>) -> Vec<GenericMessage> {
+        let mut messages = messages;
+        if self.drop_empty {
+            messages.retain(|message| !is_empty(message));
+        }
+        if self.system_first {
+            let (system, rest): (Vec<_>, Vec<_>) = messages
+                .into_iter()
+                .partition(|message| message.role == GenericRole::System);
+            messages = system.into_iter().chain(rest).collect();
+        }
+        if self.merge_system {
+            messages = merge_system(messages);
+        }
+        if let Some(max) = self.collapse_history_over {
+            messages = collapse_history(messages, max);
+        }
+        messages
+    }
+}
+
+fn is_empty(message: &GenericMessage) -> bool {
+    message
+        .content
+        .as_deref()
+        .is_none_or(|content| content.trim().is_empty())
+        && message.tool_calls.as_ref().is_none_or(Vec::is_empty)
+        && message.attachments.is_empty()
+        && message.role != GenericRole::Tool
+}
+
+fn merge_system(messages: Vec<GenericMessage>) -> Vec<GenericMessage> {
+    let mut merged: Vec<GenericMessage> = Vec::with_capacity(messages.len());
+    for message in messages {
+        match merged.last_mut() {
+            Some(previous)
+                if previous.role == GenericRole::System && message.role == GenericRole::System =>
+            {
+                let text = message.content.unwrap_or_default();
+                match &mut previous.content {
+                    Some(content) if !content.is_empty() => {
+                        content.push_str("\n\n");
+                        content.push_str(&text);
+                    }
+                    content => *content = Some(text),
+                }
+            }
+            _ => merged.push(message),
+        }
+    }
+    merged
+}
+
+/// Plain text turns that can be folded into a transcript.
+fn is_plain_turn(message: &GenericMessage) -> bool {
+    matches!(message.role, GenericRole::User | GenericRole::Assistant)
+        && message.tool_calls.is_none()
+        && message.attachments.is_empty()
+}
+
+fn collapse_history(messages: Vec<GenericMessage>, max: usize) -> Vec<GenericMessage> {
+    let last = messages.len().saturating_sub(1);
+    let mut collapsed = Vec::with_capacity(messages.len());
+    let mut run: Vec<GenericMessage> = Vec::new();
+    for (index, message) in messages.into_iter().enumerate() {
+        if index < last && is_plain_turn(&message) {
+            run.push(message);
+            continue;
+        }
+        flush_run(&mut collapsed, &mut run, max);
+        collapsed.push(message);
+    }
+    flush_run(&mut collapsed, &mut run, max);
+    collapsed
+}
+
+fn flush_run(out: &mut Vec<GenericMessage>, run: &mut Vec<GenericMessage>, max: usize) {
+    if run.len() <= max {
+        out.append(run);
+        return;
+    }
+    let transcript: Vec<String> = run
+        .drain(..)
+        .map(|message| {
+            let speaker = message.name.unwrap_or_else(|| message.role.to_string());
+            format!("{speaker}: {}", message.content.unwrap_or_default())
+        })
+        .collect();
+    out.push(GenericMessage::new(
+        format!("Conversation so far:\n{}", transcript.join("\n")),
+        GenericRole::User,
+    ));
+}
+
+impl PromptChain<GenericMessage> {
+    /// Like [`Self::build`], with the [`Normalization`] defaults applied:
+    /// system messages first and merged into one, empty messages dropped.
+    ///
+    /// Fragments naturally produce several system messages, which some
+    /// providers reject or silently ignore beyond the first.
+    ///
+    /// ```rust
+    /// # use artificial_prompt::chain::PromptChain;
+    /// # use artificial_core::generic::{GenericMessage, GenericRole};
+    /// let messages = PromptChain::new()
+    ///     .with(GenericMessage::new("You are terse.".into(), GenericRole::System))
+    ///     .with(GenericMessage::new("Summarise the ticket.".into(), GenericRole::User))
+    ///     .with(GenericMessage::new("Today is Monday.".into(), GenericRole::System))
+    ///     .with(GenericMessage::new("".into(), GenericRole::User))
+    ///     .build_normalized();
+    ///
+    /// assert_eq!(messages.len(), 2);
+    /// assert_eq!(messages[0].content.as_deref(), Some("You are terse.\n\nToday is Monday."));
+    /// assert_eq!(messages[1].role, GenericRole::User);
+    /// ```
+    pub fn build_normalized(self) -> Vec<GenericMessage> {
+        self.build_normalized_with(Normalization::default())
+    }
+
+    /// Like [`Self::build`], with `normalization` applied.
+    ///
+    /// ```rust
+    /// # use artificial_prompt::chain::{Normalization, PromptChain};
+    /// # use artificial_core::generic::{GenericMessage, GenericRole};
+    /// let turns = ["hi", "hello", "how are you?", "fine", "and now?"];
+    /// let messages = PromptChain::new()
+    ///     .with_each(turns.iter().enumerate(), |(i, text)| {
+    ///         let role = if i % 2 == 0 { GenericRole::User } else { GenericRole::Assistant };
+    ///         GenericMessage::new(text.to_string(), role)
+    ///     })
+    ///     .build_normalized_with(Normalization::new().with_history_collapsed_over(2));
+    ///
+    /// assert_eq!(messages.len(), 2);
+    /// assert!(messages[0].content.as_deref().unwrap().starts_with("Conversation so far:\nuser: hi"));
+    /// assert_eq!(messages[1].content.as_deref(), Some("and now?"));
+    /// ```
+    pub fn build_normalized_with(self, normalization: Normalization) -> Vec<GenericMessage> {
+        normalization.apply(self.build())
+    }
+
+    /// Estimated prompt tokens of the accumulated messages for `model`.
+    ///
+    /// Uses [`counter_for`], i.e. exact counts with the `tiktoken` feature
+    /// and an approximation otherwise. Compare the result with
+    /// `model.capabilities().context_window` before sending long prompts:
+    ///
+    /// ```rust
+    /// # use artificial_prompt::chain::PromptChain;
+    /// # use artificial_core::generic::{GenericMessage, GenericRole};
+    /// # use artificial_core::model::{Model, OpenAiModel};
+    /// let model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+    /// let chain = PromptChain::new()
+    ///     .with(GenericMessage::new("Summarise the ticket.".into(), GenericRole::User));
+    ///
+    /// let window = model.capabilities().context_window.unwrap() as usize;
+    /// assert!(chain.estimated_tokens(&model) < window);
+    /// ```
+    pub fn estimated_tokens(&self, model: &Model) -> usize {
+        self.estimated_tokens_with(counter_for(model).as_ref())
+    }
+
+    /// Like [`Self::estimated_tokens`] with an explicit counter.
+    pub fn estimated_tokens_with(&self, counter: &dyn TokenCounter) -> usize {
+        if self.transforms.is_empty() {
+            return counter.count_messages(&self.messages);
+        }
+        counter.count_messages(&self.transformed())
+    }
+
+    /// The messages [`Self::build`] would return, rendered as text for
+    /// review and snapshot tests; see [`crate::snapshot`] for the format.
+    pub fn render_debug(&self) -> String {
+        self.snapshot().to_string()
+    }
+
+    /// The messages [`Self::build`] would return, as a [`PromptSnapshot`].
+    pub fn snapshot(&self) -> PromptSnapshot {
+        PromptSnapshot::new(self.transformed())
+    }
+
+    /// Like [`Self::build`], without consuming the chain.
+    fn transformed(&self) -> Vec<GenericMessage> {
+        self.messages
+            .iter()
+            .cloned()
+            .map(|message| apply_transforms(&self.transforms, message))
+            .collect()
     }
 }
@@ -0,0 +1,161 @@
+//! Templates that **extend a shared base**.
+//!
+//! Most prompts of an application start the same way: a system role, output
+//! rules, maybe the current date.  Instead of re-assembling that prefix in
+//! every [`IntoPrompt`] impl, describe it once as a [`BaseTemplate`] and let
+//! concrete templates implement [`LayeredTemplate`], which only appends
+//! their own fragments and picks the output type:
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_core::model::{Model, OpenAiModel};
+//! use artificial_core::template::{IntoPrompt, PromptTemplate};
+//! use artificial_prompt::chain::PromptChain;
+//! use artificial_prompt::layered::{BaseTemplate, Layered, LayeredTemplate};
+//!
+//! struct Assistant;
+//!
+//! impl BaseTemplate for Assistant {
+//!     type Message = GenericMessage;
+//!     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+//!
+//!     fn base() -> PromptChain<GenericMessage> {
+//!         PromptChain::new()
+//!             .with(GenericMessage::new("You are R2-D2.".into(), GenericRole::System))
+//!     }
+//! }
+//!
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct Greeting { text: String }
+//!
+//! struct Greet(&'static str);
+//!
+//! impl LayeredTemplate for Greet {
+//!     type Base = Assistant;
+//!     type Output = Greeting;
+//!     // `MODEL` defaults to the base's model; override it if needed.
+//!     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4o);
+//!
+//!     fn extend(self, chain: PromptChain<GenericMessage>) -> PromptChain<GenericMessage> {
+//!         chain.with(GenericMessage::new(format!("Greet {}.", self.0), GenericRole::User))
+//!     }
+//! }
+//!
+//! let prompt = Layered(Greet("Luke"));
+//! assert_eq!(<Layered<Greet> as PromptTemplate>::MODEL, Model::OpenAi(OpenAiModel::Gpt4o));
+//! assert_eq!(prompt.into_prompt().len(), 2);
+//! ```
+//!
+//! Bases can build on other bases by starting their [`BaseTemplate::base`]
+//! with `Parent::base()`.
+use std::any::Any;
+
+use artificial_core::{
+    model::Model,
+    template::{IntoPrompt, PromptTemplate, TemplateParams, WarmablePrompt},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::chain::PromptChain;
+
+/// The shared leading messages and defaults of a family of templates.
+pub trait BaseTemplate {
+    type Message: Send + Sync;
+
+    /// Default model of every template extending this base.
+    const MODEL: Model;
+
+    /// The messages every extending template starts with.
+    fn base() -> PromptChain<Self::Message>;
+
+    /// Prompt cache key shared by all extending templates – they share the
+    /// base prefix, so they benefit from the same cache. Defaults to `None`.
+    fn cache_key() -> Option<&'static str> {
+        None
+    }
+
+    /// Default generation settings of every extending template.
+    fn params() -> TemplateParams {
+        TemplateParams::default()
+    }
+}
+
+/// A concrete template on top of [`Self::Base`].
+///
+/// Wrap a value in [`Layered`] to execute it.
+pub trait LayeredTemplate {
+    type Base: BaseTemplate;
+
+    /// See [`PromptTemplate::Output`].
+    type Output: JsonSchema + for<'de> Deserialize<'de> + Any;
+
+    /// Defaults to the base's model.
+    const MODEL: Model = <Self::Base as BaseTemplate>::MODEL;
+
+    /// Append this template's fragments to the base chain.
+    fn extend(
+        self,
+        chain: PromptChain<<Self::Base as BaseTemplate>::Message>,
+    ) -> PromptChain<<Self::Base as BaseTemplate>::Message>;
+
+    /// See [`PromptTemplate::version`].
+    fn version() -> Option<&'static str> {
+        None
+    }
+
+    /// See [`PromptTemplate::postprocess`].
+    fn postprocess(output: Self::Output) -> Self::Output {
+        output
+    }
+
+    /// Defaults to the base's settings.
+    fn params() -> TemplateParams {
+        <Self::Base as BaseTemplate>::params()
+    }
+}
+
+/// Adapter that turns a [`LayeredTemplate`] into a [`PromptTemplate`].
+///
+/// The template name reported in errors is the one of `T`, and the base
+/// prefix doubles as [`WarmablePrompt::static_prefix`].
+pub struct Layered<T>(pub T);
+
+impl<T: LayeredTemplate> IntoPrompt for Layered<T> {
+    type Message = <T::Base as BaseTemplate>::Message;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        self.0.extend(T::Base::base()).build()
+    }
+}
+
+impl<T: LayeredTemplate> PromptTemplate for Layered<T> {
+    type Output = T::Output;
+    const MODEL: Model = T::MODEL;
+
+    fn name() -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn version() -> Option<&'static str> {
+        T::version()
+    }
+
+    fn cache_key() -> Option<&'static str> {
+        T::Base::cache_key()
+    }
+
+    fn postprocess(output: Self::Output) -> Self::Output {
+        T::postprocess(output)
+    }
+
+    fn params() -> TemplateParams {
+        T::params()
+    }
+}
+
+impl<T: LayeredTemplate> WarmablePrompt for Layered<T> {
+    fn static_prefix() -> Vec<Self::Message> {
+        T::Base::base().build()
+    }
+}
@@ -1,2 +1,5 @@
 pub mod builder;
 pub mod chain;
+pub mod layered;
+pub mod snapshot;
+pub mod transform;
@@ -0,0 +1,122 @@
+//! Plain-text renderings of prompts for review and snapshot tests.
+//!
+//! Prompt wording drifts as fragments and transforms change, and the final
+//! message list is what the model actually sees. [`PromptSnapshot`] writes it
+//! in a stable, line-oriented format that diffs well, so it can be pinned
+//! with `insta::assert_snapshot!` or a plain `assert_eq!`:
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_prompt::chain::PromptChain;
+//!
+//! let chain = PromptChain::new()
+//!     .with(GenericMessage::new("You are terse.".into(), GenericRole::System))
+//!     .with(GenericMessage::new("Summarise:\nthe ticket".into(), GenericRole::User));
+//!
+//! assert_eq!(
+//!     chain.render_debug(),
+//!     "=== #0 system\nYou are terse.\n\n=== #1 user\nSummarise:\nthe ticket\n"
+//! );
+//! ```
+//!
+//! Every message starts with a `=== #<index> <role>` header, followed by
+//! `name=` and `tool_call_id=` if set. Content is written verbatim, then one
+//! `-> tool_call` line per requested tool call (arguments as canonical JSON,
+//! see [`artificial_core::canonical`]) and one `-> attachment` line per
+//! attachment. Reasoning is not part of the prompt and is left out.
+use std::fmt::{self, Display, Write};
+
+use artificial_core::{
+    canonical,
+    generic::{GenericAttachment, GenericMessage},
+    template::IntoPrompt,
+};
+
+/// A rendered message list, see the [module docs](self) for the format.
+///
+/// ```rust
+/// use artificial_core::generic::*;
+/// use artificial_prompt::snapshot::PromptSnapshot;
+///
+/// let mut call = GenericMessage::new(String::new(), GenericRole::Assistant);
+/// call.tool_calls = Some(vec![GenericFunctionCallIntent {
+///     id: "call-1".into(),
+///     function: GenericFunctionCall {
+///         name: "weather".into(),
+///         arguments: serde_json::json!({ "unit": "c", "city": "Rome" }),
+///     },
+/// }]);
+/// let mut result = GenericMessage::new("22°C".into(), GenericRole::Tool);
+/// result.tool_call_id = Some("call-1".into());
+///
+/// assert_eq!(
+///     PromptSnapshot::new(vec![call, result]).to_string(),
+///     concat!(
+///         "=== #0 assistant\n",
+///         "-> tool_call call-1 weather {\"city\":\"Rome\",\"unit\":\"c\"}\n",
+///         "\n",
+///         "=== #1 tool tool_call_id=call-1\n",
+///         "22°C\n",
+///     )
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PromptSnapshot {
+    messages: Vec<GenericMessage>,
+}
+
+impl PromptSnapshot {
+    pub fn new(messages: Vec<GenericMessage>) -> Self {
+        Self { messages }
+    }
+
+    /// Render any prompt, e.g. a template with sample data.
+    pub fn of(prompt: impl IntoPrompt<Message = GenericMessage>) -> Self {
+        Self::new(prompt.into_prompt())
+    }
+
+    pub fn messages(&self) -> &[GenericMessage] {
+        &self.messages
+    }
+}
+
+impl Display for PromptSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, message) in self.messages.iter().enumerate() {
+            if index > 0 {
+                f.write_char('\n')?;
+            }
+            write!(f, "=== #{index} {}", message.role)?;
+            if let Some(name) = &message.name {
+                write!(f, " name={name}")?;
+            }
+            if let Some(id) = &message.tool_call_id {
+                write!(f, " tool_call_id={id}")?;
+            }
+            f.write_char('\n')?;
+            if let Some(content) = message.content.as_deref().filter(|c| !c.is_empty()) {
+                f.write_str(content)?;
+                if !content.ends_with('\n') {
+                    f.write_char('\n')?;
+                }
+            }
+            for call in message.tool_calls.iter().flatten() {
+                let arguments = canonical::to_string(&call.function.arguments)
+                    .expect("JSON values always serialise");
+                writeln!(
+                    f,
+                    "-> tool_call {} {} {arguments}",
+                    call.id, call.function.name
+                )?;
+            }
+            for attachment in &message.attachments {
+                match attachment {
+                    GenericAttachment::File { file_id } => {
+                        writeln!(f, "-> attachment file {file_id}")?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
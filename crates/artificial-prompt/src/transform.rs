@@ -0,0 +1,116 @@
+//! Rewrites applied to every message of a [`PromptChain`] when it is built.
+//!
+//! Fragments describe *what* goes into a prompt; rules that apply across
+//! all of them – marking user input, stripping markup pasted from the web,
+//! redacting identifiers – are attached to the chain instead:
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_prompt::chain::PromptChain;
+//! use artificial_prompt::transform::{MapContent, StripHtml};
+//!
+//! let messages = PromptChain::new()
+//!     .with(GenericMessage::new("Answer briefly.".into(), GenericRole::System))
+//!     .with(GenericMessage::new("<p>Is <b>this</b> safe?</p>".into(), GenericRole::User))
+//!     .with_transform(StripHtml)
+//!     .with_transform(MapContent::for_role(GenericRole::User, |text| format!("[user] {text}")))
+//!     .build();
+//!
+//! assert_eq!(messages[0].content.as_deref(), Some("Answer briefly."));
+//! assert_eq!(messages[1].content.as_deref(), Some("[user] Is this safe?"));
+//! ```
+//!
+//! Transforms run in the order they were added. Any `Fn(Message) -> Message`
+//! is a transform, see [`PromptChain::map_messages`].
+//!
+//! [`PromptChain`]: crate::chain::PromptChain
+//! [`PromptChain::map_messages`]: crate::chain::PromptChain::map_messages
+use artificial_core::generic::{GenericMessage, GenericRole};
+
+/// Rewrites one message.
+pub trait MessageTransform<Message>: Send + Sync {
+    fn transform(&self, message: Message) -> Message;
+}
+
+impl<Message, F> MessageTransform<Message> for F
+where
+    F: Fn(Message) -> Message + Send + Sync,
+{
+    fn transform(&self, message: Message) -> Message {
+        self(message)
+    }
+}
+
+/// Rewrites the text content of messages, optionally of one role only.
+///
+/// Messages without text content are left alone.
+pub struct MapContent<F> {
+    role: Option<GenericRole>,
+    f: F,
+}
+
+impl<F> MapContent<F>
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    pub fn new(f: F) -> Self {
+        Self { role: None, f }
+    }
+
+    pub fn for_role(role: GenericRole, f: F) -> Self {
+        Self {
+            role: Some(role),
+            f,
+        }
+    }
+}
+
+impl<F> MessageTransform<GenericMessage> for MapContent<F>
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn transform(&self, mut message: GenericMessage) -> GenericMessage {
+        if self.role.is_none_or(|role| role == message.role) {
+            message.content = message.content.map(|content| (self.f)(&content));
+        }
+        message
+    }
+}
+
+/// Removes HTML tags and decodes the common entities, e.g. from text
+/// scraped off web pages. Not a sanitiser: the result is meant for a model,
+/// not for rendering.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripHtml;
+
+impl MessageTransform<GenericMessage> for StripHtml {
+    fn transform(&self, mut message: GenericMessage) -> GenericMessage {
+        message.content = message.content.map(|content| strip_html(&content));
+        message
+    }
+}
+
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    [
+        ("&nbsp;", " "),
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&quot;", "\""),
+        ("&#39;", "'"),
+        ("&amp;", "&"),
+    ]
+    .iter()
+    .fold(text, |text, (entity, replacement)| {
+        text.replace(entity, replacement)
+    })
+}
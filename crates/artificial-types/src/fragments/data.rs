@@ -0,0 +1,292 @@
+//! Fragment that renders structured data into the prompt.
+//!
+//! Profiles, records and search results are usually `Serialize` already;
+//! `DataFragment` turns them into a fenced block (or a Markdown table) with
+//! an optional heading, so fragments do not each pick and hand-roll a
+//! serialisation:
+//!
+//! ```rust
+//! use artificial_core::template::IntoPrompt;
+//! use artificial_types::fragments::{DataFormat, DataFragment};
+//!
+//! #[derive(serde::Serialize)]
+//! struct Order { id: u32, item: &'static str }
+//!
+//! let orders = [Order { id: 1, item: "lamp" }, Order { id: 2, item: "desk" }];
+//! let message = DataFragment::new(&orders)
+//!     .with_title("Open orders")
+//!     .with_format(DataFormat::MarkdownTable)
+//!     .into_prompt()
+//!     .remove(0);
+//!
+//! assert_eq!(
+//!     message.content.unwrap(),
+//!     "## Open orders\n| id | item |\n| --- | --- |\n| 1 | lamp |\n| 2 | desk |\n"
+//! );
+//! ```
+//!
+//! With [`DataFragment::with_max_chars`] large data is cut down to a size
+//! budget: arrays keep as many leading items as fit and say how many were
+//! left out, other values are truncated.
+//!
+//! Object keys are rendered in sorted order. A value that fails to
+//! serialise renders as `<serialization error: …>` rather than failing the
+//! whole prompt.
+
+use artificial_core::{
+    generic::{GenericMessage, GenericRole},
+    template::IntoPrompt,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// How [`DataFragment`] renders its data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataFormat {
+    /// A Markdown table for arrays of flat objects, otherwise YAML with the
+    /// `yaml` feature and JSON without it.
+    #[default]
+    Auto,
+    /// Pretty-printed JSON.
+    Json,
+    /// A table with one row per array item, or one per key for a single
+    /// object. Other values fall back to JSON.
+    MarkdownTable,
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// Falls back to JSON for values TOML cannot express, e.g. a top-level
+    /// array or `null`.
+    #[cfg(feature = "toml")]
+    Toml,
+}
+
+/// Any serde value, rendered as a single message.
+pub struct DataFragment<T> {
+    data: T,
+    title: Option<String>,
+    format: DataFormat,
+    max_chars: Option<usize>,
+    role: GenericRole,
+}
+
+impl<T: Serialize> DataFragment<T> {
+    /// Render `data` as a system message in [`DataFormat::Auto`].
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            title: None,
+            format: DataFormat::Auto,
+            max_chars: None,
+            role: GenericRole::System,
+        }
+    }
+
+    /// Put a `##` heading above the data.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_format(mut self, format: DataFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Keep the rendered data within roughly `max_chars` characters.
+    ///
+    /// ```rust
+    /// use artificial_core::template::IntoPrompt;
+    /// use artificial_types::fragments::{DataFormat, DataFragment};
+    ///
+    /// let numbers: Vec<u32> = (0..1000).collect();
+    /// let text = DataFragment::new(numbers)
+    ///     .with_format(DataFormat::Json)
+    ///     .with_max_chars(200)
+    ///     .into_prompt()
+    ///     .remove(0)
+    ///     .content
+    ///     .unwrap();
+    ///
+    /// assert!(text.chars().count() < 300);
+    /// assert!(text.ends_with("more items omitted)\n"));
+    /// ```
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    pub fn with_role(mut self, role: GenericRole) -> Self {
+        self.role = role;
+        self
+    }
+}
+
+impl<T: Serialize> IntoPrompt for DataFragment<T> {
+    type Message = GenericMessage;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        let mut content = String::new();
+        if let Some(title) = &self.title {
+            content.push_str(&format!("## {title}\n"));
+        }
+        match serde_json::to_value(&self.data) {
+            Ok(value) => content.push_str(&render_within(&value, self.format, self.max_chars)),
+            Err(err) => content.push_str(&format!("<serialization error: {err}>\n")),
+        }
+        vec![GenericMessage::new(content, self.role)]
+    }
+}
+
+/// Render `value`, dropping trailing array items or truncating until it
+/// fits `max_chars`.
+fn render_within(value: &Value, format: DataFormat, max_chars: Option<usize>) -> String {
+    let full = render(value, format);
+    let Some(max_chars) = max_chars else {
+        return full;
+    };
+    if full.chars().count() <= max_chars {
+        return full;
+    }
+
+    match value {
+        Value::Array(items) => {
+            // The rendering grows with the number of items kept.
+            let fits = |kept: usize| {
+                render(&Value::Array(items[..kept].to_vec()), format)
+                    .chars()
+                    .count()
+                    <= max_chars
+            };
+            let (mut low, mut high) = (0, items.len());
+            while low < high {
+                let mid = (low + high).div_ceil(2);
+                if fits(mid) {
+                    low = mid;
+                } else {
+                    high = mid - 1;
+                }
+            }
+            let mut rendered = render(&Value::Array(items[..low].to_vec()), format);
+            rendered.push_str(&format!(
+                "({} of {} items shown, {} more items omitted)\n",
+                low,
+                items.len(),
+                items.len() - low
+            ));
+            rendered
+        }
+        _ => {
+            let mut rendered: String = full.chars().take(max_chars).collect();
+            rendered.push_str("\n… (truncated)\n");
+            rendered
+        }
+    }
+}
+
+fn render(value: &Value, format: DataFormat) -> String {
+    match format {
+        DataFormat::Auto => match markdown_table(value) {
+            Some(table) if value.is_array() => table,
+            _ => render_default(value),
+        },
+        DataFormat::Json => fenced("json", &pretty_json(value)),
+        DataFormat::MarkdownTable => {
+            markdown_table(value).unwrap_or_else(|| fenced("json", &pretty_json(value)))
+        }
+        #[cfg(feature = "yaml")]
+        DataFormat::Yaml => fenced(
+            "yaml",
+            &serde_yaml::to_string(value)
+                .unwrap_or_else(|err| format!("<serialization error: {err}>")),
+        ),
+        #[cfg(feature = "toml")]
+        DataFormat::Toml => match toml::to_string(value) {
+            Ok(toml) => fenced("toml", &toml),
+            Err(_) => fenced("json", &pretty_json(value)),
+        },
+    }
+}
+
+#[cfg(feature = "yaml")]
+fn render_default(value: &Value) -> String {
+    render(value, DataFormat::Yaml)
+}
+
+#[cfg(not(feature = "yaml"))]
+fn render_default(value: &Value) -> String {
+    render(value, DataFormat::Json)
+}
+
+fn pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).expect("JSON values always serialise")
+}
+
+fn fenced(language: &str, body: &str) -> String {
+    let body = body.trim_end_matches('\n');
+    format!("```{language}\n{body}\n```\n")
+}
+
+/// A table for an array of flat objects or a single flat object.
+fn markdown_table(value: &Value) -> Option<String> {
+    let (header, rows): (Vec<String>, Vec<Vec<String>>) = match value {
+        Value::Array(items) if !items.is_empty() => {
+            let mut columns: Vec<String> = Vec::new();
+            for item in items {
+                let object = item.as_object().filter(|o| is_flat(o.values()))?;
+                for key in object.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            let rows = items
+                .iter()
+                .map(|item| {
+                    columns
+                        .iter()
+                        .map(|column| item.get(column).map(cell).unwrap_or_default())
+                        .collect()
+                })
+                .collect();
+            (columns, rows)
+        }
+        Value::Object(object) if is_flat(object.values()) => (
+            vec!["key".into(), "value".into()],
+            object
+                .iter()
+                .map(|(key, value)| vec![escape(key), cell(value)])
+                .collect(),
+        ),
+        _ => return None,
+    };
+
+    let mut table = format!(
+        "| {} |\n",
+        header
+            .iter()
+            .map(|h| escape(h))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    );
+    table.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+    for row in rows {
+        table.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    Some(table)
+}
+
+fn is_flat<'a>(mut values: impl Iterator<Item = &'a Value>) -> bool {
+    values.all(|value| !value.is_object() && !value.is_array())
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(text) => escape(text),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,214 @@
+//! Fragment that replays earlier chat turns within a token budget.
+//!
+//! Agents keep their own history – as `GenericMessage`s or as domain
+//! records – and need the newest part of it in every prompt. With a token
+//! budget, `HistoryFragment` keeps as many of the newest messages as fit
+//! and drops the rest:
+//!
+//! ```rust
+//! use artificial_core::generic::{GenericMessage, GenericRole};
+//! use artificial_core::template::IntoPrompt;
+//! use artificial_core::tokens::ApproxCounter;
+//! use artificial_types::fragments::HistoryFragment;
+//!
+//! let history: Vec<GenericMessage> = (0..50)
+//!     .map(|i| GenericMessage::new(format!("turn number {i}"), GenericRole::User))
+//!     .collect();
+//!
+//! let messages = HistoryFragment::new(history)
+//!     .with_budget(100, ApproxCounter::default())
+//!     .into_prompt();
+//!
+//! assert!(messages.len() < 50);
+//! assert_eq!(messages.last().unwrap().content.as_deref(), Some("turn number 49"));
+//! ```
+//!
+//! The newest message is always kept, and a tool result is never kept
+//! without the assistant message that requested it. Instead of dropping
+//! old turns silently, [`HistoryFragment::summarize_dropped`] condenses them
+//! into a system message first.
+
+use artificial_core::{
+    conversation::{SUMMARY_NAME, safe_cut, summarize_messages},
+    error::Result,
+    generic::{GenericMessage, GenericRole},
+    model::Model,
+    provider::ChatCompletionProvider,
+    template::IntoPrompt,
+    tokens::{TokenCounter, counter_for},
+};
+
+/// Earlier chat turns, newest last.
+pub struct HistoryFragment {
+    messages: Vec<GenericMessage>,
+    budget: Option<(usize, Box<dyn TokenCounter>)>,
+    summary: Option<String>,
+}
+
+impl HistoryFragment {
+    pub fn new(messages: Vec<GenericMessage>) -> Self {
+        Self {
+            messages,
+            budget: None,
+            summary: None,
+        }
+    }
+
+    /// Build the history from domain records, oldest first.
+    ///
+    /// ```rust
+    /// use artificial_core::generic::{GenericMessage, GenericRole};
+    /// use artificial_types::fragments::HistoryFragment;
+    ///
+    /// struct Post { author: &'static str, text: &'static str }
+    ///
+    /// let posts = [Post { author: "leia", text: "Help me." }];
+    /// let history = HistoryFragment::from_records(&posts, |post| {
+    ///     GenericMessage::new(post.text.into(), GenericRole::User).with_name(post.author)
+    /// });
+    /// ```
+    pub fn from_records<R>(
+        records: impl IntoIterator<Item = R>,
+        to_message: impl FnMut(R) -> GenericMessage,
+    ) -> Self {
+        Self::new(records.into_iter().map(to_message).collect())
+    }
+
+    /// Keep only the newest messages that fit into `max_tokens` for `model`,
+    /// counted with [`counter_for`].
+    pub fn with_token_budget(mut self, max_tokens: usize, model: &Model) -> Self {
+        self.budget = Some((max_tokens, counter_for(model)));
+        self
+    }
+
+    /// Like [`Self::with_token_budget`] with an explicit counter.
+    pub fn with_budget(mut self, max_tokens: usize, counter: impl TokenCounter + 'static) -> Self {
+        self.budget = Some((max_tokens, Box::new(counter)));
+        self
+    }
+
+    /// Number of messages the budget drops, oldest first.
+    pub fn dropped(&self) -> usize {
+        let Some((max_tokens, counter)) = &self.budget else {
+            return 0;
+        };
+        let max_tokens = max_tokens.saturating_sub(self.summary_tokens(counter.as_ref()));
+        let framing = counter.count_messages(&[]);
+
+        let mut used = framing;
+        let mut start = self.messages.len();
+        while start > 0 {
+            let cost = counter.count_messages(&self.messages[start - 1..start]) - framing;
+            if start < self.messages.len() && used + cost > max_tokens {
+                break;
+            }
+            used += cost;
+            start -= 1;
+        }
+        match start {
+            0 => 0,
+            start => safe_cut(&self.messages, start).min(self.messages.len() - 1),
+        }
+    }
+
+    /// Summarise the messages the budget drops with `model`, so the prompt
+    /// keeps their gist.
+    ///
+    /// The summary costs tokens itself, which may push out one more old
+    /// message. Without a budget, or with nothing to drop, no request is
+    /// made.
+    pub async fn summarize_dropped<B>(mut self, backend: &B, model: Model) -> Result<Self>
+    where
+        B: ChatCompletionProvider,
+        GenericMessage: Into<B::Message>,
+    {
+        let dropped = self.dropped();
+        if dropped > 0 {
+            let summary = summarize_messages(backend, model, &self.messages[..dropped]).await?;
+            self.summary = Some(summary);
+        }
+        Ok(self)
+    }
+
+    fn summary_message(&self) -> Option<GenericMessage> {
+        self.summary.as_ref().map(|summary| {
+            GenericMessage::new(
+                format!("Summary of the earlier conversation:\n{summary}"),
+                GenericRole::System,
+            )
+            .with_name(SUMMARY_NAME)
+        })
+    }
+
+    fn summary_tokens(&self, counter: &dyn TokenCounter) -> usize {
+        match self.summary_message() {
+            Some(message) => counter.count_messages(&[message]) - counter.count_messages(&[]),
+            None => 0,
+        }
+    }
+}
+
+impl IntoPrompt for HistoryFragment {
+    type Message = GenericMessage;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        let dropped = self.dropped();
+        let summary = self.summary_message();
+        summary
+            .into_iter()
+            .chain(self.messages.into_iter().skip(dropped))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use artificial_core::{
+        generic::{GenericFunctionCall, GenericFunctionCallIntent},
+        model::OpenAiModel,
+        testing::MockProvider,
+        tokens::ApproxCounter,
+    };
+
+    use super::*;
+
+    fn turn(i: usize) -> GenericMessage {
+        GenericMessage::new(format!("message {i} {}", "x".repeat(40)), GenericRole::User)
+    }
+
+    #[tokio::test]
+    async fn dropped_turns_are_summarized_and_tool_pairs_stay_together() {
+        let mut call = GenericMessage::new(String::new(), GenericRole::Assistant);
+        call.tool_calls = Some(vec![GenericFunctionCallIntent {
+            id: "call-1".into(),
+            function: GenericFunctionCall {
+                name: "lookup".into(),
+                arguments: serde_json::json!({}),
+            },
+        }]);
+        let mut result = GenericMessage::new("found".into(), GenericRole::Tool);
+        result.tool_call_id = Some("call-1".into());
+        let mut history: Vec<_> = (0..6).map(turn).collect();
+        history.extend([call, result, turn(6)]);
+
+        let fragment = HistoryFragment::new(history).with_budget(60, ApproxCounter::default());
+        let dropped = fragment.dropped();
+        assert!(dropped > 0);
+
+        let mock = MockProvider::new();
+        mock.push_text("They talked about x.");
+        let messages = fragment
+            .summarize_dropped(&mock, Model::OpenAi(OpenAiModel::Gpt4oMini))
+            .await
+            .unwrap()
+            .into_prompt();
+
+        assert_eq!(messages[0].name.as_deref(), Some(SUMMARY_NAME));
+        assert!(messages[1].role != GenericRole::Tool);
+        assert_eq!(
+            messages.last().unwrap().content,
+            turn(6).content,
+            "the newest turn is always kept"
+        );
+    }
+}
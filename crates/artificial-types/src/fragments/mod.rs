@@ -1,5 +1,17 @@
 mod current_date;
+mod data;
+mod history;
+mod rerank;
+mod respond_in_language;
 mod static_fragment;
+mod tool_policy;
+mod with_role;
 
 pub use current_date::CurrentDateFragment;
+pub use data::{DataFormat, DataFragment};
+pub use history::HistoryFragment;
+pub use rerank::RerankFragment;
+pub use respond_in_language::RespondInLanguage;
 pub use static_fragment::StaticFragment;
+pub use tool_policy::{ToolCost, ToolPolicyFragment};
+pub use with_role::{WithRole, WithRoleExt};
@@ -0,0 +1,117 @@
+//! Prompt fragment that asks the model to rerank retrieved chunks.
+//!
+//! Vector search returns candidates by embedding similarity, which is a
+//! rough proxy for relevance.  A cheap model can reorder the top hits much
+//! more precisely.  `RerankFragment` renders the query and the candidates
+//! with their ids; the model answers with a
+//! [`RankingResult`](crate::outputs::ranking::RankingResult).
+//!
+//! ```rust
+//! use artificial_types::fragments::RerankFragment;
+//! use artificial_core::template::IntoPrompt;
+//!
+//! let messages = RerankFragment::new("How do I rotate API keys?")
+//!     .with_candidate("doc-1", "Keys can be rotated in the dashboard …")
+//!     .with_candidate("doc-7", "Our office is closed on public holidays.")
+//!     .with_top_k(3)
+//!     .into_prompt();
+//!
+//! assert_eq!(messages.len(), 2);
+//! ```
+
+use artificial_core::{
+    generic::{GenericMessage, GenericRole},
+    template::IntoPrompt,
+};
+use artificial_prompt::builder::PromptBuilder;
+
+/// Query plus candidate chunks to be ordered by relevance.
+pub struct RerankFragment {
+    query: String,
+    candidates: Vec<(String, String)>,
+    top_k: Option<usize>,
+    with_scores: bool,
+}
+
+impl RerankFragment {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            candidates: Vec::new(),
+            top_k: None,
+            with_scores: false,
+        }
+    }
+
+    /// Add a candidate chunk identified by `id`.
+    pub fn with_candidate(mut self, id: impl Into<String>, text: impl Into<String>) -> Self {
+        self.candidates.push((id.into(), text.into()));
+        self
+    }
+
+    /// Add several `(id, text)` candidates at once.
+    pub fn with_candidates<I, K, V>(mut self, candidates: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.candidates
+            .extend(candidates.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Return at most `k` ids.
+    pub fn with_top_k(mut self, k: usize) -> Self {
+        self.top_k = Some(k);
+        self
+    }
+
+    /// Ask for a relevance score per ranked id.
+    pub fn with_scores(mut self) -> Self {
+        self.with_scores = true;
+        self
+    }
+}
+
+impl IntoPrompt for RerankFragment {
+    type Message = GenericMessage;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        let mut instructions = PromptBuilder::new()
+            .add_line("You rank text passages by how well they answer a query.")
+            .add_blank_line()
+            .add_line(
+                "- Return the ids of the relevant passages in `ranked_ids`, most relevant first.",
+            )
+            .add_line("- Use every id at most once and only ids from the list.")
+            .add_line("- Leave out passages that do not help answer the query.");
+
+        if let Some(k) = self.top_k {
+            instructions = instructions.add_line(format!("- Return at most {k} ids."));
+        }
+        instructions = if self.with_scores {
+            instructions.add_line(
+                "- Set `scores` to one relevance score from 0.0 to 1.0 per ranked id, in the same order.",
+            )
+        } else {
+            instructions.add_line("- Set `scores` to null.")
+        };
+
+        let mut passages = PromptBuilder::new()
+            .add_key_value("Query", &self.query)
+            .add_blank_line()
+            .add_section_h2("Passages");
+        for (id, text) in &self.candidates {
+            passages = passages
+                .add_blank_line()
+                .add_key_value("id", id)
+                .add_text_markdown(text);
+        }
+
+        vec![
+            GenericMessage::new(instructions.finalize(), GenericRole::System),
+            GenericMessage::new(passages.finalize(), GenericRole::User),
+        ]
+    }
+}
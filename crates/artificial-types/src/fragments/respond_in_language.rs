@@ -0,0 +1,60 @@
+//! A **prompt fragment** that pins the language of the answer.
+//!
+//! A plain "answer in German" at the top of the system prompt loses against
+//! an English question or English source material surprisingly often. The
+//! fragment states the rule explicitly, including the cases the model tends
+//! to treat as exceptions. Put it **last** in the system part of the chain,
+//! right before the conversation, where it carries the most weight.
+//!
+//! # Example
+//!
+//! ```rust
+//! use artificial_core::language::LanguageCode;
+//! use artificial_prompt::chain::PromptChain;
+//! use artificial_types::fragments::{RespondInLanguage, StaticFragment};
+//! use artificial_core::generic::GenericRole;
+//!
+//! let messages = PromptChain::new()
+//!     .with(StaticFragment::new("You are a support agent.", GenericRole::System))
+//!     .with(RespondInLanguage(LanguageCode::German))
+//!     .build();
+//!
+//! assert!(messages[1].content.as_deref().unwrap().contains("German (de)"));
+//! ```
+//!
+//! Instructions alone are not a guarantee. Wrap the backend in a
+//! [`LanguageGuard`](artificial_core::language::LanguageGuard) to check the
+//! answer and retry when the model still slips.
+
+use artificial_core::{
+    generic::{GenericMessage, GenericRole},
+    language::LanguageCode,
+    template::IntoPrompt,
+};
+use artificial_prompt::builder::PromptBuilder;
+
+/// Instructs the model to answer in the given language, as a system message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RespondInLanguage(pub LanguageCode);
+
+impl IntoPrompt for RespondInLanguage {
+    type Message = GenericMessage;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        let language = format!("{} ({})", self.0.name(), self.0.code());
+
+        let builder = PromptBuilder::new()
+            .add_section_h2("Response Language")
+            .add_line(format!("Always write your answer in {language}."))
+            .add_line(
+                "This applies even if the user writes in another language, and even if \
+                 documents, tool results or earlier messages are in another language.",
+            )
+            .add_line(
+                "Keep names, code, quotes and technical identifiers unchanged; \
+                 translate everything else.",
+            );
+
+        vec![GenericMessage::new(builder.finalize(), GenericRole::System)]
+    }
+}
@@ -0,0 +1,223 @@
+//! A **system message** that explains the available tools to the model.
+//!
+//! Function specs alone tell the model *how* to call a tool, not *when*.
+//! `ToolPolicyFragment` renders a standardized policy block from the same
+//! [`GenericFunctionSpec`]s that are sent to the provider, enriched with
+//! usage guidance and cost hints:
+//!
+//! ```rust
+//! use artificial_core::generic::GenericFunctionSpec;
+//! use artificial_core::template::IntoPrompt;
+//! use artificial_types::fragments::{ToolCost, ToolPolicyFragment};
+//! use serde_json::json;
+//!
+//! let search = GenericFunctionSpec {
+//!     name: "search_docs".into(),
+//!     description: "Full-text search over the product documentation.".into(),
+//!     parameters: json!({
+//!         "type": "object",
+//!         "properties": { "query": { "type": "string", "description": "Search terms" } },
+//!         "required": ["query"]
+//!     }),
+//! };
+//!
+//! let messages = ToolPolicyFragment::new([search])
+//!     .with_usage("search_docs", "Use before answering any product question.")
+//!     .with_cost("search_docs", ToolCost::Cheap)
+//!     .into_prompt();
+//!
+//! let text = messages[0].content.as_deref().unwrap();
+//! assert!(text.contains("### `search_docs`"));
+//! assert!(text.contains("`query` (string, required): Search terms"));
+//! ```
+
+use std::{collections::HashMap, fmt};
+
+use artificial_core::{
+    generic::{GenericFunctionSpec, GenericMessage, GenericRole},
+    template::IntoPrompt,
+    tools::ToolRegistry,
+};
+use artificial_prompt::builder::PromptBuilder;
+use serde_json::Value;
+
+/// Rough cost of calling a tool, so the model can prefer cheap ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolCost {
+    Cheap,
+    Moderate,
+    /// Slow, billed or rate-limited; call only when necessary.
+    Expensive,
+}
+
+impl fmt::Display for ToolCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolCost::Cheap => write!(f, "cheap, call freely"),
+            ToolCost::Moderate => write!(f, "moderate, avoid redundant calls"),
+            ToolCost::Expensive => write!(f, "expensive, call only when necessary"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ToolHints {
+    usage: Option<String>,
+    cost: Option<ToolCost>,
+}
+
+/// Renders tool descriptions, usage policy and argument conventions.
+pub struct ToolPolicyFragment {
+    tools: Vec<GenericFunctionSpec>,
+    hints: HashMap<String, ToolHints>,
+    rules: Vec<String>,
+}
+
+const DEFAULT_RULES: &[&str] = &[
+    "Call a tool only if its result is needed to answer; never guess data a tool can provide.",
+    "Pass arguments exactly as described. Omit optional arguments instead of sending null or empty values.",
+    "Use ISO 8601 for dates and times and plain numbers without units or thousands separators.",
+    "If a tool fails, do not repeat the identical call; adjust the arguments or explain the failure.",
+];
+
+impl ToolPolicyFragment {
+    /// Create the fragment from the tool specs sent with the request.
+    pub fn new(tools: impl IntoIterator<Item = GenericFunctionSpec>) -> Self {
+        Self {
+            tools: tools.into_iter().collect(),
+            hints: HashMap::new(),
+            rules: DEFAULT_RULES.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    /// Create the fragment from the tools of `registry`, the same specs
+    /// [`ToolRegistry::specs`] sends with the request.
+    ///
+    /// ```rust
+    /// use artificial_core::{generic::GenericFunctionSpec, template::IntoPrompt, tools::ToolRegistry};
+    /// use artificial_types::fragments::ToolPolicyFragment;
+    ///
+    /// let registry = ToolRegistry::new().with_tool(
+    ///     GenericFunctionSpec {
+    ///         name: "now".into(),
+    ///         description: "Current time in UTC.".into(),
+    ///         parameters: serde_json::json!({ "type": "object" }),
+    ///     },
+    ///     |_| async { Ok("2024-01-01T00:00:00Z".to_owned()) },
+    /// );
+    ///
+    /// let messages = ToolPolicyFragment::from_registry(&registry).into_prompt();
+    /// assert!(messages[0].content.as_deref().unwrap().contains("### `now`"));
+    /// ```
+    pub fn from_registry(registry: &ToolRegistry) -> Self {
+        Self::new(registry.specs())
+    }
+
+    /// Describe *when* the model should call `tool`.
+    pub fn with_usage(mut self, tool: &str, usage: impl Into<String>) -> Self {
+        self.hints.entry(tool.to_owned()).or_default().usage = Some(usage.into());
+        self
+    }
+
+    /// Attach a cost hint to `tool`.
+    pub fn with_cost(mut self, tool: &str, cost: ToolCost) -> Self {
+        self.hints.entry(tool.to_owned()).or_default().cost = Some(cost);
+        self
+    }
+
+    /// Add a general rule to the policy.
+    pub fn with_rule(mut self, rule: impl Into<String>) -> Self {
+        self.rules.push(rule.into());
+        self
+    }
+
+    /// Replace the built-in general rules.
+    pub fn with_rules<I, S>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rules = rules.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl IntoPrompt for ToolPolicyFragment {
+    type Message = GenericMessage;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        if self.tools.is_empty() {
+            return Vec::new();
+        }
+
+        let mut builder = PromptBuilder::new()
+            .add_section_h2("Tool usage policy")
+            .add_blank_line();
+        for rule in &self.rules {
+            builder = builder.add_line(format!("- {rule}"));
+        }
+
+        for tool in &self.tools {
+            builder = builder
+                .add_blank_line()
+                .add_line(format!("### `{}`", tool.name))
+                .add_line(&tool.description);
+
+            if let Some(hints) = self.hints.get(&tool.name) {
+                if let Some(usage) = &hints.usage {
+                    builder = builder.add_key_value("When to use", usage);
+                }
+                if let Some(cost) = hints.cost {
+                    builder = builder.add_key_value("Cost", cost);
+                }
+            }
+
+            let arguments = describe_arguments(&tool.parameters);
+            if !arguments.is_empty() {
+                builder = builder.add_line_bold("Arguments");
+                for argument in arguments {
+                    builder = builder.add_line(format!("- {argument}"));
+                }
+            }
+        }
+
+        vec![GenericMessage::new(builder.finalize(), GenericRole::System)]
+    }
+}
+
+/// One line per top-level property: name, type, required flag, description.
+fn describe_arguments(parameters: &Value) -> Vec<String> {
+    let Some(properties) = parameters.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let required: Vec<&str> = parameters
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    properties
+        .iter()
+        .map(|(name, schema)| {
+            let kind = match schema.get("type") {
+                Some(Value::String(kind)) => kind.clone(),
+                Some(Value::Array(kinds)) => kinds
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+                _ => "any".to_owned(),
+            };
+            let presence = if required.contains(&name.as_str()) {
+                "required"
+            } else {
+                "optional"
+            };
+
+            match schema.get("description").and_then(Value::as_str) {
+                Some(description) => format!("`{name}` ({kind}, {presence}): {description}"),
+                None => format!("`{name}` ({kind}, {presence})"),
+            }
+        })
+        .collect()
+}
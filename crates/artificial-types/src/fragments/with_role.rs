@@ -0,0 +1,58 @@
+//! Re-role the messages of any fragment.
+//!
+//! Fragments decide their role themselves – most render as *system*
+//! messages.  Some models follow instructions better when reference material
+//! arrives as a *user* turn, and some providers restrict what system messages
+//! may contain.  [`WithRole`] overrides the role of every message a fragment
+//! produces without touching the fragment type:
+//!
+//! ```rust
+//! use artificial_core::generic::GenericRole;
+//! use artificial_prompt::chain::PromptChain;
+//! use artificial_types::fragments::{CurrentDateFragment, WithRoleExt as _};
+//!
+//! let messages = PromptChain::new()
+//!     .with(CurrentDateFragment::new().with_role(GenericRole::User))
+//!     .build();
+//!
+//! assert_eq!(messages[0].role, GenericRole::User);
+//! ```
+
+use artificial_core::{
+    generic::{GenericMessage, GenericRole},
+    template::IntoPrompt,
+};
+
+/// Wraps a fragment and renders all of its messages with `role`.
+pub struct WithRole<F> {
+    fragment: F,
+    role: GenericRole,
+}
+
+impl<F> WithRole<F> {
+    pub fn new(fragment: F, role: GenericRole) -> Self {
+        Self { fragment, role }
+    }
+}
+
+impl<F: IntoPrompt<Message = GenericMessage>> IntoPrompt for WithRole<F> {
+    type Message = GenericMessage;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        let role = self.role;
+        self.fragment
+            .into_prompt()
+            .into_iter()
+            .map(|message| GenericMessage { role, ..message })
+            .collect()
+    }
+}
+
+/// Adds [`with_role`](WithRoleExt::with_role) to every fragment.
+pub trait WithRoleExt: IntoPrompt<Message = GenericMessage> + Sized {
+    fn with_role(self, role: GenericRole) -> WithRole<Self> {
+        WithRole::new(self, role)
+    }
+}
+
+impl<F: IntoPrompt<Message = GenericMessage>> WithRoleExt for F {}
@@ -0,0 +1,452 @@
+//! Turn documents into chunk-ready plain text.
+//!
+//! Retrieval pipelines need text split into passages small enough to embed
+//! and to show the model, and they need to cite where a passage came from.
+//! A [`Document`] is a list of [`Section`]s that remember their page and
+//! heading; [`Document::chunks`] splits them at paragraph boundaries without
+//! ever merging text from different sections:
+//!
+//! ```rust
+//! use artificial_types::fragments::RerankFragment;
+//! use artificial_types::ingest::Document;
+//!
+//! let doc = Document::from_markdown(
+//!     "handbook.md",
+//!     "# Security\n\nRotate API keys every 90 days.\n\n# Holidays\n\nThe office closes on public holidays.",
+//! );
+//! let chunks = doc.chunks(500);
+//! assert_eq!(chunks[0].heading.as_deref(), Some("Security"));
+//! assert_eq!(chunks[1].id, "handbook.md#2");
+//!
+//! let fragment = RerankFragment::new("How often do keys rotate?")
+//!     .with_candidates(chunks.iter().map(|c| (c.id.clone(), c.labelled_text())));
+//! # let _ = fragment;
+//! ```
+//!
+//! PDF (feature `pdf`) and HTML (feature `html`) input is converted into the
+//! same structure: PDFs yield one section per page, HTML one section per
+//! heading.
+
+use artificial_core::error::{ArtificialError, Result};
+
+/// A contiguous piece of a document with its location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub text: String,
+    /// 1-based page number, if the format has pages.
+    pub page: Option<u32>,
+    /// The closest preceding heading.
+    pub heading: Option<String>,
+}
+
+/// A passage produced by [`Document::chunks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// `"{source}#{n}"` with a 1-based `n`, stable for the same input.
+    pub id: String,
+    pub text: String,
+    pub page: Option<u32>,
+    pub heading: Option<String>,
+}
+
+impl Chunk {
+    /// The text prefixed with its location, e.g. `[p. 3 · Pricing] …`, so
+    /// the model can cite it.
+    pub fn labelled_text(&self) -> String {
+        let label = match (self.page, &self.heading) {
+            (Some(page), Some(heading)) => format!("[p. {page} · {heading}] "),
+            (Some(page), None) => format!("[p. {page}] "),
+            (None, Some(heading)) => format!("[{heading}] "),
+            (None, None) => String::new(),
+        };
+        format!("{label}{}", self.text)
+    }
+}
+
+/// Extracted text of one source document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    /// Name used in chunk ids, e.g. a file name or URL.
+    pub source: String,
+    pub sections: Vec<Section>,
+}
+
+impl Document {
+    pub fn new(source: impl Into<String>, sections: Vec<Section>) -> Self {
+        Self {
+            source: source.into(),
+            sections,
+        }
+    }
+
+    /// Plain text as a single section.
+    pub fn from_text(source: impl Into<String>, text: &str) -> Self {
+        Self::new(
+            source,
+            vec![Section {
+                text: normalize(text),
+                page: None,
+                heading: None,
+            }],
+        )
+    }
+
+    /// Markdown, split into one section per ATX heading (`#` … `######`).
+    /// Lines inside fenced code blocks are never headings.
+    pub fn from_markdown(source: impl Into<String>, markdown: &str) -> Self {
+        let mut builder = SectionBuilder::default();
+        let mut fences = Fences::default();
+        for line in markdown.lines() {
+            if fences.track(line) {
+                builder.line(line);
+                continue;
+            }
+
+            let trimmed = line.trim_start();
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+                builder.heading(trimmed[level..].trim());
+            } else {
+                builder.line(line);
+            }
+        }
+        Self::new(source, builder.finish())
+    }
+
+    /// Extract the text of a PDF, one section per page.
+    ///
+    /// # Errors
+    ///
+    /// [`ArtificialError::Invalid`] if the PDF cannot be parsed.
+    #[cfg(feature = "pdf")]
+    pub fn from_pdf(source: impl Into<String>, bytes: &[u8]) -> Result<Self> {
+        let pages = pdf_extract::extract_text_from_mem_by_pages(bytes)
+            .map_err(|err| ArtificialError::Invalid(format!("could not read PDF: {err}")))?;
+        let sections = pages
+            .iter()
+            .enumerate()
+            .map(|(index, text)| Section {
+                text: normalize(text),
+                page: Some(index as u32 + 1),
+                heading: None,
+            })
+            .filter(|section| !section.text.is_empty())
+            .collect();
+        Ok(Self::new(source, sections))
+    }
+
+    /// Extract the readable text of an HTML page, one section per heading.
+    /// Scripts, styles and navigation are skipped.
+    #[cfg(feature = "html")]
+    pub fn from_html(source: impl Into<String>, html: &str) -> Self {
+        Self::new(source, html::sections(html))
+    }
+
+    /// Split into chunks of at most `max_chars` characters, breaking at
+    /// paragraphs, then lines, then words. A single word longer than
+    /// `max_chars` becomes its own chunk.
+    pub fn chunks(&self, max_chars: usize) -> Vec<Chunk> {
+        let max_chars = max_chars.max(1);
+        let mut chunks = Vec::new();
+        for section in &self.sections {
+            for text in split_text(&section.text, max_chars) {
+                chunks.push(Chunk {
+                    id: format!("{}#{}", self.source, chunks.len() + 1),
+                    text,
+                    page: section.page,
+                    heading: section.heading.clone(),
+                });
+            }
+        }
+        chunks
+    }
+
+    /// Fails with [`ArtificialError::Invalid`] if no text was extracted,
+    /// e.g. from a scanned PDF without a text layer.
+    pub fn require_text(self) -> Result<Self> {
+        match self.sections.iter().any(|s| !s.text.is_empty()) {
+            true => Ok(self),
+            false => Err(ArtificialError::Invalid(format!(
+                "no text could be extracted from `{}`",
+                self.source
+            ))),
+        }
+    }
+}
+
+/// Collects lines into sections that start at each heading.
+#[derive(Default)]
+struct SectionBuilder {
+    sections: Vec<Section>,
+    heading: Option<String>,
+    text: String,
+}
+
+impl SectionBuilder {
+    fn heading(&mut self, heading: &str) {
+        self.flush();
+        self.heading = Some(heading.to_string());
+    }
+
+    fn line(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    /// Add a block of text as its own paragraph.
+    #[cfg_attr(not(feature = "html"), allow(dead_code))]
+    fn paragraph(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.text.push_str("\n\n");
+    }
+
+    fn flush(&mut self) {
+        let text = normalize(&std::mem::take(&mut self.text));
+        if !text.is_empty() {
+            self.sections.push(Section {
+                text,
+                page: None,
+                heading: self.heading.clone(),
+            });
+        }
+    }
+
+    fn finish(mut self) -> Vec<Section> {
+        self.flush();
+        self.sections
+    }
+}
+
+/// Trim lines, collapse runs of blank lines into one paragraph break.
+/// Markdown code fences (```` ``` ```` or `~~~`) seen so far.
+#[derive(Default)]
+struct Fences {
+    /// Marker character and length of the open fence.
+    open: Option<(char, usize)>,
+}
+
+impl Fences {
+    /// Whether `line` belongs to a code block, as its content or as one of
+    /// its fences.
+    fn track(&mut self, line: &str) -> bool {
+        let trimmed = line.trim_start();
+        let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'));
+        let len = marker.map_or(0, |m| trimmed.chars().take_while(|c| *c == m).count());
+        match (self.open, marker) {
+            (None, Some(marker)) if len >= 3 => {
+                self.open = Some((marker, len));
+                true
+            }
+            (Some((open, min)), Some(marker))
+                if open == marker && len >= min && trimmed[len..].trim().is_empty() =>
+            {
+                self.open = None;
+                true
+            }
+            (open, _) => open.is_some(),
+        }
+    }
+}
+
+/// Collapse runs of whitespace and blank lines; code blocks keep their
+/// indentation and blank lines.
+fn normalize(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    let mut fences = Fences::default();
+    for raw in text.lines() {
+        if fences.track(raw) {
+            if !out.is_empty() {
+                out.push_str(if blank { "\n\n" } else { "\n" });
+            }
+            out.push_str(raw.trim_end());
+            blank = false;
+            continue;
+        }
+
+        let line = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        out.push_str(&line);
+        blank = false;
+    }
+    out
+}
+
+fn split_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for (separator, piece) in pieces(text, max_chars) {
+        let joined = current.chars().count() + separator.len() + piece.chars().count();
+        if !current.is_empty() && joined > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str(separator);
+        }
+        current.push_str(piece);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Units no larger than `max_chars` (except single long words), each with
+/// the separator that joins it to its predecessor.
+fn pieces(text: &str, max_chars: usize) -> Vec<(&'static str, &str)> {
+    let mut pieces = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let mut separator = "\n\n";
+        if paragraph.chars().count() <= max_chars {
+            pieces.push((separator, paragraph));
+            continue;
+        }
+        for line in paragraph.lines() {
+            if line.chars().count() <= max_chars {
+                pieces.push((separator, line));
+            } else {
+                for word in line.split(' ') {
+                    pieces.push((separator, word));
+                    separator = " ";
+                }
+            }
+            separator = "\n";
+        }
+    }
+    pieces
+}
+
+#[cfg(feature = "html")]
+mod html {
+    use scraper::{ElementRef, Html, Selector};
+
+    use super::{Section, SectionBuilder};
+
+    const BLOCKS: &str = "h1, h2, h3, h4, h5, h6, p, li, pre, blockquote, td, th, dt, dd";
+    const SKIPPED: [&str; 6] = ["script", "style", "nav", "header", "footer", "noscript"];
+
+    pub(super) fn sections(html: &str) -> Vec<Section> {
+        let document = Html::parse_document(html);
+        let blocks = Selector::parse(BLOCKS).expect("static selector is valid");
+        let mut builder = SectionBuilder::default();
+
+        for element in document.select(&blocks) {
+            // Nested blocks (a `p` inside an `li`) are part of their parent.
+            if element
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|a| is_block(&a) || SKIPPED.contains(&a.value().name()))
+            {
+                continue;
+            }
+            let text = element.text().collect::<String>();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if text.is_empty() {
+                continue;
+            }
+            match element.value().name() {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => builder.heading(&text),
+                "li" => builder.line(&format!("- {text}")),
+                _ => builder.paragraph(&text),
+            }
+        }
+        builder.finish()
+    }
+
+    fn is_block(element: &ElementRef<'_>) -> bool {
+        matches!(
+            element.value().name(),
+            "h1" | "h2"
+                | "h3"
+                | "h4"
+                | "h5"
+                | "h6"
+                | "p"
+                | "li"
+                | "pre"
+                | "blockquote"
+                | "td"
+                | "th"
+                | "dt"
+                | "dd"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_respect_size_and_sections() {
+        let doc = Document::new(
+            "doc",
+            vec![
+                Section {
+                    text: "one two three\n\nfour five".into(),
+                    page: Some(1),
+                    heading: None,
+                },
+                Section {
+                    text: "six".into(),
+                    page: Some(2),
+                    heading: Some("Next".into()),
+                },
+            ],
+        );
+
+        let chunks = doc.chunks(10);
+        let texts: Vec<_> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["one two", "three", "four five", "six"]);
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 10));
+        assert_eq!(chunks[3].labelled_text(), "[p. 2 · Next] six");
+        assert_eq!(chunks[3].id, "doc#4");
+    }
+
+    #[test]
+    fn markdown_headings_inside_code_fences_are_text() {
+        let doc = Document::from_markdown(
+            "readme",
+            "# Setup\nRun:\n```sh\n# install the tools\nif true; then\n    make\n\n    make  test\nfi\n```\n## Usage\nCall   it.",
+        );
+        let headings: Vec<_> = doc.sections.iter().map(|s| s.heading.as_deref()).collect();
+        assert_eq!(headings, [Some("Setup"), Some("Usage")]);
+        assert_eq!(
+            doc.sections[0].text,
+            "Run:\n```sh\n# install the tools\nif true; then\n    make\n\n    make  test\nfi\n```"
+        );
+        assert_eq!(doc.sections[1].text, "Call it.");
+    }
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn html_sections_follow_headings() {
+        let doc = Document::from_html(
+            "page",
+            "<html><head><script>var x;</script></head><body><nav><p>Menu</p></nav>\
+             <h1>Intro</h1><p>Hello <b>world</b>.</p><ul><li><p>first</p></li><li>second</li></ul>\
+             <h2>Details</h2><p>More.</p></body></html>",
+        );
+        assert_eq!(
+            doc.sections,
+            [
+                Section {
+                    text: "Hello world.\n\n- first\n- second".into(),
+                    page: None,
+                    heading: Some("Intro".into()),
+                },
+                Section {
+                    text: "More.".into(),
+                    page: None,
+                    heading: Some("Details".into()),
+                },
+            ]
+        );
+    }
+}
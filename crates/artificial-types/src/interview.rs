@@ -0,0 +1,303 @@
+//! Fill a typed form by asking the user one question at a time.
+//!
+//! [`Interview`] repeats a simple loop on top of
+//! [`ArtificialClient::extract`] and [`SlotFill`]: extract what the
+//! conversation so far says about `T`, pick the first field that is still
+//! missing or uncertain, ask the user about it, and extract again. Once
+//! every field is filled it returns the completed `T`:
+//!
+//! ```rust,no_run
+//! use artificial_core::{ArtificialClient, model::{Model, OpenAiModel}};
+//! use artificial_types::interview::Interview;
+//! # use artificial_core::{generic::GenericMessage, provider::ChatCompletionProvider};
+//!
+//! #[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+//! struct Signup {
+//!     /// Full name of the new customer.
+//!     #[schemars(required)]
+//!     name: Option<String>,
+//!     #[schemars(required)]
+//!     email: Option<String>,
+//! }
+//!
+//! # async fn run<B: ChatCompletionProvider<Message = GenericMessage>>(client: ArtificialClient<B>) -> artificial_core::error::Result<()> {
+//! let signup: Signup = Interview::new(Model::OpenAi(OpenAiModel::Gpt4oMini))
+//!     .with_instructions("Collect the details of a new customer account.")
+//!     .with_question("email", "Which e-mail address should we use?")
+//!     .with_opening("Hi, I'm Ada Lovelace and I'd like an account.")
+//!     .run(&client, |question| async move {
+//!         println!("{}", question.text);
+//!         let mut answer = String::new();
+//!         std::io::stdin().read_line(&mut answer).expect("stdin");
+//!         Ok(answer)
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Fields of `T` should be `Option`s marked `#[schemars(required)]`, as for
+//! [`SlotFill`], so the model can leave them `null` until the user answers.
+//! Fields without a question of their own are asked for by name and doc
+//! comment.
+use std::{collections::BTreeMap, future::Future, marker::PhantomData};
+
+use artificial_core::{
+    ArtificialClient,
+    error::{ArtificialError, Result},
+    generic::GenericMessage,
+    model::Model,
+    provider::ChatCompletionProvider,
+};
+use schemars::{JsonSchema, schema::Schema};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::outputs::slot_fill::{SlotFill, SlotStatus};
+
+const DEFAULT_MAX_TURNS: usize = 12;
+
+/// A question for the user, handed to the callback of [`Interview::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterviewQuestion {
+    /// The field of `T` the question is about.
+    pub field: String,
+    pub text: String,
+    /// [`SlotStatus::Uncertain`] asks to confirm a guessed value.
+    pub status: SlotStatus,
+    /// 1-based number of the question.
+    pub turn: usize,
+}
+
+/// Conducts a slot-filling conversation until `T` is complete.
+#[derive(Debug, Clone)]
+pub struct Interview<T> {
+    model: Model,
+    instructions: Option<String>,
+    opening: Option<String>,
+    questions: BTreeMap<String, String>,
+    max_turns: usize,
+    _target: PhantomData<fn() -> T>,
+}
+
+impl<T> Interview<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + 'static,
+{
+    pub fn new(model: Model) -> Self {
+        Self {
+            model,
+            instructions: None,
+            opening: None,
+            questions: BTreeMap::new(),
+            max_turns: DEFAULT_MAX_TURNS,
+            _target: PhantomData,
+        }
+    }
+
+    /// What the form is for; helps the model interpret the answers.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// A first message of the user to extract from before asking anything.
+    pub fn with_opening(mut self, opening: impl Into<String>) -> Self {
+        self.opening = Some(opening.into());
+        self
+    }
+
+    /// Ask for `field` with `question` instead of the generated one.
+    pub fn with_question(mut self, field: impl Into<String>, question: impl Into<String>) -> Self {
+        self.questions.insert(field.into(), question.into());
+        self
+    }
+
+    /// Give up after this many questions. Default 12.
+    pub fn with_max_turns(mut self, max_turns: usize) -> Self {
+        self.max_turns = max_turns;
+        self
+    }
+
+    /// Run the interview; `ask` puts a question to the user and returns the
+    /// answer.
+    ///
+    /// # Errors
+    ///
+    /// Errors of the provider and of `ask` are passed through. If fields are
+    /// still missing after [`Self::with_max_turns`] questions, the call
+    /// fails with [`ArtificialError::Invalid`] naming them.
+    pub async fn run<B, F, Fut>(&self, client: &ArtificialClient<B>, mut ask: F) -> Result<T>
+    where
+        B: ChatCompletionProvider,
+        GenericMessage: Into<B::Message>,
+        F: FnMut(InterviewQuestion) -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        let mut transcript = String::new();
+        let mut fill: Option<SlotFill<T>> = None;
+        if let Some(opening) = &self.opening {
+            transcript.push_str(&format!("User: {opening}\n"));
+            fill = Some(self.extract(client, &transcript).await?);
+        }
+
+        for turn in 1..=self.max_turns {
+            let (field, status) = match &fill {
+                Some(fill) => match fill.needs_input().next() {
+                    Some(field) => (
+                        field.to_owned(),
+                        fill.status(field).unwrap_or(SlotStatus::Missing),
+                    ),
+                    None => break,
+                },
+                None => match fields::<T>().into_iter().next() {
+                    Some((field, _)) => (field, SlotStatus::Missing),
+                    None => break,
+                },
+            };
+
+            let text = self.question(&field, status);
+            transcript.push_str(&format!("Assistant: {text}\n"));
+            let answer = ask(InterviewQuestion {
+                field,
+                text,
+                status,
+                turn,
+            })
+            .await?;
+            transcript.push_str(&format!("User: {}\n", answer.trim()));
+
+            let update = self.extract(client, &transcript).await?;
+            fill = Some(match fill {
+                Some(fill) => fill.merge(update)?,
+                None => update,
+            });
+        }
+
+        match fill {
+            Some(fill) if fill.is_complete() => Ok(fill.value),
+            Some(fill) => Err(ArtificialError::Invalid(format!(
+                "interview incomplete after {} questions, still missing: {}",
+                self.max_turns,
+                fill.needs_input().collect::<Vec<_>>().join(", ")
+            ))),
+            None => Err(ArtificialError::Invalid(
+                "interview ended before anything was extracted".into(),
+            )),
+        }
+    }
+
+    async fn extract<B>(
+        &self,
+        client: &ArtificialClient<B>,
+        transcript: &str,
+    ) -> Result<SlotFill<T>>
+    where
+        B: ChatCompletionProvider,
+        GenericMessage: Into<B::Message>,
+    {
+        let mut instructions = String::from(
+            "Fill in the form from the conversation below. Only use what the user said; \
+             leave fields null that the user has not answered yet.",
+        );
+        if let Some(purpose) = &self.instructions {
+            instructions.push_str("\n\n");
+            instructions.push_str(purpose);
+        }
+        client
+            .extract(instructions, transcript, self.model.clone())
+            .await
+    }
+
+    fn question(&self, field: &str, status: SlotStatus) -> String {
+        if let Some(question) = self.questions.get(field) {
+            return question.clone();
+        }
+        let label = field.replace('_', " ");
+        let description = fields::<T>()
+            .into_iter()
+            .find(|(name, _)| name == field)
+            .and_then(|(_, description)| description);
+        match (status, description) {
+            (SlotStatus::Uncertain, _) => format!("Could you confirm the {label}?"),
+            (_, Some(description)) => format!("What is the {label}? ({description})"),
+            (_, None) => format!("What is the {label}?"),
+        }
+    }
+}
+
+/// Top-level fields of `T` with their descriptions.
+fn fields<T: JsonSchema>() -> Vec<(String, Option<String>)> {
+    let schema = schemars::schema_for!(T);
+    let Some(object) = schema.schema.object else {
+        return Vec::new();
+    };
+    object
+        .properties
+        .into_iter()
+        .map(|(name, schema)| {
+            let description = match schema {
+                Schema::Object(object) => object.metadata.and_then(|m| m.description),
+                Schema::Bool(_) => None,
+            };
+            (name, description)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use artificial_core::{model::OpenAiModel, testing::MockProvider};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, JsonSchema)]
+    struct Signup {
+        #[schemars(required)]
+        name: Option<String>,
+        /// Where we send the invoice.
+        #[schemars(required)]
+        email: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn asks_for_missing_fields_until_complete() {
+        let mock = MockProvider::new();
+        mock.push_json(serde_json::json!({
+            "value": { "name": "Ada", "email": null },
+            "slots": { "name": "filled", "email": "missing" }
+        }));
+        mock.push_json(serde_json::json!({
+            "value": { "name": null, "email": "ada@example.com" },
+            "slots": { "name": "missing", "email": "filled" }
+        }));
+        let client = ArtificialClient::new(mock.clone());
+
+        let mut asked = Vec::new();
+        let signup: Signup = Interview::new(Model::OpenAi(OpenAiModel::Gpt4oMini))
+            .with_opening("I'm Ada")
+            .run(&client, |question| {
+                asked.push(question);
+                async { Ok("ada@example.com".to_owned()) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(signup.name.as_deref(), Some("Ada"));
+        assert_eq!(signup.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(asked.len(), 1);
+        assert_eq!(asked[0].field, "email");
+        assert_eq!(
+            asked[0].text,
+            "What is the email? (Where we send the invoice.)"
+        );
+
+        let transcript = mock.last_request().unwrap().messages;
+        let transcript = transcript.last().unwrap().content.as_deref().unwrap();
+        assert!(
+            transcript.ends_with("User: ada@example.com\n"),
+            "{transcript}"
+        );
+    }
+}
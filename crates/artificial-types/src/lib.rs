@@ -1,2 +1,4 @@
 pub mod fragments;
+pub mod ingest;
+pub mod interview;
 pub mod outputs;
@@ -1,2 +1,4 @@
 pub mod any;
+pub mod ranking;
 pub mod result;
+pub mod slot_fill;
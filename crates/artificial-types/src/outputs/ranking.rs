@@ -0,0 +1,102 @@
+//! Typed output for ranking and reranking tasks.
+//!
+//! Pair it with [`crate::fragments::RerankFragment`] to let a model reorder
+//! retrieved chunks by relevance:
+//!
+//! ```rust
+//! use artificial_types::outputs::ranking::RankingResult;
+//!
+//! let result: RankingResult = serde_json::from_str(
+//!     r#"{ "ranked_ids": ["b", "a"], "scores": [0.9, 0.2] }"#,
+//! ).unwrap();
+//! result.validate(["a", "b", "c"]).unwrap();
+//!
+//! let chunks = vec![("a", "apples"), ("b", "bananas"), ("c", "cherries")];
+//! let top: Vec<_> = result.apply(chunks, |(id, _)| *id).collect();
+//! assert_eq!(top, [("b", "bananas"), ("a", "apples")]);
+//! ```
+use std::collections::HashSet;
+
+use schemars::{
+    JsonSchema,
+    r#gen::SchemaGenerator,
+    schema::{Schema, SchemaObject},
+};
+use serde::{Deserialize, Serialize};
+
+/// Candidate ids ordered from most to least relevant.
+#[derive(Debug, Clone, JsonSchema, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RankingResult {
+    /// Ids of the relevant candidates, most relevant first. Every id appears
+    /// at most once; irrelevant candidates are left out.
+    #[schemars(schema_with = "unique_strings")]
+    pub ranked_ids: Vec<String>,
+    /// Relevance score from 0.0 to 1.0 for each entry of `ranked_ids`, in the
+    /// same order. `null` if scoring was not requested.
+    #[schemars(required)]
+    pub scores: Option<Vec<f32>>,
+}
+
+impl RankingResult {
+    /// Check the constraints a model may violate despite the schema: no
+    /// duplicates, no unknown ids and one score per ranked id.
+    pub fn validate<I, S>(&self, candidate_ids: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let known: HashSet<String> = candidate_ids
+            .into_iter()
+            .map(|id| id.as_ref().to_owned())
+            .collect();
+
+        let mut seen = HashSet::new();
+        for id in &self.ranked_ids {
+            if !known.contains(id) {
+                return Err(format!("unknown id `{id}` in ranking"));
+            }
+            if !seen.insert(id) {
+                return Err(format!("id `{id}` ranked more than once"));
+            }
+        }
+
+        if let Some(scores) = &self.scores
+            && scores.len() != self.ranked_ids.len()
+        {
+            return Err(format!(
+                "{} scores for {} ranked ids",
+                scores.len(),
+                self.ranked_ids.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Reorder `candidates` according to the ranking, dropping the ones the
+    /// model left out. Ids without a matching candidate are skipped.
+    pub fn apply<T, F>(&self, candidates: Vec<T>, id_of: F) -> impl Iterator<Item = T> + use<T, F>
+    where
+        F: Fn(&T) -> &str,
+    {
+        let mut slots: Vec<Option<T>> = candidates.into_iter().map(Some).collect();
+        let order: Vec<usize> = self
+            .ranked_ids
+            .iter()
+            .filter_map(|id| {
+                slots
+                    .iter()
+                    .position(|c| c.as_ref().is_some_and(|c| id_of(c) == id))
+            })
+            .collect();
+
+        order.into_iter().filter_map(move |i| slots[i].take())
+    }
+}
+
+fn unique_strings(generator: &mut SchemaGenerator) -> Schema {
+    let mut schema: SchemaObject = <Vec<String>>::json_schema(generator).into_object();
+    schema.array().unique_items = Some(true);
+    schema.into()
+}
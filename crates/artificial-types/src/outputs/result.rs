@@ -15,6 +15,17 @@ pub struct ThinkResult<T> {
     pub data: Option<T>,
 }
 
+impl<T> ThinkResult<T> {
+    /// `true` if the model succeeded with at least `min_confidence`.
+    ///
+    /// Handy as the escalation trigger of
+    /// [`artificial_core::draft_verify::DraftVerify`]:
+    /// `.with_trigger(|r: &ThinkResult<_>| !r.is_confident(0.8))`.
+    pub fn is_confident(&self, min_confidence: f32) -> bool {
+        self.status == ThinkStatus::Succeed && self.confidence >= min_confidence
+    }
+}
+
 #[derive(Debug, Clone, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ThinkStatus {
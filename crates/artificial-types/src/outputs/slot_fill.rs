@@ -0,0 +1,192 @@
+//! Typed output for slot filling with per-field confidence.
+//!
+//! `SlotFill<T>` asks the model to extract `T` **and** to report, for every
+//! top-level field of `T`, whether it was stated by the user, guessed, or not
+//! provided at all.  The status object is generated from `T`'s schema, so it
+//! always mirrors the struct.  A filling loop then only has to ask the user
+//! about [`SlotFill::needs_input`] and merges the follow-up answer with
+//! [`SlotFill::merge`]:
+//!
+//! ```rust
+//! use artificial_core::schema_util::{derive_response_schema, strict_mode_violations};
+//! use artificial_types::outputs::slot_fill::{SlotFill, SlotStatus};
+//! use schemars::JsonSchema;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, JsonSchema, Serialize, Deserialize)]
+//! #[serde(deny_unknown_fields)]
+//! struct Booking {
+//!     #[schemars(required)]
+//!     city: Option<String>,
+//!     #[schemars(required)]
+//!     nights: Option<u32>,
+//! }
+//!
+//! assert!(strict_mode_violations(&derive_response_schema::<SlotFill<Booking>>()).is_empty());
+//!
+//! let first: SlotFill<Booking> = serde_json::from_str(r#"{
+//!     "value": { "city": "Rome", "nights": null },
+//!     "slots": { "city": "filled", "nights": "missing" }
+//! }"#).unwrap();
+//! assert_eq!(first.needs_input().collect::<Vec<_>>(), ["nights"]);
+//!
+//! let follow_up: SlotFill<Booking> = serde_json::from_str(r#"{
+//!     "value": { "city": null, "nights": 3 },
+//!     "slots": { "city": "missing", "nights": "filled" }
+//! }"#).unwrap();
+//! let merged = first.merge(follow_up).unwrap();
+//! assert!(merged.is_complete());
+//! assert_eq!(merged.value.city.as_deref(), Some("Rome"));
+//! assert_eq!(merged.status("nights"), Some(SlotStatus::Filled));
+//! ```
+use std::collections::BTreeMap;
+
+use schemars::{
+    JsonSchema,
+    r#gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, ObjectValidation, Schema, SchemaObject},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// How a single slot was filled.
+#[derive(
+    Debug, Clone, Copy, JsonSchema, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotStatus {
+    /// Not provided; the field is `null`.
+    Missing,
+    /// Inferred or ambiguous; should be confirmed with the user.
+    Uncertain,
+    /// Stated explicitly.
+    Filled,
+}
+
+/// Extracted value plus a status for each of its top-level fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlotFill<T> {
+    pub value: T,
+    pub slots: BTreeMap<String, SlotStatus>,
+}
+
+impl<T> SlotFill<T> {
+    /// Status of the field `slot`, `None` if the model did not report it.
+    pub fn status(&self, slot: &str) -> Option<SlotStatus> {
+        self.slots.get(slot).copied()
+    }
+
+    /// Fields reported as missing or uncertain, in field-name order.
+    pub fn needs_input(&self) -> impl Iterator<Item = &str> {
+        self.slots
+            .iter()
+            .filter(|(_, status)| **status != SlotStatus::Filled)
+            .map(|(slot, _)| slot.as_str())
+    }
+
+    /// `true` once every reported slot is [`SlotStatus::Filled`].
+    pub fn is_complete(&self) -> bool {
+        self.needs_input().next().is_none()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> SlotFill<T> {
+    /// Combine with a later extraction: for each slot the field with the
+    /// better status wins, ties go to `update`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `T` does not serialize to a JSON object or the merged object
+    /// no longer deserializes into `T`.
+    pub fn merge(self, update: SlotFill<T>) -> Result<Self, serde_json::Error> {
+        let mut merged = as_object(&self.value)?;
+        let newer = as_object(&update.value)?;
+        let mut slots = self.slots;
+
+        for (slot, status) in update.slots {
+            let current = slots.get(&slot).copied().unwrap_or(SlotStatus::Missing);
+            if status >= current {
+                if let Some(value) = newer.get(&slot) {
+                    merged.insert(slot.clone(), value.clone());
+                }
+                slots.insert(slot, status);
+            }
+        }
+
+        Ok(SlotFill {
+            value: serde_json::from_value(Value::Object(merged))?,
+            slots,
+        })
+    }
+}
+
+fn as_object<T: Serialize>(value: &T) -> Result<serde_json::Map<String, Value>, serde_json::Error> {
+    match serde_json::to_value(value)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(serde::de::Error::custom("slot values must be JSON objects")),
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for SlotFill<T> {
+    fn schema_name() -> String {
+        format!("SlotFill_for_{}", T::schema_name())
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let value = T::json_schema(generator);
+        let fields: Vec<String> = match &value {
+            Schema::Object(SchemaObject {
+                object: Some(object),
+                ..
+            }) => object.properties.keys().cloned().collect(),
+            _ => Vec::new(),
+        };
+
+        let status = generator.subschema_for::<SlotStatus>();
+        let slots = closed_object(
+            fields.iter().map(|f| (f.clone(), status.clone())),
+            "Status of every field of `value`: `filled` if stated explicitly, \
+             `uncertain` if inferred or ambiguous, `missing` if not provided \
+             (the field is then null).",
+        );
+        let value = match value {
+            Schema::Object(mut object) => {
+                object.metadata().description =
+                    Some("The extracted data. Use null for fields that were not provided.".into());
+                Schema::Object(object)
+            }
+            other => other,
+        };
+
+        closed_object(
+            [("value".to_owned(), value), ("slots".to_owned(), slots)],
+            "",
+        )
+    }
+}
+
+/// An object schema with every property required and no extra properties.
+fn closed_object(
+    properties: impl IntoIterator<Item = (String, Schema)>,
+    description: &str,
+) -> Schema {
+    let properties: schemars::Map<String, Schema> = properties.into_iter().collect();
+    SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        metadata: (!description.is_empty()).then(|| {
+            Box::new(Metadata {
+                description: Some(description.to_owned()),
+                ..Default::default()
+            })
+        }),
+        object: Some(Box::new(ObjectValidation {
+            required: properties.keys().cloned().collect(),
+            properties,
+            additional_properties: Some(Box::new(Schema::Bool(false))),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
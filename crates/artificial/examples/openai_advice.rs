@@ -46,7 +46,6 @@
 //!
 //! ---------------------------------------------------------------------------
 
-use artificial::generic::ResponseContent;
 use artificial::openai::OpenAiAdapterBuilder;
 use artificial::prompt::chain::PromptChain;
 use artificial::types::{fragments::StaticFragment, outputs::result::ThinkResult};
@@ -132,9 +131,7 @@ async fn main() -> anyhow::Result<()> {
 
     let response = client.prompt_execute(AdvicePrompt::new(&history)).await?;
 
-    let ResponseContent::Finished(content) = response.content else {
-        panic!("expected finished");
-    };
+    let content = response.content.into_finished()?;
 
     println!("Status: {:?}", content.status);
     println!("Reasoning: {}", content.reasoning);
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use artificial::{
     ArtificialClient,
@@ -45,7 +45,7 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn guess_mime_type(path: &PathBuf) -> &'static str {
+fn guess_mime_type(path: &Path) -> &'static str {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some("mp3") => "audio/mpeg",
         Some("m4a") => "audio/mp4",
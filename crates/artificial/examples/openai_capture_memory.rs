@@ -11,28 +11,17 @@
 //! The code is intentionally *stand-alone* so that you can run it from the crate
 //! without touching any other files.
 
-use artificial::openai::OpenAiAdapterBuilder;
-use artificial::prompt::{builder::PromptBuilder, chain::PromptChain};
-use artificial::types::{
-    fragments::{CurrentDateFragment, StaticFragment},
-    outputs::result::ThinkResult,
-};
-use artificial::{
-    ArtificialClient,
-    generic::{GenericMessage, GenericRole},
-    model::{Model, OpenAiModel},
-    provider::PromptExecutionProvider as _,
-    template::{IntoPrompt, PromptTemplate},
-};
+use artificial::prelude::*;
+use artificial::types::fragments::{CurrentDateFragment, HistoryFragment};
 use schemars::{
     JsonSchema, SchemaGenerator,
     schema::{InstanceType, Metadata, SchemaObject, SingleOrVec},
 };
 use serde::{Deserialize, Serialize};
 
-/// ---------------------------------------------------------------------------
-/// ❶ Domain stubs – nice and small so we can focus on the prompting logic
-/// ---------------------------------------------------------------------------
+// ---------------------------------------------------------------------------
+// ❶ Domain stubs – nice and small so we can focus on the prompting logic
+// ---------------------------------------------------------------------------
 
 #[derive(Clone)]
 struct Message {
@@ -40,9 +29,9 @@ struct Message {
     pub text: String,
 }
 
-/// ---------------------------------------------------------------------------
-/// ❷ Prompt snippets (markdown in `examples/data/role/**.md`)
-/// ---------------------------------------------------------------------------
+// ---------------------------------------------------------------------------
+// ❷ Prompt snippets (markdown in `examples/data/role/**.md`)
+// ---------------------------------------------------------------------------
 
 /// “Base system” instructions that *any* prompt in this workspace usually adds
 /// (e.g. stylistic guidelines, response hygiene, …).
@@ -52,19 +41,19 @@ const BASE_SYSTEM_ROLE: &str = include_str!("data/role/base_system.md");
 /// memories*.
 const MEMORY_ARCHITECT_ROLE: &str = include_str!("data/role/memory_architect.md");
 
-/// ---------------------------------------------------------------------------
-/// ❸ A high-level prompt template: *CaptureMemory*
-///
-///    It is mostly a container that wires together the individual fragments
-///    (system base, date, team profile, chat history, …).
-/// ---------------------------------------------------------------------------
+// ---------------------------------------------------------------------------
+// ❸ A high-level prompt template: *CaptureMemory*
+//
+//    It is mostly a container that wires together the individual fragments
+//    (system base, date, team profile, chat history, …).
+// ---------------------------------------------------------------------------
 
 struct CaptureMemory<'a> {
     system_base_fragment: StaticFragment<'a>,
     memory_architect_role_fragment: StaticFragment<'a>,
     agent_fragment: AgentProfileFragment<'a>,
     team_fragment: TeamProfileFragment<'a>,
-    history_fragment: MessageHistoryFragment<'a>,
+    history_fragment: HistoryFragment,
 }
 
 impl<'a> CaptureMemory<'a> {
@@ -81,7 +70,13 @@ impl<'a> CaptureMemory<'a> {
             memory_architect_role_fragment: MEMORY_ARCHITECT_ROLE.into(),
             agent_fragment: AgentProfileFragment::new(member, team_profile.team_name),
             team_fragment: TeamProfileFragment::new(team_profile),
-            history_fragment: MessageHistoryFragment::new(history),
+            // Replay the chat log, newest messages first if it gets long.
+            history_fragment: HistoryFragment::from_records(history, |message| {
+                MessageFragment::new(&message.from, &message.text)
+                    .into_prompt()
+                    .remove(0)
+            })
+            .with_token_budget(4_000, &Self::MODEL),
         }
     }
 }
@@ -116,9 +111,9 @@ impl<'a> PromptTemplate for CaptureMemory<'a> {
     const MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
 }
 
-/// ---------------------------------------------------------------------------
-/// ❹ The famous `main` function – spin up the backend, build the prompt, run it
-/// ---------------------------------------------------------------------------
+// ---------------------------------------------------------------------------
+// ❹ The famous `main` function – spin up the backend, build the prompt, run it
+// ---------------------------------------------------------------------------
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -191,11 +186,11 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-/// ===========================================================================
-/// ❺ Reusable *fragment* implementations
-/// ===========================================================================
+// ===========================================================================
+// ❺ Reusable *fragment* implementations
+// ===========================================================================
 
-/// ---- TeamProfileFragment --------------------------------------------------
+// ---- TeamProfileFragment --------------------------------------------------
 
 pub struct TeamProfileFragment<'a> {
     team_spec: &'a TeamProfile<'a>,
@@ -223,7 +218,7 @@ impl IntoPrompt for TeamProfileFragment<'_> {
     }
 }
 
-/// ---- AgentProfileFragment -------------------------------------------------
+// ---- AgentProfileFragment -------------------------------------------------
 
 pub struct AgentProfileFragment<'a> {
     member: &'a MemberProfile<'a>,
@@ -254,37 +249,7 @@ impl IntoPrompt for AgentProfileFragment<'_> {
     }
 }
 
-/// ---- MessageHistoryFragment ----------------------------------------------
-
-pub struct MessageHistoryFragment<'a> {
-    history: &'a [Message],
-}
-
-impl<'a> MessageHistoryFragment<'a> {
-    fn new(history: &'a [Message]) -> Self {
-        Self { history }
-    }
-}
-
-impl IntoPrompt for MessageHistoryFragment<'_> {
-    type Message = GenericMessage;
-
-    fn into_prompt(self) -> Vec<Self::Message> {
-        if self.history.is_empty() {
-            return vec![];
-        }
-
-        let mut messages = Vec::with_capacity(self.history.len());
-
-        for message in self.history {
-            messages.extend(MessageFragment::new(&message.from, &message.text).into_prompt());
-        }
-
-        messages
-    }
-}
-
-/// ---- MessageFragment ------------------------------------------------------
+// ---- MessageFragment ------------------------------------------------------
 
 pub struct MessageFragment<'a> {
     name: &'a str,
@@ -311,9 +276,9 @@ impl IntoPrompt for MessageFragment<'_> {
     }
 }
 
-/// ===========================================================================
-/// ❻ Small data structs that we serialise into YAML inside the prompt
-/// ===========================================================================
+// ===========================================================================
+// ❻ Small data structs that we serialise into YAML inside the prompt
+// ===========================================================================
 
 #[derive(Serialize)]
 struct TeamProfile<'a> {
@@ -327,9 +292,9 @@ struct MemberProfile<'a> {
     pub biography: &'static str,
 }
 
-/// ===========================================================================
-/// ❼ Output types – strictly validated with `schemars` to avoid accidents
-/// ===========================================================================
+// ===========================================================================
+// ❼ Output types – strictly validated with `schemars` to avoid accidents
+// ===========================================================================
 
 #[derive(Debug, Clone, JsonSchema, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
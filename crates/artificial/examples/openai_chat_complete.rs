@@ -1,11 +1,4 @@
-use artificial::generic::ResponseContent;
-use artificial::openai::OpenAiAdapterBuilder;
-use artificial::{
-    ArtificialClient,
-    generic::{GenericMessage, GenericRole},
-    model::{Model, OpenAiModel},
-    provider::{ChatCompleteParameters, ChatCompletionProvider as _},
-};
+use artificial::prelude::*;
 
 /// # Chat Completion – Direct `chat_complete` Example
 ///
@@ -42,14 +35,8 @@ async fn main() -> anyhow::Result<()> {
     let params = ChatCompleteParameters::new(messages, Model::OpenAi(OpenAiModel::Gpt4oMini));
 
     let response = client.chat_complete(params).await?;
-    let ResponseContent::Finished(content) = response.content else {
-        panic!("expected finished");
-    };
-    if let Some(answer) = content.content {
-        println!("Assistant: {answer}");
-    } else {
-        println!("Assistant returned no textual content 🤖");
-    }
+    // Empty answers surface as `ArtificialError::EmptyResponse`.
+    println!("Assistant: {}", response.content.text().unwrap_or_default());
 
     if let Some(usage) = response.usage {
         println!(
@@ -79,6 +79,9 @@ async fn main() -> anyhow::Result<()> {
                 print!("{s}");
                 io::stdout().flush().ok();
             }
+            Ok(StreamEvent::ReasoningDelta(_)) => {
+                // Only reasoning models (DeepSeek, Grok) emit these.
+            }
             Ok(StreamEvent::ToolCallStart { index, id, name }) => {
                 // You can log/debug these; not strictly necessary for execution.
                 eprintln!("\n[debug] tool-call[{index}] start: id={id:?}, name={name:?}");
@@ -90,6 +93,10 @@ async fn main() -> anyhow::Result<()> {
                 // Optional: show streamed JSON fragments for debugging.
                 eprintln!("\n[debug] tool-call[{index}] args += {arguments_fragment:?}");
             }
+            Ok(StreamEvent::ToolCallMalformed { index, reason, .. }) => {
+                // Arguments turned into garbage; no `ToolCallComplete` follows.
+                eprintln!("\n[debug] tool-call[{index}] malformed: {reason}");
+            }
             Ok(StreamEvent::ToolCallComplete { index, intent }) => {
                 eprintln!(
                     "\n[debug] tool-call[{index}] complete: {} {:?}",
@@ -97,6 +104,10 @@ async fn main() -> anyhow::Result<()> {
                 );
                 tool_intents.push(intent);
             }
+            Ok(StreamEvent::Filtered { categories, halted }) => {
+                // Only emitted behind an `OutputFilter`.
+                eprintln!("\n[debug] filtered {categories:?}, halted: {halted}");
+            }
             Ok(StreamEvent::MessageEnd) => {
                 break;
             }
@@ -121,6 +132,8 @@ async fn main() -> anyhow::Result<()> {
             name: None,
             tool_calls: Some(tool_intents.clone()),
             tool_call_id: None,
+            reasoning: None,
+            attachments: Vec::new(),
         });
 
         // Execute tool calls and push tool results to the conversation
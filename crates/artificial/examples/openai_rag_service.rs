@@ -0,0 +1,409 @@
+//! # RAG chat service
+//!
+//! An axum server that answers questions about a small employee handbook.
+//! Where the other examples show one building block each, this one wires
+//! them together the way a real service would:
+//!
+//! - **Embeddings** – the handbook is embedded once at start-up, every
+//!   question is embedded to find the closest sections.
+//! - **Retrieval fragment** – the hits are rendered into the prompt by
+//!   `RetrievalFragment`, a plain [`IntoPrompt`] implementation.
+//! - **Conversation store** – every chat id keeps its history as a
+//!   versioned `StoredConversation` between requests.
+//! - **Streaming SSE bridge** – the model's `StreamEvent`s are forwarded to
+//!   the caller as server-sent events.
+//! - **Tool registry** – the model may call `open_ticket`; calls are run by
+//!   a `ToolRegistry` and the answer continues streaming afterwards.
+//! - **Cost tracking** – a `UsageTracker` counts embeddings and chats;
+//!   `GET /usage` reports tokens and the estimated cost.
+//!
+//! Usage:
+//!   export OPENAI_API_KEY=sk-…
+//!   cargo run -p artificial --example openai_rag_service
+//!
+//!   curl -N localhost:3000/chat/demo \
+//!        -H 'content-type: application/json' \
+//!        -d '{"message": "How many vacation days do I get?"}'
+//!   curl localhost:3000/usage
+
+use std::{collections::HashMap, convert::Infallible, future::Future, pin::Pin, sync::Arc};
+
+use artificial::{
+    conversation::{SlidingWindow, TrimStrategy},
+    generic::{GenericFunctionCallIntent, StoredConversation, StreamEvent},
+    openai::OpenAiAdapter,
+    prelude::*,
+    provider::EmbeddingProvider,
+    semantic_cache::cosine_similarity,
+    tools::{ToolRegistry, TypedTool},
+    usage::{PricingTable, TokenPrice, UsageTracker},
+};
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
+};
+use futures_util::{Stream, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, mpsc};
+
+const CHAT_MODEL: Model = Model::OpenAi(OpenAiModel::Gpt4oMini);
+const EMBEDDING_MODEL: Model = Model::OpenAi(OpenAiModel::TextEmbedding3Small);
+
+/// How many handbook sections are put in front of each question.
+const TOP_K: usize = 2;
+
+const SYSTEM_PROMPT: &str = "You are the HR assistant of ACME Corp. Answer from the \
+     handbook excerpts only and say so if they do not cover the question. If the \
+     employee wants something done, open a ticket with the `open_ticket` tool.";
+
+const HANDBOOK: &[(&str, &str)] = &[
+    (
+        "Vacation",
+        "Full-time employees get 28 vacation days per year. Up to 5 unused days \
+         carry over into the first quarter of the next year.",
+    ),
+    (
+        "Remote work",
+        "Everyone may work remotely up to three days a week. Working from abroad \
+         needs approval by HR at least four weeks in advance.",
+    ),
+    (
+        "Equipment",
+        "New hires choose between a MacBook Pro and a ThinkPad. Broken equipment \
+         is replaced within two business days after a ticket is opened.",
+    ),
+    (
+        "Parental leave",
+        "Parents get 16 weeks of fully paid leave, to be taken within the first \
+         two years after birth or adoption.",
+    ),
+];
+
+// ---------------------------------------------------------------------------
+// ❶ Application state
+// ---------------------------------------------------------------------------
+
+type Backend = UsageTracker<OpenAiAdapter>;
+
+struct App {
+    client: ArtificialClient<Backend>,
+    index: HandbookIndex,
+    store: ConversationStore,
+    tools: ToolRegistry,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let backend = UsageTracker::new(OpenAiAdapterBuilder::new_from_env().build()?).with_pricing(
+        PricingTable::new()
+            .with_price("gpt-4o-mini", TokenPrice::per_million(0.15, 0.60))
+            .with_price("text-embedding-3-small", TokenPrice::per_million(0.02, 0.0)),
+    );
+    let client = ArtificialClient::new(backend);
+
+    let index = HandbookIndex::build(&client).await?;
+    let app = Arc::new(App {
+        client,
+        index,
+        store: ConversationStore::default(),
+        tools: ToolRegistry::new()
+            .with_typed_tool(OpenTicket)
+            .with_max_rounds(3),
+    });
+
+    let router = Router::new()
+        .route("/chat/{chat_id}", post(chat))
+        .route("/usage", get(usage))
+        .with_state(app);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+    println!("listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// ❷ HTTP handlers
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct ChatRequest {
+    message: String,
+}
+
+/// Retrieve context for the question, then stream the answer as SSE.
+///
+/// Retrieval errors are still reported as a regular HTTP error; once the
+/// stream has started, errors arrive as an `error` event.
+async fn chat(
+    State(app): State<Arc<App>>,
+    Path(chat_id): Path<String>,
+    Json(request): Json<ChatRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let context = app.index.search(&app.client, &request.message).await?;
+
+    let mut history = app.store.load(&chat_id).await?;
+    history.push(GenericMessage::new(request.message, GenericRole::User));
+
+    let (events, receiver) = mpsc::channel(32);
+    tokio::spawn(async move {
+        if let Err(err) = answer(&app, &chat_id, context, history, &events).await {
+            let _ = events
+                .send(Event::default().event("error").data(err.to_string()))
+                .await;
+        }
+    });
+
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((Ok(event), receiver))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Serialize)]
+struct UsageReport {
+    requests: u64,
+    total_tokens: i64,
+    estimated_cost_usd: Option<f64>,
+}
+
+async fn usage(State(app): State<Arc<App>>) -> Json<UsageReport> {
+    let ledger = app.client.backend().ledger();
+    let total = ledger.total();
+    Json(UsageReport {
+        requests: total.requests,
+        total_tokens: total.total_tokens,
+        estimated_cost_usd: ledger.cost(),
+    })
+}
+
+/// Maps [`ArtificialError`] onto a `500` with the error message as body.
+struct AppError(ArtificialError);
+
+impl From<ArtificialError> for AppError {
+    fn from(err: ArtificialError) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0.to_string()).into_response()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ❸ The chat turn: stream, run tools, stream again, persist
+// ---------------------------------------------------------------------------
+
+async fn answer(
+    app: &App,
+    chat_id: &str,
+    context: RetrievalFragment,
+    mut history: Vec<GenericMessage>,
+    events: &mpsc::Sender<Event>,
+) -> Result<(), ArtificialError> {
+    // Retrieved context is rebuilt for every question and never stored.
+    let prefix = PromptChain::new()
+        .with(StaticFragment::new(SYSTEM_PROMPT, GenericRole::System))
+        .with(context)
+        .build();
+
+    for _ in 0..app.tools.max_rounds() {
+        let messages = prefix.iter().chain(&history).cloned().collect();
+        let params =
+            ChatCompleteParameters::new(messages, CHAT_MODEL).with_tools(app.tools.specs());
+
+        let mut text = String::new();
+        let mut calls: Vec<GenericFunctionCallIntent> = Vec::new();
+        let mut stream = app.client.chat_complete_events_stream(params);
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            match &event {
+                StreamEvent::TextDelta(delta) => text.push_str(delta),
+                StreamEvent::ToolCallComplete { intent, .. } => calls.push(intent.clone()),
+                _ => {}
+            }
+            if events.send(to_sse(&event)).await.is_err() {
+                // The caller hung up; keep what was said so far.
+                break;
+            }
+        }
+        drop(stream);
+
+        if calls.is_empty() {
+            history.push(GenericMessage::new(text, GenericRole::Assistant));
+            return app.store.save(chat_id, history).await;
+        }
+
+        let mut request = GenericMessage::new(text, GenericRole::Assistant);
+        request.tool_calls = Some(calls.clone());
+        history.push(request);
+        for call in &calls {
+            history.push(app.tools.execute(call).await);
+        }
+    }
+
+    Err(ArtificialError::Other(format!(
+        "no final answer after {} tool rounds",
+        app.tools.max_rounds()
+    )))
+}
+
+/// The SSE bridge: every [`StreamEvent`] becomes one server-sent event named
+/// after its `type`, with the event's JSON wire format as data.
+fn to_sse(event: &StreamEvent) -> Event {
+    let data = serde_json::to_value(event).unwrap_or_default();
+    let name = data["type"].as_str().unwrap_or("event").to_owned();
+    Event::default().event(name).data(data.to_string())
+}
+
+// ---------------------------------------------------------------------------
+// ❹ Retrieval: an in-memory vector index and the fragment rendering its hits
+// ---------------------------------------------------------------------------
+
+struct HandbookIndex {
+    sections: Vec<(&'static str, &'static str, Vec<f32>)>,
+}
+
+impl HandbookIndex {
+    async fn build(client: &ArtificialClient<Backend>) -> Result<Self, ArtificialError> {
+        let texts = HANDBOOK.iter().map(|(_, text)| text.to_string()).collect();
+        let response = client.embed(texts, EMBEDDING_MODEL).await?;
+        let sections = HANDBOOK
+            .iter()
+            .zip(response.embeddings)
+            .map(|((title, text), embedding)| (*title, *text, embedding))
+            .collect();
+        Ok(Self { sections })
+    }
+
+    /// The [`TOP_K`] sections closest to `question`.
+    async fn search(
+        &self,
+        client: &ArtificialClient<Backend>,
+        question: &str,
+    ) -> Result<RetrievalFragment, ArtificialError> {
+        let response = client
+            .embed(vec![question.to_owned()], EMBEDDING_MODEL)
+            .await?;
+        let query = response
+            .embeddings
+            .first()
+            .ok_or(ArtificialError::EmptyResponse)?;
+
+        let mut hits: Vec<_> = self
+            .sections
+            .iter()
+            .map(|(title, text, embedding)| (cosine_similarity(query, embedding), *title, *text))
+            .collect();
+        hits.sort_by(|a, b| b.0.total_cmp(&a.0));
+        hits.truncate(TOP_K);
+        Ok(RetrievalFragment { hits })
+    }
+}
+
+/// Renders the retrieved sections, best match first, as one system message.
+struct RetrievalFragment {
+    hits: Vec<(f32, &'static str, &'static str)>,
+}
+
+impl IntoPrompt for RetrievalFragment {
+    type Message = GenericMessage;
+
+    fn into_prompt(self) -> Vec<Self::Message> {
+        let mut builder = PromptBuilder::new().add_section_h2("Handbook excerpts");
+        for (score, title, text) in self.hits {
+            builder = builder
+                .add_line_bold(format!("{title} (similarity {score:.2})"))
+                .add_line(text)
+                .add_blank_line();
+        }
+        vec![GenericMessage::new(builder.finalize(), GenericRole::System)]
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ❺ Conversation store
+// ---------------------------------------------------------------------------
+
+/// Keeps each chat as the JSON a database column would hold, trimmed to the
+/// most recent messages.
+#[derive(Default)]
+struct ConversationStore {
+    chats: Mutex<HashMap<String, String>>,
+}
+
+impl ConversationStore {
+    async fn load(&self, chat_id: &str) -> Result<Vec<GenericMessage>, ArtificialError> {
+        match self.chats.lock().await.get(chat_id) {
+            Some(json) => Ok(StoredConversation::from_json(json)?.messages),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn save(
+        &self,
+        chat_id: &str,
+        messages: Vec<GenericMessage>,
+    ) -> Result<(), ArtificialError> {
+        let messages = SlidingWindow::new(20).trim(messages).await?;
+        let json = StoredConversation::new(messages).to_json()?;
+        self.chats.lock().await.insert(chat_id.to_owned(), json);
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ❻ Tools
+// ---------------------------------------------------------------------------
+
+/// A request for the HR team.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TicketRequest {
+    /// One-line summary of what the employee needs.
+    subject: String,
+    /// Handbook section the request relates to, e.g. "Equipment".
+    category: String,
+}
+
+#[derive(Serialize)]
+struct Ticket {
+    id: String,
+    status: &'static str,
+}
+
+struct OpenTicket;
+
+impl TypedTool<TicketRequest, Ticket> for OpenTicket {
+    fn name(&self) -> &str {
+        "open_ticket"
+    }
+
+    fn description(&self) -> &str {
+        "Open a ticket with the HR team and return its id."
+    }
+
+    fn call(
+        &self,
+        request: TicketRequest,
+    ) -> Pin<Box<dyn Future<Output = artificial::error::Result<Ticket>> + Send + '_>> {
+        Box::pin(async move {
+            // A real service would call the ticketing system here.
+            println!("ticket [{}] {}", request.category, request.subject);
+            Ok(Ticket {
+                id: "HR-1042".into(),
+                status: "open",
+            })
+        })
+    }
+}
@@ -1,11 +1,19 @@
-use artificial::generic::{GenericFunctionSpec, ResponseContent};
+use std::{future::Future, pin::Pin};
+
 use artificial::openai::OpenAiAdapterBuilder;
 use artificial::{
     ArtificialClient,
+    agent_events::MemoryEventLog,
+    error::Result,
     generic::{GenericMessage, GenericRole},
     model::{Model, OpenAiModel},
-    provider::{ChatCompleteParameters, ChatCompletionProvider as _},
+    provider::ChatCompleteParameters,
+    tools::{ToolRegistry, TypedTool},
+    transcript::Transcript,
+    usage::TokenPrice,
 };
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 /// ---------------------------------------------------------------------------
 /// Example  –  OpenAI “Function Calling”
@@ -15,110 +23,108 @@ use artificial::{
 /// export OPENAI_API_KEY=sk-…      # mandatory
 /// cargo run -p artificial --example openai_tool_weather
 /// ```
+///
+/// The run's events are saved to `weather_run.jsonl`; print the step-by-step
+/// transcript of a saved run again without calling the API:
+/// ```bash
+/// cargo run -p artificial --example openai_tool_weather -- --replay weather_run.jsonl
+/// ```
 /// ---------------------------------------------------------------------------
 
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct WeatherArgs {
+    /// The city and state, e.g. San Francisco, CA
+    location: String,
+    unit: Unit,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Unit {
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug, Serialize)]
+struct WeatherReport {
+    location: String,
+    value: f64,
+    unit: Unit,
+}
+
+struct CurrentWeather;
+
+impl TypedTool<WeatherArgs, WeatherReport> for CurrentWeather {
+    fn name(&self) -> &str {
+        "current_weather"
+    }
+
+    fn description(&self) -> &str {
+        "Fetch the current weather report (temperature in °C and condition)."
+    }
+
+    fn call(
+        &self,
+        args: WeatherArgs,
+    ) -> Pin<Box<dyn Future<Output = Result<WeatherReport>> + Send + '_>> {
+        Box::pin(async move {
+            Ok(WeatherReport {
+                location: args.location,
+                value: 32.2,
+                unit: args.unit,
+            })
+        })
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--replay") {
+        let path = args.next().unwrap_or_else(|| "weather_run.jsonl".into());
+        println!("{}", transcript(&std::fs::read_to_string(path)?)?);
+        return Ok(());
+    }
+
     let backend = OpenAiAdapterBuilder::new_from_env().build()?;
     let client = ArtificialClient::new(backend);
 
-    let weather_api_tool = GenericFunctionSpec {
-        name: "current_weather".to_string(),
-        description: "Fetch the current weather report (temperature in °C and condition)."
-            .to_string(),
-        parameters: serde_json::json!({
-          "type": "object",
-          "properties": {
-            "location": {
-              "type": "string",
-              "description": "The city and state, e.g. San Francisco, CA"
-            },
-            "unit": {
-              "type": "string",
-              "enum": ["celsius", "fahrenheit"]
-            }
-          },
-          "required": ["location", "unit"],
-          "additionalProperties": false
-        }),
-    };
-
-    let mut messages = vec![];
-
-    messages.push(GenericMessage::new(
-        "What’s the current weather in Berlin?".into(),
-        GenericRole::User,
-    ));
-
-    loop {
-        let params =
-            ChatCompleteParameters::new(messages.clone(), Model::OpenAi(OpenAiModel::Gpt4oMini))
-                .with_tools(vec![weather_api_tool.clone()]);
-
-        let response = client.chat_complete(params).await?;
-
-        let message = match &response.content {
-            ResponseContent::Finished(message) => message,
-            ResponseContent::ToolCalls(message) => message,
-        };
-
-        messages.push(message.clone());
-
-        match response.content {
-            ResponseContent::ToolCalls(generic_message) => {
-                if let Some(tool_calls) = &generic_message.tool_calls {
-                    for tool_call in tool_calls {
-                        match tool_call.function.name.as_str() {
-                            "current_weather" => {
-                                let location = tool_call
-                                    .function
-                                    .arguments
-                                    .as_object()
-                                    .and_then(|o| o.get("location"))
-                                    .and_then(|o| o.as_str())
-                                    .map(String::from)
-                                    .unwrap_or("Berlin".to_string());
-                                let unit = tool_call
-                                    .function
-                                    .arguments
-                                    .as_object()
-                                    .and_then(|o| o.get("unit"))
-                                    .and_then(|o| o.as_str())
-                                    .map(String::from);
-
-                                messages.push(
-                                    GenericMessage::new(
-                                        get_weather(location, unit),
-                                        GenericRole::Tool,
-                                    )
-                                    .with_tool_call_id(tool_call.id.clone()),
-                                );
-                                continue;
-                            }
-                            other => panic!("tool not registered: {other}"),
-                        }
-                    }
-                }
-            }
-            ResponseContent::Finished(content) => {
-                if let Some(answer) = content.content {
-                    println!("LLM answered:\n{answer}");
-                    break;
-                } else {
-                    println!("Assistant returned no textual content 🤖");
-                }
-            }
-        }
+    // The parameter schema is derived from `WeatherArgs`.
+    let registry = ToolRegistry::new().with_typed_tool(CurrentWeather);
+
+    let params = ChatCompleteParameters::new(
+        vec![GenericMessage::new(
+            "What’s the current weather in Berlin?".into(),
+            GenericRole::User,
+        )],
+        Model::OpenAi(OpenAiModel::Gpt4oMini),
+    );
+
+    // Sends the chat, runs `current_weather` whenever the model asks for it
+    // and resends until the model answers.
+    let log = MemoryEventLog::new();
+    let outcome = client
+        .chat_complete_with_tools_observed(params, &registry, &log)
+        .await?;
+    println!(
+        "LLM answered after {} round(s):\n{}\n",
+        outcome.rounds,
+        outcome.message.content.unwrap_or_default()
+    );
+
+    let mut jsonl = String::new();
+    for event in log.events() {
+        jsonl.push_str(&serde_json::to_string(&event)?);
+        jsonl.push('\n');
     }
+    std::fs::write("weather_run.jsonl", &jsonl)?;
+    println!("{}", transcript(&jsonl)?);
 
     Ok(())
 }
 
-fn get_weather(location: String, unit: Option<String>) -> String {
-    serde_json::json!({
-        "location": location,
-        "value": 32.2,
-        "unit": unit.unwrap_or("celsius".to_string())
-    })
-    .to_string()
+fn transcript(jsonl: &str) -> Result<Transcript> {
+    Ok(Transcript::from_jsonl(jsonl)?
+        .with_price("gpt-4o-mini", TokenPrice::per_million(0.15, 0.60)))
 }
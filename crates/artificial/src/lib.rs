@@ -32,13 +32,7 @@
 //! ## Quick example
 //!
 //! ```rust,no_run
-//! use artificial::{
-//!     ArtificialClient,
-//!     generic::{GenericMessage, GenericRole},
-//!     model::{Model, OpenAiModel},
-//!     template::{IntoPrompt, PromptTemplate},
-//!     provider::PromptExecutionProvider,
-//! };
+//! use artificial::prelude::*;
 //!
 //! // Define the answer shape
 //! #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -59,7 +53,7 @@
 //!
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
-//!     let backend = artificial::openai::OpenAiAdapterBuilder::new_from_env().build()?;
+//!     let backend = OpenAiAdapterBuilder::new_from_env().build()?;
 //!     let client  = ArtificialClient::new(backend);
 //!     let answer  = client.prompt_execute(AskHello).await?;
 //!     println!("{:?}", answer.content);
@@ -71,7 +65,8 @@
 //!
 //! The `pub use` statements below simply forward the public API of the
 //! individual crates so users can write `artificial::ArtificialClient` instead
-//! of juggling four separate dependencies.
+//! of juggling four separate dependencies. The most common items are also
+//! collected in [`prelude`].
 //!
 //! ---
 //! _Happy prompting & may your JSON always validate!_
@@ -84,3 +79,5 @@ pub use artificial_types as types;
 
 #[cfg(feature = "openai")]
 pub use artificial_openai as openai;
+
+pub mod prelude;
@@ -0,0 +1,25 @@
+//! The items nearly every program needs, in one import:
+//!
+//! ```rust
+//! use artificial::prelude::*;
+//! ```
+//!
+//! Contents are kept small and only grow between minor versions; anything
+//! more specialised is imported from its module.
+
+pub use artificial_core::ArtificialClient;
+pub use artificial_core::error::ArtificialError;
+pub use artificial_core::generic::{GenericMessage, GenericRole, StreamingEventsProvider};
+pub use artificial_core::model::{DeepSeekModel, Model, OpenAiModel, XAiModel};
+pub use artificial_core::provider::{
+    ChatCompleteParameters, ChatCompletionProvider, PromptExecutionProvider,
+    PromptStreamingProvider, StreamingChatProvider,
+};
+pub use artificial_core::template::{IntoPrompt, PromptTemplate};
+pub use artificial_prompt::builder::PromptBuilder;
+pub use artificial_prompt::chain::PromptChain;
+pub use artificial_types::fragments::StaticFragment;
+pub use artificial_types::outputs::result::ThinkResult;
+
+#[cfg(feature = "openai")]
+pub use artificial_openai::OpenAiAdapterBuilder;